@@ -0,0 +1,552 @@
+//! # Permutations, combinations and friends, one at a time
+//!
+//! Every arrangement is built in place, in a buffer the iterator owns: `next`
+//! lends it as a slice, and the next call changes it. `Iterator` can't do
+//! that (its items can't borrow from the iterator), hence `Lending`: the
+//! buffer is reused, not allocated again for every item (only `PowerSet`
+//! grows it, once for every size), which is what makes the 3628800
+//! permutations of 10 items cheap to walk.
+//!
+//! - `Heap`: the permutations by Heap's algorithm, one swap apart;
+//! - `Lexicographic`: the permutations in lexicographic order, with
+//!   `next_permutation`, and `rank` and `unrank` to get the position of a
+//!   permutation, or the permutation at a position;
+//! - `Combinations`, `Multisets` (combinations with repetition), `Product`
+//!   (the cartesian product of lists) and `PowerSet`.
+//!
+//! - https://en.wikipedia.org/wiki/Heap%27s_algorithm
+//! - https://en.wikipedia.org/wiki/Permutation#Generation_in_lexicographic_order
+//! - https://en.wikipedia.org/wiki/Lehmer_code
+
+/// An iterator that lends its items, valid until the next call.
+pub trait Lending {
+    type Item;
+
+    fn next(&mut self) -> Option<&[Self::Item]>;
+
+    fn for_each<F: FnMut(&[Self::Item])>(mut self, mut f: F)
+    where
+        Self: Sized,
+    {
+        while let Some(item) = self.next() {
+            f(item);
+        }
+    }
+
+    /// How many items are left, walking them.
+    fn count(mut self) -> usize
+    where
+        Self: Sized,
+    {
+        let mut count = 0;
+        while self.next().is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Every item, copied.
+    fn to_vecs(mut self) -> Vec<Vec<Self::Item>>
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = self.next() {
+            items.push(item.to_vec());
+        }
+        items
+    }
+}
+
+/// The permutations of some items, in the order of Heap's algorithm: every
+/// permutation is the one before with two items swapped.
+#[derive(Debug, Clone)]
+pub struct Heap<T> {
+    items: Vec<T>,
+    // The counters of the loops of the recursive algorithm.
+    c: Vec<usize>,
+    i: usize,
+    started: bool,
+}
+
+impl<T> Heap<T> {
+    pub fn new(items: Vec<T>) -> Heap<T> {
+        Heap {
+            c: vec![0; items.len()],
+            items,
+            i: 1,
+            started: false,
+        }
+    }
+}
+
+impl<T> Lending for Heap<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<&[T]> {
+        if !self.started {
+            self.started = true;
+            return Some(&self.items);
+        }
+        while self.i < self.items.len() {
+            let i = self.i;
+            if self.c[i] < i {
+                let j = if i.is_multiple_of(2) { 0 } else { self.c[i] };
+                self.items.swap(j, i);
+                self.c[i] += 1;
+                self.i = 1;
+                return Some(&self.items);
+            }
+            self.c[i] = 0;
+            self.i += 1;
+        }
+        None
+    }
+}
+
+/// The next permutation in lexicographic order, in place: false, and the
+/// items back to the first permutation (sorted), after the last one. Equal
+/// items are permuted only once.
+pub fn next_permutation<T: Ord>(items: &mut [T]) -> bool {
+    // The longest decreasing suffix can't grow: the item before it is
+    // swapped with the next larger one of the suffix, which is then reversed.
+    let pivot = match items.windows(2).rposition(|w| w[0] < w[1]) {
+        Some(pivot) => pivot,
+        None => {
+            items.reverse();
+            return false;
+        }
+    };
+    let successor = items.iter().rposition(|x| *x > items[pivot]).unwrap();
+    items.swap(pivot, successor);
+    items[pivot + 1..].reverse();
+    true
+}
+
+/// The one before `next_permutation`.
+pub fn prev_permutation<T: Ord>(items: &mut [T]) -> bool {
+    let pivot = match items.windows(2).rposition(|w| w[0] > w[1]) {
+        Some(pivot) => pivot,
+        None => {
+            items.reverse();
+            return false;
+        }
+    };
+    let predecessor = items.iter().rposition(|x| *x < items[pivot]).unwrap();
+    items.swap(pivot, predecessor);
+    items[pivot + 1..].reverse();
+    true
+}
+
+fn factorial(n: usize) -> Option<u128> {
+    (1..=n as u128).try_fold(1u128, |f, i| f.checked_mul(i))
+}
+
+/// The position of a permutation of distinct items among all of them, in
+/// lexicographic order, from 0: the sorted items are the first one. None if
+/// it doesn't fit in a u128, as it may not from 35 items on.
+pub fn rank<T: Ord>(permutation: &[T]) -> Option<u128> {
+    // The Lehmer code: how many of the items after each one are smaller.
+    // Beyond 34 items the first digits weigh more than a u128 holds, but a
+    // rank that fits has them 0.
+    let n = permutation.len();
+    let mut rank = 0u128;
+    for (i, x) in permutation.iter().enumerate() {
+        let smaller = permutation[i + 1..].iter().filter(|&y| y < x).count() as u128;
+        if smaller > 0 {
+            let weight = factorial(n - 1 - i)?;
+            rank = rank.checked_add(smaller.checked_mul(weight)?)?;
+        }
+    }
+    Some(rank)
+}
+
+/// The permutation of the items (sorted first) at a position, if there are
+/// that many.
+pub fn unrank<T: Ord>(mut items: Vec<T>, mut rank: u128) -> Option<Vec<T>> {
+    items.sort();
+    let n = items.len();
+    if factorial(n).is_some_and(|f| rank >= f) {
+        return None;
+    }
+    // Every position takes one of the items left, the more permutations
+    // there are after it, the further it moves.
+    for i in 0..n {
+        if let Some(weight) = factorial(n - 1 - i) {
+            let skip = (rank / weight) as usize;
+            rank %= weight;
+            items[i..=i + skip].rotate_right(1);
+        }
+    }
+    Some(items)
+}
+
+/// The permutations in lexicographic order, from the items as they're given:
+/// sort them first for all of them.
+#[derive(Debug, Clone)]
+pub struct Lexicographic<T> {
+    items: Vec<T>,
+    started: bool,
+    done: bool,
+}
+
+impl<T: Ord> Lexicographic<T> {
+    pub fn new(items: Vec<T>) -> Lexicographic<T> {
+        Lexicographic {
+            items,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// From the permutation at a position, see `unrank`.
+    pub fn from_rank(items: Vec<T>, rank: u128) -> Option<Lexicographic<T>> {
+        unrank(items, rank).map(Lexicographic::new)
+    }
+}
+
+impl<T: Ord> Lending for Lexicographic<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<&[T]> {
+        if self.done {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+        } else if !next_permutation(&mut self.items) {
+            self.done = true;
+            return None;
+        }
+        Some(&self.items)
+    }
+}
+
+/// The choices of `k` of the items, in lexicographic order of their
+/// positions.
+#[derive(Debug, Clone)]
+pub struct Combinations<T> {
+    items: Vec<T>,
+    indices: Vec<usize>,
+    chosen: Vec<T>,
+    started: bool,
+}
+
+impl<T: Clone> Combinations<T> {
+    pub fn new(items: Vec<T>, k: usize) -> Combinations<T> {
+        let indices: Vec<usize> = (0..k).collect();
+        Combinations {
+            chosen: items.iter().take(k).cloned().collect(),
+            // None if there aren't k items.
+            started: k > items.len(),
+            items,
+            indices,
+        }
+    }
+}
+
+impl<T: Clone> Lending for Combinations<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<&[T]> {
+        if !self.started {
+            self.started = true;
+            return Some(&self.chosen);
+        }
+        let (n, k) = (self.items.len(), self.indices.len());
+        if k > n {
+            return None;
+        }
+        // The last index that can still move right.
+        let i = (0..k).rev().find(|&i| self.indices[i] < n - k + i)?;
+        self.indices[i] += 1;
+        for j in i..k {
+            if j > i {
+                self.indices[j] = self.indices[j - 1] + 1;
+            }
+            self.chosen[j] = self.items[self.indices[j]].clone();
+        }
+        Some(&self.chosen)
+    }
+}
+
+/// The choices of `k` of the items, each as many times as wanted: the
+/// combinations with repetition.
+#[derive(Debug, Clone)]
+pub struct Multisets<T> {
+    items: Vec<T>,
+    // Never decreasing.
+    indices: Vec<usize>,
+    chosen: Vec<T>,
+    started: bool,
+}
+
+impl<T: Clone> Multisets<T> {
+    pub fn new(items: Vec<T>, k: usize) -> Multisets<T> {
+        Multisets {
+            chosen: items
+                .first()
+                .map(|x| vec![x.clone(); k])
+                .unwrap_or_default(),
+            // Nothing to choose from, unless nothing is chosen.
+            started: items.is_empty() && k > 0,
+            items,
+            indices: vec![0; k],
+        }
+    }
+}
+
+impl<T: Clone> Lending for Multisets<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<&[T]> {
+        if !self.started {
+            self.started = true;
+            return Some(&self.chosen);
+        }
+        let n = self.items.len();
+        let i = self.indices.iter().rposition(|&x| x + 1 < n)?;
+        let index = self.indices[i] + 1;
+        for j in i..self.indices.len() {
+            self.indices[j] = index;
+            self.chosen[j] = self.items[index].clone();
+        }
+        Some(&self.chosen)
+    }
+}
+
+/// An item of every list, in lexicographic order: the last list changes
+/// first, like the digits of a counter.
+#[derive(Debug, Clone)]
+pub struct Product<T> {
+    lists: Vec<Vec<T>>,
+    indices: Vec<usize>,
+    chosen: Vec<T>,
+    started: bool,
+    done: bool,
+}
+
+impl<T: Clone> Product<T> {
+    pub fn new(lists: Vec<Vec<T>>) -> Product<T> {
+        Product {
+            indices: vec![0; lists.len()],
+            chosen: lists.iter().filter_map(|l| l.first().cloned()).collect(),
+            started: false,
+            // An empty list, and there is nothing to choose.
+            done: lists.iter().any(Vec::is_empty),
+            lists,
+        }
+    }
+
+    /// The same list `k` times.
+    pub fn repeat(items: Vec<T>, k: usize) -> Product<T> {
+        Product::new(vec![items; k])
+    }
+}
+
+impl<T: Clone> Lending for Product<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<&[T]> {
+        if self.done {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            return Some(&self.chosen);
+        }
+        for i in (0..self.lists.len()).rev() {
+            self.indices[i] += 1;
+            if self.indices[i] < self.lists[i].len() {
+                self.chosen[i] = self.lists[i][self.indices[i]].clone();
+                return Some(&self.chosen);
+            }
+            self.indices[i] = 0;
+            self.chosen[i] = self.lists[i][0].clone();
+        }
+        // Every digit went back to 0.
+        self.done = true;
+        None
+    }
+}
+
+/// Every subset of the items: the empty one, then the ones of 1 item, of 2,
+/// and so on, each size as `Combinations`.
+#[derive(Debug, Clone)]
+pub struct PowerSet<T> {
+    combinations: Combinations<T>,
+}
+
+impl<T: Clone> PowerSet<T> {
+    pub fn new(items: Vec<T>) -> PowerSet<T> {
+        PowerSet {
+            combinations: Combinations::new(items, 0),
+        }
+    }
+}
+
+impl<T: Clone> Lending for PowerSet<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<&[T]> {
+        let c = &mut self.combinations;
+        if c.next().is_none() {
+            let k = c.indices.len() + 1;
+            if k > c.items.len() {
+                return None;
+            }
+            // The buffers only grow, and up to the number of items.
+            c.indices.clear();
+            c.indices.extend(0..k);
+            c.chosen.clear();
+            c.chosen.extend(c.items[..k].iter().cloned());
+            c.started = true;
+        }
+        Some(&c.chosen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heap() {
+        let all = Heap::new(vec![1, 2, 3]).to_vecs();
+        assert_eq!(
+            all,
+            vec![
+                vec![1, 2, 3],
+                vec![2, 1, 3],
+                vec![3, 1, 2],
+                vec![1, 3, 2],
+                vec![2, 3, 1],
+                vec![3, 2, 1]
+            ]
+        );
+        let mut sorted = Heap::new((0..7).collect()).to_vecs();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 5040);
+        assert_eq!(Heap::new(Vec::<u8>::new()).to_vecs(), vec![vec![]]);
+        assert_eq!(Heap::new(vec!["a"]).count(), 1);
+    }
+
+    #[test]
+    fn lexicographic() {
+        let all = Lexicographic::new(vec![1, 2, 3]).to_vecs();
+        let mut sorted = all.clone();
+        sorted.sort();
+        assert_eq!(all, sorted);
+        assert_eq!(all.len(), 6);
+        // Equal items, once.
+        assert_eq!(
+            Lexicographic::new(vec![1, 1, 2]).to_vecs(),
+            vec![vec![1, 1, 2], vec![1, 2, 1], vec![2, 1, 1]]
+        );
+
+        let mut items = vec![3, 2, 1];
+        assert!(!next_permutation(&mut items));
+        assert_eq!(items, vec![1, 2, 3]);
+        assert!(!prev_permutation(&mut items));
+        assert_eq!(items, vec![3, 2, 1]);
+        assert!(prev_permutation(&mut items));
+        assert_eq!(items, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn rank_and_unrank() {
+        let mut position = 0;
+        Lexicographic::new((0..6).collect()).for_each(|p| {
+            assert_eq!(rank(p), Some(position));
+            assert_eq!(unrank((0..6).collect(), position).unwrap(), p);
+            position += 1;
+        });
+        assert_eq!(unrank(vec![0, 1, 2], 6), None);
+
+        // The millionth permutation of the ten digits (Project Euler 24).
+        let digits: Vec<u8> = (0..10).collect();
+        let millionth = unrank(digits.clone(), 999_999).unwrap();
+        assert_eq!(millionth, vec![2, 7, 8, 3, 9, 1, 5, 4, 6, 0]);
+        let mut from = Lexicographic::from_rank(digits, 999_999).unwrap();
+        assert_eq!(from.next(), Some(&millionth[..]));
+        assert_eq!(from.count(), 3_628_800 - 1_000_000);
+
+        // Past the factorials a u128 can hold.
+        let many: Vec<u32> = (0..40).collect();
+        let p = unrank(many.clone(), u128::MAX).unwrap();
+        assert_eq!(rank(&p), Some(u128::MAX));
+        assert_eq!(&p[..5], &many[..5]);
+
+        // The last permutation of 34 items fits, the one of 35 doesn't.
+        let last: Vec<u32> = (0..34).rev().collect();
+        assert_eq!(rank(&last), Some(factorial(34).unwrap() - 1));
+        let last: Vec<u32> = (0..35).rev().collect();
+        assert_eq!(rank(&last), None);
+        let mut late = many.clone();
+        late.swap(0, 1);
+        assert_eq!(rank(&late), None);
+    }
+
+    #[test]
+    fn combinations() {
+        assert_eq!(
+            Combinations::new(vec!['a', 'b', 'c', 'd'], 2).to_vecs(),
+            vec![
+                vec!['a', 'b'],
+                vec!['a', 'c'],
+                vec!['a', 'd'],
+                vec!['b', 'c'],
+                vec!['b', 'd'],
+                vec!['c', 'd'],
+            ]
+        );
+        assert_eq!(Combinations::new((0..10).collect(), 4).count(), 210);
+        assert_eq!(Combinations::new(vec![1, 2], 0).to_vecs(), vec![vec![]]);
+        assert_eq!(Combinations::new(vec![1, 2], 3).count(), 0);
+
+        assert_eq!(
+            Multisets::new(vec![1, 2, 3], 2).to_vecs(),
+            vec![
+                vec![1, 1],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 2],
+                vec![2, 3],
+                vec![3, 3]
+            ]
+        );
+        // C(n + k - 1, k)
+        assert_eq!(Multisets::new((0..5).collect(), 3).count(), 35);
+        assert_eq!(Multisets::new(Vec::<u8>::new(), 2).count(), 0);
+        assert_eq!(Multisets::new(Vec::<u8>::new(), 0).count(), 1);
+    }
+
+    #[test]
+    fn product_and_power_set() {
+        assert_eq!(
+            Product::new(vec![vec![1, 2], vec![3], vec![4, 5]]).to_vecs(),
+            vec![vec![1, 3, 4], vec![1, 3, 5], vec![2, 3, 4], vec![2, 3, 5]]
+        );
+        assert_eq!(Product::repeat(vec![0, 1], 10).count(), 1024);
+        assert_eq!(Product::new(vec![vec![1], vec![]]).count(), 0);
+        assert_eq!(Product::<u8>::new(vec![]).to_vecs(), vec![vec![]]);
+
+        assert_eq!(
+            PowerSet::new(vec![1, 2, 3]).to_vecs(),
+            vec![
+                vec![],
+                vec![1],
+                vec![2],
+                vec![3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 3],
+                vec![1, 2, 3],
+            ]
+        );
+        assert_eq!(PowerSet::new((0..12).collect()).count(), 4096);
+        assert_eq!(PowerSet::new(Vec::<u8>::new()).count(), 1);
+    }
+}
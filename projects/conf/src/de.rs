@@ -0,0 +1,438 @@
+// Serde deserialization: a struct from the keys before the first section,
+// with a nested struct for every section. Keys match the fields whatever
+// their case, and the keys no field asks for are ignored.
+
+use super::parse::{Entry, Value};
+use super::value::FromEntry;
+use super::{Config, Error, Section};
+use serde::de::value::{StrDeserializer, StringDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use std::fmt::Display;
+use std::slice;
+
+impl de::Error for Error {
+    fn custom<T: Display>(message: T) -> Error {
+        Error::Value {
+            key: String::new(),
+            line: None,
+            column: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Parse a configuration and deserialize it.
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, Error> {
+    text.parse::<Config>()?.deserialize()
+}
+
+impl Config {
+    /// Deserialize the whole configuration: the fields of `T` are the keys
+    /// before the first section, or the sections themselves.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        T::deserialize(Sections {
+            config: self,
+            section: self.root(),
+        })
+    }
+}
+
+// What a field is read from.
+enum Item<'c> {
+    // A key, or None if it's not there.
+    Entry(Option<&'c Entry>),
+    Section(Section<'c>),
+}
+
+struct Sections<'c> {
+    config: &'c Config,
+    section: Section<'c>,
+}
+
+impl<'c> Sections<'c> {
+    fn item(&self, key: &str) -> Item<'c> {
+        if let Some(entry) = self.section.entry(key) {
+            return Item::Entry(Some(entry));
+        }
+        // Only the root has sections in it.
+        match self.section.name() {
+            None => match self.config.section(key) {
+                Some(section) => Item::Section(section),
+                None => Item::Entry(None),
+            },
+            Some(_) => Item::Entry(None),
+        }
+    }
+
+    fn fields<V: Visitor<'c>>(self, keys: Vec<String>, visitor: V) -> Result<V::Value, Error> {
+        let items = keys
+            .into_iter()
+            .map(|key| {
+                let item = self.item(&key);
+                (key, item)
+            })
+            .collect::<Vec<_>>();
+        visitor.visit_map(Fields {
+            config: self.config,
+            items: items.into_iter(),
+            next: None,
+        })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Sections<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut keys: Vec<String> = self.section.keys().into_iter().map(String::from).collect();
+        if self.section.name().is_none() {
+            keys.extend(self.config.sections().into_iter().map(String::from));
+        }
+        self.fields(keys, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.fields(fields.iter().map(|f| f.to_string()).collect(), visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct enum
+        identifier ignored_any
+    }
+}
+
+struct Fields<'c> {
+    config: &'c Config,
+    items: ::std::vec::IntoIter<(String, Item<'c>)>,
+    next: Option<(String, Item<'c>)>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.items.next() {
+            Some((key, item)) => {
+                let name: StrDeserializer<Error> = key.as_str().into_deserializer();
+                let field = seed.deserialize(name)?;
+                self.next = Some((key, item));
+                Ok(Some(field))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, item) = self.next.take().expect("a value before its key");
+        match item {
+            Item::Section(section) => seed.deserialize(Sections {
+                config: self.config,
+                section,
+            }),
+            Item::Entry(entry) => {
+                let values = entry.map(|e| &e.values[..]);
+                seed.deserialize(Values { values }).map_err(|e| match e {
+                    // Errors of serde itself, or of the struct, don't know
+                    // which key they are about.
+                    Error::Value {
+                        key: ref k,
+                        line,
+                        column,
+                        ref message,
+                    } => Error::Value {
+                        key: if k.is_empty() { key.clone() } else { k.clone() },
+                        line: line.or_else(|| entry.map(|e| e.line)),
+                        column,
+                        message: message.clone(),
+                    },
+                    e => e,
+                })
+            }
+        }
+    }
+}
+
+// The values of a key: None if it's not there.
+struct Values<'c> {
+    values: Option<&'c [Value]>,
+}
+
+impl<'c> Values<'c> {
+    fn get<T: FromEntry>(&self) -> Result<T, Error> {
+        T::from_entry(self.values).map_err(|invalid| Error::Value {
+            key: String::new(),
+            line: None,
+            column: invalid.column,
+            message: invalid.message,
+        })
+    }
+}
+
+macro_rules! deserialize {
+    ($($method:ident $visit:ident $t:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.get::<$t>()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Values<'de> {
+    type Error = Error;
+
+    // A flag, a value or a list of them.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.values {
+            None => visitor.visit_none(),
+            Some([]) => visitor.visit_bool(true),
+            Some([value]) => visitor.visit_str(&value.text),
+            Some(_) => self.deserialize_seq(visitor),
+        }
+    }
+
+    deserialize! {
+        deserialize_bool visit_bool bool,
+        deserialize_i8 visit_i8 i8,
+        deserialize_i16 visit_i16 i16,
+        deserialize_i32 visit_i32 i32,
+        deserialize_i64 visit_i64 i64,
+        deserialize_u8 visit_u8 u8,
+        deserialize_u16 visit_u16 u16,
+        deserialize_u32 visit_u32 u32,
+        deserialize_u64 visit_u64 u64,
+        deserialize_f32 visit_f32 f32,
+        deserialize_f64 visit_f64 f64,
+        deserialize_char visit_char char,
+        deserialize_str visit_string String,
+        deserialize_string visit_string String
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.values {
+            None => visitor.visit_none(),
+            Some(_) => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Every value is an element.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Elements {
+            values: self.values.unwrap_or(&[]).iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    // The variants with no data, by name.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let variant = self.get::<String>()?;
+        let value = &self.values.unwrap_or(&[])[0];
+        let variant: StringDeserializer<Error> = variant.into_deserializer();
+        visitor.visit_enum(variant).map_err(|e| at(e, value))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(de::Error::custom(match self.values {
+            None => "missing",
+            Some(_) => "expected a section, found a key",
+        }))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit_struct identifier
+    }
+}
+
+// The column of the value, for the errors that don't have one.
+fn at(error: Error, value: &Value) -> Error {
+    match error {
+        Error::Value {
+            key,
+            line,
+            column: None,
+            message,
+        } => Error::Value {
+            key,
+            line,
+            column: Some(value.column),
+            message,
+        },
+        e => e,
+    }
+}
+
+struct Elements<'c> {
+    values: slice::Iter<'c, Value>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.values.next() {
+            Some(value) => {
+                let values = Some(slice::from_ref(value));
+                seed.deserialize(Values { values })
+                    .map(Some)
+                    .map_err(|e| at(e, value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::from_str;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Day {
+        Mon,
+        Tue,
+        Fri,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Shop {
+        opens: u8,
+        days: Vec<Day>,
+        motto: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[allow(non_snake_case)]
+    struct File {
+        fullname: String,
+        FavouriteFruit: String,
+        needspeeling: bool,
+        seedsremoved: bool,
+        otherfamily: Vec<String>,
+        shop: Shop,
+        warehouse: Option<Shop>,
+    }
+
+    #[test]
+    fn into_structs() {
+        let file: File = from_str(
+            "FULLNAME Foo Barber\n\
+             FAVOURITEFRUIT banana\n\
+             NEEDSPEELING\n\
+             OTHERFAMILY Rhu Barber, Harry Barber\n\
+             IGNORED\n\
+             [SHOP]\n\
+             opens 9\n\
+             days Mon, Fri\n",
+        )
+        .unwrap();
+        assert_eq!(
+            file,
+            File {
+                fullname: String::from("Foo Barber"),
+                FavouriteFruit: String::from("banana"),
+                needspeeling: true,
+                seedsremoved: false,
+                otherfamily: vec![String::from("Rhu Barber"), String::from("Harry Barber")],
+                shop: Shop {
+                    opens: 9,
+                    days: vec![Day::Mon, Day::Fri],
+                    motto: None,
+                },
+                warehouse: None,
+            }
+        );
+    }
+
+    #[test]
+    fn errors_know_the_key() {
+        let error = |text: &str| from_str::<Shop>(text).unwrap_err().to_string();
+        assert_eq!(error("days Mon\n"), "opens: missing");
+        assert_eq!(
+            error("opens 9\ndays Mon, Sun\n"),
+            "line 2, column 11: days: unknown variant `Sun`, expected one of `Mon`, `Tue`, `Fri`"
+        );
+        assert_eq!(
+            error("days Tue\nopens 9am\n"),
+            "line 2, column 7: opens: invalid value '9am': invalid digit found in string"
+        );
+    }
+}
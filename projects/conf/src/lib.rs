@@ -0,0 +1,430 @@
+//! # Configuration files
+//!
+//! The format of the Rosetta Code task "Read a configuration file", the one
+//! of `file.conf`, with the sections of INI files:
+//!
+//! ```text
+//! # A comment, and so is this:
+//! ; SEEDSREMOVED
+//! FULLNAME Foo Barber
+//! FAVOURITEFRUIT = banana    # an optional '=', and a comment
+//! NEEDSPEELING
+//! OTHERFAMILY Rhu Barber, Harry Barber
+//!
+//! [shop]
+//! motto = "fresh, and cheap"
+//! ```
+//!
+//! - keys are case insensitive, values aren't;
+//! - a key with no value is a flag: `get::<bool>` is true if it's there;
+//! - commas separate the values of a key, quotes keep them together;
+//! - a `#` or a `;` after a space starts a comment.
+//!
+//! `get` returns whatever implements `FromEntry`: `bool`, `String`, the
+//! numbers, `Vec<T>` for the values of a list, and `Option<T>` for keys that
+//! may not be there. With the "serde" feature, a whole file can also be
+//! deserialized into a struct, its sections into nested structs.
+//!
+//! A `Config` keeps every line, comments included: changing a value rewrites
+//! only the line of that value, and writing the configuration back (with
+//! Display) gives the same file with that line changed.
+//!
+//! - http://rosettacode.org/wiki/Read_a_configuration_file
+//! - https://en.wikipedia.org/wiki/INI_file
+
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+
+#[cfg(feature = "serde")]
+mod de;
+mod parse;
+mod value;
+
+#[cfg(feature = "serde")]
+pub use self::de::from_str;
+pub use self::parse::{parse_line, Entry, Line, Lines, Value};
+pub use self::value::{FromEntry, Invalid};
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Where, counted from 1, and what's wrong.
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A key that is missing, or whose values don't fit the type asked for.
+    Value {
+        key: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Syntax {
+                line,
+                column,
+                ref message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            Error::Value {
+                ref key,
+                line,
+                column,
+                ref message,
+            } => {
+                match (line, column) {
+                    (Some(line), Some(column)) => write!(f, "line {}, column {}: ", line, column)?,
+                    (Some(line), None) => write!(f, "line {}: ", line)?,
+                    _ => {}
+                }
+                if key.is_empty() {
+                    write!(f, "{}", message)
+                } else {
+                    write!(f, "{}: {}", key, message)
+                }
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a == b || a.to_lowercase() == b.to_lowercase()
+}
+
+/// The lines of a configuration file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    lines: Vec<Line>,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Stops at the first error.
+    pub fn read<R: BufRead>(reader: R) -> Result<Config, Error> {
+        let lines = Lines::new(reader).collect::<Result<_, _>>()?;
+        Ok(Config { lines })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        Config::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// The keys before the first section.
+    pub fn root<'c>(&'c self) -> Section<'c> {
+        Section {
+            config: self,
+            name: None,
+        }
+    }
+
+    pub fn section<'c>(&'c self, name: &str) -> Option<Section<'c>> {
+        self.sections()
+            .into_iter()
+            .find(|n| same(n, name))
+            .map(|name| Section {
+                config: self,
+                name: Some(name),
+            })
+    }
+
+    /// The names of the sections, as first written, in order.
+    pub fn sections(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for line in &self.lines {
+            if let Line::Section { ref name, .. } = *line {
+                if !names.iter().any(|n| same(n, name)) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// A key before the first section.
+    pub fn get<T: FromEntry>(&self, key: &str) -> Result<T, Error> {
+        self.root().get(key)
+    }
+
+    /// Set the values of a key, in a section or before the first one: the
+    /// key (and the section) is added if it isn't there.
+    pub fn set(&mut self, section: Option<&str>, key: &str, values: &[&str]) {
+        if let Some(&(i, _)) = self
+            .entries(section)
+            .iter()
+            .rev()
+            .find(|e| same(&e.1.key, key))
+        {
+            if let Line::Entry(ref mut entry) = self.lines[i] {
+                entry.set(values);
+            }
+            return;
+        }
+        let entry = Line::Entry(Entry::new(key, values));
+        // After the last entry of the section, or its header.
+        let after = self
+            .entries(section)
+            .last()
+            .map(|&(i, _)| i + 1)
+            .or_else(|| {
+                let name = section?;
+                self.lines
+                    .iter()
+                    .rposition(|line| match *line {
+                        Line::Section { name: ref n, .. } => same(n, name),
+                        _ => false,
+                    })
+                    .map(|i| i + 1)
+            });
+        match (after, section) {
+            (Some(i), _) => self.lines.insert(i, entry),
+            (None, None) => self.lines.insert(0, entry),
+            (None, Some(name)) => {
+                if let Some(&Line::Entry(_)) = self.lines.last() {
+                    self.lines.push(Line::Other(String::new()));
+                }
+                self.lines.push(Line::Section {
+                    name: name.to_string(),
+                    raw: format!("[{}]", name),
+                });
+                self.lines.push(entry);
+            }
+        }
+    }
+
+    /// Remove a key, true if it was there.
+    pub fn remove(&mut self, section: Option<&str>, key: &str) -> bool {
+        let found: Vec<usize> = self
+            .entries(section)
+            .iter()
+            .filter(|e| same(&e.1.key, key))
+            .map(|e| e.0)
+            .collect();
+        for &i in found.iter().rev() {
+            self.lines.remove(i);
+        }
+        !found.is_empty()
+    }
+
+    // The entries of a section, or before the first one, and their index.
+    fn entries(&self, section: Option<&str>) -> Vec<(usize, &Entry)> {
+        let mut current = None;
+        let mut entries = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            match *line {
+                Line::Section { ref name, .. } => current = Some(name.as_str()),
+                Line::Entry(ref entry) => {
+                    let inside = match (current, section) {
+                        (None, None) => true,
+                        (Some(a), Some(b)) => same(a, b),
+                        _ => false,
+                    };
+                    if inside {
+                        entries.push((i, entry));
+                    }
+                }
+                Line::Other(_) => {}
+            }
+        }
+        entries
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Config, Error> {
+        Config::read(text.as_bytes())
+    }
+}
+
+/// The file as it was read, but for the changed values.
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            match *line {
+                Line::Other(ref text) => writeln!(f, "{}", text)?,
+                Line::Section { ref raw, .. } => writeln!(f, "{}", raw)?,
+                Line::Entry(ref entry) => writeln!(f, "{}", entry.to_line())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The keys of a section, or the ones before the first section.
+#[derive(Debug, Clone, Copy)]
+pub struct Section<'c> {
+    config: &'c Config,
+    name: Option<&'c str>,
+}
+
+impl<'c> Section<'c> {
+    /// None before the first section.
+    pub fn name(&self) -> Option<&'c str> {
+        self.name
+    }
+
+    /// The keys, as first written, in order.
+    pub fn keys(&self) -> Vec<&'c str> {
+        let mut keys: Vec<&str> = Vec::new();
+        for (_, entry) in self.config.entries(self.name) {
+            if !keys.iter().any(|k| same(k, &entry.key)) {
+                keys.push(&entry.key);
+            }
+        }
+        keys
+    }
+
+    /// The last entry of the key, if it's there more than once.
+    pub fn entry(&self, key: &str) -> Option<&'c Entry> {
+        self.config
+            .entries(self.name)
+            .into_iter()
+            .rev()
+            .find(|e| same(&e.1.key, key))
+            .map(|e| e.1)
+    }
+
+    pub fn get<T: FromEntry>(&self, key: &str) -> Result<T, Error> {
+        let entry = self.entry(key);
+        T::from_entry(entry.map(|e| &e.values[..])).map_err(|invalid| Error::Value {
+            key: key.to_string(),
+            line: entry.map(|e| e.line),
+            column: invalid.column,
+            message: invalid.message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+# Rosetta Code
+FULLNAME Foo Barber
+favouritefruit = banana   # a comment
+NEEDSPEELING
+; SEEDSREMOVED
+OTHERFAMILY Rhu Barber, Harry Barber
+
+[Shop]
+opens = 9
+days = mon, tue, fri
+";
+
+    #[test]
+    fn typed_getters() {
+        let config: Config = FILE.parse().unwrap();
+        assert_eq!(config.get::<String>("fullname").unwrap(), "Foo Barber");
+        assert_eq!(config.get::<String>("FAVOURITEFRUIT").unwrap(), "banana");
+        assert!(config.get::<bool>("needspeeling").unwrap());
+        assert!(!config.get::<bool>("seedsremoved").unwrap());
+        assert_eq!(
+            config.get::<Vec<String>>("otherfamily").unwrap(),
+            vec!["Rhu Barber", "Harry Barber"]
+        );
+        assert_eq!(config.get::<Option<u32>>("opens").unwrap(), None);
+
+        assert_eq!(config.sections(), vec!["Shop"]);
+        let shop = config.section("shop").unwrap();
+        assert_eq!(shop.name(), Some("Shop"));
+        assert_eq!(shop.keys(), vec!["opens", "days"]);
+        assert_eq!(shop.get::<u8>("opens").unwrap(), 9);
+
+        let error = |e: Error| e.to_string();
+        assert_eq!(
+            error(config.get::<u32>("fullname").unwrap_err()),
+            "line 2, column 10: fullname: invalid value 'Foo Barber': invalid digit found in string"
+        );
+        assert_eq!(
+            error(shop.get::<String>("days").unwrap_err()),
+            "line 10, column 13: days: expected one value, found 3"
+        );
+        assert_eq!(
+            error(shop.get::<bool>("opens").unwrap_err()),
+            "line 9, column 9: opens: expected a boolean, found '9'"
+        );
+        assert_eq!(
+            error(config.get::<f64>("needspeeling").unwrap_err()),
+            "line 4: needspeeling: expected a value"
+        );
+        assert_eq!(
+            error(config.get::<String>("colour").unwrap_err()),
+            "colour: missing"
+        );
+    }
+
+    #[test]
+    fn syntax_errors_stop_the_reading() {
+        let error = "a = 1\n[b\nc = 2\n".parse::<Config>().unwrap_err();
+        assert_eq!(error.to_string(), "line 2, column 3: expected ']'");
+    }
+
+    #[test]
+    fn writes_back_the_comments() {
+        let mut config: Config = FILE.parse().unwrap();
+        assert_eq!(config.to_string(), FILE);
+
+        config.set(None, "FavouriteFruit", &["kiwi"]);
+        config.set(None, "seedsremoved", &[]);
+        config.set(Some("shop"), "days", &["sat", "sun, maybe"]);
+        config.set(Some("shop"), "closes", &["19"]);
+        config.set(Some("warehouse"), "open", &["no"]);
+        assert!(config.remove(None, "NeedsPeeling"));
+        assert!(!config.remove(Some("shop"), "needspeeling"));
+        assert_eq!(
+            config.to_string(),
+            "\
+# Rosetta Code
+FULLNAME Foo Barber
+favouritefruit = kiwi   # a comment
+; SEEDSREMOVED
+OTHERFAMILY Rhu Barber, Harry Barber
+seedsremoved
+
+[Shop]
+opens = 9
+days = sat, \"sun, maybe\"
+closes = 19
+
+[warehouse]
+open = no
+"
+        );
+        let again: Config = config.to_string().parse().unwrap();
+        assert_eq!(again.to_string(), config.to_string());
+    }
+}
@@ -0,0 +1,423 @@
+// One line at a time: a reader of lines is all the parser needs, so a file
+// doesn't have to be read in memory before its first line is seen.
+
+use super::Error;
+use std::io::BufRead;
+
+/// A line of a configuration file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    /// Blank lines and comments, kept as they are.
+    Other(String),
+    /// `[name]`, maybe followed by a comment.
+    Section {
+        name: String,
+        raw: String,
+    },
+    Entry(Entry),
+}
+
+/// A key, and its values: none for a flag like `NEEDSPEELING`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub values: Vec<Value>,
+    /// Counted from 1.
+    pub line: usize,
+    // What separates the key from the values, as written: " " or " = ".
+    separator: String,
+    // The comment at the end of the line, with the spaces before it.
+    comment: String,
+    // The line as it was read, until the entry is changed.
+    raw: Option<String>,
+}
+
+/// A value, and the column (counted from 1) where it starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub text: String,
+    pub column: usize,
+}
+
+impl Entry {
+    pub(crate) fn new(key: &str, values: &[&str]) -> Entry {
+        let mut entry = Entry {
+            key: key.to_string(),
+            values: Vec::new(),
+            line: 0,
+            separator: String::from(" = "),
+            comment: String::new(),
+            raw: None,
+        };
+        entry.set(values);
+        entry
+    }
+
+    pub(crate) fn set(&mut self, values: &[&str]) {
+        self.values = values
+            .iter()
+            .map(|v| Value {
+                text: v.to_string(),
+                column: 0,
+            })
+            .collect();
+        self.raw = None;
+    }
+
+    /// The line as it's written back: as it was read, unless it changed.
+    pub fn to_line(&self) -> String {
+        if let Some(ref raw) = self.raw {
+            return raw.clone();
+        }
+        let values: Vec<_> = self.values.iter().map(|v| quote(&v.text)).collect();
+        if values.is_empty() {
+            format!("{}{}", self.key, self.comment)
+        } else {
+            format!(
+                "{}{}{}{}",
+                self.key,
+                self.separator,
+                values.join(", "),
+                self.comment
+            )
+        }
+    }
+}
+
+// Quoted if it wouldn't read back the same otherwise.
+fn quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text.trim() == text
+        && !text.starts_with('"')
+        && !text.contains([',', '#', ';']);
+    if plain {
+        text.to_string()
+    } else {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn is_comment(c: char) -> bool {
+    c == '#' || c == ';'
+}
+
+// The characters of a line, with their column.
+struct Cursor<'a> {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    text: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn column(&self) -> usize {
+        self.position + 1
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    // The rest of the line, from the current position.
+    fn rest(&self) -> String {
+        self.chars[self.position..].iter().collect()
+    }
+
+    fn error<T>(&self, column: usize, message: String) -> Result<T, Error> {
+        Err(Error::Syntax {
+            line: self.line,
+            column,
+            message,
+        })
+    }
+}
+
+/// Parse a line of text, the number-th of its file.
+pub fn parse_line(text: &str, number: usize) -> Result<Line, Error> {
+    let mut cursor = Cursor {
+        chars: text.chars().collect(),
+        position: 0,
+        line: number,
+        text,
+    };
+    cursor.skip_whitespace();
+    match cursor.peek() {
+        None => Ok(Line::Other(text.to_string())),
+        Some(c) if is_comment(c) => Ok(Line::Other(text.to_string())),
+        Some('[') => section(&mut cursor),
+        Some(_) => entry(&mut cursor).map(Line::Entry),
+    }
+}
+
+fn section(cursor: &mut Cursor) -> Result<Line, Error> {
+    let open = cursor.column();
+    cursor.position += 1;
+    let start = cursor.position;
+    while cursor.peek().is_some_and(|c| c != ']') {
+        cursor.position += 1;
+    }
+    if cursor.peek().is_none() {
+        return cursor.error(cursor.column(), String::from("expected ']'"));
+    }
+    let name: String = cursor.chars[start..cursor.position].iter().collect();
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return cursor.error(open, String::from("empty section name"));
+    }
+    cursor.position += 1;
+    trailing_comment(cursor, "the section")?;
+    Ok(Line::Section {
+        name,
+        raw: cursor.text.to_string(),
+    })
+}
+
+// Only a comment can follow.
+fn trailing_comment(cursor: &mut Cursor, after: &str) -> Result<String, Error> {
+    let start = cursor.position;
+    cursor.skip_whitespace();
+    match cursor.peek() {
+        None => Ok(String::new()),
+        Some(c) if is_comment(c) => {
+            cursor.position = start;
+            Ok(cursor.rest())
+        }
+        Some(c) => cursor.error(
+            cursor.column(),
+            format!("unexpected '{}' after {}", c, after),
+        ),
+    }
+}
+
+fn entry(cursor: &mut Cursor) -> Result<Entry, Error> {
+    let start = cursor.position;
+    while let Some(c) = cursor.peek() {
+        if c.is_whitespace() || c == '=' {
+            break;
+        }
+        if !is_key_char(c) {
+            return cursor.error(
+                cursor.column(),
+                format!("invalid character '{}' in a key", c),
+            );
+        }
+        cursor.position += 1;
+    }
+    if cursor.position == start {
+        return cursor.error(cursor.column(), String::from("expected a key"));
+    }
+    let key: String = cursor.chars[start..cursor.position].iter().collect();
+
+    let separator_start = cursor.position;
+    cursor.skip_whitespace();
+    if cursor.peek() == Some('=') {
+        cursor.position += 1;
+        cursor.skip_whitespace();
+    }
+    let separator: String = cursor.chars[separator_start..cursor.position]
+        .iter()
+        .collect();
+
+    let mut values = Vec::new();
+    let comment = match cursor.peek() {
+        Some(c) if !is_comment(c) => loop {
+            values.push(value(cursor)?);
+            // After a value: a comma and the next one, or the end.
+            let end = cursor.position;
+            cursor.skip_whitespace();
+            if cursor.peek() == Some(',') {
+                cursor.position += 1;
+                cursor.skip_whitespace();
+                continue;
+            }
+            cursor.position = end;
+            break trailing_comment(cursor, "the value")?;
+        },
+        _ if separator.contains('=') => {
+            return cursor.error(cursor.column(), String::from("expected a value after '='"));
+        }
+        _ => {
+            cursor.position = separator_start;
+            trailing_comment(cursor, "the key")?
+        }
+    };
+    Ok(Entry {
+        key,
+        values,
+        line: cursor.line,
+        separator,
+        comment,
+        raw: Some(cursor.text.to_string()),
+    })
+}
+
+// A value, plain or quoted, up to a comma, a comment or the end of the line.
+fn value(cursor: &mut Cursor) -> Result<Value, Error> {
+    let column = cursor.column();
+    match cursor.peek() {
+        Some('"') => {
+            cursor.position += 1;
+            let mut text = String::new();
+            loop {
+                match cursor.peek() {
+                    None => return cursor.error(column, String::from("unterminated quote")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        cursor.position += 1;
+                        match cursor.peek() {
+                            Some(c) if c == '"' || c == '\\' => text.push(c),
+                            _ => {
+                                let message = String::from("expected '\"' or '\\' after '\\'");
+                                return cursor.error(cursor.column(), message);
+                            }
+                        }
+                    }
+                    Some(c) => text.push(c),
+                }
+                cursor.position += 1;
+            }
+            cursor.position += 1;
+            Ok(Value { text, column })
+        }
+        Some(',') | None => cursor.error(column, String::from("empty value")),
+        Some(_) => {
+            let start = cursor.position;
+            while let Some(c) = cursor.peek() {
+                // A comment starts after a space: "a#b" is a value.
+                let after_space =
+                    cursor.position > start && cursor.chars[cursor.position - 1].is_whitespace();
+                if c == ',' || (is_comment(c) && after_space) {
+                    break;
+                }
+                cursor.position += 1;
+            }
+            let text: String = cursor.chars[start..cursor.position].iter().collect();
+            // Leave the spaces before a comment to the comment.
+            let trimmed = text.trim_end();
+            cursor.position = start + trimmed.chars().count();
+            Ok(Value {
+                text: trimmed.to_string(),
+                column,
+            })
+        }
+    }
+}
+
+/// The lines of a reader, parsed as they're read.
+pub struct Lines<R> {
+    reader: R,
+    number: usize,
+}
+
+impl<R: BufRead> Lines<R> {
+    pub fn new(reader: R) -> Lines<R> {
+        Lines { reader, number: 0 }
+    }
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = Result<Line, Error>;
+
+    fn next(&mut self) -> Option<Result<Line, Error>> {
+        let mut text = String::new();
+        match self.reader.read_line(&mut text) {
+            Ok(0) => None,
+            Ok(_) => {
+                self.number += 1;
+                let text = text.trim_end_matches(['\n', '\r']);
+                Some(parse_line(text, self.number))
+            }
+            Err(e) => Some(Err(Error::Io(e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(line: &str) -> Vec<(String, usize)> {
+        match parse_line(line, 1).unwrap() {
+            Line::Entry(e) => e.values.into_iter().map(|v| (v.text, v.column)).collect(),
+            other => panic!("not an entry: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn entries() {
+        let v = |text: &str, column| (text.to_string(), column);
+        assert_eq!(values("FULLNAME Foo Barber"), vec![v("Foo Barber", 10)]);
+        assert_eq!(values("SOME = options"), vec![v("options", 8)]);
+        assert_eq!(values("OTHER option # with comment"), vec![v("option", 7)]);
+        assert_eq!(values("NEEDSPEELING"), vec![]);
+        assert_eq!(values("  flag ; a comment"), vec![]);
+        assert_eq!(values("url=http://x/#top"), vec![v("http://x/#top", 5)]);
+        assert_eq!(
+            values("OTHERFAMILY Rhu Barber, Harry Barber"),
+            vec![v("Rhu Barber", 13), v("Harry Barber", 25)]
+        );
+        assert_eq!(
+            values(r#"motto "a, b; \"c\"" , plain"#),
+            vec![v(r#"a, b; "c""#, 7), v("plain", 23)]
+        );
+    }
+
+    #[test]
+    fn lines_are_written_back() {
+        for line in &[
+            "key = a, b  # why",
+            "[section] ; comment",
+            "  # indented",
+            "",
+        ] {
+            match parse_line(line, 1).unwrap() {
+                Line::Entry(e) => assert_eq!(e.to_line(), *line),
+                Line::Section { raw, .. } => assert_eq!(raw, *line),
+                Line::Other(text) => assert_eq!(text, *line),
+            }
+        }
+        let mut entry = match parse_line("key = a, b  # why", 1).unwrap() {
+            Line::Entry(e) => e,
+            _ => unreachable!(),
+        };
+        entry.set(&["c", "d, e", " f"]);
+        assert_eq!(entry.to_line(), r#"key = c, "d, e", " f"  # why"#);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |line: &str| parse_line(line, 7).unwrap_err().to_string();
+        assert_eq!(error("[fruit"), "line 7, column 7: expected ']'");
+        assert_eq!(error("[ ]"), "line 7, column 1: empty section name");
+        assert_eq!(
+            error("[fruit] x"),
+            "line 7, column 9: unexpected 'x' after the section"
+        );
+        assert_eq!(error("= 3"), "line 7, column 1: expected a key");
+        assert_eq!(
+            error("ke!y 3"),
+            "line 7, column 3: invalid character '!' in a key"
+        );
+        assert_eq!(
+            error("key = # none"),
+            "line 7, column 7: expected a value after '='"
+        );
+        assert_eq!(error("key a,,b"), "line 7, column 7: empty value");
+        assert_eq!(error("key a,"), "line 7, column 7: empty value");
+        assert_eq!(error("key \"abc"), "line 7, column 5: unterminated quote");
+        assert_eq!(
+            error("key \"a\"b"),
+            "line 7, column 8: unexpected 'b' after the value"
+        );
+    }
+}
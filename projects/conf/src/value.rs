@@ -0,0 +1,107 @@
+// From the values of a key to the types `get` returns.
+
+use super::parse::Value;
+use std::str::FromStr;
+
+/// Why the values of a key don't make a `T`, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invalid {
+    /// Of the value at fault, if there's one.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Invalid {
+    fn new(value: Option<&Value>, message: String) -> Invalid {
+        Invalid {
+            column: value.map(|v| v.column),
+            message,
+        }
+    }
+}
+
+/// What `get` can return.
+pub trait FromEntry: Sized {
+    /// The values of the key: None if there's no such key, and none at all
+    /// for a flag.
+    fn from_entry(values: Option<&[Value]>) -> Result<Self, Invalid>;
+}
+
+// Exactly one value.
+pub(crate) fn single(values: Option<&[Value]>) -> Result<&Value, Invalid> {
+    match values {
+        None => Err(Invalid::new(None, String::from("missing"))),
+        Some([]) => Err(Invalid::new(None, String::from("expected a value"))),
+        Some([value]) => Ok(value),
+        Some(values) => {
+            let message = format!("expected one value, found {}", values.len());
+            Err(Invalid::new(values.get(1), message))
+        }
+    }
+}
+
+pub(crate) fn parse<T: FromStr>(value: &Value) -> Result<T, Invalid>
+where
+    T::Err: ToString,
+{
+    value.text.parse().map_err(|e: T::Err| {
+        let message = format!("invalid value '{}': {}", value.text, e.to_string());
+        Invalid::new(Some(value), message)
+    })
+}
+
+/// A flag: false if the key isn't there, true if it's there with no value,
+/// or the value itself: true, yes, on or 1, false, no, off or 0.
+impl FromEntry for bool {
+    fn from_entry(values: Option<&[Value]>) -> Result<bool, Invalid> {
+        match values {
+            None => Ok(false),
+            Some([]) => Ok(true),
+            _ => {
+                let value = single(values)?;
+                match value.text.to_lowercase().as_str() {
+                    "true" | "yes" | "on" | "1" => Ok(true),
+                    "false" | "no" | "off" | "0" => Ok(false),
+                    _ => {
+                        let message = format!("expected a boolean, found '{}'", value.text);
+                        Err(Invalid::new(Some(value), message))
+                    }
+                }
+            }
+        }
+    }
+}
+
+macro_rules! from_str {
+    ($($t:ty),*) => {
+        $(
+            impl FromEntry for $t {
+                fn from_entry(values: Option<&[Value]>) -> Result<$t, Invalid> {
+                    parse(single(values)?)
+                }
+            }
+        )*
+    };
+}
+
+from_str!(String, char, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+/// Every value, none if the key isn't there.
+impl<T: FromStr> FromEntry for Vec<T>
+where
+    T::Err: ToString,
+{
+    fn from_entry(values: Option<&[Value]>) -> Result<Vec<T>, Invalid> {
+        values.unwrap_or(&[]).iter().map(parse).collect()
+    }
+}
+
+/// None if the key isn't there.
+impl<T: FromEntry> FromEntry for Option<T> {
+    fn from_entry(values: Option<&[Value]>) -> Result<Option<T>, Invalid> {
+        match values {
+            None => Ok(None),
+            Some(values) => T::from_entry(Some(values)).map(Some),
+        }
+    }
+}
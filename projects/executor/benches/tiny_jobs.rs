@@ -0,0 +1,68 @@
+// Many tiny jobs: the cost of a job is all in the queue.
+//
+//     cargo bench
+//
+// Note that the workers of hello::ThreadPool print a line for every job they
+// pick up, and that's part of what is measured.
+
+#[macro_use]
+extern crate criterion;
+extern crate executor;
+extern crate hello;
+
+use criterion::Criterion;
+use executor::Executor;
+use hello::ThreadPool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const WORKERS: usize = 4;
+const JOBS: usize = 1000;
+
+fn tiny_jobs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("1000 tiny jobs");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(3));
+
+    let pool = Executor::new(WORKERS);
+    group.bench_function("executor, handles", |b| {
+        b.iter(|| {
+            let handles: Vec<_> = (0..JOBS).map(|i| pool.execute(move || i * 2)).collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("executor, scope", |b| {
+        b.iter(|| {
+            let total = AtomicUsize::new(0);
+            pool.scope(|s| {
+                for i in 0..JOBS {
+                    let total = &total;
+                    s.execute(move || total.fetch_add(i * 2, Ordering::Relaxed));
+                }
+            });
+            total.into_inner()
+        })
+    });
+
+    // The book's pool has no results: every job sends its own on a channel.
+    let pool = ThreadPool::new(WORKERS);
+    group.bench_function("hello::ThreadPool", |b| {
+        b.iter(|| {
+            let (sender, receiver) = mpsc::channel();
+            for i in 0..JOBS {
+                let sender = sender.clone();
+                pool.execute(move || sender.send(i * 2).unwrap());
+            }
+            receiver.iter().take(JOBS).sum::<usize>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, tiny_jobs);
+criterion_main!(benches);
@@ -0,0 +1,107 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+// What the workers run: the task, wrapped so that its result (or its panic)
+// reaches the JoinHandle. It returns true if the task panicked.
+pub(crate) type Job<'a> = Box<dyn FnOnce() -> bool + Send + 'a>;
+
+pub(crate) fn task<'a, F, T>(f: F) -> (Job<'a>, JoinHandle<T>)
+where
+    F: FnOnce() -> T + Send + 'a,
+    T: Send + 'a,
+{
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let job = move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        let panicked = result.is_err();
+        // Nobody is waiting if the handle has been dropped, that's fine.
+        let _ = sender.send(result);
+        panicked
+    };
+    (Box::new(job), JoinHandle { result: receiver })
+}
+
+/// An owned permission to wait for a task and get its result.
+///
+/// Dropping the handle doesn't cancel the task, it just forgets about it.
+pub struct JoinHandle<T> {
+    result: mpsc::Receiver<thread::Result<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Wait for the task to finish.
+    ///
+    /// On a worker of an executor, the queued jobs of that executor run
+    /// meanwhile: a job can join the jobs it executes, even with a single
+    /// worker.
+    pub fn join(self) -> Result<T, JoinError> {
+        let result = loop {
+            match self.result.try_recv() {
+                Err(TryRecvError::Empty) if ::run_queued_job() => {}
+                // Nothing is queued: the task runs elsewhere, if it's not
+                // over already.
+                Err(TryRecvError::Empty) => break self.result.recv().ok(),
+                Err(TryRecvError::Disconnected) => break None,
+                Ok(result) => break Some(result),
+            }
+        };
+        match result {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(payload)) => Err(JoinError::Panicked(payload)),
+            // The job has been dropped without running.
+            None => Err(JoinError::Cancelled),
+        }
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("JoinHandle { .. }")
+    }
+}
+
+/// Why a task has no result.
+#[derive(Debug)]
+pub enum JoinError {
+    /// The task panicked, this is what it panicked with.
+    Panicked(Box<dyn Any + Send + 'static>),
+    /// The executor has been shut down before the task could run.
+    Cancelled,
+}
+
+impl JoinError {
+    pub fn is_panic(&self) -> bool {
+        match *self {
+            JoinError::Panicked(_) => true,
+            JoinError::Cancelled => false,
+        }
+    }
+
+    /// The message of the panic, if the task panicked with one (as `panic!`
+    /// does).
+    pub fn panic_message(&self) -> Option<&str> {
+        match *self {
+            JoinError::Panicked(ref payload) => payload
+                .downcast_ref::<&str>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str())),
+            JoinError::Cancelled => None,
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self, self.panic_message()) {
+            (&JoinError::Panicked(_), Some(message)) => write!(f, "the task panicked: {}", message),
+            (&JoinError::Panicked(_), None) => write!(f, "the task panicked"),
+            (&JoinError::Cancelled, _) => write!(f, "the task has been cancelled"),
+        }
+    }
+}
+
+impl Error for JoinError {}
@@ -0,0 +1,595 @@
+//! # A work stealing executor
+//!
+//! The `ThreadPool` of `projects/hello` (chapter 20 of the book) sends every
+//! job through one channel, and every worker locks the same
+//! `Mutex<Receiver<Message>>` to get the next one: with many tiny jobs the
+//! workers spend more time fighting for the lock than working.
+//!
+//! Here every worker owns a deque of jobs:
+//!
+//! - jobs submitted from outside the pool go to a shared injector queue;
+//! - jobs submitted by a job go to the deque of the worker running it;
+//! - a worker pops jobs from its own deque, and when it's empty it takes a
+//!   batch from the injector, or steals from the deques of the others.
+//!
+//! `execute` returns a `JoinHandle` with the result of the job. A job that
+//! panics doesn't take its worker down: the panic ends up in the handle.
+//! Joining on a worker runs the queued jobs meanwhile, instead of blocking
+//! it: the job waited for may well be queued behind the one waiting.
+//!
+//! - https://docs.rs/crossbeam-deque
+//! - https://en.wikipedia.org/wiki/Work_stealing
+
+extern crate crossbeam_deque;
+
+mod handle;
+mod scope;
+
+pub use handle::{JoinError, JoinHandle};
+pub use scope::Scope;
+
+use crossbeam_deque::{Injector, Stealer, Worker};
+use handle::Job;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // On a worker thread: what it shares with its executor, and its deque.
+    static LOCAL: RefCell<Option<(Arc<Shared>, Worker<Job<'static>>)>> = const { RefCell::new(None) };
+}
+
+// What the workers share with the executor.
+struct Shared {
+    injector: Injector<Job<'static>>,
+    stealers: Vec<Stealer<Job<'static>>>,
+    // Jobs queued and not started yet, wherever they are.
+    pending: AtomicUsize,
+    // Workers waiting on `wake` for a job.
+    sleeping: AtomicUsize,
+    // Run every queued job, then exit.
+    draining: AtomicBool,
+    // Exit as soon as possible, leaving the queued jobs alone.
+    stopping: AtomicBool,
+    // How many workers are still running.
+    alive: Mutex<usize>,
+    wake: Condvar,
+    exited: Condvar,
+    completed: AtomicUsize,
+    panicked: AtomicUsize,
+}
+
+impl Shared {
+    fn find_job(&self, local: &Worker<Job<'static>>) -> Option<Job<'static>> {
+        local.pop().or_else(|| {
+            // A steal can fail because of a race with another thief: retry
+            // until something is found, or everything is really empty.
+            iter::repeat_with(|| {
+                self.injector
+                    .steal_batch_and_pop(local)
+                    .or_else(|| self.stealers.iter().map(|s| s.steal()).collect())
+            })
+            .find(|s| !s.is_retry())
+            .and_then(|s| s.success())
+        })
+    }
+
+    // Wait for a job to be queued. False if the worker should exit instead.
+    fn park(&self) -> bool {
+        let mut alive = self.alive.lock().expect("poisoned executor");
+        // The executor increments `pending` and then checks `sleeping`, here
+        // it's the other way around: one of the two sees the other, and no
+        // wake up gets lost.
+        self.sleeping.fetch_add(1, Ordering::SeqCst);
+        let found = loop {
+            if self.pending.load(Ordering::SeqCst) > 0 {
+                break true;
+            }
+            if self.draining.load(Ordering::SeqCst) || self.stopping.load(Ordering::SeqCst) {
+                break false;
+            }
+            alive = self.wake.wait(alive).expect("poisoned executor");
+        };
+        self.sleeping.fetch_sub(1, Ordering::SeqCst);
+        found
+    }
+
+    fn wake_all(&self) {
+        let _alive = self.alive.lock().expect("poisoned executor");
+        self.wake.notify_all();
+    }
+
+    // A job found by find_job, no longer pending.
+    fn run(&self, job: Job<'static>) {
+        self.pending.fetch_sub(1, Ordering::SeqCst);
+        // The job catches the panics of the task, this is just in case.
+        let panicked = panic::catch_unwind(AssertUnwindSafe(job)).unwrap_or(true);
+        if panicked {
+            self.panicked.fetch_add(1, Ordering::SeqCst);
+        }
+        self.completed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // Drop every queued job: their handles will say they've been cancelled,
+    // and they aren't pending anymore.
+    fn cancel(&self) -> usize {
+        let mut cancelled = 0;
+        loop {
+            let steal: crossbeam_deque::Steal<Job<'static>> = iter::once(self.injector.steal())
+                .chain(self.stealers.iter().map(|s| s.steal()))
+                .collect();
+            if steal.is_retry() {
+                continue;
+            }
+            match steal.success() {
+                Some(_) => {
+                    self.pending.fetch_sub(1, Ordering::SeqCst);
+                    cancelled += 1;
+                }
+                None => return cancelled,
+            }
+        }
+    }
+}
+
+fn run_worker(shared: Arc<Shared>, deque: Worker<Job<'static>>) {
+    LOCAL.with(|local| *local.borrow_mut() = Some((Arc::clone(&shared), deque)));
+    loop {
+        if shared.stopping.load(Ordering::SeqCst) {
+            break;
+        }
+        // Don't hold the borrow while the job runs: the job may execute more
+        // jobs, that go to this same deque.
+        let job = LOCAL.with(|local| {
+            let local = local.borrow();
+            let deque = &local.as_ref().expect("a worker has a deque").1;
+            shared.find_job(deque)
+        });
+        match job {
+            Some(job) => shared.run(job),
+            None => {
+                if !shared.park() {
+                    break;
+                }
+            }
+        }
+    }
+    // When stopping, whatever is left in the deque is cancelled here.
+    LOCAL.with(|local| {
+        if let Some((_, deque)) = local.borrow_mut().take() {
+            while deque.pop().is_some() {
+                shared.pending.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    });
+    let mut alive = shared.alive.lock().expect("poisoned executor");
+    *alive -= 1;
+    shared.exited.notify_all();
+}
+
+// On a worker, runs one of the jobs queued in its executor: false if there
+// are none (or if it's stopping), or if this isn't a worker.
+pub(crate) fn run_queued_job() -> bool {
+    let found = LOCAL.with(|local| {
+        let local = local.borrow();
+        let (shared, deque) = local.as_ref()?;
+        if shared.stopping.load(Ordering::SeqCst) {
+            return None;
+        }
+        shared.find_job(deque).map(|job| (Arc::clone(shared), job))
+    });
+    match found {
+        Some((shared, job)) => {
+            shared.run(job);
+            true
+        }
+        None => false,
+    }
+}
+
+/// A pool of threads running jobs.
+pub struct Executor {
+    shared: Arc<Shared>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+/// A snapshot of what an executor has done so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub completed: usize,
+    pub panicked: usize,
+    pub pending: usize,
+}
+
+/// The executor didn't finish its jobs in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout {
+    /// Workers still busy with a job: they are left behind, and they exit
+    /// as soon as their job is over.
+    pub busy: usize,
+    /// Queued jobs that have been dropped without running.
+    pub cancelled: usize,
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "shutdown timed out: {} busy workers, {} cancelled jobs",
+            self.busy, self.cancelled
+        )
+    }
+}
+
+impl Error for Timeout {}
+
+impl Executor {
+    /// Create an executor with `size` worker threads.
+    ///
+    /// # Panics
+    ///
+    /// If the size is zero.
+    pub fn new(size: usize) -> Executor {
+        assert!(size > 0, "an executor needs at least one worker");
+
+        let deques: Vec<Worker<Job<'static>>> = (0..size).map(|_| Worker::new_fifo()).collect();
+        let shared = Arc::new(Shared {
+            injector: Injector::new(),
+            stealers: deques.iter().map(|d| d.stealer()).collect(),
+            pending: AtomicUsize::new(0),
+            sleeping: AtomicUsize::new(0),
+            draining: AtomicBool::new(false),
+            stopping: AtomicBool::new(false),
+            alive: Mutex::new(size),
+            wake: Condvar::new(),
+            exited: Condvar::new(),
+            completed: AtomicUsize::new(0),
+            panicked: AtomicUsize::new(0),
+        });
+        let threads = deques
+            .into_iter()
+            .enumerate()
+            .map(|(i, deque)| {
+                let shared = Arc::clone(&shared);
+                thread::Builder::new()
+                    .name(format!("executor-{}", i))
+                    .spawn(move || run_worker(shared, deque))
+                    .expect("could not spawn a worker")
+            })
+            .collect();
+        Executor { shared, threads }
+    }
+
+    pub fn size(&self) -> usize {
+        self.shared.stealers.len()
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            completed: self.shared.completed.load(Ordering::SeqCst),
+            panicked: self.shared.panicked.load(Ordering::SeqCst),
+            pending: self.shared.pending.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Run `f` on one of the workers.
+    pub fn execute<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (job, handle) = handle::task(f);
+        self.push(job);
+        handle
+    }
+
+    /// Run `f` with a scope whose tasks can borrow from the stack: `scope`
+    /// returns only when all of them are over.
+    ///
+    /// The panics of the tasks are isolated as usual, and reported by their
+    /// handles. Don't call `scope` from a job of the same executor if every
+    /// worker could end up waiting for a scope: nobody would be left to run
+    /// the tasks.
+    ///
+    /// ```
+    /// # use executor::Executor;
+    /// let pool = Executor::new(2);
+    /// let words = vec!["hello", "world"];
+    /// let lengths: Vec<usize> = pool.scope(|s| {
+    ///     let handles: Vec<_> = words.iter().map(|w| s.execute(move || w.len())).collect();
+    ///     handles.into_iter().map(|h| h.join().unwrap()).collect()
+    /// });
+    /// assert_eq!(lengths, vec![5, 5]);
+    /// ```
+    pub fn scope<'pool, 'env, F, R>(&'pool self, f: F) -> R
+    where
+        F: FnOnce(&Scope<'pool, 'env>) -> R,
+    {
+        let scope = Scope::new(self);
+        // Wait for the tasks even if `f` panics: they may borrow what it
+        // borrows.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.wait();
+        match result {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    pub(crate) fn push(&self, job: Job<'static>) {
+        let shared = &self.shared;
+        shared.pending.fetch_add(1, Ordering::SeqCst);
+        let job = LOCAL.with(|local| match *local.borrow() {
+            Some((ref local, ref deque)) if Arc::ptr_eq(local, shared) => {
+                deque.push(job);
+                None
+            }
+            _ => Some(job),
+        });
+        if let Some(job) = job {
+            shared.injector.push(job);
+        }
+        if shared.sleeping.load(Ordering::SeqCst) > 0 {
+            let _alive = shared.alive.lock().expect("poisoned executor");
+            shared.wake.notify_one();
+        }
+    }
+
+    /// Run every queued job, then stop the workers.
+    pub fn shutdown(mut self) {
+        self.finish(None)
+            .expect("without a timeout the shutdown can't time out");
+    }
+
+    /// Run the queued jobs for at most `timeout`. When the time is over, the
+    /// jobs that haven't started yet are cancelled, and the workers still busy
+    /// are left behind to finish their job on their own.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Result<(), Timeout> {
+        self.finish(Some(timeout))
+    }
+
+    fn finish(&mut self, timeout: Option<Duration>) -> Result<(), Timeout> {
+        if self.threads.is_empty() {
+            return Ok(());
+        }
+        let shared = Arc::clone(&self.shared);
+        shared.draining.store(true, Ordering::SeqCst);
+        shared.wake_all();
+
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let busy = {
+            let mut alive = shared.alive.lock().expect("poisoned executor");
+            while *alive > 0 {
+                alive = match deadline {
+                    None => shared.exited.wait(alive).expect("poisoned executor"),
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            break;
+                        }
+                        shared
+                            .exited
+                            .wait_timeout(alive, deadline - now)
+                            .expect("poisoned executor")
+                            .0
+                    }
+                };
+            }
+            *alive
+        };
+
+        if busy == 0 {
+            for thread in self.threads.drain(..) {
+                thread.join().expect("workers don't panic");
+            }
+            return Ok(());
+        }
+
+        shared.stopping.store(true, Ordering::SeqCst);
+        shared.wake_all();
+        let cancelled = shared.cancel();
+        // Join the workers that are done, detach the others.
+        for thread in self.threads.drain(..) {
+            if thread.is_finished() {
+                thread.join().expect("workers don't panic");
+            }
+        }
+        Err(Timeout { busy, cancelled })
+    }
+}
+
+// Like the ThreadPool of the book, dropping the executor waits for the jobs.
+impl Drop for Executor {
+    fn drop(&mut self) {
+        let _ = self.finish(None);
+    }
+}
+
+impl fmt::Debug for Executor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Executor")
+            .field("size", &self.size())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn execute_returns_the_result() {
+        let pool = Executor::new(4);
+        let handles: Vec<JoinHandle<usize>> =
+            (0..100).map(|i| pool.execute(move || i * i)).collect();
+        let squares: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(squares, (0..100).map(|i| i * i).collect::<Vec<usize>>());
+        pool.shutdown();
+    }
+
+    #[test]
+    fn a_panic_does_not_kill_the_worker() {
+        let pool = Executor::new(1);
+        let bad = pool.execute(|| -> u32 { panic!("boom") });
+        let good = pool.execute(|| 42);
+        let err = bad.join().unwrap_err();
+        assert!(err.is_panic());
+        assert_eq!(err.panic_message(), Some("boom"));
+        assert_eq!(err.to_string(), "the task panicked: boom");
+        assert_eq!(good.join().unwrap(), 42);
+        // The worker counts a job right after the handle gets its result.
+        while pool.stats().completed < 2 {
+            thread::yield_now();
+        }
+        let stats = pool.stats();
+        assert_eq!((stats.completed, stats.panicked), (2, 1));
+    }
+
+    #[test]
+    fn jobs_can_execute_jobs() {
+        let pool = Arc::new(Executor::new(2));
+        let inner = Arc::clone(&pool);
+        let outer = pool.execute(move || {
+            let handles: Vec<JoinHandle<u64>> = (1..11).map(|i| inner.execute(move || i)).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum::<u64>()
+        });
+        assert_eq!(outer.join().unwrap(), 55);
+    }
+
+    #[test]
+    fn a_job_joins_its_jobs_on_one_worker() {
+        let pool = Arc::new(Executor::new(1));
+        let inner = Arc::clone(&pool);
+        let (sender, receiver) = mpsc::channel();
+        pool.execute(move || {
+            let handles: Vec<JoinHandle<u64>> = (1..11).map(|i| inner.execute(move || i)).collect();
+            let sum = handles.into_iter().map(|h| h.join().unwrap()).sum::<u64>();
+            // The pool goes with the test, not with this job.
+            drop(inner);
+            let _ = sender.send(sum);
+        });
+        let sum = receiver.recv_timeout(Duration::from_secs(10));
+        assert_eq!(sum, Ok(55));
+    }
+
+    #[test]
+    fn scoped_tasks_borrow_from_the_stack() {
+        let pool = Executor::new(3);
+        let numbers: Vec<u64> = (1..1001).collect();
+        let total = AtomicUsize::new(0);
+        let sums: Vec<u64> = pool.scope(|s| {
+            let handles: Vec<JoinHandle<u64>> = numbers
+                .chunks(100)
+                .map(|chunk| {
+                    let total = &total;
+                    s.execute(move || {
+                        total.fetch_add(chunk.len(), Ordering::SeqCst);
+                        chunk.iter().sum()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(sums.iter().sum::<u64>(), 500_500);
+        assert_eq!(total.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    fn the_scope_waits_for_forgotten_tasks() {
+        let pool = Executor::new(2);
+        let mut done = [false; 8];
+        pool.scope(|s| {
+            for d in done.iter_mut() {
+                s.execute(move || {
+                    thread::sleep(Duration::from_millis(5));
+                    *d = true;
+                });
+            }
+        });
+        assert!(done.iter().all(|&d| d));
+    }
+
+    #[test]
+    fn shutdown_runs_the_queued_jobs() {
+        let pool = Executor::new(2);
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..50 {
+            let sender = sender.clone();
+            pool.execute(move || sender.send(i).unwrap());
+        }
+        drop(sender);
+        pool.shutdown();
+        assert_eq!(receiver.iter().count(), 50);
+    }
+
+    #[test]
+    fn shutdown_timeout_cancels_what_is_left() {
+        let pool = Executor::new(1);
+        let (sender, receiver) = mpsc::channel::<()>();
+        // Keeps the only worker busy until the sender is dropped.
+        let busy = pool.execute(move || receiver.recv().is_err());
+        let queued: Vec<JoinHandle<()>> = (0..3).map(|_| pool.execute(|| ())).collect();
+
+        let timeout = pool
+            .shutdown_timeout(Duration::from_millis(50))
+            .unwrap_err();
+        assert_eq!(
+            timeout,
+            Timeout {
+                busy: 1,
+                cancelled: 3
+            }
+        );
+        for h in queued {
+            match h.join() {
+                Err(JoinError::Cancelled) => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        // The worker left behind still finishes its job.
+        drop(sender);
+        assert!(busy.join().unwrap());
+    }
+
+    #[test]
+    fn cancelled_jobs_are_not_pending() {
+        let mut pool = Executor::new(1);
+        let (sender, receiver) = mpsc::channel::<()>();
+        let busy = pool.execute(move || receiver.recv().is_err());
+        for _ in 0..3 {
+            pool.execute(|| ());
+        }
+
+        // What shutdown_timeout does, keeping the pool to look at it after.
+        let timeout = pool.finish(Some(Duration::from_millis(50))).unwrap_err();
+        assert_eq!(timeout.cancelled, 3);
+        assert_eq!(
+            pool.stats(),
+            Stats {
+                completed: 0,
+                panicked: 0,
+                pending: 0
+            }
+        );
+        drop(sender);
+        assert!(busy.join().unwrap());
+    }
+
+    #[test]
+    fn shutdown_timeout_in_time() {
+        let pool = Executor::new(2);
+        let handles: Vec<JoinHandle<()>> = (0..4)
+            .map(|_| pool.execute(|| thread::sleep(Duration::from_millis(10))))
+            .collect();
+        assert_eq!(pool.shutdown_timeout(Duration::from_secs(10)), Ok(()));
+        assert!(handles.into_iter().all(|h| h.join().is_ok()));
+    }
+}
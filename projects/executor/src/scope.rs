@@ -0,0 +1,77 @@
+use handle::{self, JoinHandle};
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use Executor;
+
+/// A scope to run tasks that borrow from the stack, see `Executor::scope`.
+pub struct Scope<'pool, 'env> {
+    executor: &'pool Executor,
+    running: Arc<Running>,
+    // Invariant over 'env, as std::thread::Scope: it can't be shrunk to a
+    // lifetime shorter than the call to `Executor::scope`.
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+// How many tasks of the scope are queued or running.
+struct Running {
+    count: Mutex<usize>,
+    done: Condvar,
+}
+
+// Held by every task of the scope: dropped when the task is over, or when it
+// has been dropped without running.
+struct Ticket(Arc<Running>);
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut count = self.0.count.lock().expect("poisoned scope");
+        *count -= 1;
+        if *count == 0 {
+            self.0.done.notify_all();
+        }
+    }
+}
+
+impl<'pool, 'env> Scope<'pool, 'env> {
+    pub(crate) fn new(executor: &'pool Executor) -> Scope<'pool, 'env> {
+        Scope {
+            executor,
+            running: Arc::new(Running {
+                count: Mutex::new(0),
+                done: Condvar::new(),
+            }),
+            env: PhantomData,
+        }
+    }
+
+    /// Run a task on the executor. Unlike `Executor::execute`, the task can
+    /// borrow anything that outlives the scope.
+    pub fn execute<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'env,
+        T: Send + 'env,
+    {
+        *self.running.count.lock().expect("poisoned scope") += 1;
+        let ticket = Ticket(Arc::clone(&self.running));
+        let (job, handle) = handle::task(f);
+        let job: handle::Job<'env> = Box::new(move || {
+            let _ticket = ticket;
+            job()
+        });
+        // The job may borrow data that lives only for 'env, and the executor
+        // wants 'static jobs. It's fine: `Executor::scope` doesn't return
+        // until every ticket has been dropped, that is until every job of the
+        // scope has run (or has been dropped).
+        let job: handle::Job<'static> = unsafe { mem::transmute(job) };
+        self.executor.push(job);
+        handle
+    }
+
+    pub(crate) fn wait(&self) {
+        let mut count = self.running.count.lock().expect("poisoned scope");
+        while *count > 0 {
+            count = self.running.done.wait(count).expect("poisoned scope");
+        }
+    }
+}
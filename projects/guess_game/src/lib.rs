@@ -0,0 +1,672 @@
+//! # Guess the number
+//!
+//! The rules of the game, without any input or output: a `Game` takes
+//! guesses and answers too small, too big, won or lost. Whoever plays is a
+//! `Strategy`: a person at a terminal (`Human`), a bot halving the numbers
+//! still possible (`Bisect`) or one picking any of them (`RandomBot`).
+//! A `Tournament` plays every strategy against the same secrets and ranks
+//! them by the guesses they needed on average.
+
+use std::error;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    EmptyRange {
+        low: u32,
+        high: u32,
+    },
+    OutOfRange {
+        value: u32,
+        low: u32,
+        high: u32,
+    },
+    /// A guess after the game was won or lost.
+    Over,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::EmptyRange { low, high } => write!(f, "no number between {} and {}", low, high),
+            Error::OutOfRange { value, low, high } => {
+                write!(
+                    f,
+                    "guess value must be between {} and {}, got {}",
+                    low, high, value
+                )
+            }
+            Error::Over => write!(f, "the game is over"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// The numbers the secret is among, both included, and how many guesses
+/// there are to find it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    low: u32,
+    high: u32,
+    attempts: Option<u32>,
+}
+
+impl Rules {
+    pub fn new(low: u32, high: u32) -> Result<Rules, Error> {
+        if low > high {
+            return Err(Error::EmptyRange { low, high });
+        }
+        Ok(Rules {
+            low,
+            high,
+            attempts: None,
+        })
+    }
+
+    /// Lost after that many wrong guesses.
+    pub fn with_attempts(mut self, attempts: u32) -> Rules {
+        self.attempts = Some(attempts);
+        self
+    }
+
+    pub fn low(&self) -> u32 {
+        self.low
+    }
+
+    pub fn high(&self) -> u32 {
+        self.high
+    }
+
+    pub fn attempts(&self) -> Option<u32> {
+        self.attempts
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        self.low <= value && value <= self.high
+    }
+}
+
+/// 1 to 100, as many guesses as needed.
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            low: 1,
+            high: 100,
+            attempts: None,
+        }
+    }
+}
+
+/// A number within the rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Guess {
+    value: u32,
+}
+
+impl Guess {
+    pub fn new(value: u32, rules: &Rules) -> Result<Guess, Error> {
+        if !rules.contains(value) {
+            return Err(Error::OutOfRange {
+                value,
+                low: rules.low,
+                high: rules.high,
+            });
+        }
+        Ok(Guess { value })
+    }
+
+    // The field is private: there's no Guess that didn't go through new.
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    TooSmall,
+    TooBig,
+    Won { attempts: u32 },
+    Lost { secret: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    rules: Rules,
+    secret: u32,
+    attempts: u32,
+    over: bool,
+}
+
+impl Game {
+    pub fn new(rules: Rules, secret: u32) -> Result<Game, Error> {
+        Guess::new(secret, &rules)?;
+        Ok(Game {
+            rules,
+            secret,
+            attempts: 0,
+            over: false,
+        })
+    }
+
+    /// A secret picked by the generator.
+    pub fn random(rules: Rules, rng: &mut Rng) -> Game {
+        let secret = rng.between(rules.low, rules.high);
+        Game {
+            rules,
+            secret,
+            attempts: 0,
+            over: false,
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// To cheat.
+    pub fn secret(&self) -> u32 {
+        self.secret
+    }
+
+    /// The guesses so far.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// A guess outside of the rules is an error, and doesn't count.
+    pub fn guess(&mut self, value: u32) -> Result<Outcome, Error> {
+        if self.over {
+            return Err(Error::Over);
+        }
+        let guess = Guess::new(value, &self.rules)?;
+        self.attempts += 1;
+        let outcome = if guess.value() == self.secret {
+            Outcome::Won {
+                attempts: self.attempts,
+            }
+        } else if self.rules.attempts.is_some_and(|n| self.attempts >= n) {
+            Outcome::Lost {
+                secret: self.secret,
+            }
+        } else if guess.value() < self.secret {
+            Outcome::TooSmall
+        } else {
+            Outcome::TooBig
+        };
+        if let Outcome::Won { .. } | Outcome::Lost { .. } = outcome {
+            self.over = true;
+        }
+        Ok(outcome)
+    }
+}
+
+/// Whoever plays.
+pub trait Strategy {
+    fn name(&self) -> String;
+
+    /// Before every game.
+    fn start(&mut self, _rules: &Rules) {}
+
+    /// None gives up.
+    fn guess(&mut self) -> Option<u32>;
+
+    /// What the game answered to the last guess.
+    fn hint(&mut self, _guess: u32, _outcome: Outcome) {}
+}
+
+/// Halves the numbers still possible: 7 guesses at most from 1 to 100.
+#[derive(Debug, Clone, Default)]
+pub struct Bisect {
+    low: u32,
+    high: u32,
+}
+
+impl Bisect {
+    pub fn new() -> Bisect {
+        Bisect::default()
+    }
+}
+
+impl Strategy for Bisect {
+    fn name(&self) -> String {
+        String::from("bisect")
+    }
+
+    fn start(&mut self, rules: &Rules) {
+        self.low = rules.low;
+        self.high = rules.high;
+    }
+
+    fn guess(&mut self) -> Option<u32> {
+        if self.low > self.high {
+            return None;
+        }
+        Some(self.low + (self.high - self.low) / 2)
+    }
+
+    fn hint(&mut self, guess: u32, outcome: Outcome) {
+        narrow(&mut self.low, &mut self.high, guess, outcome);
+    }
+}
+
+// The numbers still possible after a hint: none, low > high, if there's no
+// number past the guess.
+fn narrow(low: &mut u32, high: &mut u32, guess: u32, outcome: Outcome) {
+    let (l, h) = match outcome {
+        Outcome::TooSmall => (guess.checked_add(1), Some(*high)),
+        Outcome::TooBig => (Some(*low), guess.checked_sub(1)),
+        _ => return,
+    };
+    match (l, h) {
+        (Some(l), Some(h)) => {
+            *low = l;
+            *high = h;
+        }
+        _ => {
+            *low = 1;
+            *high = 0;
+        }
+    }
+}
+
+/// Any of the numbers still possible.
+#[derive(Debug, Clone)]
+pub struct RandomBot {
+    rng: Rng,
+    low: u32,
+    high: u32,
+}
+
+impl RandomBot {
+    pub fn new(rng: Rng) -> RandomBot {
+        RandomBot {
+            rng,
+            low: 1,
+            high: 0,
+        }
+    }
+}
+
+impl Strategy for RandomBot {
+    fn name(&self) -> String {
+        String::from("random")
+    }
+
+    fn start(&mut self, rules: &Rules) {
+        self.low = rules.low;
+        self.high = rules.high;
+    }
+
+    fn guess(&mut self) -> Option<u32> {
+        if self.low > self.high {
+            return None;
+        }
+        Some(self.rng.between(self.low, self.high))
+    }
+
+    fn hint(&mut self, guess: u32, outcome: Outcome) {
+        narrow(&mut self.low, &mut self.high, guess, outcome);
+    }
+}
+
+/// Someone typing guesses: on stdin and stdout, or whatever else reads and
+/// writes lines.
+pub struct Human<R, W> {
+    input: R,
+    output: W,
+    rules: Rules,
+}
+
+impl<R: BufRead, W: Write> Human<R, W> {
+    pub fn new(input: R, output: W) -> Human<R, W> {
+        Human {
+            input,
+            output,
+            rules: Rules::default(),
+        }
+    }
+}
+
+// What's written to the output doesn't change the game: errors writing it
+// are ignored.
+impl<R: BufRead, W: Write> Strategy for Human<R, W> {
+    fn name(&self) -> String {
+        String::from("human")
+    }
+
+    fn start(&mut self, rules: &Rules) {
+        self.rules = *rules;
+        let _ = writeln!(
+            self.output,
+            "Guess the number between {} and {}!",
+            rules.low, rules.high
+        );
+        if let Some(n) = rules.attempts {
+            let _ = writeln!(self.output, "You have {} attempts.", n);
+        }
+    }
+
+    fn guess(&mut self) -> Option<u32> {
+        loop {
+            let _ = writeln!(self.output, "Please input your guess.");
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+            let value: u32 = match line.trim().parse() {
+                Ok(value) => value,
+                Err(_) => {
+                    let _ = writeln!(self.output, "Please type a number!");
+                    continue;
+                }
+            };
+            match Guess::new(value, &self.rules) {
+                Ok(guess) => return Some(guess.value()),
+                Err(e) => {
+                    let _ = writeln!(self.output, "{}", e);
+                }
+            }
+        }
+    }
+
+    fn hint(&mut self, guess: u32, outcome: Outcome) {
+        let _ = writeln!(self.output, "You guessed: {}", guess);
+        let _ = match outcome {
+            Outcome::TooSmall => writeln!(self.output, "Too small!"),
+            Outcome::TooBig => writeln!(self.output, "Too big!"),
+            Outcome::Won { attempts } => writeln!(self.output, "You win in {} attempts!", attempts),
+            Outcome::Lost { secret } => {
+                writeln!(self.output, "You lose! The number was {}.", secret)
+            }
+        };
+    }
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Played {
+    pub attempts: u32,
+    pub won: bool,
+}
+
+/// Plays a game to its end, or until the strategy gives up: a guess outside
+/// of the rules is an error.
+pub fn play<S: Strategy + ?Sized>(game: &mut Game, strategy: &mut S) -> Result<Played, Error> {
+    strategy.start(&game.rules);
+    loop {
+        let value = match strategy.guess() {
+            Some(value) => value,
+            None => {
+                return Ok(Played {
+                    attempts: game.attempts,
+                    won: false,
+                })
+            }
+        };
+        let outcome = game.guess(value)?;
+        strategy.hint(value, outcome);
+        match outcome {
+            Outcome::Won { attempts } => {
+                return Ok(Played {
+                    attempts,
+                    won: true,
+                })
+            }
+            Outcome::Lost { .. } => {
+                return Ok(Played {
+                    attempts: game.attempts,
+                    won: false,
+                })
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How a strategy did in a tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub games: u32,
+    pub wins: u32,
+    /// Guesses per game, lost ones included.
+    pub average: f64,
+    pub worst: u32,
+}
+
+impl fmt::Display for Standing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.2} guesses on average, {} at worst, {} of {} games won",
+            self.name, self.average, self.worst, self.wins, self.games
+        )
+    }
+}
+
+/// The same secrets for every strategy.
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    rules: Rules,
+    games: u32,
+    seed: u64,
+}
+
+impl Tournament {
+    pub fn new(rules: Rules, games: u32) -> Tournament {
+        Tournament {
+            rules,
+            games,
+            seed: 0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Tournament {
+        self.seed = seed;
+        self
+    }
+
+    /// The best first: fewer guesses on average, then more wins.
+    pub fn run(&self, strategies: &mut [Box<dyn Strategy>]) -> Result<Vec<Standing>, Error> {
+        let mut standings = Vec::new();
+        for strategy in strategies.iter_mut() {
+            let mut secrets = Rng::new(self.seed);
+            let mut standing = Standing {
+                name: strategy.name(),
+                games: self.games,
+                wins: 0,
+                average: 0.0,
+                worst: 0,
+            };
+            let mut total = 0u64;
+            for _ in 0..self.games {
+                let mut game = Game::random(self.rules, &mut secrets);
+                let played = play(&mut game, &mut **strategy)?;
+                total += u64::from(played.attempts);
+                standing.worst = standing.worst.max(played.attempts);
+                if played.won {
+                    standing.wins += 1;
+                }
+            }
+            if self.games > 0 {
+                standing.average = total as f64 / f64::from(self.games);
+            }
+            standings.push(standing);
+        }
+        standings.sort_by(|a, b| {
+            a.average
+                .partial_cmp(&b.average)
+                .unwrap_or(::std::cmp::Ordering::Equal)
+                .then(b.wins.cmp(&a.wins))
+        });
+        Ok(standings)
+    }
+}
+
+/// A small seeded generator (SplitMix64), for secrets and random guesses.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Seeded by the clock.
+    pub fn from_time() -> Rng {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Rng::new(now.as_secs() ^ u64::from(now.subsec_nanos()) << 32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// From low to high, both included.
+    pub fn between(&mut self, low: u32, high: u32) -> u32 {
+        let n = u64::from(high - low) + 1;
+        // The bias of the modulo is below 2^-31.
+        low + (self.next_u64() % n) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn rules() {
+        let rules = Rules::new(10, 20).unwrap().with_attempts(2);
+        assert_eq!(Guess::new(20, &rules).map(|g| g.value()), Ok(20));
+        assert_eq!(
+            Guess::new(101, &Rules::default()).unwrap_err().to_string(),
+            "guess value must be between 1 and 100, got 101"
+        );
+        assert_eq!(Rules::new(5, 4), Err(Error::EmptyRange { low: 5, high: 4 }));
+
+        let mut game = Game::new(rules, 15).unwrap();
+        assert_eq!(
+            game.guess(9),
+            Err(Error::OutOfRange {
+                value: 9,
+                low: 10,
+                high: 20
+            })
+        );
+        assert_eq!(game.guess(12), Ok(Outcome::TooSmall));
+        assert_eq!(game.guess(18), Ok(Outcome::Lost { secret: 15 }));
+        assert_eq!(game.guess(15), Err(Error::Over));
+        assert_eq!(game.attempts(), 2);
+
+        let mut game = Game::new(Rules::default(), 1).unwrap();
+        assert_eq!(game.guess(50), Ok(Outcome::TooBig));
+        assert_eq!(game.guess(1), Ok(Outcome::Won { attempts: 2 }));
+        assert!(Game::new(Rules::default(), 0).is_err());
+    }
+
+    #[test]
+    fn bots() {
+        let rules = Rules::default();
+        for secret in 1..101 {
+            let mut game = Game::new(rules, secret).unwrap();
+            let played = play(&mut game, &mut Bisect::new()).unwrap();
+            assert!(
+                played.won && played.attempts <= 7,
+                "{}: {:?}",
+                secret,
+                played
+            );
+
+            let mut game = Game::new(rules, secret).unwrap();
+            let played = play(&mut game, &mut RandomBot::new(Rng::new(u64::from(secret)))).unwrap();
+            assert!(played.won && played.attempts <= 100);
+        }
+        // Down to 0, up to the largest number.
+        for &(low, high) in &[(0, 0), (0, 9), (u32::MAX - 9, u32::MAX)] {
+            let rules = Rules::new(low, high).unwrap();
+            for &secret in &[low, high] {
+                let mut game = Game::new(rules, secret).unwrap();
+                assert!(play(&mut game, &mut Bisect::new()).unwrap().won);
+            }
+        }
+
+        let mut game = Game::new(Rules::default().with_attempts(3), 100).unwrap();
+        assert_eq!(
+            play(&mut game, &mut Bisect::new()),
+            Ok(Played {
+                attempts: 3,
+                won: false
+            })
+        );
+    }
+
+    #[test]
+    fn human() {
+        let input = Cursor::new("fifty\n500\n50\n75\n");
+        let mut output = Vec::new();
+        let played = {
+            let mut human = Human::new(input, &mut output);
+            play(&mut Game::new(Rules::default(), 75).unwrap(), &mut human).unwrap()
+        };
+        assert_eq!(
+            played,
+            Played {
+                attempts: 2,
+                won: true
+            }
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Please type a number!\n"));
+        assert!(output.contains("guess value must be between 1 and 100, got 500\n"));
+        assert!(output.contains("You guessed: 50\nToo small!\n"));
+        assert!(output.ends_with("You win in 2 attempts!\n"));
+
+        // Giving up at the end of the input.
+        let mut human = Human::new(Cursor::new("1\n"), Vec::new());
+        let played = play(&mut Game::new(Rules::default(), 2).unwrap(), &mut human).unwrap();
+        assert_eq!(
+            played,
+            Played {
+                attempts: 1,
+                won: false
+            }
+        );
+    }
+
+    #[test]
+    fn tournament() {
+        let mut strategies: Vec<Box<dyn Strategy>> = vec![
+            Box::new(RandomBot::new(Rng::new(1))),
+            Box::new(Bisect::new()),
+        ];
+        let tournament = Tournament::new(Rules::default(), 500).with_seed(7);
+        let standings = tournament.run(&mut strategies).unwrap();
+        assert_eq!(standings[0].name, "bisect");
+        assert_eq!(standings[0].wins, 500);
+        assert!(standings[0].worst <= 7);
+        // About 5.8 guesses on average, against about 8.4 for the random one.
+        assert!(
+            standings[0].average < 6.0 && standings[1].average > 7.0,
+            "{:?}",
+            standings
+        );
+        assert_eq!(tournament.run(&mut strategies).unwrap()[0], standings[0]);
+    }
+}
@@ -0,0 +1,585 @@
+//! # The Tower of Hanoi, with any number of pegs
+//!
+//! Disks are numbered from 0, the smallest, and pegs from 0. A solution is a
+//! list of `Move`s: `Towers` replays it and checks every move against the
+//! rules, so the solvers don't have to be trusted.
+//!
+//! - `frame_stewart` moves a tower with three pegs or more. With three it's
+//!   the classic recursive solution, with more it splits the tower where the
+//!   Frame–Stewart recurrence says, which is optimal for four pegs and
+//!   conjectured to be for more.
+//! - `solve` brings every disk to one peg from any legal configuration; with
+//!   three pegs it takes the fewest moves.
+//! - `iterative` gives the moves of the classic puzzle one at a time, with
+//!   neither recursion nor a list: 30 disks are a billion moves, 64 the
+//!   ones of the legend.
+//!
+//! - https://en.wikipedia.org/wiki/Tower_of_Hanoi
+//! - https://en.wikipedia.org/wiki/Tower_of_Hanoi#Frame%E2%80%93Stewart_algorithm
+
+use std::error;
+use std::fmt;
+
+/// Take `disk` from the top of `from` and put it on top of `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub disk: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move disk {} from {} to {}",
+            self.disk, self.from, self.to
+        )
+    }
+}
+
+/// What breaks the rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    TooFewPegs(usize),
+    NoSuchPeg(usize),
+    /// For `iterative`, which counts the moves with a u64.
+    TooManyDisks(u32),
+    /// The disk isn't on top of the peg it's moved from.
+    NotOnTop {
+        disk: usize,
+        peg: usize,
+    },
+    LargerOnSmaller {
+        disk: usize,
+        onto: usize,
+    },
+    /// In a configuration: disks are 0 to n - 1, once each.
+    MissingDisk(usize),
+    DuplicateDisk(usize),
+    /// The `index`-th move (from 0) of a solution.
+    AtMove {
+        index: usize,
+        error: Box<Error>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::TooFewPegs(pegs) => write!(f, "at least 3 pegs are needed, not {}", pegs),
+            Error::NoSuchPeg(peg) => write!(f, "there is no peg {}", peg),
+            Error::TooManyDisks(disks) => write!(f, "{} disks are too many, 64 at most", disks),
+            Error::NotOnTop { disk, peg } => {
+                write!(f, "disk {} is not on top of peg {}", disk, peg)
+            }
+            Error::LargerOnSmaller { disk, onto } => {
+                write!(f, "disk {} can't go on the smaller disk {}", disk, onto)
+            }
+            Error::MissingDisk(disk) => write!(f, "disk {} is missing", disk),
+            Error::DuplicateDisk(disk) => write!(f, "disk {} is there twice", disk),
+            Error::AtMove { index, ref error } => write!(f, "move {}: {}", index + 1, error),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// The pegs, and the disks on them from the bottom up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Towers {
+    pegs: Vec<Vec<usize>>,
+}
+
+impl Towers {
+    /// Every disk on peg 0.
+    pub fn new(pegs: usize, disks: usize) -> Result<Towers, Error> {
+        let mut stacks = vec![Vec::new(); pegs];
+        if let Some(first) = stacks.first_mut() {
+            first.extend((0..disks).rev());
+        }
+        Towers::from_pegs(stacks)
+    }
+
+    /// Any legal configuration: no disk on a smaller one.
+    pub fn from_pegs(pegs: Vec<Vec<usize>>) -> Result<Towers, Error> {
+        if pegs.len() < 3 {
+            return Err(Error::TooFewPegs(pegs.len()));
+        }
+        let disks: usize = pegs.iter().map(Vec::len).sum();
+        let mut seen = vec![false; disks];
+        for peg in &pegs {
+            for (i, &disk) in peg.iter().enumerate() {
+                if disk < disks {
+                    if seen[disk] {
+                        return Err(Error::DuplicateDisk(disk));
+                    }
+                    seen[disk] = true;
+                }
+                if i > 0 && peg[i - 1] < disk {
+                    return Err(Error::LargerOnSmaller {
+                        disk,
+                        onto: peg[i - 1],
+                    });
+                }
+            }
+        }
+        // A disk too large for their number takes the place of a smaller one.
+        match seen.iter().position(|&s| !s) {
+            Some(disk) => Err(Error::MissingDisk(disk)),
+            None => Ok(Towers { pegs }),
+        }
+    }
+
+    pub fn pegs(&self) -> &[Vec<usize>] {
+        &self.pegs
+    }
+
+    pub fn disks(&self) -> usize {
+        self.pegs.iter().map(Vec::len).sum()
+    }
+
+    /// The peg of a disk.
+    pub fn peg_of(&self, disk: usize) -> Option<usize> {
+        self.pegs.iter().position(|p| p.contains(&disk))
+    }
+
+    /// True if every disk is on `peg`.
+    pub fn is_solved(&self, peg: usize) -> bool {
+        self.pegs.get(peg).is_some_and(|p| p.len() == self.disks())
+    }
+
+    /// Make a move, if it's legal.
+    pub fn apply(&mut self, m: Move) -> Result<(), Error> {
+        for &peg in &[m.from, m.to] {
+            if peg >= self.pegs.len() {
+                return Err(Error::NoSuchPeg(peg));
+            }
+        }
+        if self.pegs[m.from].last() != Some(&m.disk) {
+            return Err(Error::NotOnTop {
+                disk: m.disk,
+                peg: m.from,
+            });
+        }
+        if let Some(&top) = self.pegs[m.to].last() {
+            if top < m.disk {
+                return Err(Error::LargerOnSmaller {
+                    disk: m.disk,
+                    onto: top,
+                });
+            }
+        }
+        self.pegs[m.from].pop();
+        self.pegs[m.to].push(m.disk);
+        Ok(())
+    }
+
+    /// Make the moves, up to the first illegal one.
+    pub fn replay<I: IntoIterator<Item = Move>>(&mut self, moves: I) -> Result<(), Error> {
+        for (index, m) in moves.into_iter().enumerate() {
+            self.apply(m).map_err(|e| Error::AtMove {
+                index,
+                error: Box::new(e),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Towers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, peg) in self.pegs.iter().enumerate() {
+            writeln!(f, "peg {:02}: {:?}", i, peg)?;
+        }
+        Ok(())
+    }
+}
+
+/// How many moves `frame_stewart` takes, saturating at u64::MAX.
+pub fn moves_needed(disks: usize, pegs: usize) -> u64 {
+    Splits::new(disks, pegs).moves(disks, pegs)
+}
+
+// The Frame–Stewart recurrence: with p pegs, move the smallest disks aside
+// (with p pegs), the others to the end (with p - 1, one is taken), then the
+// smallest on them. `best[p][n]` is how many disks go aside, `moves[p][n]`
+// the moves it takes.
+struct Splits {
+    moves: Vec<Vec<u64>>,
+    best: Vec<Vec<usize>>,
+}
+
+impl Splits {
+    fn new(disks: usize, pegs: usize) -> Splits {
+        let mut moves: Vec<Vec<u64>> = vec![Vec::new(); pegs + 1];
+        let mut best = vec![Vec::new(); pegs + 1];
+        for p in 3..=pegs {
+            moves[p] = vec![0; disks + 1];
+            best[p] = vec![0; disks + 1];
+            for n in 1..=disks {
+                if p == 3 {
+                    moves[3][n] = moves[3][n - 1].saturating_mul(2).saturating_add(1);
+                    best[3][n] = n - 1;
+                    continue;
+                }
+                let (k, m) = (1..=n)
+                    .map(|k| {
+                        (
+                            k,
+                            moves[p][n - k]
+                                .saturating_mul(2)
+                                .saturating_add(moves[p - 1][k]),
+                        )
+                    })
+                    .min_by_key(|&(_, m)| m)
+                    .unwrap();
+                best[p][n] = n - k;
+                moves[p][n] = m;
+            }
+        }
+        Splits { moves, best }
+    }
+
+    fn moves(&self, disks: usize, pegs: usize) -> u64 {
+        if disks == 0 {
+            0
+        } else if pegs < 3 {
+            if disks == 1 {
+                1
+            } else {
+                u64::MAX
+            }
+        } else {
+            self.moves[pegs][disks]
+        }
+    }
+}
+
+/// The moves of a tower of `disks` from one peg to another, using all the
+/// `pegs` (three or more).
+pub fn frame_stewart(
+    disks: usize,
+    pegs: usize,
+    from: usize,
+    to: usize,
+) -> Result<Vec<Move>, Error> {
+    if pegs < 3 {
+        return Err(Error::TooFewPegs(pegs));
+    }
+    for &peg in &[from, to] {
+        if peg >= pegs {
+            return Err(Error::NoSuchPeg(peg));
+        }
+    }
+    let mut moves = Vec::new();
+    if from != to {
+        let free: Vec<usize> = (0..pegs).filter(|&p| p != from && p != to).collect();
+        let splits = Splits::new(disks, pegs);
+        tower(&splits, 0..disks, from, to, &free, &mut moves);
+    }
+    Ok(moves)
+}
+
+// Move the disks of the range, on top of `from`, to `to` through the `free`
+// pegs, where nothing smaller than them is.
+fn tower(
+    splits: &Splits,
+    disks: ::std::ops::Range<usize>,
+    from: usize,
+    to: usize,
+    free: &[usize],
+    moves: &mut Vec<Move>,
+) {
+    let n = disks.end - disks.start;
+    if n == 0 {
+        return;
+    }
+    if n == 1 {
+        moves.push(Move {
+            disk: disks.start,
+            from,
+            to,
+        });
+        return;
+    }
+    let aside = splits.best[free.len() + 2][n];
+    let (via, rest) = (free[0], &free[1..]);
+    let middle = disks.start + aside;
+
+    let mut others: Vec<usize> = rest.to_vec();
+    others.push(to);
+    tower(splits, disks.start..middle, from, via, &others, moves);
+    tower(splits, middle..disks.end, from, to, rest, moves);
+    others.pop();
+    others.push(from);
+    tower(splits, disks.start..middle, via, to, &others, moves);
+}
+
+/// The moves that bring every disk to `to`, from any configuration.
+pub fn solve(towers: &Towers, to: usize) -> Result<Vec<Move>, Error> {
+    let pegs = towers.pegs().len();
+    if to >= pegs {
+        return Err(Error::NoSuchPeg(to));
+    }
+    let mut position = vec![0; towers.disks()];
+    for (peg, disks) in towers.pegs().iter().enumerate() {
+        for &disk in disks {
+            position[disk] = peg;
+        }
+    }
+    let splits = Splits::new(towers.disks(), pegs);
+    let mut moves = Vec::new();
+    gather(&splits, &mut position, towers.disks(), to, &mut moves);
+    Ok(moves)
+}
+
+// Bring the `n` smallest disks to `to`: the largest of them that isn't there
+// yet goes there once the smaller ones are all out of the way, on a peg that
+// is neither its nor `to`; then the smaller ones follow it, as a tower.
+fn gather(splits: &Splits, position: &mut [usize], n: usize, to: usize, moves: &mut Vec<Move>) {
+    let disk = match (0..n).rev().find(|&d| position[d] != to) {
+        Some(disk) => disk,
+        None => return,
+    };
+    let from = position[disk];
+    let pegs = splits.moves.len() - 1;
+    let spare = (0..pegs).find(|&p| p != from && p != to).unwrap();
+    gather(splits, position, disk, spare, moves);
+    moves.push(Move { disk, from, to });
+    let free: Vec<usize> = (0..pegs).filter(|&p| p != spare && p != to).collect();
+    tower(splits, 0..disk, spare, to, &free, moves);
+    for p in position.iter_mut().take(disk + 1) {
+        *p = to;
+    }
+}
+
+/// The moves of the classic puzzle, with three pegs, one at a time.
+pub fn iterative(disks: u32, from: usize, to: usize) -> Result<Iterative, Error> {
+    if disks > 64 {
+        return Err(Error::TooManyDisks(disks));
+    }
+    for &peg in &[from, to] {
+        if peg > 2 {
+            return Err(Error::NoSuchPeg(peg));
+        }
+    }
+    let total = if disks == 64 {
+        u64::MAX
+    } else {
+        (1 << disks) - 1
+    };
+    // The formula of `at` brings a tower from 0 to 2 if it has an odd
+    // number of disks, to 1 if it's even: the pegs are renamed to fit.
+    let pegs = if from == to {
+        [from; 3]
+    } else if disks % 2 == 1 {
+        [from, 3 - from - to, to]
+    } else {
+        [from, to, 3 - from - to]
+    };
+    Ok(Iterative {
+        pegs,
+        step: 0,
+        total,
+    })
+}
+
+/// The moves of `iterative`. The m-th move moves the disk that is the number
+/// of trailing zeros of m, and its pegs come from m alone: `nth` and `len`
+/// are O(1).
+#[derive(Debug, Clone)]
+pub struct Iterative {
+    pegs: [usize; 3],
+    step: u64,
+    total: u64,
+}
+
+impl Iterative {
+    // The m-th move, from 1.
+    fn at(&self, m: u64) -> Move {
+        Move {
+            disk: m.trailing_zeros() as usize,
+            from: self.pegs[((m & (m - 1)) % 3) as usize],
+            to: self.pegs[((u128::from(m | (m - 1)) + 1) % 3) as usize],
+        }
+    }
+
+    fn left(&self) -> u64 {
+        if self.pegs[0] == self.pegs[2] {
+            0
+        } else {
+            self.total - self.step
+        }
+    }
+}
+
+impl Iterator for Iterative {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.left() == 0 {
+            return None;
+        }
+        self.step += 1;
+        Some(self.at(self.step))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Move> {
+        self.step = self.step.saturating_add(n as u64).min(self.total);
+        self.next()
+    }
+
+    fn last(self) -> Option<Move> {
+        if self.left() == 0 {
+            None
+        } else {
+            Some(self.at(self.total))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.left();
+        if left > usize::MAX as u64 {
+            (usize::MAX, None)
+        } else {
+            (left as usize, Some(left as usize))
+        }
+    }
+}
+
+impl ExactSizeIterator for Iterative {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(mut towers: Towers, moves: Vec<Move>, to: usize) {
+        towers.replay(moves).unwrap();
+        assert!(towers.is_solved(to), "not solved:\n{}", towers);
+    }
+
+    #[test]
+    fn frame_stewart_takes_the_fewest_moves() {
+        // https://oeis.org/A007664 for four pegs, A007665 for five.
+        let four = [0, 1, 3, 5, 9, 13, 17, 25, 33, 41, 49, 65, 81, 97, 113, 129];
+        let five = [0, 1, 3, 5, 7, 11, 15, 19, 23, 27, 31, 39, 47, 55, 63, 71];
+        for disks in 0..16 {
+            for &(pegs, expected) in &[(3, (1 << disks) - 1), (4, four[disks]), (5, five[disks])] {
+                assert_eq!(moves_needed(disks, pegs), expected);
+                let moves = frame_stewart(disks, pegs, 0, pegs - 1).unwrap();
+                assert_eq!(moves.len() as u64, expected);
+                check(Towers::new(pegs, disks).unwrap(), moves, pegs - 1);
+            }
+        }
+        assert_eq!(moves_needed(100, 3), u64::MAX);
+        assert_eq!(frame_stewart(3, 2, 0, 1), Err(Error::TooFewPegs(2)));
+        assert_eq!(frame_stewart(3, 3, 0, 3), Err(Error::NoSuchPeg(3)));
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let mut towers = Towers::new(3, 3).unwrap();
+        let m = |disk, from, to| Move { disk, from, to };
+        assert_eq!(
+            towers.apply(m(1, 0, 1)),
+            Err(Error::NotOnTop { disk: 1, peg: 0 })
+        );
+        assert_eq!(towers.apply(m(0, 0, 3)), Err(Error::NoSuchPeg(3)));
+        let error = towers.replay(vec![m(0, 0, 1), m(1, 0, 1)]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "move 2: disk 1 can't go on the smaller disk 0"
+        );
+        assert_eq!(towers.pegs(), &[vec![2, 1], vec![0], vec![]]);
+
+        let from = |pegs: Vec<Vec<usize>>| Towers::from_pegs(pegs).unwrap_err();
+        assert_eq!(from(vec![vec![0], vec![]]), Error::TooFewPegs(2));
+        assert_eq!(
+            from(vec![vec![0, 1], vec![], vec![]]),
+            Error::LargerOnSmaller { disk: 1, onto: 0 }
+        );
+        assert_eq!(
+            from(vec![vec![2, 0], vec![], vec![]]),
+            Error::MissingDisk(1)
+        );
+        assert_eq!(
+            from(vec![vec![1], vec![1], vec![]]),
+            Error::DuplicateDisk(1)
+        );
+    }
+
+    #[test]
+    fn from_anywhere() {
+        let towers = Towers::from_pegs(vec![vec![4, 1], vec![3, 2], vec![5, 0]]).unwrap();
+        for to in 0..3 {
+            check(towers.clone(), solve(&towers, to).unwrap(), to);
+        }
+        // Already there, or one move away.
+        let solved = Towers::from_pegs(vec![vec![], vec![], vec![1, 0]]).unwrap();
+        assert_eq!(solve(&solved, 2).unwrap(), vec![]);
+        let almost = Towers::from_pegs(vec![vec![0], vec![], vec![1]]).unwrap();
+        assert_eq!(
+            solve(&almost, 2).unwrap(),
+            vec![Move {
+                disk: 0,
+                from: 0,
+                to: 2
+            }]
+        );
+        // Every configuration of 4 disks on 4 pegs.
+        for code in 0..4usize.pow(4) {
+            let mut pegs = vec![Vec::new(); 4];
+            for disk in (0..4).rev() {
+                pegs[code / 4usize.pow(disk as u32) % 4].push(disk);
+            }
+            let towers = Towers::from_pegs(pegs).unwrap();
+            check(towers.clone(), solve(&towers, 3).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn iterative_matches_the_recursion() {
+        assert_eq!(iterative(65, 0, 2).unwrap_err(), Error::TooManyDisks(65));
+        for disks in 0..12 {
+            for &(from, to) in &[(0, 2), (0, 1), (2, 0), (1, 2)] {
+                let moves: Vec<Move> = iterative(disks, from, to).unwrap().collect();
+                let expected = frame_stewart(disks as usize, 3, from, to).unwrap();
+                assert_eq!(moves, expected);
+            }
+        }
+        let mut towers = Towers::new(3, 20).unwrap();
+        towers.replay(iterative(20, 0, 2).unwrap()).unwrap();
+        assert!(towers.is_solved(2));
+
+        // The largest disk moves in the middle, once.
+        let mut moves = iterative(64, 0, 2).unwrap();
+        assert_eq!(
+            moves.nth((1 << 63) - 1),
+            Some(Move {
+                disk: 63,
+                from: 0,
+                to: 2
+            })
+        );
+        let mut moves = iterative(40, 0, 2).unwrap();
+        assert_eq!(moves.len(), (1 << 40) - 1);
+        assert_eq!(
+            moves.nth((1 << 39) - 1),
+            Some(Move {
+                disk: 39,
+                from: 0,
+                to: 2
+            })
+        );
+        assert_eq!(moves.len(), (1 << 39) - 1);
+        assert_eq!(
+            moves.last(),
+            Some(Move {
+                disk: 0,
+                from: 1,
+                to: 2
+            })
+        );
+    }
+}
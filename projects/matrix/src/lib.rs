@@ -0,0 +1,973 @@
+//! # Matrices
+//!
+//! `Matrix<T>` has its size decided at run time, and keeps its elements row
+//! after row in one Vec; `SMatrix<T, R, C>` has it in its type, and a product
+//! of matrices that don't fit doesn't compile.
+//!
+//! - rotations by any multiple of 90 degrees and transposition happen in
+//!   place, square or not: a 2x3 matrix becomes a 3x2 one;
+//! - `+`, `-` and `*` work on references (and on values), `checked_mul`
+//!   returns an error instead of panicking;
+//! - `determinant` and `inverse`, by Gaussian elimination with partial
+//!   pivoting, for f32 and f64;
+//! - `view` borrows a block of rows and columns, `row` and `column` one of
+//!   them;
+//! - the text format is a row per line, the elements separated by spaces:
+//!   `parse` reads it, Display writes it with aligned columns.
+//!
+//! - https://en.wikipedia.org/wiki/Gaussian_elimination
+//! - https://en.wikipedia.org/wiki/In-place_matrix_transposition
+
+use std::error;
+use std::fmt;
+use std::ops::{Add, Div, Index, IndexMut, Mul, Neg, Range, Sub};
+use std::str::FromStr;
+
+/// What a matrix can hold for arithmetic.
+pub trait Scalar:
+    Copy + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+}
+
+/// What a matrix can hold to be inverted.
+pub trait Float: Scalar + PartialOrd + Div<Output = Self> + Neg<Output = Self> {
+    fn abs(self) -> Self;
+}
+
+macro_rules! scalar {
+    ($zero:expr, $one:expr, $($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                fn zero() -> $t {
+                    $zero
+                }
+
+                fn one() -> $t {
+                    $one
+                }
+            }
+        )*
+    };
+}
+
+scalar!(0, 1, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+scalar!(0.0, 1.0, f32, f64);
+
+impl Float for f32 {
+    fn abs(self) -> f32 {
+        f32::abs(self)
+    }
+}
+
+impl Float for f64 {
+    fn abs(self) -> f64 {
+        f64::abs(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    NotSquare {
+        rows: usize,
+        cols: usize,
+    },
+    /// Sizes, as (rows, columns), that don't fit together.
+    Mismatch {
+        left: (usize, usize),
+        right: (usize, usize),
+    },
+    Singular,
+    /// Not a multiple of 90 degrees.
+    Angle(i32),
+    /// Counted from 1.
+    Ragged {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotSquare { rows, cols } => {
+                write!(f, "a {}x{} matrix is not square", rows, cols)
+            }
+            Error::Mismatch { left, right } => write!(
+                f,
+                "a {}x{} matrix and a {}x{} one don't fit",
+                left.0, left.1, right.0, right.1
+            ),
+            Error::Singular => write!(f, "the matrix is singular"),
+            Error::Angle(degrees) => write!(f, "{} degrees is not a multiple of 90", degrees),
+            Error::Ragged {
+                line,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "line {}: expected {} elements, found {}",
+                    line, expected, found
+                )
+            }
+            Error::Parse {
+                line,
+                column,
+                ref message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// A matrix of any size.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    // Row after row.
+    data: Vec<T>,
+}
+
+impl<T> Matrix<T> {
+    pub fn from_fn<F: FnMut(usize, usize) -> T>(rows: usize, cols: usize, mut f: F) -> Matrix<T> {
+        let mut data = Vec::with_capacity(rows * cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                data.push(f(r, c));
+            }
+        }
+        Matrix { rows, cols, data }
+    }
+
+    /// Every row must be as long as the first one.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Matrix<T>, Error> {
+        let cols = rows.first().map_or(0, Vec::len);
+        let count = rows.len();
+        let mut data = Vec::with_capacity(count * cols);
+        for (i, row) in rows.into_iter().enumerate() {
+            if row.len() != cols {
+                return Err(Error::Ragged {
+                    line: i + 1,
+                    expected: cols,
+                    found: row.len(),
+                });
+            }
+            data.extend(row);
+        }
+        Ok(Matrix {
+            rows: count,
+            cols,
+            data,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        if row < self.rows && col < self.cols {
+            self.data.get(row * self.cols + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if row < self.rows && col < self.cols {
+            self.data.get_mut(row * self.cols + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        assert!(row < self.rows, "row {} of {}", row, self.rows);
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut [T] {
+        assert!(row < self.rows, "row {} of {}", row, self.rows);
+        &mut self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn column<'a>(&'a self, col: usize) -> impl Iterator<Item = &'a T> + 'a {
+        assert!(col < self.cols, "column {} of {}", col, self.cols);
+        self.data[col..].iter().step_by(self.cols)
+    }
+
+    /// The elements, row after row.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    /// A block of the matrix: it panics outside of it, as slicing does.
+    pub fn view<'a>(&'a self, rows: Range<usize>, cols: Range<usize>) -> View<'a, T> {
+        assert!(
+            rows.start <= rows.end && rows.end <= self.rows,
+            "rows {:?} of {}",
+            rows,
+            self.rows
+        );
+        assert!(
+            cols.start <= cols.end && cols.end <= self.cols,
+            "columns {:?} of {}",
+            cols,
+            self.cols
+        );
+        View {
+            matrix: self,
+            rows,
+            cols,
+        }
+    }
+
+    /// In place: a rows x cols matrix becomes a cols x rows one.
+    pub fn transpose(&mut self) {
+        let (rows, cols) = (self.rows, self.cols);
+        if rows == cols {
+            for r in 0..rows {
+                for c in r + 1..cols {
+                    self.data.swap(r * cols + c, c * cols + r);
+                }
+            }
+        } else if rows > 1 && cols > 1 {
+            // Every element moves along a cycle of positions: follow each
+            // cycle once, swapping the next element into the first position.
+            let to = |i: usize| (i % cols) * rows + i / cols;
+            let mut moved = vec![false; self.data.len()];
+            for start in 0..self.data.len() {
+                if moved[start] {
+                    continue;
+                }
+                let mut i = start;
+                loop {
+                    let next = to(i);
+                    moved[next] = true;
+                    if next == start {
+                        break;
+                    }
+                    self.data.swap(start, next);
+                    i = next;
+                }
+            }
+        }
+        self.rows = cols;
+        self.cols = rows;
+    }
+
+    /// Clockwise, in place, by a multiple of 90 degrees: counterclockwise if
+    /// negative.
+    pub fn rotate(&mut self, degrees: i32) -> Result<(), Error> {
+        if degrees % 90 != 0 {
+            return Err(Error::Angle(degrees));
+        }
+        match (degrees / 90).rem_euclid(4) {
+            1 => {
+                self.transpose();
+                for r in 0..self.rows {
+                    self.row_mut(r).reverse();
+                }
+            }
+            2 => self.data.reverse(),
+            3 => {
+                self.transpose();
+                for r in 0..self.rows / 2 {
+                    let last = self.rows - 1 - r;
+                    for c in 0..self.cols {
+                        self.data.swap(r * self.cols + c, last * self.cols + c);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Matrix<U> {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(f).collect(),
+        }
+    }
+}
+
+impl<T: Scalar> Matrix<T> {
+    pub fn zeros(rows: usize, cols: usize) -> Matrix<T> {
+        Matrix::from_fn(rows, cols, |_, _| T::zero())
+    }
+
+    pub fn identity(n: usize) -> Matrix<T> {
+        Matrix::from_fn(n, n, |r, c| if r == c { T::one() } else { T::zero() })
+    }
+
+    /// The product, or an error if the columns of this matrix aren't as many
+    /// as the rows of the other.
+    pub fn checked_mul(&self, other: &Matrix<T>) -> Result<Matrix<T>, Error> {
+        if self.cols != other.rows {
+            return Err(Error::Mismatch {
+                left: (self.rows, self.cols),
+                right: (other.rows, other.cols),
+            });
+        }
+        let mut product = Matrix::zeros(self.rows, other.cols);
+        for r in 0..self.rows {
+            for k in 0..self.cols {
+                let a = self.data[r * self.cols + k];
+                let (from, to) = (k * other.cols, (k + 1) * other.cols);
+                for (p, &b) in product.row_mut(r).iter_mut().zip(&other.data[from..to]) {
+                    *p = *p + a * b;
+                }
+            }
+        }
+        Ok(product)
+    }
+
+    fn zip_with<F: Fn(T, T) -> T>(&self, other: &Matrix<T>, f: F) -> Result<Matrix<T>, Error> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(Error::Mismatch {
+                left: (self.rows, self.cols),
+                right: (other.rows, other.cols),
+            });
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self
+                .data
+                .iter()
+                .zip(&other.data)
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+        })
+    }
+}
+
+impl<T: Float> Matrix<T> {
+    // Gaussian elimination on a copy, with the largest pivot of every column:
+    // the sign of the determinant flips with every swap of rows. The other
+    // matrix, if any, goes through the same operations.
+    fn eliminate(&self, mut other: Option<&mut Matrix<T>>) -> Result<(Matrix<T>, T), Error> {
+        if !self.is_square() {
+            return Err(Error::NotSquare {
+                rows: self.rows,
+                cols: self.cols,
+            });
+        }
+        let n = self.rows;
+        let mut a = self.clone();
+        let mut determinant = T::one();
+        for col in 0..n {
+            let pivot = (col..n)
+                .max_by(|&i, &j| {
+                    let (x, y) = (a[(i, col)].abs(), a[(j, col)].abs());
+                    x.partial_cmp(&y).unwrap_or(::std::cmp::Ordering::Equal)
+                })
+                .unwrap();
+            if a[(pivot, col)] == T::zero() {
+                return Err(Error::Singular);
+            }
+            if pivot != col {
+                swap_rows(&mut a, pivot, col);
+                if let Some(ref mut b) = other {
+                    swap_rows(b, pivot, col);
+                }
+                determinant = -determinant;
+            }
+            let p = a[(col, col)];
+            determinant = determinant * p;
+            for row in 0..n {
+                if row == col || (other.is_none() && row < col) {
+                    continue;
+                }
+                let factor = a[(row, col)] / p;
+                if factor == T::zero() {
+                    continue;
+                }
+                for c in 0..n {
+                    let x = a[(col, c)];
+                    a[(row, c)] = a[(row, c)] - factor * x;
+                }
+                if let Some(ref mut b) = other {
+                    for c in 0..b.cols {
+                        let x = b[(col, c)];
+                        b[(row, c)] = b[(row, c)] - factor * x;
+                    }
+                }
+            }
+        }
+        Ok((a, determinant))
+    }
+
+    pub fn determinant(&self) -> Result<T, Error> {
+        match self.eliminate(None) {
+            Ok((_, determinant)) => Ok(determinant),
+            Err(Error::Singular) => Ok(T::zero()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn inverse(&self) -> Result<Matrix<T>, Error> {
+        let mut inverse = Matrix::identity(self.rows);
+        let (reduced, _) = self.eliminate(Some(&mut inverse))?;
+        // What's left is diagonal.
+        for r in 0..self.rows {
+            let d = reduced[(r, r)];
+            for x in inverse.row_mut(r) {
+                *x = *x / d;
+            }
+        }
+        Ok(inverse)
+    }
+}
+
+fn swap_rows<T>(m: &mut Matrix<T>, a: usize, b: usize) {
+    for c in 0..m.cols {
+        m.data.swap(a * m.cols + c, b * m.cols + c);
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        assert!(
+            row < self.rows && col < self.cols,
+            "({}, {}) of {}x{}",
+            row,
+            col,
+            self.rows,
+            self.cols
+        );
+        &self.data[row * self.cols + col]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        assert!(
+            row < self.rows && col < self.cols,
+            "({}, {}) of {}x{}",
+            row,
+            col,
+            self.rows,
+            self.cols
+        );
+        &mut self.data[row * self.cols + col]
+    }
+}
+
+// The operators panic when the sizes don't fit, as the ones of arrays do.
+macro_rules! operator {
+    ($trait:ident, $method:ident, $body:expr) => {
+        impl<'a, T: Scalar> $trait<&'a Matrix<T>> for &'a Matrix<T> {
+            type Output = Matrix<T>;
+
+            fn $method(self, other: &'a Matrix<T>) -> Matrix<T> {
+                let f: fn(&Matrix<T>, &Matrix<T>) -> Result<Matrix<T>, Error> = $body;
+                f(self, other).unwrap_or_else(|e| panic!("{}", e))
+            }
+        }
+
+        impl<T: Scalar> $trait for Matrix<T> {
+            type Output = Matrix<T>;
+
+            fn $method(self, other: Matrix<T>) -> Matrix<T> {
+                $trait::$method(&self, &other)
+            }
+        }
+    };
+}
+
+operator!(Add, add, |a, b| a.zip_with(b, |x, y| x + y));
+operator!(Sub, sub, |a, b| a.zip_with(b, |x, y| x - y));
+operator!(Mul, mul, |a, b| a.checked_mul(b));
+
+/// A block of rows and columns of a matrix.
+#[derive(Debug, Clone)]
+pub struct View<'a, T: 'a> {
+    matrix: &'a Matrix<T>,
+    rows: Range<usize>,
+    cols: Range<usize>,
+}
+
+impl<'a, T> View<'a, T> {
+    pub fn rows(&self) -> usize {
+        self.rows.end - self.rows.start
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols.end - self.cols.start
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&'a T> {
+        if row < self.rows() && col < self.cols() {
+            self.matrix
+                .get(self.rows.start + row, self.cols.start + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self, row: usize) -> &'a [T] {
+        assert!(row < self.rows(), "row {} of {}", row, self.rows());
+        &self.matrix.row(self.rows.start + row)[self.cols.clone()]
+    }
+
+    pub fn column(&self, col: usize) -> impl Iterator<Item = &'a T> + 'a {
+        assert!(col < self.cols(), "column {} of {}", col, self.cols());
+        let rows = self.rows();
+        self.matrix
+            .column(self.cols.start + col)
+            .skip(self.rows.start)
+            .take(rows)
+    }
+
+    /// A block of this block.
+    pub fn view(&self, rows: Range<usize>, cols: Range<usize>) -> View<'a, T> {
+        assert!(
+            rows.start <= rows.end && rows.end <= self.rows(),
+            "rows {:?} of {}",
+            rows,
+            self.rows()
+        );
+        assert!(
+            cols.start <= cols.end && cols.end <= self.cols(),
+            "columns {:?} of {}",
+            cols,
+            self.cols()
+        );
+        View {
+            matrix: self.matrix,
+            rows: self.rows.start + rows.start..self.rows.start + rows.end,
+            cols: self.cols.start + cols.start..self.cols.start + cols.end,
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        Matrix::from_fn(self.rows(), self.cols(), |r, c| self[(r, c)].clone())
+    }
+}
+
+impl<'a, T> Index<(usize, usize)> for View<'a, T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        self.get(row, col)
+            .unwrap_or_else(|| panic!("({}, {}) of {}x{}", row, col, self.rows(), self.cols()))
+    }
+}
+
+// The columns aligned to the right, as wide as their widest element.
+fn write_rows<'a, T, I>(f: &mut fmt::Formatter, rows: I) -> fmt::Result
+where
+    T: fmt::Display + 'a,
+    I: Iterator<Item = &'a [T]>,
+{
+    let cells: Vec<Vec<String>> = rows
+        .map(|row| {
+            row.iter()
+                .map(|x| match f.precision() {
+                    Some(p) => format!("{:.*}", p, x),
+                    None => x.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    let cols = cells.first().map_or(0, Vec::len);
+    let widths: Vec<usize> = (0..cols)
+        .map(|c| {
+            cells
+                .iter()
+                .map(|row| row[c].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in &cells {
+        for (c, cell) in row.iter().enumerate() {
+            if c > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{:>1$}", cell, widths[c])?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// A row per line, the elements separated by spaces.
+impl<T: fmt::Display> fmt::Display for Matrix<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_rows(f, (0..self.rows).map(|r| self.row(r)))
+    }
+}
+
+impl<'a, T: fmt::Display> fmt::Display for View<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_rows(f, (0..self.rows()).map(|r| self.row(r)))
+    }
+}
+
+/// The format of Display: blank lines are skipped.
+impl<T: FromStr> FromStr for Matrix<T>
+where
+    T::Err: fmt::Display,
+{
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Matrix<T>, Error> {
+        let mut rows: Vec<Vec<T>> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut row = Vec::new();
+            for word in line.split_whitespace() {
+                let offset = word.as_ptr() as usize - line.as_ptr() as usize;
+                let x = word.parse().map_err(|e: T::Err| Error::Parse {
+                    line: i + 1,
+                    column: line[..offset].chars().count() + 1,
+                    message: format!("invalid element '{}': {}", word, e),
+                })?;
+                row.push(x);
+            }
+            if row.is_empty() {
+                continue;
+            }
+            if let Some(first) = rows.first() {
+                if first.len() != row.len() {
+                    return Err(Error::Ragged {
+                        line: i + 1,
+                        expected: first.len(),
+                        found: row.len(),
+                    });
+                }
+            }
+            rows.push(row);
+        }
+        Matrix::from_rows(rows)
+    }
+}
+
+/// A matrix of R rows and C columns, on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SMatrix<T, const R: usize, const C: usize> {
+    pub data: [[T; C]; R],
+}
+
+impl<T, const R: usize, const C: usize> SMatrix<T, R, C> {
+    pub fn new(data: [[T; C]; R]) -> SMatrix<T, R, C> {
+        SMatrix { data }
+    }
+}
+
+impl<T: Copy, const R: usize, const C: usize> SMatrix<T, R, C> {
+    pub fn transposed(&self) -> SMatrix<T, C, R> {
+        SMatrix {
+            data: ::std::array::from_fn(|r| ::std::array::from_fn(|c| self.data[c][r])),
+        }
+    }
+
+    /// A quarter turn clockwise.
+    pub fn rotated_90(&self) -> SMatrix<T, C, R> {
+        SMatrix {
+            data: ::std::array::from_fn(|r| ::std::array::from_fn(|c| self.data[R - 1 - c][r])),
+        }
+    }
+
+    pub fn rotated_180(&self) -> SMatrix<T, R, C> {
+        SMatrix {
+            data: ::std::array::from_fn(|r| {
+                ::std::array::from_fn(|c| self.data[R - 1 - r][C - 1 - c])
+            }),
+        }
+    }
+
+    /// A quarter turn counterclockwise.
+    pub fn rotated_270(&self) -> SMatrix<T, C, R> {
+        SMatrix {
+            data: ::std::array::from_fn(|r| ::std::array::from_fn(|c| self.data[c][C - 1 - r])),
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> SMatrix<T, N, N> {
+    /// In place, clockwise, by a multiple of 90 degrees.
+    pub fn rotate(&mut self, degrees: i32) -> Result<(), Error> {
+        if degrees % 90 != 0 {
+            return Err(Error::Angle(degrees));
+        }
+        *self = match (degrees / 90).rem_euclid(4) {
+            1 => self.rotated_90(),
+            2 => self.rotated_180(),
+            3 => self.rotated_270(),
+            _ => *self,
+        };
+        Ok(())
+    }
+}
+
+impl<T: Scalar, const N: usize> SMatrix<T, N, N> {
+    pub fn identity() -> SMatrix<T, N, N> {
+        SMatrix {
+            data: ::std::array::from_fn(|r| {
+                ::std::array::from_fn(|c| if r == c { T::one() } else { T::zero() })
+            }),
+        }
+    }
+}
+
+impl<T: Float, const N: usize> SMatrix<T, N, N> {
+    pub fn determinant(&self) -> T {
+        Matrix::from(*self).determinant().unwrap()
+    }
+
+    pub fn inverse(&self) -> Result<SMatrix<T, N, N>, Error> {
+        let inverse = Matrix::from(*self).inverse()?;
+        Ok(SMatrix {
+            data: ::std::array::from_fn(|r| ::std::array::from_fn(|c| inverse[(r, c)])),
+        })
+    }
+}
+
+/// Only matrices that fit: R x K times K x C.
+impl<T: Scalar, const R: usize, const K: usize, const C: usize> Mul<SMatrix<T, K, C>>
+    for SMatrix<T, R, K>
+{
+    type Output = SMatrix<T, R, C>;
+
+    fn mul(self, other: SMatrix<T, K, C>) -> SMatrix<T, R, C> {
+        SMatrix {
+            data: ::std::array::from_fn(|r| {
+                ::std::array::from_fn(|c| {
+                    (0..K).fold(T::zero(), |sum, k| sum + self.data[r][k] * other.data[k][c])
+                })
+            }),
+        }
+    }
+}
+
+impl<T, const R: usize, const C: usize> Index<(usize, usize)> for SMatrix<T, R, C> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        &self.data[row][col]
+    }
+}
+
+impl<T, const R: usize, const C: usize> IndexMut<(usize, usize)> for SMatrix<T, R, C> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        &mut self.data[row][col]
+    }
+}
+
+impl<T, const R: usize, const C: usize> From<SMatrix<T, R, C>> for Matrix<T> {
+    fn from(m: SMatrix<T, R, C>) -> Matrix<T> {
+        Matrix {
+            rows: R,
+            cols: C,
+            data: IntoIterator::into_iter(m.data)
+                .flat_map(IntoIterator::into_iter)
+                .collect(),
+        }
+    }
+}
+
+/// If the sizes are the same.
+impl<'a, T: Copy, const R: usize, const C: usize> ::std::convert::TryFrom<&'a Matrix<T>>
+    for SMatrix<T, R, C>
+{
+    type Error = Error;
+
+    fn try_from(m: &'a Matrix<T>) -> Result<SMatrix<T, R, C>, Error> {
+        if (m.rows, m.cols) != (R, C) {
+            return Err(Error::Mismatch {
+                left: (m.rows, m.cols),
+                right: (R, C),
+            });
+        }
+        Ok(SMatrix {
+            data: ::std::array::from_fn(|r| ::std::array::from_fn(|c| m[(r, c)])),
+        })
+    }
+}
+
+impl<T: fmt::Display, const R: usize, const C: usize> fmt::Display for SMatrix<T, R, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_rows(f, self.data.iter().map(|row| &row[..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn counting(rows: usize, cols: usize) -> Matrix<usize> {
+        Matrix::from_fn(rows, cols, |r, c| r * cols + c)
+    }
+
+    #[test]
+    fn rotations() {
+        let mut m = counting(2, 3);
+        m.rotate(90).unwrap();
+        assert_eq!(m, "3 0\n4 1\n5 2".parse().unwrap());
+        m.rotate(90).unwrap();
+        assert_eq!(m, "5 4 3\n2 1 0".parse().unwrap());
+        m.rotate(-90).unwrap();
+        m.rotate(-180).unwrap();
+        assert_eq!(m, "2 5\n1 4\n0 3".parse().unwrap());
+        m.rotate(-270).unwrap();
+        assert_eq!(m, counting(2, 3));
+        assert_eq!(m.rotate(45), Err(Error::Angle(45)));
+
+        // Four quarter turns, of every shape.
+        for &(rows, cols) in &[(0, 0), (1, 1), (1, 5), (5, 1), (4, 4), (3, 7), (6, 4)] {
+            let mut m = counting(rows, cols);
+            for _ in 0..4 {
+                let before = m.clone();
+                m.rotate(90).unwrap();
+                assert_eq!((m.rows(), m.cols()), (before.cols(), before.rows()));
+                for r in 0..m.rows() {
+                    for c in 0..m.cols() {
+                        assert_eq!(m[(r, c)], before[(before.rows() - 1 - c, r)]);
+                    }
+                }
+            }
+            assert_eq!(m, counting(rows, cols));
+            m.transpose();
+            m.transpose();
+            assert_eq!(m, counting(rows, cols));
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a: Matrix<i32> = "1 2 3\n4 5 6".parse().unwrap();
+        let b: Matrix<i32> = "7 8\n9 10\n11 12".parse().unwrap();
+        assert_eq!(&a * &b, "58 64\n139 154".parse().unwrap());
+        assert_eq!(&a + &a, "2 4 6\n8 10 12".parse().unwrap());
+        assert_eq!(a.clone() - a.clone(), Matrix::zeros(2, 3));
+        assert_eq!(&a * &Matrix::identity(3), a);
+        assert_eq!(
+            a.checked_mul(&a),
+            Err(Error::Mismatch {
+                left: (2, 3),
+                right: (2, 3)
+            })
+        );
+    }
+
+    #[test]
+    fn determinant_and_inverse() {
+        let m: Matrix<f64> = "2 -1 0\n-1 2 -1\n0 -1 2".parse().unwrap();
+        assert!((m.determinant().unwrap() - 4.0).abs() < 1e-12);
+        let inverse = m.inverse().unwrap();
+        let product = &m * &inverse;
+        for r in 0..3 {
+            for c in 0..3 {
+                let expected = if r == c { 1.0 } else { 0.0 };
+                assert!((product[(r, c)] - expected).abs() < 1e-12, "{}", product);
+            }
+        }
+        // A zero pivot to swap away.
+        let swap: Matrix<f64> = "0 1\n1 0".parse().unwrap();
+        assert_eq!(swap.determinant(), Ok(-1.0));
+        assert_eq!(swap.inverse(), Ok(swap.clone()));
+
+        let singular: Matrix<f64> = "1 2\n2 4".parse().unwrap();
+        assert_eq!(singular.determinant(), Ok(0.0));
+        assert_eq!(singular.inverse(), Err(Error::Singular));
+        let wide: Matrix<f64> = Matrix::zeros(2, 3);
+        assert_eq!(wide.inverse(), Err(Error::NotSquare { rows: 2, cols: 3 }));
+    }
+
+    #[test]
+    fn views() {
+        let m = counting(4, 5);
+        assert_eq!(m.row(1), &[5, 6, 7, 8, 9]);
+        assert_eq!(m.column(2).cloned().collect::<Vec<_>>(), vec![2, 7, 12, 17]);
+
+        let v = m.view(1..3, 2..5);
+        assert_eq!((v.rows(), v.cols()), (2, 3));
+        assert_eq!(v.row(1), &[12, 13, 14]);
+        assert_eq!(v.column(0).cloned().collect::<Vec<_>>(), vec![7, 12]);
+        assert_eq!(v[(0, 2)], 9);
+        assert_eq!(v.get(2, 0), None);
+        let inner = v.view(1..2, 1..3);
+        assert_eq!(inner.to_matrix(), "13 14".parse().unwrap());
+        assert_eq!(v.to_string(), " 7  8  9\n12 13 14\n");
+    }
+
+    #[test]
+    fn text() {
+        let m: Matrix<f64> = "\n 1   -2.5\n\n10 0.25\n".parse().unwrap();
+        assert_eq!(m.to_string(), " 1 -2.5\n10 0.25\n");
+        assert_eq!(format!("{:.1}", m), " 1.0 -2.5\n10.0  0.2\n");
+        assert_eq!(m.to_string().parse::<Matrix<f64>>().unwrap(), m);
+
+        let error = |text: &str| text.parse::<Matrix<i32>>().unwrap_err().to_string();
+        assert_eq!(error("1 2\n3"), "line 2: expected 2 elements, found 1");
+        assert_eq!(
+            error("1 2\n3  x"),
+            "line 2, column 4: invalid element 'x': invalid digit found in string"
+        );
+        assert_eq!(
+            "".parse::<Matrix<i32>>().unwrap(),
+            Matrix::from_rows(vec![]).unwrap()
+        );
+    }
+
+    #[test]
+    fn const_sizes() {
+        let a = SMatrix::new([[1, 2, 3], [4, 5, 6]]);
+        let b = SMatrix::new([[7, 8], [9, 10], [11, 12]]);
+        let product: SMatrix<i32, 2, 2> = a * b;
+        assert_eq!(product, SMatrix::new([[58, 64], [139, 154]]));
+        assert_eq!(a.transposed().transposed(), a);
+        assert_eq!(a.rotated_90(), SMatrix::new([[4, 1], [5, 2], [6, 3]]));
+        assert_eq!(a.rotated_270(), SMatrix::new([[3, 6], [2, 5], [1, 4]]));
+        assert_eq!(a.rotated_180(), SMatrix::new([[6, 5, 4], [3, 2, 1]]));
+
+        let mut square = SMatrix::new([[1, 2], [3, 4]]);
+        square.rotate(-90).unwrap();
+        assert_eq!(square, SMatrix::new([[2, 4], [1, 3]]));
+
+        // The same as the runtime ones.
+        let dynamic = Matrix::from(a);
+        let mut rotated = dynamic.clone();
+        rotated.rotate(90).unwrap();
+        assert_eq!(Matrix::from(a.rotated_90()), rotated);
+        assert_eq!(SMatrix::<i32, 2, 3>::try_from(&dynamic), Ok(a));
+        assert!(SMatrix::<i32, 3, 2>::try_from(&dynamic).is_err());
+
+        let f = SMatrix::new([[4.0, 7.0], [2.0, 6.0]]);
+        assert!((f.determinant() - 10.0).abs() < 1e-12);
+        let identity = f * f.inverse().unwrap();
+        assert!((identity[(0, 0)] - 1.0).abs() < 1e-12 && identity[(0, 1)].abs() < 1e-12);
+        assert_eq!(
+            SMatrix::<f64, 2, 2>::identity(),
+            SMatrix::new([[1.0, 0.0], [0.0, 1.0]])
+        );
+    }
+}
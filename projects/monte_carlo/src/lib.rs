@@ -0,0 +1,577 @@
+//! # Monte Carlo simulations
+//!
+//! An `Experiment` is one random trial: it gets a random number generator
+//! and returns an outcome, a number or a bool (1 for true). The `Runner`
+//! repeats it, on several threads, and reports the mean of the outcomes with
+//! a confidence interval, and how the estimate got there.
+//!
+//! The same seed gives the same report, whatever the number of threads: the
+//! trials are split in blocks, every block has a generator seeded from the
+//! seed and its own index, and the results of the blocks are merged in order.
+//!
+//! The generator is xoshiro256**, seeded with SplitMix64: fast, good enough
+//! for simulations, not for cryptography, and with no dependencies, so that a
+//! script can include this file.
+//!
+//! - https://en.wikipedia.org/wiki/Monte_Carlo_method
+//! - https://prng.di.unimi.it/
+//! - https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Parallel_algorithm
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// A seeded random number generator.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut x = seed;
+        Rng {
+            state: [
+                splitmix64(&mut x),
+                splitmix64(&mut x),
+                splitmix64(&mut x),
+                splitmix64(&mut x),
+            ],
+        }
+    }
+
+    // The generator of a block of trials.
+    fn for_block(seed: u64, block: usize) -> Rng {
+        let mut x = block as u64;
+        Rng::new(seed ^ splitmix64(&mut x))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// In [0, 1).
+    pub fn float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// In [low, high).
+    pub fn uniform(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.float()
+    }
+
+    /// In [0, n), every number as likely: n must not be 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "below(0)");
+        // Lemire's multiply and reject: the 2^64 % n lowest products would
+        // make the first numbers more likely.
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let m = u128::from(self.next_u64()) * u128::from(n);
+            if m as u64 >= threshold {
+                return (m >> 64) as u64;
+            }
+        }
+    }
+
+    /// True with probability p.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.float() < p
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            items.get(self.below(items.len() as u64) as usize)
+        }
+    }
+
+    /// Fisher–Yates.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+/// What a trial returns: its mean is what is estimated.
+pub trait Outcome {
+    fn value(self) -> f64;
+}
+
+impl Outcome for bool {
+    fn value(self) -> f64 {
+        if self {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+macro_rules! outcome {
+    ($($t:ty),*) => {
+        $(
+            impl Outcome for $t {
+                fn value(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+outcome!(f64, f32, u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// A random trial. Closures are experiments too.
+pub trait Experiment: Sync {
+    type Outcome: Outcome;
+
+    fn trial(&self, rng: &mut Rng) -> Self::Outcome;
+}
+
+impl<F, O> Experiment for F
+where
+    F: Fn(&mut Rng) -> O + Sync,
+    O: Outcome,
+{
+    type Outcome = O;
+
+    fn trial(&self, rng: &mut Rng) -> O {
+        self(rng)
+    }
+}
+
+/// Count, mean and variance, one value at a time (Welford), and mergeable.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Moments {
+    count: u64,
+    mean: f64,
+    // The sum of the squared differences from the mean.
+    m2: f64,
+}
+
+impl Moments {
+    pub fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn merge(&mut self, other: &Moments) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 +=
+            other.m2 + delta * delta * (self.count as f64 * other.count as f64 / count as f64);
+        self.count = count;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Of the sample.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// The standard deviation of the mean.
+    pub fn std_error(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.variance() / self.count as f64).sqrt()
+        }
+    }
+}
+
+// The standard normal cumulative distribution, from the error function of
+// Abramowitz and Stegun (7.1.26): 1.5e-7 is close enough for an interval.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / 2f64.sqrt();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+// How many standard errors away from the mean a two-sided interval goes:
+// 1.96 for 0.95.
+fn z_score(confidence: f64) -> f64 {
+    let target = 0.5 + confidence / 2.0;
+    let (mut low, mut high) = (0.0, 10.0);
+    for _ in 0..60 {
+        let middle = (low + high) / 2.0;
+        if normal_cdf(middle) < target {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Runs an experiment many times.
+#[derive(Debug, Clone)]
+pub struct Runner {
+    trials: u64,
+    seed: u64,
+    threads: usize,
+    confidence: f64,
+    block: u64,
+}
+
+impl Runner {
+    /// 0 as the seed, 0.95 as the confidence, and a thread per CPU.
+    pub fn new(trials: u64) -> Runner {
+        Runner {
+            trials,
+            seed: 0,
+            threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            confidence: 0.95,
+            block: 4096,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Runner {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Runner {
+        assert!(threads > 0, "no threads to run on");
+        self.threads = threads;
+        self
+    }
+
+    /// Of the intervals, between 0 and 1.
+    pub fn with_confidence(mut self, confidence: f64) -> Runner {
+        assert!(
+            confidence > 0.0 && confidence < 1.0,
+            "the confidence is between 0 and 1"
+        );
+        self.confidence = confidence;
+        self
+    }
+
+    /// How many trials make a block, the unit of work of a thread and the
+    /// step of the convergence: the report depends on it, as on the seed.
+    pub fn with_block(mut self, block: u64) -> Runner {
+        assert!(block > 0, "empty blocks");
+        self.block = block;
+        self
+    }
+
+    pub fn run<E: Experiment>(&self, experiment: &E) -> Report {
+        let blocks = self.trials.div_ceil(self.block) as usize;
+        let results = Mutex::new(vec![Moments::default(); blocks]);
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(blocks.max(1)) {
+                scope.spawn(|| loop {
+                    let block = next.fetch_add(1, Ordering::Relaxed);
+                    if block >= blocks {
+                        break;
+                    }
+                    let start = block as u64 * self.block;
+                    let end = (start + self.block).min(self.trials);
+                    let mut rng = Rng::for_block(self.seed, block);
+                    let mut moments = Moments::default();
+                    for _ in start..end {
+                        moments.add(experiment.trial(&mut rng).value());
+                    }
+                    results.lock().unwrap()[block] = moments;
+                });
+            }
+        });
+
+        // In order, so that the sums are the same whatever the threads did.
+        let z = z_score(self.confidence);
+        let mut total = Moments::default();
+        let mut convergence = Vec::new();
+        for (i, moments) in results.into_inner().unwrap().iter().enumerate() {
+            total.merge(moments);
+            // After 1, 2, 4, 8... blocks, and the last one.
+            if (i + 1).is_power_of_two() || i + 1 == blocks {
+                convergence.push(Estimate::new(&total, z));
+            }
+        }
+        Report {
+            estimate: Estimate::new(&total, z),
+            confidence: self.confidence,
+            variance: total.variance(),
+            convergence,
+        }
+    }
+}
+
+/// A mean, and its confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub trials: u64,
+    pub mean: f64,
+    /// The interval is the mean, plus or minus this.
+    pub margin: f64,
+}
+
+impl Estimate {
+    fn new(moments: &Moments, z: f64) -> Estimate {
+        Estimate {
+            trials: moments.count(),
+            mean: moments.mean(),
+            margin: z * moments.std_error(),
+        }
+    }
+
+    pub fn interval(&self) -> (f64, f64) {
+        (self.mean - self.margin, self.mean + self.margin)
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        let (low, high) = self.interval();
+        low <= x && x <= high
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = f.precision().unwrap_or(4);
+        write!(
+            f,
+            "{:.*} ± {:.*}",
+            precision, self.mean, precision, self.margin
+        )
+    }
+}
+
+/// What a run found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub estimate: Estimate,
+    pub confidence: f64,
+    /// Of the outcomes.
+    pub variance: f64,
+    /// The estimate after more and more trials.
+    pub convergence: Vec<Estimate>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ({}% confidence, {} trials)",
+            self.estimate,
+            self.confidence * 100.0,
+            self.estimate.trials
+        )?;
+        for e in &self.convergence {
+            writeln!(f, "{:>12} trials: {}", e.trials, e)?;
+        }
+        Ok(())
+    }
+}
+
+/// Monty Hall, with `doors` doors: the player picks one, the host opens
+/// `opened` of the others, never the one with the car, and the player sticks
+/// to the first choice or switches to another closed door.
+#[derive(Debug, Clone, Copy)]
+pub struct MontyHall {
+    pub doors: u64,
+    pub opened: u64,
+    pub switch: bool,
+}
+
+impl MontyHall {
+    /// The host must leave a door to switch to.
+    pub fn new(doors: u64, opened: u64, switch: bool) -> MontyHall {
+        assert!(
+            opened + 2 <= doors,
+            "the host can open at most {} of {} doors",
+            doors.saturating_sub(2),
+            doors
+        );
+        MontyHall {
+            doors,
+            opened,
+            switch,
+        }
+    }
+
+    /// The chance to win: 1/n sticking, (n - 1)/n times 1/(n - k - 1)
+    /// switching.
+    pub fn exact(&self) -> f64 {
+        let n = self.doors as f64;
+        if self.switch {
+            (n - 1.0) / n / (n - self.opened as f64 - 1.0)
+        } else {
+            1.0 / n
+        }
+    }
+}
+
+impl Experiment for MontyHall {
+    type Outcome = bool;
+
+    fn trial(&self, rng: &mut Rng) -> bool {
+        let car = rng.below(self.doors);
+        let mut choice = rng.below(self.doors);
+        // The host opens doors at random, among the ones that are neither
+        // the car nor the choice.
+        let mut openable: Vec<u64> = (0..self.doors)
+            .filter(|&d| d != car && d != choice)
+            .collect();
+        rng.shuffle(&mut openable);
+        let opened = &openable[..self.opened as usize];
+        if self.switch {
+            let closed: Vec<u64> = (0..self.doors)
+                .filter(|&d| d != choice && !opened.contains(&d))
+                .collect();
+            choice = *rng.choose(&closed).unwrap();
+        }
+        choice == car
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generator() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(
+            first,
+            (0..10).map(|_| Rng::new(8).next_u64()).collect::<Vec<_>>()
+        );
+
+        let mut counts = [0; 6];
+        for _ in 0..60_000 {
+            counts[a.below(6) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (9_000..11_000).contains(&c)),
+            "{:?}",
+            counts
+        );
+        assert!((0..1000)
+            .map(|_| a.float())
+            .all(|x| (0.0..1.0).contains(&x)));
+
+        let mut items: Vec<u32> = (0..20).collect();
+        a.shuffle(&mut items);
+        assert_ne!(items, (0..20).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn moments_merge() {
+        let values: Vec<f64> = (0..100).map(|i| (i * i % 17) as f64).collect();
+        let mut all = Moments::default();
+        values.iter().for_each(|&x| all.add(x));
+        let (mut a, mut b) = (Moments::default(), Moments::default());
+        values[..30].iter().for_each(|&x| a.add(x));
+        values[30..].iter().for_each(|&x| b.add(x));
+        a.merge(&b);
+        assert_eq!(a.count(), 100);
+        assert!((a.mean() - all.mean()).abs() < 1e-12);
+        assert!((a.variance() - all.variance()).abs() < 1e-9);
+        assert!((z_score(0.95) - 1.96).abs() < 1e-3);
+        assert!((z_score(0.99) - 2.576).abs() < 1e-3);
+    }
+
+    #[test]
+    fn reproducible_whatever_the_threads() {
+        let pi = |rng: &mut Rng| {
+            let (x, y) = (rng.uniform(-1.0, 1.0), rng.uniform(-1.0, 1.0));
+            if x * x + y * y <= 1.0 {
+                4.0
+            } else {
+                0.0
+            }
+        };
+        let runner = Runner::new(200_000).with_seed(42).with_block(1000);
+        let one = runner.clone().with_threads(1).run(&pi);
+        let four = runner.clone().with_threads(4).run(&pi);
+        assert_eq!(one, four);
+        assert_ne!(one, runner.with_seed(43).run(&pi));
+
+        assert_eq!(one.estimate.trials, 200_000);
+        assert!(one.estimate.contains(::std::f64::consts::PI), "{}", one);
+        let counts: Vec<u64> = one.convergence.iter().map(|e| e.trials).collect();
+        assert_eq!(
+            counts,
+            vec![1000, 2000, 4000, 8000, 16_000, 32_000, 64_000, 128_000, 200_000]
+        );
+        // The margin shrinks with the square root of the trials.
+        let (first, last) = (one.convergence[0], one.convergence[8]);
+        assert!(first.margin / last.margin > 10.0);
+    }
+
+    #[test]
+    fn monty_hall() {
+        for &(doors, opened) in &[(3, 1), (10, 8), (10, 3), (100, 0)] {
+            for &switch in &[false, true] {
+                let game = MontyHall::new(doors, opened, switch);
+                let report = Runner::new(40_000)
+                    .with_seed(doors)
+                    .with_confidence(0.999)
+                    .run(&game);
+                assert!(
+                    report.estimate.contains(game.exact()),
+                    "{} doors, {} opened, switch {}: {} and not {}",
+                    doors,
+                    opened,
+                    switch,
+                    report.estimate,
+                    game.exact()
+                );
+            }
+        }
+        assert!((MontyHall::new(3, 1, true).exact() - 2.0 / 3.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,111 @@
+// Dijkstra on large random graphs, with BinaryHeap (pushing a node again
+// every time its distance gets shorter) and with PriorityQueue (moving it
+// forward with decrease_key).
+//
+//     cargo bench
+//
+// The denser the graph, the more often a distance gets shorter, and the more
+// stale states BinaryHeap has to pop and skip. On sparse graphs there are few
+// of them, and BinaryHeap is faster: it doesn't keep track of where every
+// item is. On dense ones, decrease_key pays for that, the more so with a
+// larger arity.
+
+#[macro_use]
+extern crate criterion;
+extern crate priority_queue;
+extern crate rand;
+
+use criterion::{BenchmarkId, Criterion};
+use priority_queue::{Handle, PriorityQueue};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+const NODES: usize = 20_000;
+
+// Every node has `degree` edges to random nodes, weighing 1 to 1000.
+fn random_graph(nodes: usize, degree: usize) -> Vec<Vec<(usize, u64)>> {
+    let mut rng = StdRng::seed_from_u64(2018);
+    (0..nodes)
+        .map(|_| {
+            (0..degree)
+                .map(|_| (rng.gen_range(0..nodes), rng.gen_range(1..=1000)))
+                .collect()
+        })
+        .collect()
+}
+
+fn with_binary_heap(graph: &[Vec<(usize, u64)>], start: usize) -> Vec<u64> {
+    let mut distances = vec![u64::MAX; graph.len()];
+    let mut heap = BinaryHeap::new();
+    distances[start] = 0;
+    heap.push(Reverse((0, start)));
+    while let Some(Reverse((distance, node))) = heap.pop() {
+        if distance > distances[node] {
+            continue;
+        }
+        for &(next, weight) in &graph[node] {
+            let through = distance + weight;
+            if through < distances[next] {
+                distances[next] = through;
+                heap.push(Reverse((through, next)));
+            }
+        }
+    }
+    distances
+}
+
+fn with_priority_queue(graph: &[Vec<(usize, u64)>], start: usize, arity: usize) -> Vec<u64> {
+    let mut distances = vec![u64::MAX; graph.len()];
+    let mut handles: Vec<Option<Handle>> = vec![None; graph.len()];
+    let mut queue = PriorityQueue::new().with_arity(arity);
+    distances[start] = 0;
+    queue.push((0, start));
+    while let Some((distance, node)) = queue.pop() {
+        for &(next, weight) in &graph[node] {
+            let through = distance + weight;
+            if through < distances[next] {
+                distances[next] = through;
+                match handles[next] {
+                    Some(handle) if queue.contains(handle) => {
+                        let _ = queue.decrease_key(handle, (through, next));
+                    }
+                    _ => handles[next] = Some(queue.push((through, next))),
+                }
+            }
+        }
+    }
+    distances
+}
+
+fn dijkstra(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dijkstra, 20000 nodes");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(5));
+
+    for &degree in &[4, 32] {
+        let graph = random_graph(NODES, degree);
+        let expected = with_binary_heap(&graph, 0);
+        for &arity in &[2, 4, 8] {
+            assert_eq!(with_priority_queue(&graph, 0, arity), expected);
+        }
+
+        let edges = format!("{} edges", NODES * degree);
+        group.bench_with_input(BenchmarkId::new("BinaryHeap", &edges), &graph, |b, g| {
+            b.iter(|| with_binary_heap(g, 0))
+        });
+        for &arity in &[2, 4, 8] {
+            let name = format!("PriorityQueue, arity {}", arity);
+            group.bench_with_input(BenchmarkId::new(name, &edges), &graph, |b, g| {
+                b.iter(|| with_priority_queue(g, 0, arity))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, dijkstra);
+criterion_main!(benches);
@@ -0,0 +1,476 @@
+//! # An indexed priority queue
+//!
+//! `std::collections::BinaryHeap` is a max-heap over `Ord`, so a min-heap
+//! needs a type with a reversed `Ord` (as `State` in scripts/dijkstra.rs),
+//! and an item can't change its priority once it's pushed: Dijkstra pushes
+//! the node again, and skips the stale copies when they're popped.
+//!
+//! This queue is different in three ways:
+//!
+//! - `push` returns a `Handle`, and the item behind a handle can be moved
+//!   forward (`decrease_key`), backward (`increase_key`) or taken out
+//!   (`remove`), in O(log n);
+//! - what comes out first is decided by a comparator: `MinFirst`, `MaxFirst`,
+//!   or any `Fn(&T, &T) -> Ordering`;
+//! - it's a d-ary heap: every node has `arity` children (4 by default), so
+//!   the heap is shallower, and sifting up is cheaper than with 2.
+//!
+//! A handle is only valid as long as its item is in the queue: `get`,
+//! `remove` and the key updates return None (or Err) for a handle whose item
+//! was popped or removed, even if its slot has been reused since.
+//!
+//! - https://en.wikipedia.org/wiki/D-ary_heap
+//! - https://en.wikipedia.org/wiki/Priority_queue#Decrease-key
+
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::mem;
+use std::slice;
+
+/// The order in which the items come out of the queue.
+pub trait Compare<T> {
+    /// Less if `a` comes out before `b`.
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// The smallest item first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinFirst;
+
+/// The largest item first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MaxFirst;
+
+impl<T: Ord> Compare<T> for MinFirst {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: Ord> Compare<T> for MaxFirst {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// Where an item is, for as long as it's in the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: usize,
+    generation: u32,
+}
+
+// What a handle points to: where its item is in the heap.
+#[derive(Debug, Clone)]
+struct Slot {
+    // VACANT when the item has left.
+    position: usize,
+    // Incremented when the item leaves, to tell old handles from new ones.
+    generation: u32,
+}
+
+const VACANT: usize = usize::MAX;
+
+const DEFAULT_ARITY: usize = 4;
+
+#[derive(Clone)]
+pub struct PriorityQueue<T, C = MinFirst> {
+    compare: C,
+    arity: usize,
+    // The items, in heap order, next to the number of their slot: the
+    // comparisons only touch this vector.
+    heap: Vec<(T, usize)>,
+    slots: Vec<Slot>,
+    // Vacant slots, to be reused.
+    free: Vec<usize>,
+}
+
+impl<T: Ord> PriorityQueue<T, MinFirst> {
+    /// The smallest item first.
+    pub fn new() -> PriorityQueue<T, MinFirst> {
+        PriorityQueue::with_comparator(MinFirst)
+    }
+}
+
+impl<T: Ord> Default for PriorityQueue<T, MinFirst> {
+    fn default() -> PriorityQueue<T, MinFirst> {
+        PriorityQueue::new()
+    }
+}
+
+impl<T: Ord> PriorityQueue<T, MaxFirst> {
+    /// The largest item first, as in BinaryHeap.
+    pub fn max() -> PriorityQueue<T, MaxFirst> {
+        PriorityQueue::with_comparator(MaxFirst)
+    }
+}
+
+impl<T, C: Compare<T>> PriorityQueue<T, C> {
+    pub fn with_comparator(compare: C) -> PriorityQueue<T, C> {
+        PriorityQueue {
+            compare,
+            arity: DEFAULT_ARITY,
+            heap: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// How many children every node of the heap has.
+    ///
+    /// # Panics
+    ///
+    /// If the arity is less than 2, or the queue isn't empty.
+    pub fn with_arity(mut self, arity: usize) -> PriorityQueue<T, C> {
+        assert!(arity >= 2, "the arity of a heap is at least 2");
+        assert!(self.is_empty(), "the arity is set on an empty queue");
+        self.arity = arity;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn push(&mut self, item: T) -> Handle {
+        let position = self.heap.len();
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot].position = position;
+                slot
+            }
+            None => {
+                self.slots.push(Slot {
+                    position,
+                    generation: 0,
+                });
+                self.slots.len() - 1
+            }
+        };
+        self.heap.push((item, slot));
+        self.sift_up(position);
+        Handle {
+            slot,
+            generation: self.slots[slot].generation,
+        }
+    }
+
+    /// The item that comes out next.
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.first().map(|entry| &entry.0)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.take(0))
+        }
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle).map(|p| &self.heap[p].0)
+    }
+
+    /// Whether the item of the handle is still in the queue.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position(handle).is_some()
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.position(handle).map(|p| self.take(p))
+    }
+
+    /// Replace the item of the handle with one that comes out earlier (or at
+    /// the same time), and return the old one. If the handle's item isn't in
+    /// the queue anymore, the new item is given back in Err.
+    ///
+    /// # Panics
+    ///
+    /// If the new item comes out later than the old one.
+    pub fn decrease_key(&mut self, handle: Handle, item: T) -> Result<T, T> {
+        self.replace(handle, item, Ordering::Greater)
+    }
+
+    /// Replace the item of the handle with one that comes out later (or at
+    /// the same time), and return the old one. If the handle's item isn't in
+    /// the queue anymore, the new item is given back in Err.
+    ///
+    /// # Panics
+    ///
+    /// If the new item comes out earlier than the old one.
+    pub fn increase_key(&mut self, handle: Handle, item: T) -> Result<T, T> {
+        self.replace(handle, item, Ordering::Less)
+    }
+
+    /// Replace the item of the handle, wherever it goes.
+    pub fn update(&mut self, handle: Handle, item: T) -> Result<T, T> {
+        let position = match self.position(handle) {
+            Some(p) => p,
+            None => return Err(item),
+        };
+        let old = mem::replace(&mut self.heap[position].0, item);
+        let position = self.sift_up(position);
+        self.sift_down(position);
+        Ok(old)
+    }
+
+    /// The items in no particular order.
+    pub fn iter<'a>(&'a self) -> Iter<'a, T> {
+        Iter(self.heap.iter())
+    }
+
+    /// Empty the queue: the handles given so far aren't valid anymore.
+    pub fn clear(&mut self) {
+        while let Some((_, slot)) = self.heap.pop() {
+            self.release(slot);
+        }
+    }
+
+    fn replace(&mut self, handle: Handle, item: T, wrong: Ordering) -> Result<T, T> {
+        let position = match self.position(handle) {
+            Some(p) => p,
+            None => return Err(item),
+        };
+        assert!(
+            self.compare.compare(&item, &self.heap[position].0) != wrong,
+            "the new item goes the wrong way: use update"
+        );
+        let old = mem::replace(&mut self.heap[position].0, item);
+        if wrong == Ordering::Greater {
+            self.sift_up(position);
+        } else {
+            self.sift_down(position);
+        }
+        Ok(old)
+    }
+
+    // Where the handle's item is in the heap, if it's still there.
+    fn position(&self, handle: Handle) -> Option<usize> {
+        self.slots
+            .get(handle.slot)
+            .filter(|s| s.generation == handle.generation && s.position != VACANT)
+            .map(|s| s.position)
+    }
+
+    // Take the item at a position of the heap, and fill the hole with the
+    // last one.
+    fn take(&mut self, position: usize) -> T {
+        let (item, slot) = self.heap.swap_remove(position);
+        self.release(slot);
+        if position < self.heap.len() {
+            let position = self.sift_up(position);
+            self.sift_down(position);
+        }
+        item
+    }
+
+    fn release(&mut self, slot: usize) {
+        let slot_ref = &mut self.slots[slot];
+        slot_ref.position = VACANT;
+        slot_ref.generation = slot_ref.generation.wrapping_add(1);
+        self.free.push(slot);
+    }
+
+    // Whether the item at position a comes out before the one at b.
+    fn before(&self, a: usize, b: usize) -> bool {
+        self.compare.compare(&self.heap[a].0, &self.heap[b].0) == Ordering::Less
+    }
+
+    // Swap the item at `position` with the one at `to`: only the other
+    // item's slot is updated, the moving one is updated where it stops.
+    fn step(&mut self, position: usize, to: usize) {
+        self.heap.swap(position, to);
+        self.slots[self.heap[position].1].position = position;
+    }
+
+    // Where the item ends up.
+    fn sift_up(&mut self, mut position: usize) -> usize {
+        while position > 0 {
+            let parent = (position - 1) / self.arity;
+            if !self.before(position, parent) {
+                break;
+            }
+            self.step(position, parent);
+            position = parent;
+        }
+        self.slots[self.heap[position].1].position = position;
+        position
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        let len = self.heap.len();
+        loop {
+            let first = position * self.arity + 1;
+            if first >= len {
+                break;
+            }
+            let last = cmp::min(first + self.arity, len);
+            let mut child = first;
+            for c in first + 1..last {
+                if self.before(c, child) {
+                    child = c;
+                }
+            }
+            if !self.before(child, position) {
+                break;
+            }
+            self.step(position, child);
+            position = child;
+        }
+        self.slots[self.heap[position].1].position = position;
+    }
+}
+
+/// The items of a queue, in no particular order.
+pub struct Iter<'a, T: 'a>(slice::Iter<'a, (T, usize)>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next().map(|entry| &entry.0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T, C: Compare<T>> IntoIterator for &'a PriorityQueue<T, C> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: fmt::Debug, C: Compare<T>> fmt::Debug for PriorityQueue<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The same sequence of numbers every time, without a dependency.
+    fn numbers(count: usize, seed: u64) -> Vec<u64> {
+        let mut state = seed;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % 1000
+            })
+            .collect()
+    }
+
+    fn drain<T, C: Compare<T>>(queue: &mut PriorityQueue<T, C>) -> Vec<T> {
+        let mut items = Vec::new();
+        while let Some(item) = queue.pop() {
+            items.push(item);
+        }
+        items
+    }
+
+    #[test]
+    fn pops_in_order_whatever_the_arity() {
+        for arity in 2..7 {
+            let mut expected = numbers(500, arity as u64);
+            let mut queue = PriorityQueue::new().with_arity(arity);
+            for &n in &expected {
+                queue.push(n);
+            }
+            expected.sort();
+            assert_eq!(queue.len(), 500);
+            assert_eq!(queue.peek(), expected.first());
+            assert_eq!(drain(&mut queue), expected);
+
+            let mut queue = PriorityQueue::max().with_arity(arity);
+            for &n in &expected {
+                queue.push(n);
+            }
+            expected.reverse();
+            assert_eq!(drain(&mut queue), expected);
+        }
+    }
+
+    #[test]
+    fn comparators() {
+        let words = vec!["pear", "fig", "banana", "kiwi"];
+        let mut queue = PriorityQueue::with_comparator(|a: &&str, b: &&str| {
+            a.len().cmp(&b.len()).then_with(|| b.cmp(a))
+        });
+        for w in words {
+            queue.push(w);
+        }
+        assert_eq!(drain(&mut queue), vec!["fig", "pear", "kiwi", "banana"]);
+    }
+
+    #[test]
+    fn handles() {
+        let values: Vec<_> = numbers(200, 7).into_iter().map(|n| n + 10).collect();
+        let mut queue = PriorityQueue::new();
+        let handles: Vec<_> = values.iter().map(|&n| queue.push(n)).collect();
+        assert_eq!(queue.decrease_key(handles[150], 1), Ok(values[150]));
+        assert_eq!(queue.peek(), Some(&1));
+        assert_eq!(queue.increase_key(handles[150], 2000), Ok(1));
+        assert_eq!(queue.update(handles[3], 0), Ok(values[3]));
+        assert_eq!(queue.remove(handles[3]), Some(0));
+        assert_eq!(queue.remove(handles[3]), None);
+        assert!(!queue.contains(handles[3]));
+        assert_eq!(queue.get(handles[150]), Some(&2000));
+
+        let items = drain(&mut queue);
+        assert_eq!(items.len(), 199);
+        assert!(items.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(items.last(), Some(&2000));
+
+        // The slots are reused, the old handles don't see the new items.
+        let handle = queue.push(5);
+        assert!(handles.iter().all(|&h| queue.get(h).is_none()));
+        assert_eq!(queue.decrease_key(handles[0], 1), Err(1));
+        assert_eq!(queue.get(handle), Some(&5));
+        queue.clear();
+        assert!(queue.is_empty() && !queue.contains(handle));
+    }
+
+    #[test]
+    #[should_panic(expected = "the wrong way")]
+    fn decrease_key_moves_forward() {
+        let mut queue = PriorityQueue::new();
+        let handle = queue.push(5);
+        let _ = queue.decrease_key(handle, 6);
+    }
+
+    #[test]
+    fn removes_anywhere() {
+        let values = numbers(300, 42);
+        let mut queue = PriorityQueue::new().with_arity(3);
+        let handles: Vec<_> = values.iter().map(|&n| queue.push(n)).collect();
+        let mut expected = Vec::new();
+        for (i, (&handle, &n)) in handles.iter().zip(&values).enumerate() {
+            if i % 3 == 0 {
+                assert_eq!(queue.remove(handle), Some(n));
+            } else {
+                expected.push(n);
+            }
+        }
+        expected.sort();
+        assert_eq!(drain(&mut queue), expected);
+    }
+}
@@ -1,7 +1,7 @@
 // https://github.com/kbknapp/clap-rs/
 #[macro_use]
 extern crate clap;
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
 
 // https://github.com/rust-lang-nursery/log
 // https://github.com/sebasmagri/env_logger/
@@ -14,8 +14,8 @@ extern crate taker;
 
 use log::Level;
 use std::env;
-use std::error::Error;
 use std::io;
+use std::path::{Path, PathBuf};
 
 fn run_app() -> Result<bool, io::Error> {
     // let version = format!("{}.{}.{}{}",
@@ -36,6 +36,14 @@ fn run_app() -> Result<bool, io::Error> {
                 .help("Sets a custom [c]onfig file")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("profile")
+                .short("p")
+                .long("profile")
+                .value_name("NAME")
+                .help("Uses the [p]rofile NAME of the config instead of its default")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("verbosity")
                 .short("v")
@@ -50,6 +58,57 @@ fn run_app() -> Result<bool, io::Error> {
                 .takes_value(false)
                 .multiple(false)
                 .help("e[x]ecute the taker"),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Works on the config")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Reads the config, and tells what every profile would take"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("backup")
+                .about("Takes an incremental snapshot of the files into the chunk store"),
+        )
+        .subcommand(SubCommand::with_name("snapshots").about("Lists the snapshots in the chunk store"))
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Shows the files added, removed and modified from a snapshot to another")
+                .arg(Arg::with_name("from").required(true).help("The older snapshot"))
+                .arg(Arg::with_name("to").required(true).help("The newer snapshot")),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Puts the files of an archive back where they were taken from")
+                .arg(Arg::with_name("archive").required(true).help("A .tar, or an encrypted one"))
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .help("Restores under DIR instead of /"),
+                )
+                .arg(
+                    Arg::with_name("only")
+                        .long("only")
+                        .value_name("PATTERN")
+                        .takes_value(true)
+                        .help("Restores only the paths (or directories) matching the glob"),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .short("n")
+                        .long("dry-run")
+                        .help("Lists what would be restored, without writing anything"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .short("f")
+                        .long("force")
+                        .help("Overwrites files newer than the archived ones"),
+                ),
         );
 
     let mut a = app.clone();
@@ -98,10 +157,81 @@ fn run_app() -> Result<bool, io::Error> {
         info!("running the taker CLI");
     }
 
+    let profile = matches.value_of("profile");
+    match matches.subcommand() {
+        ("config", Some(_)) => {
+            let path = taker::config_path();
+            let cfg = taker::config()?;
+            for (name, p) in &cfg.profiles {
+                let (files, unmatched) = taker::check(p)?;
+                let level = p.level.unwrap_or_else(|| p.compression.default_level());
+                println!(
+                    "{}: {} files into {} as {}.enc, compression {} (level {}), {}",
+                    name,
+                    files,
+                    p.destination,
+                    p.archive_name,
+                    p.compression,
+                    level,
+                    p.retention
+                );
+                for include in unmatched {
+                    println!("{}: {:?} takes no file", name, include);
+                }
+            }
+            let default = cfg
+                .profile(None)
+                .map(|p| format!("default profile {}", p.name))
+                .unwrap_or_else(|e| e.to_string());
+            println!("{}: ok, {}", path.display(), default);
+            return Ok(true);
+        }
+        ("backup", _) => {
+            let (snapshot, stats) = taker::backup(&taker::profile(profile)?)?;
+            println!("snapshot {}: {}", snapshot.id, stats);
+            return Ok(true);
+        }
+        ("snapshots", _) => {
+            for snapshot in taker::snapshots(&taker::profile(profile)?)? {
+                println!(
+                    "{}  {} files  {} bytes",
+                    snapshot.id,
+                    snapshot.files.len(),
+                    snapshot.size()
+                );
+            }
+            return Ok(true);
+        }
+        ("diff", Some(m)) => {
+            print!(
+                "{}",
+                taker::diff(
+                    &taker::profile(profile)?,
+                    m.value_of("from").unwrap(),
+                    m.value_of("to").unwrap()
+                )?
+            );
+            return Ok(true);
+        }
+        ("restore", Some(m)) => {
+            let options = taker::restore::Options {
+                to: m.value_of("to").map(PathBuf::from),
+                only: m.value_of("only").map(String::from),
+                dry_run: m.is_present("dry-run"),
+                force: m.is_present("force"),
+            };
+            let restored = taker::restore(Path::new(m.value_of("archive").unwrap()), options)?;
+            for r in &restored {
+                println!("{}{}", if m.is_present("dry-run") { "would " } else { "" }, r);
+            }
+            return Ok(true);
+        }
+        _ => {}
+    }
+
     if matches.is_present("execute") {
-        return taker::run(taker::config());
+        return taker::run(&taker::profile(profile)?);
     } else {
-        taker::config();
         a.print_long_help().expect("something is broken");
     }
     Ok(false)
@@ -111,7 +241,7 @@ fn main() {
     ::std::process::exit(match run_app() {
         Ok(_) => 0,
         Err(err) => {
-            eprintln!("error: {:?}", err.description());
+            eprintln!("error: {}", err);
             1
         }
     });
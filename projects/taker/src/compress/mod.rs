@@ -1,29 +1,434 @@
+// Tar archives, compressed on the way out with gzip, zstd or xz, and read
+// back whatever they are compressed with.
+//
+// Files are appended one at a time, straight from where they are: a symlink
+// goes in as a symlink, a fifo or a device as one, all of them with their
+// permission bits, owner and mtime.
+
+extern crate flate2;
 extern crate tar;
+extern crate xz2;
+extern crate zstd;
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+
+use self::flate2::read::GzDecoder;
+use self::flate2::write::GzEncoder;
+use self::tar::{Builder, Header};
+use self::xz2::read::XzDecoder;
+use self::xz2::write::XzEncoder;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// A plain tar.
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+const ZSTD_MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+const XZ_MAGIC: &[u8] = b"\xfd7zXZ\x00";
+
+impl Compression {
+    pub const NAMES: &'static [&'static str] = &["none", "gzip", "zstd", "xz"];
+
+    pub fn parse(name: &str) -> Option<Compression> {
+        match name {
+            "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// The lowest and the highest level; none without compression.
+    pub fn levels(self) -> Option<(u32, u32)> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip | Compression::Xz => Some((0, 9)),
+            Compression::Zstd => Some((1, 22)),
+        }
+    }
+
+    pub fn default_level(self) -> u32 {
+        match self {
+            Compression::None => 0,
+            Compression::Gzip | Compression::Xz => 6,
+            Compression::Zstd => 3,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "tar",
+            Compression::Gzip => "tar.gz",
+            Compression::Zstd => "tar.zst",
+            Compression::Xz => "tar.xz",
+        }
+    }
+
+    // What the first bytes of an archive tell.
+    fn of(start: &[u8]) -> Compression {
+        if start.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if start.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if start.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A compressor has to be finished, not dropped: its last bytes are written
+// only then.
+enum Encoder<W: Write> {
+    None(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Xz(XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(out: W, compression: Compression, level: u32) -> io::Result<Encoder<W>> {
+        Ok(match compression {
+            Compression::None => Encoder::None(out),
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(out, flate2::Compression::new(level)))
+            }
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(out, level as i32)?),
+            Compression::Xz => Encoder::Xz(XzEncoder::new(out, level)),
+        })
+    }
+
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::None(out) => Ok(out),
+            Encoder::Gzip(e) => e.finish(),
+            Encoder::Zstd(e) => e.finish(),
+            Encoder::Xz(e) => e.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Encoder::None(ref mut out) => out.write(buf),
+            Encoder::Gzip(ref mut e) => e.write(buf),
+            Encoder::Zstd(ref mut e) => e.write(buf),
+            Encoder::Xz(ref mut e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Encoder::None(ref mut out) => out.flush(),
+            Encoder::Gzip(ref mut e) => e.flush(),
+            Encoder::Zstd(ref mut e) => e.flush(),
+            Encoder::Xz(ref mut e) => e.flush(),
+        }
+    }
+}
+
+// The major and the minor number of a device, as glibc packs them.
+fn major(dev: u64) -> u32 {
+    (((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff)) as u32
+}
+
+fn minor(dev: u64) -> u32 {
+    (((dev >> 12) & 0xffff_ff00) | (dev & 0x0000_00ff)) as u32
+}
+
+/// The device of a major and a minor number.
+pub fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (u64::from(major), u64::from(minor));
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+// The first len bytes of data, and zeros after it if it ends sooner.
+struct Padded<'a, R: Read> {
+    data: R,
+    left: u64,
+    src: &'a Path,
+    short: bool,
+}
+
+impl<'a, R: Read> Padded<'a, R> {
+    fn new(data: R, len: u64, src: &'a Path) -> Padded<'a, R> {
+        Padded {
+            data,
+            left: len,
+            src,
+            short: false,
+        }
+    }
+}
 
-// use std::io::prelude::*;
-use self::tar::Builder;
-
-use std::fs::metadata;
-use std::fs::File;
-use std::io;
-use std::path::PathBuf;
-
-pub fn compress(src: &PathBuf, dst: &PathBuf) -> io::Result<()> {
-    debug!("creating {:?}", dst);
-    let file = File::create(dst).unwrap();
-    debug!("building...");
-    let mut a = Builder::new(file);
-
-    match metadata(src) {
-        Ok(meta) => {
-            if meta.is_dir() {
-                debug!("compressing {:?} as a directory", src.file_name().unwrap());
-                a.append_dir_all(src.file_name().unwrap() , src).unwrap();
-            } else {
-                a.append_path(src.file_name().unwrap()).unwrap();
+impl<'a, R: Read> Read for Padded<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (buf.len() as u64).min(self.left) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let mut n = 0;
+        if !self.short {
+            n = self.data.read(&mut buf[..len])?;
+            if n == 0 {
+                warn!("{:?} got shorter: padded with zeros", self.src);
+                self.short = true;
             }
         }
-        _ => panic!("cannot read the path to compress"),
+        if self.short {
+            n = len;
+            buf[..n].iter_mut().for_each(|b| *b = 0);
+        }
+        self.left -= n as u64;
+        Ok(n)
+    }
+}
+
+/// A tar archive being written into out.
+pub struct Archive<W: Write> {
+    builder: Builder<Encoder<W>>,
+}
+
+impl<W: Write> Archive<W> {
+    /// With the default level of the compression for none.
+    pub fn new(out: W, compression: Compression, level: Option<u32>) -> io::Result<Archive<W>> {
+        let level = level.unwrap_or_else(|| compression.default_level());
+        Ok(Archive {
+            builder: Builder::new(Encoder::new(out, compression, level)?),
+        })
+    }
+
+    /// Appends what is at src, not following a symlink, as path.
+    pub fn append(&mut self, src: &Path, path: &Path) -> io::Result<()> {
+        let meta = fs::symlink_metadata(src)?;
+        let kind = meta.file_type();
+        let mut header = Header::new_gnu();
+        header.set_metadata(&meta);
+        if kind.is_symlink() {
+            let target = fs::read_link(src)?;
+            self.builder.append_link(&mut header, path, target)
+        } else if kind.is_file() {
+            let file = File::open(src)?;
+            // The header says meta.len(): what goes in is that long, should
+            // the file grow or shrink meanwhile.
+            self.builder
+                .append_data(&mut header, path, Padded::new(file, meta.len(), src))
+        } else if kind.is_fifo() || kind.is_char_device() || kind.is_block_device() {
+            header.set_size(0);
+            header.set_device_major(major(meta.rdev()))?;
+            header.set_device_minor(minor(meta.rdev()))?;
+            self.builder.append_data(&mut header, path, io::empty())
+        } else if kind.is_dir() {
+            self.builder.append_data(&mut header, path, io::empty())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?}: a socket can't be archived", src),
+            ))
+        }
+    }
+
+    /// Appends data as a file at path, readable by its owner only.
+    pub fn append_data(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(
+            ::std::time::SystemTime::now()
+                .duration_since(::std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        );
+        self.builder.append_data(&mut header, path, data)
+    }
+
+    /// Ends the archive, and the compression.
+    pub fn finish(self) -> io::Result<W> {
+        self.builder.into_inner()?.finish()
+    }
+}
+
+/// The tar in input, decompressed as its first bytes tell.
+pub fn reader<'a, R: BufRead + 'a>(mut input: R) -> io::Result<Box<dyn Read + 'a>> {
+    let compression = Compression::of(input.fill_buf()?);
+    debug!("reading a {} archive", compression);
+    Ok(match compression {
+        Compression::None => Box::new(input),
+        Compression::Gzip => Box::new(GzDecoder::new(input)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(input)?),
+        Compression::Xz => Box::new(XzDecoder::new(input)),
+    })
+}
+
+/// The tar in the file at path.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    reader(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate libc;
+
+    use super::tar::{self, EntryType};
+    use super::*;
+    use std::env;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::process;
+
+    #[test]
+    fn round_trips() {
+        let dir = env::temp_dir().join(format!("taker-compress-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("script");
+        fs::write(&script, b"#!/bin/sh\n".repeat(100)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+        let link = dir.join("link");
+        symlink("script", &link).unwrap();
+        let fifo = dir.join("fifo");
+        let name = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(name.as_ptr(), 0o640) }, 0);
+
+        for &compression in &[
+            Compression::None,
+            Compression::Gzip,
+            Compression::Zstd,
+            Compression::Xz,
+        ] {
+            let mut archive = Archive::new(Vec::new(), compression, None).unwrap();
+            for name in &["script", "link", "fifo"] {
+                archive
+                    .append(&dir.join(name), &Path::new("top").join(name))
+                    .unwrap();
+            }
+            archive
+                .append_data(Path::new("top/notes"), b"notes")
+                .unwrap();
+            let bytes = archive.finish().unwrap();
+            assert_eq!(Compression::of(&bytes), compression);
+            if compression == Compression::Gzip {
+                assert!(bytes.len() < 1000, "{} bytes", bytes.len());
+            }
+
+            let mut entries = Vec::new();
+            let mut tar = tar::Archive::new(reader(&bytes[..]).unwrap());
+            for entry in tar.entries().unwrap() {
+                let mut entry = entry.unwrap();
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                let header = entry.header();
+                entries.push((
+                    entry.path().unwrap().to_string_lossy().into_owned(),
+                    header.entry_type(),
+                    header.mode().unwrap() & 0o7777,
+                    entry
+                        .link_name()
+                        .unwrap()
+                        .map(|l| l.to_string_lossy().into_owned()),
+                    data.len(),
+                ));
+            }
+            assert_eq!(
+                entries,
+                vec![
+                    (
+                        String::from("top/script"),
+                        EntryType::Regular,
+                        0o750,
+                        None,
+                        1000
+                    ),
+                    (
+                        String::from("top/link"),
+                        EntryType::Symlink,
+                        0o777,
+                        Some(String::from("script")),
+                        0
+                    ),
+                    (String::from("top/fifo"), EntryType::Fifo, 0o640, None, 0),
+                    (
+                        String::from("top/notes"),
+                        EntryType::Regular,
+                        0o600,
+                        None,
+                        5
+                    ),
+                ],
+                "{}",
+                compression
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_length() {
+        let src = Path::new("data");
+        let read = |data: &[u8], len: u64| {
+            let mut out = Vec::new();
+            Padded::new(data, len, src).read_to_end(&mut out).unwrap();
+            out
+        };
+        assert_eq!(read(b"abc", 3), b"abc");
+        assert_eq!(read(b"abcdef", 3), b"abc");
+        assert_eq!(read(b"abc", 5), b"abc\0\0");
+        assert_eq!(read(b"", 2), b"\0\0");
+        assert_eq!(read(b"", 0), b"");
+
+        // The tar stays readable, with the entry its header says.
+        let mut archive = Archive::new(Vec::new(), Compression::None, None).unwrap();
+        let mut header = Header::new_gnu();
+        header.set_size(1000);
+        archive
+            .builder
+            .append_data(&mut header, "short", Padded::new(&b"begins"[..], 1000, src))
+            .unwrap();
+        archive.append_data(Path::new("next"), b"next").unwrap();
+        let bytes = archive.finish().unwrap();
+        let mut tar = tar::Archive::new(&bytes[..]);
+        let mut entries = tar.entries().unwrap();
+        let mut first = Vec::new();
+        entries
+            .next()
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut first)
+            .unwrap();
+        assert_eq!(first.len(), 1000);
+        assert!(first.starts_with(b"begins"));
+        let mut next = entries.next().unwrap().unwrap();
+        assert_eq!(next.path().unwrap(), Path::new("next"));
+        let mut data = Vec::new();
+        next.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"next");
     }
-    a.finish()
 }
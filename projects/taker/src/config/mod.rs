@@ -1,63 +1,590 @@
+// What to take, and where to put it: the profiles of ~/.taker.toml (or of
+// $TAKER_CFG).
+//
+//     default = "dotfiles"
+//
+//     [profiles.dotfiles]
+//     include = ["~/.bash_profile", "~/.ssh", "~/.config/*/rc.lua"]
+//     exclude = ["*.swp", "~/.ssh/known_hosts"]
+//     destination = "~/Downloads"
+//     archive_name = "{date}.takenfiles"
+//     compression = "zstd"
+//     level = 19
+//     retention = { keep_last = 10, keep_days = 90 }
+//
+// Only include is required. A config with nothing but `files = [...]`, as
+// before profiles, is a single profile named "default". A mistake is an
+// error at its line and column: nothing falls back to a default silently.
+
+extern crate chrono;
 extern crate toml;
 
-// https://doc.rust-lang.org/std/io/trait.Read.html
-use std::error::Error;
-use std::fs::File;
-use std::io::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 
-#[derive(Deserialize, Debug)]
-pub struct Config {
-    pub files: Vec<String>,
+use self::chrono::prelude::*;
+use self::chrono::Duration;
+use self::toml::Value;
+
+use compress::Compression;
+use timez::FMT_DATETAG;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const DEFAULT_DESTINATION: &str = "~/Downloads";
+const DEFAULT_ARCHIVE_NAME: &str = "{date}.takenfiles";
+const DEFAULT_ROOT_DIR: &str = "takentarget";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Retention {
+    /// The newest archives to keep.
+    pub keep_last: Option<usize>,
+    /// Archives younger than this many days are kept.
+    pub keep_days: Option<u32>,
 }
 
-macro_rules! vec_of_strings {
-    ($($x:expr),*) => (vec![$($x.to_string()),*]);
+impl Retention {
+    /// The archives to remove, out of (datetag, path) pairs: those neither
+    /// among the keep_last newest nor younger than keep_days at now. With
+    /// neither set, none.
+    pub fn expired<P: Clone>(&self, archives: &[(String, P)], now: &str) -> io::Result<Vec<P>> {
+        if self.keep_last.is_none() && self.keep_days.is_none() {
+            return Ok(Vec::new());
+        }
+        let cutoff = match self.keep_days {
+            Some(days) => {
+                let now = Utc.datetime_from_str(now, FMT_DATETAG).map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", now, e))
+                })?;
+                Some(
+                    (now - Duration::days(i64::from(days)))
+                        .format(FMT_DATETAG)
+                        .to_string(),
+                )
+            }
+            None => None,
+        };
+        let mut newest: Vec<&(String, P)> = archives.iter().collect();
+        newest.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(newest
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, (datetag, _))| {
+                let last = self.keep_last.is_some_and(|n| i < n);
+                // Datetags have a fixed width: they sort as the times do.
+                let young = cutoff.as_ref().is_some_and(|c| datetag >= c);
+                !last && !young
+            })
+            .map(|(_, (_, path))| path.clone())
+            .collect())
+    }
 }
 
-impl Default for Config {
-    fn default() -> Config {
-        Config {
-            files: vec_of_strings![
-                "~/.bash_profile",
-                "~/.ssh/",
-                "~/.emacs.el",
-                "~/.aws",
-                "~/.config/awesome/rc.lua",
-                "~/this/pwsafe.psafe3"
-            ],
+impl fmt::Display for Retention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.keep_last, self.keep_days) {
+            (None, None) => write!(f, "keeping every archive"),
+            (Some(n), None) => write!(f, "keeping the last {}", n),
+            (None, Some(d)) => write!(f, "keeping {} days", d),
+            (Some(n), Some(d)) => write!(f, "keeping the last {} and {} days", n, d),
         }
     }
 }
 
-pub fn parse(path: String) -> Config {
-    let mut config_toml = String::new();
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// Files and directories, or globs of them.
+    pub include: Vec<String>,
+    /// Globs of what not to take, wherever it is in what is included.
+    pub exclude: Vec<String>,
+    /// The directory of the archives, and of the chunk store.
+    pub destination: String,
+    /// The archive file name, before its extension: {date} is the datetag of
+    /// the run, {profile} the name of the profile.
+    pub archive_name: String,
+    /// The top directory in the archive.
+    pub root_dir: String,
+    pub compression: Compression,
+    /// Of the compression: its default one when none.
+    pub level: Option<u32>,
+    pub retention: Retention,
+}
 
-    let mut file = match File::open(&path) {
-        Ok(file) => file,
-        Err(_) => {
-            warn!("Could not find config file {:?}, using default!", &path);
-            let cfg: Config = Config::default();
-            info!("running with this configuration: {:?}", &cfg);
-            return cfg;
+impl Profile {
+    fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_owned(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            destination: DEFAULT_DESTINATION.to_owned(),
+            archive_name: DEFAULT_ARCHIVE_NAME.to_owned(),
+            root_dir: DEFAULT_ROOT_DIR.to_owned(),
+            compression: Compression::Gzip,
+            level: None,
+            retention: Retention::default(),
         }
-    };
+    }
 
-    // https://doc.rust-lang.org/std/io/trait.Read.html
-    file.read_to_string(&mut config_toml)
-        .unwrap_or_else(|err| panic!("Error while reading config: [{}]", err));
+    pub fn archive_name(&self, datetag: &str) -> String {
+        self.archive_name
+            .replace("{profile}", &self.name)
+            .replace("{date}", datetag)
+    }
 
-    // https://docs.rs/toml/0.4.6/toml/de/fn.from_str.html
-    let parsed = toml::from_str(&config_toml);
-    match parsed {
-        Ok(c) => {
-            let cfg: Config = c;
-            debug!("running with this configuration: {:?}", cfg);
-            return cfg;
+    /// The datetag of an archive this profile named, none for any other file
+    /// name (without its extension).
+    pub fn datetag_of(&self, name: &str) -> Option<String> {
+        let template = self.archive_name.replace("{profile}", &self.name);
+        let at = template.find("{date}")?;
+        let (prefix, suffix) = (&template[..at], &template[at + "{date}".len()..]);
+        if !name.starts_with(prefix) || !name.ends_with(suffix) {
+            return None;
+        }
+        let datetag = name.get(prefix.len()..name.len().checked_sub(suffix.len())?)?;
+        if datetag.len() == 14 && datetag.bytes().all(|b| b.is_ascii_digit()) {
+            Some(datetag.to_owned())
+        } else {
+            None
         }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The profile to use when none is asked for.
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// The profile called name, or the default one.
+    pub fn profile(&self, name: Option<&str>) -> io::Result<&Profile> {
+        let name = match (name, &self.default) {
+            (Some(name), _) => name,
+            (None, Some(name)) => name,
+            (None, None) if self.profiles.len() == 1 => self.profiles.keys().next().unwrap(),
+            (None, None) if self.profiles.contains_key(DEFAULT_PROFILE) => DEFAULT_PROFILE,
+            (None, None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no `default` profile in the config: choose one with --profile",
+                ))
+            }
+        };
+        self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(|n| n.as_str()).collect();
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no profile {:?}, only {}", name, names.join(", ")),
+            )
+        })
+    }
+}
+
+/// Reads and checks the config at path.
+pub fn load(path: &Path) -> io::Result<Config> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot read the config {}: {}", path.display(), e),
+        )
+    })?;
+    parse(path, &text)
+}
+
+/// Checks text, read from path: errors are at the line and the column of
+/// the mistake.
+pub fn parse(path: &Path, text: &str) -> io::Result<Config> {
+    let source = Source { path, text };
+    let root = match text.parse::<Value>() {
+        Ok(Value::Table(table)) => table,
+        Ok(_) => return Err(source.at(1, 1, "not a table")),
         Err(e) => {
-            let (line, col) = e.line_col().expect("unparseable parse error");
-            error!("{}:{} error: {}", line, col, e.description());
-            return Config::default();
+            let (line, col) = e.line_col().map_or((1, 1), |(l, c)| (l + 1, c + 1));
+            let message = e.to_string();
+            // The line is in front of the message already.
+            let message = message.split(" at line ").next().unwrap_or("");
+            return Err(source.at(line, col, message));
+        }
+    };
+
+    let mut default = None;
+    let mut files = None;
+    let mut profiles = BTreeMap::new();
+    for (key, value) in &root {
+        match key.as_str() {
+            "default" => default = Some(source.string(value, &["default"])?),
+            "files" => files = Some(source.strings(value, &["files"])?),
+            "profiles" => {
+                for (name, value) in source.table(value, &["profiles"])? {
+                    profiles.insert(name.clone(), source.profile(name, value)?);
+                }
+            }
+            _ => return Err(source.error(&[key], format!("unknown key `{}`", key))),
+        }
+    }
+    if let Some(files) = files {
+        if !profiles.is_empty() {
+            return Err(source.error(
+                &["files"],
+                "`files` is the include of a config without profiles: move it into one",
+            ));
+        }
+        let mut profile = Profile::new(DEFAULT_PROFILE);
+        profile.include = files;
+        profiles.insert(DEFAULT_PROFILE.to_owned(), profile);
+    }
+    if profiles.is_empty() {
+        return Err(source.at(1, 1, "no profiles: add a [profiles.<name>] with an include"));
+    }
+    if let Some(ref name) = default {
+        if !profiles.contains_key(name) {
+            return Err(source.error(&["default"], format!("no profile `{}`", name)));
+        }
+    }
+    Ok(Config { default, profiles })
+}
+
+struct Source<'a> {
+    path: &'a Path,
+    text: &'a str,
+}
+
+impl<'a> Source<'a> {
+    fn at<M: fmt::Display>(&self, line: usize, col: usize, message: M) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{}:{}: {}", self.path.display(), line, col, message),
+        )
+    }
+
+    fn error<M: fmt::Display>(&self, key: &[&str], message: M) -> io::Error {
+        let (line, col) = locate(self.text, key);
+        self.at(line, col, message)
+    }
+
+    fn mismatch(&self, key: &[&str], expected: &str, value: &Value) -> io::Error {
+        let name = key.last().unwrap_or(&"");
+        let message = format!("`{}` must be {}, not {}", name, expected, value.type_str());
+        self.error(key, message)
+    }
+
+    fn string(&self, value: &Value, key: &[&str]) -> io::Result<String> {
+        match *value {
+            Value::String(ref s) => Ok(s.clone()),
+            _ => Err(self.mismatch(key, "a string", value)),
         }
     }
+
+    fn strings(&self, value: &Value, key: &[&str]) -> io::Result<Vec<String>> {
+        let strings = match *value {
+            Value::Array(ref values) => values
+                .iter()
+                .map(|v| v.as_str().map(String::from))
+                .collect(),
+            _ => None,
+        };
+        strings.ok_or_else(|| self.mismatch(key, "an array of strings", value))
+    }
+
+    fn count(&self, value: &Value, key: &[&str]) -> io::Result<u64> {
+        match value.as_integer() {
+            Some(n) if n > 0 => Ok(n as u64),
+            Some(n) => {
+                let name = key.last().unwrap_or(&"");
+                Err(self.error(key, format!("`{}` must be positive, not {}", name, n)))
+            }
+            None => Err(self.mismatch(key, "a positive integer", value)),
+        }
+    }
+
+    fn table<'v>(&self, value: &'v Value, key: &[&str]) -> io::Result<&'v BTreeMap<String, Value>> {
+        value
+            .as_table()
+            .ok_or_else(|| self.mismatch(key, "a table", value))
+    }
+
+    fn profile(&self, name: &str, value: &Value) -> io::Result<Profile> {
+        let mut profile = Profile::new(name);
+        let mut include = None;
+        for (key, value) in self.table(value, &["profiles", name])? {
+            let at = ["profiles", name, key.as_str()];
+            match key.as_str() {
+                "include" => include = Some(self.strings(value, &at)?),
+                "exclude" => profile.exclude = self.strings(value, &at)?,
+                "destination" => profile.destination = self.string(value, &at)?,
+                "archive_name" => {
+                    profile.archive_name = self.string(value, &at)?;
+                    check_template(&profile.archive_name).map_err(|e| self.error(&at, e))?;
+                }
+                "root_dir" => {
+                    profile.root_dir = self.string(value, &at)?;
+                    if profile.root_dir.is_empty() || profile.root_dir.contains('/') {
+                        return Err(self.error(&at, "`root_dir` must be a directory name"));
+                    }
+                }
+                "compression" => {
+                    let name = self.string(value, &at)?;
+                    profile.compression = Compression::parse(&name).ok_or_else(|| {
+                        let message = format!(
+                            "unknown compression `{}`, expected one of {}",
+                            name,
+                            Compression::NAMES.join(", ")
+                        );
+                        self.error(&at, message)
+                    })?;
+                }
+                "level" => {
+                    profile.level = match value.as_integer() {
+                        Some(n) if n >= 0 => Some(n as u32),
+                        Some(n) => {
+                            return Err(self.error(&at, format!("a negative `level`: {}", n)))
+                        }
+                        None => return Err(self.mismatch(&at, "an integer", value)),
+                    }
+                }
+                "retention" => profile.retention = self.retention(value, &at)?,
+                _ => return Err(self.error(&at, format!("unknown key `{}`", key))),
+            }
+        }
+        if let Some(level) = profile.level {
+            let at = ["profiles", name, "level"];
+            match profile.compression.levels() {
+                Some((low, high)) if level < low || level > high => {
+                    let message = format!(
+                        "the `level` of {} is from {} to {}, not {}",
+                        profile.compression, low, high, level
+                    );
+                    return Err(self.error(&at, message));
+                }
+                Some(_) => {}
+                None => return Err(self.error(&at, "no `level` without a compression")),
+            }
+        }
+        profile.include = include.ok_or_else(|| {
+            self.error(
+                &["profiles", name],
+                format!("profile `{}` has no `include`", name),
+            )
+        })?;
+        Ok(profile)
+    }
+
+    fn retention(&self, value: &Value, key: &[&str]) -> io::Result<Retention> {
+        let mut retention = Retention::default();
+        for (name, value) in self.table(value, key)? {
+            let mut at = key.to_vec();
+            at.push(name);
+            match name.as_str() {
+                "keep_last" => retention.keep_last = Some(self.count(value, &at)? as usize),
+                "keep_days" => retention.keep_days = Some(self.count(value, &at)? as u32),
+                _ => return Err(self.error(&at, format!("unknown key `{}`", name))),
+            }
+        }
+        Ok(retention)
+    }
+}
+
+// An archive name has its {date}, to be told apart from the others, and no
+// other placeholder.
+fn check_template(template: &str) -> Result<(), String> {
+    if !template.contains("{date}") {
+        return Err(String::from("`archive_name` has no {date}"));
+    }
+    if template.contains('/') {
+        return Err(String::from("`archive_name` must be a file name"));
+    }
+    let rest = template.replace("{date}", "").replace("{profile}", "");
+    match rest.find('{') {
+        Some(at) => Err(format!(
+            "unknown placeholder `{}` in `archive_name`, only {{date}} and {{profile}}",
+            &rest[at..rest[at..].find('}').map_or(rest.len(), |end| at + end + 1)]
+        )),
+        None => Ok(()),
+    }
+}
+
+fn unquote(key: &str) -> String {
+    key.trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .to_owned()
+}
+
+// Where the value of key is written, its line and column from 1; where its
+// closest table is when the key itself isn't written down.
+fn locate(text: &str, key: &[&str]) -> (usize, usize) {
+    let prefix =
+        |path: &[String]| path.len() <= key.len() && path.iter().zip(key).all(|(p, k)| p == k);
+    let mut found = (1, 1);
+    let mut depth = 0;
+    let mut table: Vec<String> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.starts_with('[') {
+            let header = trimmed.trim_start_matches('[');
+            let header = &header[..header.find(']').unwrap_or(header.len())];
+            table = header.split('.').map(unquote).collect();
+            if prefix(&table) && table.len() > depth {
+                found = (n + 1, indent + 1);
+                depth = table.len();
+            }
+            continue;
+        }
+        let eq = match trimmed.find('=') {
+            Some(eq) if !trimmed.starts_with('#') => eq,
+            _ => continue,
+        };
+        let mut path = table.clone();
+        path.push(unquote(&trimmed[..eq]));
+        if !prefix(&path) || path.len() <= depth {
+            continue;
+        }
+        let value = |from: usize| {
+            let rest = &line[from..];
+            from + rest.len() - rest.trim_start().len() + 1
+        };
+        let at = indent + eq + 1;
+        if path.len() == key.len() {
+            return (n + 1, value(at));
+        }
+        // An inline table: the key is on this line, after the brace.
+        found = (n + 1, value(at));
+        depth = path.len();
+        let inner = key[key.len() - 1];
+        if let Some(start) = line[at..].find(inner).map(|s| at + s) {
+            if let Some(eq) = line[start..].find('=') {
+                return (n + 1, value(start + eq + 1));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"default = "dotfiles"
+
+[profiles.dotfiles]
+include = ["~/.bash_profile", "~/.ssh", "~/.config/*/rc.lua"]
+exclude = ["*.swp"]
+retention = { keep_last = 2, keep_days = 30 }
+
+[profiles.work]
+include = ["~/work/notes"]
+destination = "/mnt/backup"
+archive_name = "{profile}-{date}"
+compression = "xz"
+level = 9
+"#;
+
+    fn error(text: &str) -> String {
+        parse(Path::new("taker.toml"), text)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn parses() {
+        let config = parse(Path::new("taker.toml"), CONFIG).unwrap();
+        let dotfiles = config.profile(None).unwrap();
+        assert_eq!(dotfiles.include.len(), 3);
+        assert_eq!(dotfiles.exclude, vec!["*.swp"]);
+        assert_eq!(dotfiles.destination, DEFAULT_DESTINATION);
+        assert_eq!(dotfiles.retention.keep_last, Some(2));
+        let work = config.profile(Some("work")).unwrap();
+        assert_eq!(work.archive_name("20180501120000"), "work-20180501120000");
+        assert_eq!((work.compression, work.level), (Compression::Xz, Some(9)));
+        assert_eq!(
+            work.datetag_of("work-20180501120000").unwrap(),
+            "20180501120000"
+        );
+        assert_eq!(work.datetag_of("work-2018"), None);
+        assert_eq!(work.datetag_of("home-20180501120000"), None);
+        assert!(config.profile(Some("home")).is_err());
+
+        let old = parse(Path::new("taker.toml"), r#"files = ["~/.aws"]"#).unwrap();
+        assert_eq!(old.profile(None).unwrap().include, vec!["~/.aws"]);
+
+        // The sample shipped along.
+        let sample = include_str!("../../config/.taker.toml");
+        parse(Path::new("config/.taker.toml"), sample).unwrap();
+    }
+
+    #[test]
+    fn errors_are_located() {
+        assert_eq!(
+            error("files = [\"~/.aws\"\n"),
+            "taker.toml:2:1: expected a right bracket, found eof"
+        );
+        assert_eq!(error("file = []"), "taker.toml:1:8: unknown key `file`");
+        let wrong = CONFIG.replace("destination", "destinaton");
+        assert_eq!(error(&wrong), "taker.toml:10:14: unknown key `destinaton`");
+        let wrong = CONFIG.replace("keep_days = 30", "keep_days = \"30\"");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:6:42: `keep_days` must be a positive integer, not string"
+        );
+        let wrong = CONFIG.replace("keep_last = 2", "keep_last = 0");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:6:27: `keep_last` must be positive, not 0"
+        );
+        let wrong = CONFIG.replace("{profile}-{date}", "{profile}-{time}");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:11:16: `archive_name` has no {date}"
+        );
+        let wrong = CONFIG.replace("include = [\"~/work/notes\"]\n", "");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:8:1: profile `work` has no `include`"
+        );
+        let wrong = CONFIG.replace("\"dotfiles\"", "\"home\"");
+        assert_eq!(error(&wrong), "taker.toml:1:11: no profile `home`");
+        let wrong = CONFIG.replace("exclude = [\"*.swp\"]", "compression = \"rar\"");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:5:15: unknown compression `rar`, expected one of none, gzip, zstd, xz"
+        );
+        let wrong = CONFIG.replace("exclude = [\"*.swp\"]", "level = 12");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:5:9: the `level` of gzip is from 0 to 9, not 12"
+        );
+        let wrong = CONFIG.replace("exclude = [\"*.swp\"]", "compression = \"none\"\nlevel = 1");
+        assert_eq!(
+            error(&wrong),
+            "taker.toml:6:9: no `level` without a compression"
+        );
+    }
+
+    #[test]
+    fn retention() {
+        let archives: Vec<(String, u32)> = vec![
+            (String::from("20180101000000"), 1),
+            (String::from("20180501000000"), 5),
+            (String::from("20180301000000"), 3),
+            (String::from("20180401000000"), 4),
+        ];
+        let now = "20180502000000";
+        let expired = |keep_last, keep_days| {
+            let retention = Retention {
+                keep_last,
+                keep_days,
+            };
+            retention.expired(&archives, now).unwrap()
+        };
+        assert!(expired(None, None).is_empty());
+        assert_eq!(expired(Some(2), None), vec![3, 1]);
+        assert_eq!(expired(None, Some(45)), vec![3, 1]);
+        assert_eq!(expired(Some(1), Some(45)), vec![3, 1]);
+        assert_eq!(expired(Some(3), Some(10)), vec![1]);
+    }
 }
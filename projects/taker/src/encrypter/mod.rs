@@ -1,241 +1,544 @@
-// https://github.com/RustCrypto/traits
-// https://github.com/fizyk20/generic-array
-// http://fizyk20.github.io/generic-array/generic_array/
-
-// https://github.com/ctz/fastpbkdf2
-// https://github.com/briansmith/crypto-bench
-// https://github.com/briansmith/crypto-bench/blob/master/fastpbkdf2/fastpbkdf2.rs
+// Password-based authenticated encryption of an archive, in process.
+//
+// The key comes from the password through PBKDF2-HMAC-SHA256, with a random
+// salt per archive. The archive is sealed in chunks with ChaCha20-Poly1305,
+// so it never has to fit in memory:
+//
+//     header:  "TAKERv1\0", kdf (1), iterations (4), salt (16),
+//              aead (1), chunk size (4), nonce prefix (7)
+//     chunks:  ciphertext of chunk size bytes (less for the last one) + tag
+//
+// The nonce of a chunk is the prefix, its number and whether it's the last
+// one; the header is the associated data of every chunk. Changing a byte,
+// dropping or reordering chunks, cutting the archive short or adding to it:
+// decryption fails, instead of returning something else.
+//
+// A chunk store has one key for all of its chunks and snapshots instead,
+// derived once with the same header (its nonce prefix unused), kept in the
+// store with an HMAC of it to tell a wrong password. Each of them is sealed alone, under a random nonce
+// written before it; chunks are named after an HMAC of their content, not
+// its digest, so the names don't tell what is in them.
+//
+// https://tools.ietf.org/html/rfc8439
+// https://tools.ietf.org/html/rfc2104
+// https://tools.ietf.org/html/rfc2898#section-5.2
+// https://eprint.iacr.org/2015/189.pdf (the STREAM construction)
 
-extern crate block_cipher_trait;
-extern crate crypto;
 extern crate ring;
-extern crate twofish;
-
-// https://github.com/DaGenix/rust-crypto/blob/master/examples/symmetriccipher.rs
-// https://tools.ietf.org/html/rfc2898#section-5.2
-// https://github.com/RustCrypto/block-ciphers
-
-use std::str;
-// https://doc.rust-lang.org/std/fs/struct.File.html
-use std::fs::File;
-// https://doc.rust-lang.org/std/io/trait.Read.html
-// https://doc.rust-lang.org/std/io/trait.BufRead.html
-// use std::collections::HashMap;
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::io::Error;
-use std::path::PathBuf;
-
-// use rand::{OsRng, RngCore};
-// use self::crypto::buffer::{BufferResult, ReadBuffer, WriteBuffer};
-// use self::crypto::{aes, blockmodes, buffer, symmetriccipher};
-
-use self::ring::{digest, pbkdf2};
-
-// http://fizyk20.github.io/generic-array/generic_array/
-// https://github.com/fizyk20/generic-array
-// https://github.com/RustCrypto/traits
-use self::block_cipher_trait::generic_array::GenericArray;
-use self::block_cipher_trait::BlockCipher;
-
-use self::twofish::Twofish;
-
-use std::collections::HashMap;
-use std::env;
-use std::process::{Command, Stdio};
-
-mod progressbar;
-mod prompts;
 
-static DIGEST_ALG: &'static digest::Algorithm = &digest::SHA256;
-const CREDENTIAL_LEN: usize = digest::SHA256_OUTPUT_LEN;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
 
-type Credential = [u8; CREDENTIAL_LEN];
+use self::ring::aead::{self, OpeningKey, SealingKey, CHACHA20_POLY1305};
+use self::ring::constant_time;
+use self::ring::digest;
+use self::ring::hmac;
+use self::ring::pbkdf2;
+use self::ring::rand::{SecureRandom, SystemRandom};
 
-fn get_file_buffer(path: &PathBuf) -> Result<Vec<u8>, Error> {
-    debug!("getting buffer of {:?}", path);
+mod prompts;
 
-    let file = File::open(&path)?;
-    let mut buffer = Vec::new();
-    let mut reader = BufReader::new(file);
+const MAGIC: &[u8; 8] = b"TAKERv1\0";
+const KDF_PBKDF2_SHA256: u8 = 1;
+const AEAD_CHACHA20_POLY1305: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_PREFIX_LEN: usize = 7;
+const HEADER_LEN: usize = 8 + 1 + 4 + SALT_LEN + 1 + 4 + NONCE_PREFIX_LEN;
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const MAC_LEN: usize = 32;
+// Past these, the header is more likely broken than not.
+const MAX_ITERATIONS: u32 = 100_000_000;
+const MAX_CHUNK_SIZE: u32 = 1 << 24;
+
+/// The extension of the encrypted archives.
+pub const EXTENSION: &str = "enc";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    pub iterations: u32,
+    pub chunk_size: u32,
+}
 
-    match reader.read_to_end(&mut buffer) {
-        Ok(usz) => {
-            debug!("buffer from {:?} is {:?} bytes long", &path, usz);
-            Ok(buffer)
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            iterations: 100_000,
+            chunk_size: 64 * 1024,
         }
-        Err(e) => Err(e),
     }
 }
 
-fn salt(component: Vec<u8>, input: &str) -> Vec<u8> {
-    let mut output: Vec<u8> = Vec::with_capacity(component.len() + input.as_bytes().len());
-    output.extend(component);
-    output.extend(input.as_bytes());
-    output
+struct Header {
+    params: Params,
+    salt: [u8; SALT_LEN],
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
 }
 
-// This value was generated from a secure PRNG.
-fn component() -> Vec<u8> {
-    #[cfg_attr(rustfmt, rustfmt_skip)]
-    vec![
-        0xd6, 0x26, 0x98, 0xda, 0xf4, 0xdc, 0x50, 0x52,
-        0x24, 0xf2, 0x27, 0xd1, 0xfe, 0x39, 0x01, 0x8a,
-    ]
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
 }
 
-fn tf() -> Twofish {
-    let password: String;
-    loop {
-        let pv = prompts::getpass::get_pass("password: ");
-        let pa = match str::from_utf8(&pv) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+impl Header {
+    fn random(params: Params) -> io::Result<Header> {
+        let mut header = Header {
+            params,
+            salt: [0; SALT_LEN],
+            nonce_prefix: [0; NONCE_PREFIX_LEN],
         };
+        let rng = SystemRandom::new();
+        rng.fill(&mut header.salt)
+            .and_then(|_| rng.fill(&mut header.nonce_prefix))
+            .map_err(|_| io::Error::other("no random numbers from the system"))?;
+        Ok(header)
+    }
 
-        let pv = prompts::getpass::get_pass("confirm: ");
-        let pb = match str::from_utf8(&pv) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
-        };
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(KDF_PBKDF2_SHA256);
+        bytes.extend_from_slice(&self.params.iterations.to_be_bytes());
+        bytes.extend_from_slice(&self.salt);
+        bytes.push(AEAD_CHACHA20_POLY1305);
+        bytes.extend_from_slice(&self.params.chunk_size.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce_prefix);
+        bytes
+    }
 
-        if pa == pb {
-            password = pa.to_owned();
-            break;
-        } else {
-            warn!("passwords aren't matching");
-            continue;
+    fn parse(bytes: &[u8]) -> io::Result<Header> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(invalid("not an archive encrypted by taker"));
+        }
+        let u32_at =
+            |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        if bytes[8] != KDF_PBKDF2_SHA256 || bytes[13 + SALT_LEN] != AEAD_CHACHA20_POLY1305 {
+            return Err(invalid("unknown key derivation or cipher"));
+        }
+        let params = Params {
+            iterations: u32_at(9),
+            chunk_size: u32_at(14 + SALT_LEN),
         };
+        if params.iterations == 0
+            || params.iterations > MAX_ITERATIONS
+            || params.chunk_size == 0
+            || params.chunk_size > MAX_CHUNK_SIZE
+        {
+            return Err(invalid("the header of the archive is broken"));
+        }
+        let mut header = Header {
+            params,
+            salt: [0; SALT_LEN],
+            nonce_prefix: [0; NONCE_PREFIX_LEN],
+        };
+        header.salt.copy_from_slice(&bytes[13..13 + SALT_LEN]);
+        header
+            .nonce_prefix
+            .copy_from_slice(&bytes[HEADER_LEN - NONCE_PREFIX_LEN..HEADER_LEN]);
+        Ok(header)
     }
 
-    let pbkdf2_iterations: u32 = 100_000;
-    let salt = salt(component(), "taker");
-    let mut key: Credential = [0u8; CREDENTIAL_LEN];
+    fn key(&self, password: &[u8]) -> [u8; KEY_LEN] {
+        let mut key = [0u8; KEY_LEN];
+        pbkdf2::derive(
+            &digest::SHA256,
+            self.params.iterations,
+            &self.salt,
+            password,
+            &mut key,
+        );
+        key
+    }
 
-    pbkdf2::derive(
-        DIGEST_ALG,
-        pbkdf2_iterations,
-        &salt,
-        password.as_bytes(),
-        &mut key,
-    );
+    fn nonce(&self, counter: u32, last: bool) -> [u8; NONCE_LEN] {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+        nonce[11] = last as u8;
+        nonce
+    }
+}
+
+/// The key of a chunk store: one to seal with, one to name chunks with.
+pub struct StoreKey {
+    header: Vec<u8>,
+    sealing: SealingKey,
+    opening: OpeningKey,
+    naming: hmac::SigningKey,
+}
+
+impl StoreKey {
+    fn derive(header: &Header, password: &[u8]) -> io::Result<StoreKey> {
+        let mut keys = [0u8; 2 * KEY_LEN];
+        pbkdf2::derive(
+            &digest::SHA256,
+            header.params.iterations,
+            &header.salt,
+            password,
+            &mut keys,
+        );
+        let (cipher, naming) = keys.split_at(KEY_LEN);
+        let no_key = |_| io::Error::other("cannot make a key");
+        Ok(StoreKey {
+            header: header.to_bytes(),
+            sealing: SealingKey::new(&CHACHA20_POLY1305, cipher).map_err(no_key)?,
+            opening: OpeningKey::new(&CHACHA20_POLY1305, cipher).map_err(no_key)?,
+            naming: hmac::SigningKey::new(&digest::SHA256, naming),
+        })
+    }
 
-    debug!("initial password: {:?}", password);
-    debug!("initial salt: {:?}", salt);
-    debug!("initial key: {:?}", key);
-    debug!("pbkdf2-ed key: {:?}", key);
+    /// For a new store, with a random salt.
+    pub fn new(password: &[u8], params: Params) -> io::Result<StoreKey> {
+        StoreKey::derive(&Header::random(params)?, password)
+    }
 
-    return Twofish::new_varkey(&key).unwrap();
+    /// The key that to_bytes gave, for the same password.
+    pub fn from_bytes(password: &[u8], bytes: &[u8]) -> io::Result<StoreKey> {
+        if bytes.len() != HEADER_LEN + MAC_LEN {
+            return Err(invalid("not the key of a store"));
+        }
+        let key = StoreKey::derive(&Header::parse(&bytes[..HEADER_LEN])?, password)?;
+        constant_time::verify_slices_are_equal(
+            hmac::sign(&key.naming, &key.header).as_ref(),
+            &bytes[HEADER_LEN..],
+        )
+        .map_err(|_| invalid("wrong password"))?;
+        Ok(key)
+    }
+
+    /// What the store keeps: the salt and the iterations, not the key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.clone();
+        bytes.extend_from_slice(hmac::sign(&self.naming, &self.header).as_ref());
+        bytes
+    }
+
+    /// The HMAC-SHA256 of data, in hex.
+    pub fn name(&self, data: &[u8]) -> String {
+        hmac::sign(&self.naming, data)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Data encrypted and authenticated along with ad, which open needs too.
+    pub fn seal(&self, ad: &[u8], data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| io::Error::other("no random numbers from the system"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(data);
+        sealed.resize(NONCE_LEN + data.len() + TAG_LEN, 0);
+        aead::seal_in_place(&self.sealing, &nonce, ad, &mut sealed[NONCE_LEN..], TAG_LEN)
+            .map_err(|_| io::Error::other("cannot encrypt"))?;
+        Ok(sealed)
+    }
+
+    pub fn open(&self, ad: &[u8], sealed: &[u8]) -> io::Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err(invalid("cut short"));
+        }
+        let (nonce, data) = sealed.split_at(NONCE_LEN);
+        let mut data = data.to_vec();
+        let len = aead::open_in_place(&self.opening, nonce, ad, 0, &mut data)
+            .map_err(|_| invalid("wrong key, or modified"))?
+            .len();
+        data.truncate(len);
+        Ok(data)
+    }
 }
 
-#[allow(dead_code)]
-pub fn cipher(src: &PathBuf) -> Result<PathBuf, Error> {
-    let f_buf: Vec<u8> = get_file_buffer(src)?;
-    let f_buf: &[u8] = &f_buf;
+// As many bytes as fit, fewer only at the end of the input.
+fn read_full<R: Read>(input: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    input.take(len as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
 
-    let twofish = tf();
-    let mut encrypted = Vec::new();
-    let mut pb = progressbar::simple(((f_buf.len() as f64) / 16.0).ceil() as u64);
+fn next_counter(counter: u32) -> io::Result<u32> {
+    counter
+        .checked_add(1)
+        .ok_or_else(|| invalid("too many chunks"))
+}
 
-    for chunk in f_buf.chunks(16) {
-        let plain = GenericArray::from_slice(chunk);
-        let mut buf = plain.clone();
+/// Encrypts what is written into it, a chunk at a time: the last one goes
+/// out with finish, not when it is dropped.
+pub struct Encrypter<W: Write> {
+    output: W,
+    header: Header,
+    ad: Vec<u8>,
+    key: SealingKey,
+    chunk: Vec<u8>,
+    counter: u32,
+}
+
+impl<W: Write> Encrypter<W> {
+    pub fn new(password: &[u8], params: Params, mut output: W) -> io::Result<Encrypter<W>> {
+        let header = Header::random(params)?;
+        let ad = header.to_bytes();
+        output.write_all(&ad)?;
+        let key = SealingKey::new(&CHACHA20_POLY1305, &header.key(password))
+            .map_err(|_| io::Error::other("cannot make a key"))?;
+        Ok(Encrypter {
+            output,
+            header,
+            ad,
+            key,
+            chunk: Vec::with_capacity(params.chunk_size as usize + TAG_LEN),
+            counter: 0,
+        })
+    }
 
-        twofish.encrypt_block(&mut buf);
-        let mut cipher = buf.clone();
-        // https://doc.rust-lang.org/std/vec/struct.Vec.html#method.extend_from_slice
-        encrypted.extend_from_slice(&cipher);
+    // The chunk so far, which has to be a full one unless it's the last.
+    fn seal(&mut self, last: bool) -> io::Result<()> {
+        let len = self.chunk.len();
+        self.chunk.resize(len + TAG_LEN, 0);
+        let nonce = self.header.nonce(self.counter, last);
+        let sealed = aead::seal_in_place(&self.key, &nonce, &self.ad, &mut self.chunk, TAG_LEN)
+            .map_err(|_| io::Error::other("cannot encrypt"))?;
+        self.output.write_all(&self.chunk[..sealed])?;
+        self.chunk.clear();
+        if !last {
+            self.counter = next_counter(self.counter)?;
+        }
+        Ok(())
+    }
 
-        twofish.decrypt_block(&mut cipher);
-        assert_eq!(plain, &cipher);
-        pb.inc();
+    /// Seals the last chunk, empty if nothing was written.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.seal(true)?;
+        self.output.flush()?;
+        Ok(self.output)
     }
+}
 
-    assert_eq!(f_buf.len(), encrypted.len());
-    pb.finish();
+impl<W: Write> Write for Encrypter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A full chunk is the last one only if nothing follows it: it is
+        // sealed when more comes.
+        let size = self.header.params.chunk_size as usize;
+        if self.chunk.len() == size && !buf.is_empty() {
+            self.seal(false)?;
+        }
+        let n = buf.len().min(size - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
 
-    let mut dst: PathBuf = src.clone();
-    dst.set_extension("enc");
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
 
-    debug!("encryption dst is {:?}", dst);
-    let mut file = File::create(&dst)?;
-    return match file.write_all(&encrypted) {
-        Ok(n) => {
-            info!("done with {:?}", n);
-            Ok(dst)
+/// Decrypts the input, checking every chunk before writing it out: on an
+/// error, what was written already is authentic, but not all of it.
+pub fn decrypt<R: Read, W: Write>(password: &[u8], mut input: R, mut output: W) -> io::Result<()> {
+    let ad = read_full(&mut input, HEADER_LEN)?;
+    let header = Header::parse(&ad)?;
+    let key = OpeningKey::new(&CHACHA20_POLY1305, &header.key(password))
+        .map_err(|_| io::Error::other("cannot make a key"))?;
+
+    let size = header.params.chunk_size as usize + TAG_LEN;
+    let mut block = read_full(&mut input, size)?;
+    let mut counter = 0;
+    loop {
+        if block.len() < TAG_LEN {
+            return Err(invalid("the archive is cut short"));
         }
-        Err(e) => Err(e),
-    };
+        let next = if block.len() == size {
+            read_full(&mut input, size)?
+        } else {
+            Vec::new()
+        };
+        let last = next.is_empty();
+        let plain = aead::open_in_place(&key, &header.nonce(counter, last), &ad, 0, &mut block)
+            .map_err(|_| invalid("wrong password, or the archive was modified"))?;
+        output.write_all(plain)?;
+        if last {
+            if input.read(&mut [0u8; 1])? != 0 {
+                return Err(invalid("wrong password, or the archive was modified"));
+            }
+            return output.flush();
+        }
+        block = next;
+        counter = next_counter(counter)?;
+    }
 }
 
-#[allow(dead_code)]
-pub fn decipher(src: &PathBuf) -> Result<PathBuf, Error> {
-    let f_buf: Vec<u8> = get_file_buffer(src)?;
-    let f_buf: &[u8] = &f_buf;
+/// Starts the way encrypted archives do.
+pub fn is_encrypted(path: &Path) -> io::Result<bool> {
+    let magic = read_full(&mut File::open(path)?, MAGIC.len())?;
+    Ok(magic == MAGIC)
+}
 
-    let twofish = tf();
-    let mut decrypted = Vec::new();
-    let mut pb = progressbar::simple(((f_buf.len() as f64) / 16.0).ceil() as u64);
+/// The password of a store, asked on the terminal: twice for a new one.
+pub fn store_password(new: bool) -> Vec<u8> {
+    if new {
+        new_password()
+    } else {
+        prompts::getpass::get_pass("password")
+    }
+}
 
-    for chunk in f_buf.chunks(16) {
-        let enc = GenericArray::from_slice(chunk);
-        let mut buf = enc.clone();
+// Asked twice, until both are the same.
+fn new_password() -> Vec<u8> {
+    loop {
+        let password = prompts::getpass::get_pass("password");
+        if password == prompts::getpass::get_pass("confirm") {
+            return password;
+        }
+        warn!("passwords aren't matching");
+    }
+}
 
-        twofish.decrypt_block(&mut buf);
+/// An encrypter into a new file at out, readable by its owner only, for a
+/// password asked on the terminal.
+pub fn symmetric(out: &Path) -> io::Result<Encrypter<BufWriter<File>>> {
+    let password = new_password();
+    debug!("encrypting into {:?}", out);
+    let output = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(out)?;
+    Encrypter::new(&password, Params::default(), BufWriter::new(output))
+}
 
-        let mut plain = buf.clone();
-        // https://doc.rust-lang.org/std/vec/struct.Vec.html#method.extend_from_slice
-        decrypted.extend_from_slice(&plain);
-        pb.inc();
+/// Decrypts src into out, for a password asked on the terminal: out is a new
+/// file, readable by its owner only, and nothing is left in it if it fails.
+pub fn decrypt_symmetric(src: &Path, out: &Path) -> io::Result<PathBuf> {
+    let password = prompts::getpass::get_pass("password");
+    debug!("decrypting {:?} into {:?}", src, out);
+    let input = BufReader::new(File::open(src)?);
+    let output = BufWriter::new(
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(out)?,
+    );
+    if let Err(e) = decrypt(&password, input, output) {
+        let _ = fs::remove_file(out);
+        return Err(e);
     }
+    Ok(out.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fast to derive, small chunks: many of them.
+    const PARAMS: Params = Params {
+        iterations: 10,
+        chunk_size: 64,
+    };
 
-    assert_eq!(f_buf.len(), decrypted.len());
-    pb.finish();
+    fn sealed(data: &[u8]) -> Vec<u8> {
+        let mut encrypter = Encrypter::new(b"secret", PARAMS, Vec::new()).unwrap();
+        encrypter.write_all(data).unwrap();
+        encrypter.finish().unwrap()
+    }
 
-    let mut dst: PathBuf = src.clone();
-    dst.set_extension("foo");
+    fn opened(password: &[u8], data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        decrypt(password, data, &mut out).map(|_| out)
+    }
 
-    debug!("decryption dst is {:?}", dst);
-    let mut file = File::create(&dst)?;
-    return match file.write_all(&decrypted) {
-        Ok(n) => {
-            info!("done with {:?}", n);
-            Ok(dst)
+    #[test]
+    fn round_trips() {
+        for &len in &[0usize, 1, 63, 64, 65, 128, 1000, 3 * 64 + 17] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            let archive = sealed(&data);
+            // A full chunk can be the last one; an empty input is one chunk.
+            let chunks = len.div_ceil(64).max(1);
+            assert_eq!(archive.len(), HEADER_LEN + len + chunks * TAG_LEN);
+            assert_eq!(opened(b"secret", &archive).unwrap(), data, "{} bytes", len);
         }
-        Err(e) => Err(e),
-    };
-}
+        // Written in pieces of any size, as a compressor does.
+        let data: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+        let mut encrypter = Encrypter::new(b"secret", PARAMS, Vec::new()).unwrap();
+        for piece in data.chunks(37) {
+            encrypter.write_all(piece).unwrap();
+        }
+        let archive = encrypter.finish().unwrap();
+        assert_eq!(archive.len(), HEADER_LEN + 1000 + 16 * TAG_LEN);
+        assert_eq!(opened(b"secret", &archive).unwrap(), data);
+        // A new salt and nonce every time.
+        assert_ne!(sealed(b"same"), sealed(b"same"));
+
+        let dir = ::std::env::temp_dir().join(format!("taker-encrypter-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.enc");
+        fs::write(&path, sealed(b"data")).unwrap();
+        assert!(is_encrypted(&path).unwrap());
+        fs::write(&path, b"data").unwrap();
+        assert!(!is_encrypted(&path).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-pub fn symmetric(src: &PathBuf, out: &PathBuf) -> Result<PathBuf, Error> {
-    let filtered_env: HashMap<String, String> = env::vars()
-        .filter(|&(ref k, _)| k == "TERM" || k == "TZ" || k == "LANG" || k == "PATH")
-        .collect();
-    let mut dst = out.clone();
-    dst.set_extension("gpg");
-    // https://doc.rust-lang.org/std/process/struct.Command.html
-    match Command::new("gpg")
-        .arg("--symmetric")
-        .args(&["--cipher-algo", "AES256"])
-        .args(&["--compress-level", "6"])
-        .args(&["--compress-algo", "ZLIB"])
-        .args(&["--output", dst.to_str().unwrap()])
-        .arg(src.to_str().unwrap())
-        .current_dir(src.parent().expect("src must be a file"))
-        .stdin(Stdio::null())
-        .stdout(Stdio::inherit())
-        .env_clear()
-        .envs(&filtered_env)
-        .spawn()
-    {
-        Ok(child) => {
-            let output = child.wait_with_output().expect("failed to wait on child");
-            info!("Child has finished its execution!");
-            assert!(output.status.success());
-            return Ok(dst);
+    #[test]
+    fn detects_tampering() {
+        let data: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let archive = sealed(&data);
+        let fails = |archive: &[u8]| opened(b"secret", archive).is_err();
+
+        assert!(opened(b"guess", &archive).is_err());
+        // Only the low byte of the iterations: millions of them take long.
+        for i in (0..archive.len()).filter(|i| !(9..12).contains(i)) {
+            let mut flipped = archive.clone();
+            flipped[i] ^= 1;
+            assert!(fails(&flipped), "byte {} changed", i);
         }
-        Err(e) => {
-            error!("ls command didn't start");
-            return Err(e);
+        // Without the last chunk, the last two swapped, with more at the end.
+        let block = 64 + TAG_LEN;
+        let full = HEADER_LEN + 3 * block;
+        assert!(fails(&archive[..full]));
+        assert!(fails(&archive[..archive.len() - 1]));
+        let mut swapped = archive[..HEADER_LEN + block].to_vec();
+        swapped.extend_from_slice(&archive[HEADER_LEN + 2 * block..full]);
+        swapped.extend_from_slice(&archive[HEADER_LEN + block..HEADER_LEN + 2 * block]);
+        swapped.extend_from_slice(&archive[full..]);
+        assert!(fails(&swapped));
+        let mut longer = archive.clone();
+        longer.push(0);
+        assert!(fails(&longer));
+        assert!(fails(b"TAKERv0"));
+    }
+
+    #[test]
+    fn store_keys() {
+        let key = StoreKey::new(b"secret", PARAMS).unwrap();
+        let bytes = key.to_bytes();
+        assert!(StoreKey::from_bytes(b"guess", &bytes).is_err());
+        assert!(StoreKey::from_bytes(b"secret", &bytes[1..]).is_err());
+        let again = StoreKey::from_bytes(b"secret", &bytes).unwrap();
+        assert_eq!(again.name(b"data"), key.name(b"data"));
+        assert_eq!(key.name(b"data").len(), 64);
+        // Not the digest: nobody without the key can tell the name.
+        let other = StoreKey::new(b"secret", PARAMS).unwrap();
+        assert_ne!(other.name(b"data"), key.name(b"data"));
+
+        let sealed = key.seal(b"ad", b"data").unwrap();
+        assert_eq!(sealed.len(), NONCE_LEN + 4 + TAG_LEN);
+        assert_ne!(key.seal(b"ad", b"data").unwrap(), sealed);
+        assert_eq!(again.open(b"ad", &sealed).unwrap(), b"data");
+        assert_eq!(
+            key.open(b"ad", &key.seal(b"ad", b"").unwrap()).unwrap(),
+            b""
+        );
+        assert!(key.open(b"other", &sealed).is_err());
+        assert!(other.open(b"ad", &sealed).is_err());
+        assert!(key.open(b"ad", &sealed[..NONCE_LEN + TAG_LEN - 1]).is_err());
+        for i in 0..sealed.len() {
+            let mut flipped = sealed.clone();
+            flipped[i] ^= 1;
+            assert!(key.open(b"ad", &flipped).is_err(), "byte {} changed", i);
         }
     }
 }
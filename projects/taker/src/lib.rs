@@ -23,13 +23,15 @@ extern crate rand;
 extern crate serde_derive;
 extern crate toml;
 
-use std::error::Error;
 // use std::io::prelude::*;
 use std::iter;
 // use std::fmt;
+use std::collections::HashSet;
 use std::env;
-use std::fs::{self, DirBuilder};
-use std::io;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::process;
 // use std::io::{Error, ErrorKind};
 
 // use std::io::Read;
@@ -57,71 +59,32 @@ use rand::Rng;
 // scope so it's easier to refer to them.
 
 mod compress;
-mod config;
+pub mod config;
 mod encrypter;
+mod manifest;
+pub mod restore;
+pub mod store;
 mod timez;
 mod walkers;
 
-const BASE_URL: &'static str = "Downloads";
-const ARCHIVE_NAME: &'static str = "takenfiles";
-const COPY_DEST: &'static str = "takentarget";
+const STORE_DEST: &str = "takenstore";
 pub const ENV_CFG: &'static str = "TAKER_CFG";
 pub const LOG_CFG: &'static str = "RUST_LOG";
 
-#[derive(Debug)]
-enum Operation {
-    CopyFile,
-    #[allow(dead_code)]
-    CopyDir,
+pub fn config_path() -> PathBuf {
+    match env::var(ENV_CFG) {
+        Ok(p) => PathBuf::from(p),
+        Err(_) => maybe_expand_home(&PathBuf::from("~/.taker.toml")),
+    }
 }
 
-pub fn config() -> config::Config {
-    let cfg_path: String = ENV_CFG.to_owned();
-    config::parse(match env::var(cfg_path) {
-        Ok(h) => h,
-        Err(_) => maybe_expand_home(&PathBuf::from("~/.taker.toml"))
-            .to_str()
-            .unwrap()
-            .to_owned(),
-    })
+pub fn config() -> Result<config::Config, io::Error> {
+    config::load(&config_path())
 }
 
-fn verify_operation<S: AsRef<Path> + std::fmt::Debug, D: AsRef<Path> + std::fmt::Debug>(
-    op: Operation,
-    src: &S,
-    dst: &D,
-) -> Result<bool, String> {
-    debug!("verifying the result of {:?}", op);
-    let mut s_size: u64 = 0;
-    let mut d_size: u64 = 0;
-    match fs::metadata(src) {
-        Ok(md) => {
-            // md: fs::Metadata
-            s_size = md.len();
-            debug!("meta from the source (size: {:?})", s_size);
-        }
-        Err(e) => {
-            error!("cannot get metadata from {:?}: {}", src, e);
-        }
-    };
-    match fs::metadata(dst) {
-        Ok(md) => {
-            d_size = md.len();
-            debug!("meta from the destination (size: {:?})", d_size);
-        }
-        Err(e) => {
-            error!("cannot get metadata from {:?}: {}", dst, e);
-        }
-    };
-    if s_size != d_size {
-        error!(
-            "something something has been lost performing {:?} on {:?} and {:?}",
-            op, src, dst
-        );
-        return Err(String::from("mismatching_size"));
-    }
-    info!("operation {:?} on {:?} and {:?} successful", op, src, dst);
-    Ok(true)
+// The profile called name, or the default one, of the config.
+pub fn profile(name: Option<&str>) -> Result<config::Profile, io::Error> {
+    config()?.profile(name).cloned()
 }
 
 // Minimal example of an AsRef conversion.
@@ -137,21 +100,14 @@ fn verify_operation<S: AsRef<Path> + std::fmt::Debug, D: AsRef<Path> + std::fmt:
 // is_hello(s);
 //
 
-fn tag_name<P>(home: &P, path: &P, name: &P) -> PathBuf
+fn tag_name<P>(path: &P, name: &P) -> PathBuf
 where
     // P: AsRef<Path> + std::fmt::Debug + std::fmt::Display,
     P: AsRef<std::path::Path>,
 {
-    let b_url: PathBuf = PathBuf::from(BASE_URL);
     let name: &Path = name.as_ref();
-
-    [
-        home.as_ref(),
-        b_url.as_ref(),
-        path.as_ref(),
-        format!("{}.{}", timez::datetag(), name.display()).as_ref(),
-    ].iter()
-        .collect()
+    path.as_ref()
+        .join(format!("{}.{}", timez::datetag(), name.display()))
 }
 
 fn home_name() -> String {
@@ -161,37 +117,6 @@ fn home_name() -> String {
     }
 }
 
-fn copy_file_in<S: AsRef<Path> + std::fmt::Debug, D: AsRef<Path> + std::fmt::Debug>(
-    src: S,
-    dst: D,
-) -> Result<bool, io::Error> {
-    // https://doc.rust-lang.org/std/fs/fn.copy.html
-    // https://doc.rust-lang.org/std/fs/struct.File.html
-    info!("copying {:?} into {:?}", &src, &dst);
-    // https://github.com/rust-lang/rfcs/pull/243
-    let r = fs::copy(&src, &dst);
-    match &r {
-        &Ok(n_bytes) => {
-            debug!("copied {} bytes from {:?} to {:?}", n_bytes, &src, &dst);
-        }
-        &Err(ref e) => {
-            error!("cannot copy {:?} into {:?}: {:?}", &src, &dst, e);
-        }
-    };
-
-    match verify_operation(Operation::CopyFile, &src, &dst) {
-        Ok(_) => {
-            return Ok(true);
-        }
-        // https://doc.rust-lang.org/std/process/fn.exit.html
-        // https://doc.rust-lang.org/std/io/struct.Error.html
-        Err(e) => {
-            let custom_error = io::Error::new(io::ErrorKind::Other, e);
-            return Err(custom_error);
-        }
-    };
-}
-
 // https://github.com/rust-lang-nursery/rand/blob/master/src/lib.rs#L601
 // https://github.com/rust-lang-nursery/rand/blob/master/src/lib.rs#L380
 // https://github.com/rust-lang-nursery/rand/blob/0.5.0-pre.0/src/distributions/other.rs
@@ -206,41 +131,6 @@ fn random_from(seed: &str) -> String {
     return chars;
 }
 
-fn create_dir(mut dst: PathBuf, name: &str) -> Result<PathBuf, io::Error> {
-    dst.push(name);
-    match DirBuilder::new().recursive(false).create(&dst) {
-        Ok(_) => {
-            info!("directory {:?} created", &dst);
-            debug!("ensuring that {:?} is a directory", &dst);
-            assert!(fs::metadata(&dst).unwrap().is_dir());
-        }
-        Err(e) => {
-            error!(
-                "cannot create directory {:?}: {}... destroy (try again)!",
-                &dst, e
-            );
-            return Err(e);
-        }
-    };
-    return Ok(dst);
-}
-
-// Create the target directory where the files will be copied.
-fn create_target_dir(home: &str) -> Result<PathBuf, io::Error> {
-    create_dir([home, BASE_URL].iter().collect(), COPY_DEST)
-}
-
-// Return the file name for the backup archive.
-fn create_archive_name(home: &str) -> Result<PathBuf, io::Error> {
-    let mut fln: PathBuf = [home, BASE_URL, ARCHIVE_NAME].iter().collect();
-    if fln.set_extension("tar") {
-        return Ok(fln);
-    } else {
-        let custom_error = io::Error::new(io::ErrorKind::Other, "cannot set the file extension");
-        return Err(custom_error);
-    }
-}
-
 fn maybe_expand_home(f: &PathBuf) -> PathBuf {
     match f.to_owned().strip_prefix("~") {
         Ok(p) => Path::new(&home_name()).join(p),
@@ -260,110 +150,256 @@ fn maybe_expand_dot(f: &PathBuf) -> PathBuf {
     }
 }
 
-pub fn run(cfg: config::Config) -> Result<bool, io::Error> {
-    match cfg.files.len() {
-        0 => info!("nothing to do"),
-        c => {
-            info!("taking {:?} entries", c);
-            // check what's the $HOME path here.
-            let home: String = home_name();
-            debug!("considering {} as $HOME", home);
-
-            let dst: PathBuf = create_target_dir(&home).unwrap();
-            // pick up one entry at the time from the given config.
-            for cf in cfg.files {
-                let mut f: PathBuf = PathBuf::from(cf);
-                debug!("considering {:?}", f);
-
-                f = maybe_expand_home(&f);
-
-                match fs::metadata(&f) {
-                    Ok(meta) => {
-                        if meta.is_dir() {
-                            debug!("{:?} is a directory", f);
-
-                            info!(
-                                "copying content of {:?} (file name: {:?} expanded as {:?}) into {:?}",
-                                &f,
-                                &f.file_name().unwrap(),
-                                maybe_expand_dot(&PathBuf::from(&f.file_name().unwrap())),
-                                &dst
-                            );
-
-                            let inner: PathBuf = create_dir(
-                                dst.to_owned(),
-                                &maybe_expand_dot(&PathBuf::from(&f.file_name().unwrap()))
-                                    .into_os_string()
-                                    .into_string()
-                                    .unwrap(),
-                            )?;
-
-                            walkers::visit_dirs(&f, &|f_src| {
-                                debug!("entering {:?} found {:?}", f, f_src.file_name());
-
-                                let home = PathBuf::from(&home);
-
-                                let f_dst: PathBuf =
-                                    tag_name(&home, &inner, &PathBuf::from(f_src.file_name()));
-                                debug!("destination filename: {:?}", &f_dst);
-                                let f_src_s: PathBuf = [&f, &f_src.path()].iter().collect();
-                                debug!("source filename: {:?}", &f_src_s);
-                                return copy_file_in(f_src_s, f_dst);
-                            })?;
-                        } else {
-                            debug!("{:?} is a file", f);
-                            let f_dst: PathBuf = tag_name(
-                                &PathBuf::from(&home),
-                                &dst,
-                                &maybe_expand_dot(&PathBuf::from(f.file_name().unwrap())),
-                            );
-                            debug!("destination filename: {:?}", &f_dst);
-                            copy_file_in(f, f_dst)?;
-                        }
-                    }
-                    Err(e) => error!("cannot read {:?}: {:?}", &f, e.description()),
-                }
-            }
+// A file to take, and its path in the archive, under the root directory.
+type Entry = (PathBuf, PathBuf);
+
+fn has_glob(p: &str) -> bool {
+    p.contains('*') || p.contains('?')
+}
 
-            match create_archive_name(&home) {
-                Ok(tan) => {
-                    info!("compressing {:?}", dst);
-                    compress::compress(&dst, &tan)?;
-                    let out: PathBuf = tag_name(
-                        &PathBuf::from(&home),
-                        &PathBuf::from(""),
-                        &maybe_expand_dot(&PathBuf::from(tan.file_name().unwrap())),
-                    );
-                    info!("ciphering {:?} into {:?}", tan, out);
-                    match encrypter::symmetric(&tan, &out) {
-                        Ok(r) => {
-                            info!(
-                                "encryption of {:?} into {:?} was successful ({:?})",
-                                &tan, &out, &r
-                            );
-                            // let p = encrypter::decipher(&r).expect("cannot decrypt; this is bad");
-                            // info!("decryption of {:?} was successful ({:?})", &r, &p);
-                            return fs::remove_dir_all(&dst)
-                                .and_then(|_| fs::remove_file(&tan))
-                                .and_then(|_| Ok(true));
-                        }
-                        Err(e) => {
-                            error!("error encrypting {:?}: {}", &tan, e);
-                            return Err(e);
-                        }
-                    }
+// A path in the archive: the first directory "dot"-expanded, the file name
+// datetagged; a file alone is both.
+fn staged(rel: &Path) -> PathBuf {
+    let mut names: Vec<PathBuf> = rel.iter().map(PathBuf::from).collect();
+    let name: PathBuf = names.pop().unwrap_or_default();
+    if names.is_empty() {
+        return tag_name(&PathBuf::new(), &maybe_expand_dot(&name));
+    }
+    names[0] = maybe_expand_dot(&names[0]);
+    tag_name(&names.iter().collect::<PathBuf>(), &name)
+}
+
+// The files of an include, and where they go in the archive. A
+// directory keeps its tree; so does the directory a glob starts from, the
+// files it matches under their own first directories.
+fn entries_of(include: &str) -> Result<Vec<Entry>, io::Error> {
+    let f: PathBuf = expand_tilde(include);
+    debug!("considering {:?}", f);
+    let mut entries: Vec<Entry> = Vec::new();
+    if has_glob(include) {
+        let base: PathBuf = f
+            .iter()
+            .take_while(|c| !has_glob(&c.to_string_lossy()))
+            .collect();
+        let pattern = f.to_string_lossy().into_owned();
+        walkers::visit_mut_dirs(&base, &mut |entry| {
+            let f_src: PathBuf = entry.path();
+            if restore::matches(&pattern, &f_src.to_string_lossy()) {
+                if let Ok(rel) = f_src.strip_prefix(&base) {
+                    entries.push((f_src.to_owned(), staged(rel)));
                 }
-                Err(e) => {
-                    error!("cannot create the archive destination");
-                    return Err(e);
+            }
+            Ok(true)
+        })?;
+        return Ok(entries);
+    }
+    match fs::metadata(&f) {
+        Ok(ref meta) if meta.is_dir() => {
+            debug!("{:?} is a directory", f);
+            let name: PathBuf = f.file_name().map(PathBuf::from).unwrap_or_default();
+            walkers::visit_mut_dirs(&f, &mut |entry| {
+                let f_src: PathBuf = entry.path();
+                if let Ok(rel) = f_src.strip_prefix(&f) {
+                    entries.push((f_src.to_owned(), staged(&name.join(rel))));
                 }
+                Ok(true)
+            })?;
+        }
+        Ok(_) => {
+            debug!("{:?} is a file", f);
+            let name: PathBuf = f.file_name().map(PathBuf::from).unwrap_or_default();
+            entries.push((f.to_owned(), staged(&name)));
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => error!("cannot read {:?}: {}", &f, e),
+    }
+    Ok(entries)
+}
+
+// The files a profile takes, with their paths in the archive, and the
+// includes that take none.
+fn entries(profile: &config::Profile) -> Result<(Vec<Entry>, Vec<String>), io::Error> {
+    let excludes: Vec<String> = profile
+        .exclude
+        .iter()
+        .map(|p| expand_tilde(p).to_string_lossy().into_owned())
+        .collect();
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut unmatched: Vec<String> = Vec::new();
+    for include in &profile.include {
+        let found = entries_of(include)?;
+        if found.is_empty() {
+            unmatched.push(include.to_owned());
+        }
+        for (f_src, f_dst) in found {
+            let path = f_src.to_string_lossy().into_owned();
+            if excludes.iter().any(|p| restore::matches(p, &path)) {
+                debug!("excluding {:?}", f_src);
+            } else if taken.insert(f_src.to_owned()) {
+                entries.push((f_src, f_dst));
             }
         }
+    }
+    Ok((entries, unmatched))
+}
+
+/// How many files a profile takes, and the includes that take none.
+pub fn check(profile: &config::Profile) -> Result<(usize, Vec<String>), io::Error> {
+    entries(profile).map(|(entries, unmatched)| (entries.len(), unmatched))
+}
+
+// Every file goes from where it is straight into the compressor, the manifest
+// last, and from there into the encrypter: nothing is written in the clear.
+fn write_archive(
+    profile: &config::Profile,
+    entries: Vec<Entry>,
+    created: String,
+    out: &Path,
+) -> Result<(), io::Error> {
+    let root: &Path = Path::new(&profile.root_dir);
+    let mut manifest = manifest::Manifest {
+        created,
+        files: Vec::new(),
     };
+    let encrypter = encrypter::symmetric(out)?;
+    info!(
+        "archiving into {:?} ({}, encrypted)",
+        out, profile.compression
+    );
+    let mut archive = compress::Archive::new(encrypter, profile.compression, profile.level)?;
+    for (f_src, rel) in entries {
+        let archived: PathBuf = root.join(&rel);
+        debug!("archiving {:?} as {:?}", &f_src, &archived);
+        archive.append(&f_src, &archived)?;
+        manifest.files.push(manifest::Item::of(&f_src, &archived)?);
+    }
+    let text: String = manifest.to_toml()?;
+    archive.append_data(&root.join(manifest::MANIFEST_NAME), text.as_bytes())?;
+    archive.finish()?.finish()?.flush()
+}
 
+pub fn run(profile: &config::Profile) -> Result<bool, io::Error> {
+    let (entries, unmatched) = entries(profile)?;
+    for include in unmatched {
+        error!("{:?} takes no file", include);
+    }
+    if entries.is_empty() {
+        info!("nothing to do");
+        return Ok(true);
+    }
+    info!("taking {:?} files", entries.len());
+    let destination: PathBuf = expand_tilde(&profile.destination);
+    fs::create_dir_all(&destination)?;
+    let created: String = timez::datetag();
+    let name: String = profile.archive_name(&created);
+    let out: PathBuf = destination.join(format!("{}.{}", name, encrypter::EXTENSION));
+    if let Err(e) = write_archive(profile, entries, created, &out) {
+        error!("cannot write the archive {:?}: {}", &out, e);
+        let _ = fs::remove_file(&out);
+        return Err(e);
+    }
+    info!("encryption into {:?} was successful", &out);
+    for removed in prune(profile)? {
+        info!("{:?} removed: past the retention", removed);
+    }
     Ok(true)
 }
 
+// Removes the archives of the profile that its retention doesn't keep.
+fn prune(profile: &config::Profile) -> Result<Vec<PathBuf>, io::Error> {
+    let mut archives: Vec<(String, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(expand_tilde(&profile.destination))? {
+        let path: PathBuf = entry?.path();
+        let datetag = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| profile.datetag_of(s));
+        match datetag {
+            Some(datetag) if path.extension().is_some_and(|e| e == encrypter::EXTENSION) => {
+                archives.push((datetag, path))
+            }
+            _ => {}
+        }
+    }
+    let expired = profile.retention.expired(&archives, &timez::datetag())?;
+    for path in &expired {
+        fs::remove_file(path)?;
+    }
+    Ok(expired)
+}
+
+// The chunk store, next to the archives, sealed with a password asked on the
+// terminal.
+fn open_store(profile: &config::Profile) -> Result<store::Store, io::Error> {
+    store::Store::open(
+        expand_tilde(&profile.destination).join(STORE_DEST),
+        encrypter::Params::default(),
+        encrypter::store_password,
+    )
+}
+
+// Incremental: only the chunks that aren't in the store already are written.
+pub fn backup(profile: &config::Profile) -> Result<(store::Snapshot, store::Stats), io::Error> {
+    let (entries, unmatched) = entries(profile)?;
+    for include in unmatched {
+        error!("{:?} takes no file", include);
+    }
+    // The store keeps contents: what a symlink points to, no fifos.
+    let files: Vec<PathBuf> = entries
+        .into_iter()
+        .map(|(f_src, _)| f_src)
+        .filter(|f| f.is_file())
+        .collect();
+    info!("backing up {:?} files", files.len());
+    open_store(profile)?.backup(&files, &timez::datetag())
+}
+
+pub fn snapshots(profile: &config::Profile) -> Result<Vec<store::Snapshot>, io::Error> {
+    open_store(profile)?.snapshots()
+}
+
+pub fn diff(profile: &config::Profile, from: &str, to: &str) -> Result<store::Diff, io::Error> {
+    let store = open_store(profile)?;
+    Ok(store::diff(&store.snapshot(from)?, &store.snapshot(to)?))
+}
+
+fn expand_tilde(p: &str) -> PathBuf {
+    if p.starts_with('~') {
+        maybe_expand_home(&PathBuf::from(p))
+    } else {
+        PathBuf::from(p)
+    }
+}
+
+// An encrypted archive is decrypted into a temporary tar first, in a directory
+// only its owner can enter, removed whatever happens.
+pub fn restore(archive: &Path, options: restore::Options) -> Result<Vec<restore::Restored>, io::Error> {
+    let home: PathBuf = PathBuf::from(home_name());
+    let options = restore::Options {
+        to: options.to.map(|d| expand_tilde(&d.to_string_lossy())),
+        only: options.only.map(|p| expand_tilde(&p).to_string_lossy().into_owned()),
+        ..options
+    };
+    if encrypter::is_encrypted(archive)? {
+        let dir: PathBuf = env::temp_dir().join(format!(
+            "taker-restore-{}-{}",
+            process::id(),
+            random_from("restore")
+        ));
+        fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        let tar: PathBuf = dir.join("archive.tar");
+        info!("deciphering {:?} into {:?}", archive, tar);
+        let restored = encrypter::decrypt_symmetric(archive, &tar)
+            .and_then(|tar| restore::from_tar(&tar, &home, &options));
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            error!("cannot remove {:?}: {}", dir, e);
+        }
+        return restored;
+    }
+    restore::from_tar(archive, &home, &options)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,78 @@
+// Where every file of an archive comes from.
+//
+// In the archive a file is renamed: a datetag in front of its name
+// (tag_name), "dot" in front of a leading '.' (maybe_expand_dot). The
+// manifest, MANIFEST.toml at the top of the archive, maps every archived
+// name back to the original path.
+
+extern crate toml;
+
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub const MANIFEST_NAME: &str = "MANIFEST.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Item {
+    /// The absolute path the file was taken from.
+    pub original: String,
+    /// Its path in the archive.
+    pub archived: String,
+    pub size: u64,
+    pub mode: u32,
+    /// Seconds since the epoch.
+    pub mtime: i64,
+}
+
+impl Item {
+    /// The metadata of the original file, of a symlink itself.
+    pub fn of(original: &Path, archived: &Path) -> io::Result<Item> {
+        let meta = fs::symlink_metadata(original)?;
+        let path = |p: &Path| {
+            p.to_str().map(String::from).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is not UTF-8", p))
+            })
+        };
+        Ok(Item {
+            original: path(original)?,
+            archived: path(archived)?,
+            size: meta.len(),
+            mode: meta.permissions().mode(),
+            mtime: meta
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Manifest {
+    pub created: String,
+    pub files: Vec<Item>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> io::Result<Manifest> {
+        toml::from_str(text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", MANIFEST_NAME, e),
+            )
+        })
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Manifest> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Manifest::parse(&text)
+    }
+
+    pub fn to_toml(&self) -> io::Result<String> {
+        toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
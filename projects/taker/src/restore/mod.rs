@@ -0,0 +1,662 @@
+// Puts the files of an archive back where they were taken from.
+//
+// The archive is read twice: for its manifest first, wherever it is, then
+// for the files the manifest lists. Archives made before the manifest are
+// mapped back by undoing tag_name and maybe_expand_dot, as if everything was
+// taken from $HOME. Symlinks, fifos and devices come back as what they were.
+
+extern crate libc;
+extern crate tar;
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{symlink, OpenOptionsExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use self::tar::{Archive, EntryType};
+
+use compress;
+use manifest::{Item, Manifest, MANIFEST_NAME};
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Under this directory instead of /.
+    pub to: Option<PathBuf>,
+    /// A glob on the original paths: a match of a directory takes all of it.
+    pub only: Option<String>,
+    /// Nothing is written.
+    pub dry_run: bool,
+    /// Files newer than the archived ones are overwritten too.
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Create,
+    Overwrite,
+    /// The file on disk is newer: left alone.
+    KeepNewer,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Restored {
+    pub original: String,
+    pub destination: PathBuf,
+    pub action: Action,
+}
+
+impl fmt::Display for Restored {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.action {
+            Action::Create => write!(f, "restore {}", self.destination.display()),
+            Action::Overwrite => write!(f, "overwrite {}", self.destination.display()),
+            Action::KeepNewer => write!(
+                f,
+                "keep {}: newer than the archived one (--force to overwrite)",
+                self.destination.display()
+            ),
+        }
+    }
+}
+
+// '*' matches anything, '/' included, '?' any character.
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where the last '*' was, and where its match in text ends so far.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The path, or a directory of it, matches the pattern.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.trim_end_matches('/').chars().collect();
+    Path::new(path)
+        .ancestors()
+        .filter_map(|p| p.to_str())
+        .filter(|p| !p.is_empty())
+        .any(|p| glob(&pattern, &p.chars().collect::<Vec<char>>()))
+}
+
+fn strip_datetag(name: &str) -> &str {
+    let digits = name.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 14 && name[digits..].starts_with('.') {
+        &name[digits + 1..]
+    } else {
+        name
+    }
+}
+
+fn undot(name: &str) -> String {
+    if name.starts_with("dot.") {
+        name[3..].to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// The original path of a file archived without a manifest, under home.
+///
+/// A file in a directory was archived by its name alone: one taken from a
+/// subdirectory comes back in the directory itself.
+pub fn unmangle(archived: &Path, home: &Path) -> Option<PathBuf> {
+    let names: Vec<&str> = archived.iter().map(|c| c.to_str()).collect::<Option<_>>()?;
+    match names.len() {
+        2 => Some(home.join(undot(strip_datetag(names[1])))),
+        n if n > 2 => {
+            let mut path = home.join(undot(names[1]));
+            for name in &names[2..n - 1] {
+                path.push(name);
+            }
+            path.push(strip_datetag(names[n - 1]));
+            Some(path)
+        }
+        _ => None,
+    }
+}
+
+// The manifest of the archive, or one made of what its entries say.
+fn manifest_of(tar: &Path, home: &Path) -> io::Result<Manifest> {
+    let mut fallback = Manifest::default();
+    let mut archive = Archive::new(compress::open(tar)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        if path.file_name().is_some_and(|n| n == MANIFEST_NAME) {
+            debug!("manifest found at {:?}", path);
+            return Manifest::read(entry);
+        }
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        if let Some(original) = unmangle(&path, home) {
+            fallback.files.push(Item {
+                original: original.to_string_lossy().into_owned(),
+                archived: path.to_string_lossy().into_owned(),
+                size: entry.header().size()?,
+                mode: entry.header().mode()?,
+                mtime: entry.header().mtime()? as i64,
+            });
+        }
+    }
+    warn!(
+        "no {} in {:?}: guessing the original paths",
+        MANIFEST_NAME, tar
+    );
+    Ok(fallback)
+}
+
+// Where original goes, under to if there is one: nothing of the manifest is
+// taken for granted, an original has to be absolute and can't climb.
+fn destination_of(original: &str, to: Option<&PathBuf>) -> io::Result<PathBuf> {
+    let path = Path::new(original);
+    if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: not a path to restore", original),
+        ));
+    }
+    Ok(match to {
+        Some(dir) => dir.join(path.strip_prefix("/").unwrap_or(path)),
+        None => path.to_owned(),
+    })
+}
+
+/// Restores the files of an unencrypted tar archive, compressed or not; with
+/// dry_run, what would be restored.
+pub fn from_tar(tar: &Path, home: &Path, options: &Options) -> io::Result<Vec<Restored>> {
+    let manifest = manifest_of(tar, home)?;
+
+    let mut plan: HashMap<String, (Item, usize)> = HashMap::new();
+    let mut restored = Vec::new();
+    for item in manifest.files {
+        if let Some(ref pattern) = options.only {
+            if !matches(pattern, &item.original) {
+                continue;
+            }
+        }
+        let destination = destination_of(&item.original, options.to.as_ref())?;
+        let action = match fs::symlink_metadata(&destination) {
+            Ok(meta) => {
+                let mtime = meta
+                    .modified()?
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0);
+                if mtime > item.mtime && !options.force {
+                    Action::KeepNewer
+                } else {
+                    Action::Overwrite
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Action::Create,
+            Err(e) => return Err(e),
+        };
+        restored.push(Restored {
+            original: item.original.clone(),
+            destination,
+            action,
+        });
+        plan.insert(item.archived.clone(), (item, restored.len() - 1));
+    }
+
+    // Symlinks come last, for nothing to be written through them.
+    let mut links: Vec<(PathBuf, usize, i64)> = Vec::new();
+    let mut archive = Archive::new(compress::open(tar)?);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let (item, i) = match plan.remove(&path) {
+            Some(planned) => planned,
+            None => continue,
+        };
+        let r = &restored[i];
+        if options.dry_run || r.action == Action::KeepNewer {
+            continue;
+        }
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() {
+            let target = entry.link_name()?.map(|t| t.into_owned()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: a symlink to nothing", path),
+                )
+            })?;
+            links.push((target, i, item.mtime));
+            continue;
+        }
+        info!("restoring {:?} into {:?}", path, r.destination);
+        make_way(&r.destination, options.to.as_ref(), &[])?;
+        if kind.is_fifo() || kind.is_character_special() || kind.is_block_special() {
+            let major = entry.header().device_major()?.unwrap_or(0);
+            let minor = entry.header().device_minor()?.unwrap_or(0);
+            make_node(&r.destination, kind, compress::makedev(major, minor))?;
+            set_mtime(&r.destination, item.mtime)?;
+        } else {
+            // Never readable by more than the mode says, not even while it
+            // is written.
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(item.mode & 0o777)
+                .open(&r.destination)?;
+            io::copy(&mut entry, &mut file)?;
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(item.mtime.max(0) as u64))?;
+        }
+        // Past the umask, and the setuid, setgid and sticky bits: only now.
+        let mode = item.mode & 0o7777;
+        if fs::symlink_metadata(&r.destination)?.permissions().mode() & 0o7777 != mode {
+            fs::set_permissions(&r.destination, fs::Permissions::from_mode(mode))?;
+        }
+    }
+    let mut linked: Vec<PathBuf> = Vec::new();
+    for (target, i, mtime) in links {
+        let destination = &restored[i].destination;
+        info!("linking {:?} to {:?}", destination, target);
+        make_way(destination, options.to.as_ref(), &linked)?;
+        symlink(&target, destination)?;
+        // A symlink has no permissions of its own.
+        set_mtime(destination, mtime)?;
+        linked.push(destination.to_owned());
+    }
+    if let Some(missing) = plan.keys().next() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is in the manifest, not in the archive", missing),
+        ));
+    }
+    Ok(restored)
+}
+
+// Makes the directories of destination, and removes what is there but a
+// directory: it is replaced, not written through. No symlink is followed on
+// the way, neither one of linked (restored already) nor, under to, one
+// between to and destination; wherever they point, it is out of to.
+fn make_way(destination: &Path, to: Option<&PathBuf>, linked: &[PathBuf]) -> io::Result<()> {
+    let through = |link: &Path| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{:?}: through the symlink {:?}", destination, link),
+        )
+    };
+    if let Some(link) = linked
+        .iter()
+        .find(|l| destination.starts_with(l) && destination != l.as_path())
+    {
+        return Err(through(link));
+    }
+    let parents = to.and_then(|dir| {
+        let rel = destination.strip_prefix(dir).ok()?.parent()?;
+        Some((dir, rel))
+    });
+    if let Some((dir, rel)) = parents {
+        let mut parent = dir.to_owned();
+        for name in rel.components() {
+            parent.push(name);
+            match fs::symlink_metadata(&parent) {
+                Ok(ref meta) if meta.file_type().is_symlink() => return Err(through(&parent)),
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => break,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    if let Some(dir) = destination.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match fs::symlink_metadata(destination) {
+        Ok(ref meta) if !meta.is_dir() => fs::remove_file(destination),
+        _ => Ok(()),
+    }
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+// A fifo or a device; its permissions are set afterwards, past the umask.
+fn make_node(path: &Path, kind: EntryType, dev: u64) -> io::Result<()> {
+    let kind = if kind.is_fifo() {
+        libc::S_IFIFO
+    } else if kind.is_character_special() {
+        libc::S_IFCHR
+    } else {
+        libc::S_IFBLK
+    };
+    if unsafe { libc::mknod(c_path(path)?.as_ptr(), kind | 0o600, dev as libc::dev_t) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Of the path itself, not of what a symlink points to: nothing is opened,
+// a fifo neither.
+fn set_mtime(path: &Path, mtime: i64) -> io::Result<()> {
+    let times = [
+        libc::timespec {
+            tv_sec: 0,
+            tv_nsec: libc::UTIME_OMIT,
+        },
+        libc::timespec {
+            tv_sec: mtime as libc::time_t,
+            tv_nsec: 0,
+        },
+    ];
+    let path = c_path(path)?;
+    let done = unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if done != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tar::{Builder, EntryType, Header};
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::process;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("taker-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn append(builder: &mut Builder<File>, path: &str, data: &[u8]) {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(1_500_000_000);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+
+    fn mtime(path: &Path) -> i64 {
+        let modified = fs::metadata(path).unwrap().modified().unwrap();
+        modified.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches("/home/u/.ssh", "/home/u/.ssh/id_rsa"));
+        assert!(matches("/home/u/.ssh/", "/home/u/.ssh/id_rsa"));
+        assert!(matches("*/.ssh", "/home/u/.ssh/config"));
+        assert!(matches("*.pub", "/home/u/.ssh/id_rsa.pub"));
+        assert!(matches("/home/?/.bash*", "/home/u/.bash_profile"));
+        assert!(!matches("/home/u/.ss", "/home/u/.ssh/id_rsa"));
+        assert!(!matches("*.pub", "/home/u/.ssh/id_rsa"));
+
+        let home = Path::new("/home/u");
+        let unmangled = |p: &str| unmangle(Path::new(p), home).unwrap();
+        assert_eq!(
+            unmangled("takentarget/20180501120000.dot.bash_profile"),
+            home.join(".bash_profile")
+        );
+        assert_eq!(
+            unmangled("takentarget/dot.ssh/20180501120000.id_rsa"),
+            home.join(".ssh/id_rsa")
+        );
+        assert_eq!(
+            unmangled("takentarget/awesome/20180501120000.rc.lua"),
+            home.join("awesome/rc.lua")
+        );
+    }
+
+    #[test]
+    fn restores() {
+        let dir = scratch("restore");
+        let tar = dir.join("takenfiles.tar");
+        let manifest = Manifest {
+            created: String::from("20180501120000"),
+            files: vec![
+                Item {
+                    original: String::from("/home/u/.ssh/id_rsa"),
+                    archived: String::from("takentarget/dot.ssh/20180501120000.id_rsa"),
+                    size: 3,
+                    mode: 0o100600,
+                    mtime: 1_400_000_000,
+                },
+                Item {
+                    original: String::from("/home/u/.bash_profile"),
+                    archived: String::from("takentarget/20180501120000.dot.bash_profile"),
+                    size: 4,
+                    mode: 0o104775,
+                    mtime: 1_400_000_000,
+                },
+                Item {
+                    original: String::from("/home/u/.ssh/current"),
+                    archived: String::from("takentarget/dot.ssh/20180501120000.current"),
+                    size: 0,
+                    mode: 0o120777,
+                    mtime: 1_400_000_000,
+                },
+            ],
+        };
+        {
+            let mut builder = Builder::new(File::create(&tar).unwrap());
+            append(&mut builder, &manifest.files[0].archived, b"key");
+            append(&mut builder, &manifest.files[1].archived, b"bash");
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            header.set_mode(0o777);
+            header.set_mtime(1_500_000_000);
+            builder
+                .append_link(&mut header, &manifest.files[2].archived, "id_rsa")
+                .unwrap();
+            let text = ::toml::to_string(&manifest).unwrap();
+            append(&mut builder, "takentarget/MANIFEST.toml", text.as_bytes());
+            builder.finish().unwrap();
+        }
+        let to = dir.join("root");
+        let home = Path::new("/home/u");
+        let mut options = Options {
+            to: Some(to.clone()),
+            dry_run: true,
+            ..Options::default()
+        };
+
+        let planned = from_tar(&tar, home, &options).unwrap();
+        assert_eq!(planned.len(), 3);
+        assert!(planned.iter().all(|r| r.action == Action::Create));
+        assert!(!to.exists());
+
+        options.dry_run = false;
+        options.only = Some(String::from("/home/u/.ssh"));
+        let restored = from_tar(&tar, home, &options).unwrap();
+        let key = to.join("home/u/.ssh/id_rsa");
+        assert_eq!(restored[0].destination, key);
+        assert_eq!(fs::read(&key).unwrap(), b"key");
+        assert_eq!(
+            fs::metadata(&key).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(mtime(&key), 1_400_000_000);
+        let current = to.join("home/u/.ssh/current");
+        assert_eq!(fs::read_link(&current).unwrap(), Path::new("id_rsa"));
+        assert_eq!(fs::read(&current).unwrap(), b"key");
+        let modified = fs::symlink_metadata(&current).unwrap().modified().unwrap();
+        assert_eq!(modified, UNIX_EPOCH + Duration::from_secs(1_400_000_000));
+        assert!(!to.join("home/u/.bash_profile").exists());
+
+        // A newer file stays, unless forced.
+        options.only = None;
+        let profile = to.join("home/u/.bash_profile");
+        fs::write(&profile, b"newer").unwrap();
+        let restored = from_tar(&tar, home, &options).unwrap();
+        let actions: Vec<Action> = restored.iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            vec![Action::Overwrite, Action::KeepNewer, Action::Overwrite]
+        );
+        assert_eq!(fs::read(&profile).unwrap(), b"newer");
+        options.force = true;
+        from_tar(&tar, home, &options).unwrap();
+        assert_eq!(fs::read(&profile).unwrap(), b"bash");
+        // Whatever the umask, with the setuid bit.
+        assert_eq!(
+            fs::metadata(&profile).unwrap().permissions().mode() & 0o7777,
+            0o4775
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stays_under_to() {
+        let dir = scratch("restore-under");
+        let tar = dir.join("takenfiles.tar");
+        let manifest = Manifest {
+            created: String::from("20180501120000"),
+            files: vec![
+                Item {
+                    original: String::from("/home/u/.bashrc"),
+                    archived: String::from("takentarget/20180501120000.dot.bashrc"),
+                    size: 4,
+                    mode: 0o100644,
+                    mtime: 1_400_000_000,
+                },
+                Item {
+                    original: String::from("/home/u/../../../x"),
+                    archived: String::from("takentarget/20180501120000.x"),
+                    size: 1,
+                    mode: 0o100644,
+                    mtime: 1_400_000_000,
+                },
+            ],
+        };
+        {
+            let mut builder = Builder::new(File::create(&tar).unwrap());
+            append(&mut builder, &manifest.files[0].archived, b"bash");
+            append(&mut builder, &manifest.files[1].archived, b"x");
+            let text = ::toml::to_string(&manifest).unwrap();
+            append(&mut builder, "takentarget/MANIFEST.toml", text.as_bytes());
+            builder.finish().unwrap();
+        }
+        let to = dir.join("a/b/c");
+        let options = Options {
+            to: Some(to.clone()),
+            ..Options::default()
+        };
+        let e = from_tar(&tar, Path::new("/home/u"), &options).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(!dir.join("a").exists());
+        assert!(!dir.join("x").exists());
+
+        let to = Some(&to);
+        assert_eq!(
+            destination_of("/etc/./x", to).unwrap(),
+            dir.join("a/b/c/etc/x")
+        );
+        assert_eq!(
+            destination_of("//etc/x", to).unwrap(),
+            dir.join("a/b/c/etc/x")
+        );
+        assert!(destination_of("/etc/..", to).is_err());
+        assert!(destination_of("etc/x", to).is_err());
+        // Without --to too.
+        assert_eq!(
+            destination_of("/etc/./x", None).unwrap(),
+            Path::new("/etc/x")
+        );
+        assert!(destination_of("/etc/../x", None).is_err());
+        assert!(destination_of("etc/x", None).is_err());
+        assert!(destination_of("", None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // A symlink to outside, then a file through it: the file can't follow.
+    #[test]
+    fn not_through_symlinks() {
+        let dir = scratch("restore-links");
+        let outside = dir.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        let tar = dir.join("takenfiles.tar");
+        let manifest = Manifest {
+            created: String::from("20180501120000"),
+            files: vec![
+                Item {
+                    original: String::from("/home/u/evil"),
+                    archived: String::from("takentarget/20180501120000.evil"),
+                    size: 0,
+                    mode: 0o120777,
+                    mtime: 1_400_000_000,
+                },
+                Item {
+                    original: String::from("/home/u/evil/pwned"),
+                    archived: String::from("takentarget/evil/20180501120000.pwned"),
+                    size: 5,
+                    mode: 0o100644,
+                    mtime: 1_400_000_000,
+                },
+            ],
+        };
+        {
+            let mut builder = Builder::new(File::create(&tar).unwrap());
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            header.set_mode(0o777);
+            header.set_mtime(1_500_000_000);
+            builder
+                .append_link(&mut header, &manifest.files[0].archived, &outside)
+                .unwrap();
+            append(&mut builder, &manifest.files[1].archived, b"pwned");
+            let text = ::toml::to_string(&manifest).unwrap();
+            append(&mut builder, "takentarget/MANIFEST.toml", text.as_bytes());
+            builder.finish().unwrap();
+        }
+        let to = dir.join("root");
+        let options = Options {
+            to: Some(to.clone()),
+            ..Options::default()
+        };
+        let home = Path::new("/home/u");
+        assert!(from_tar(&tar, home, &options).is_err());
+        assert!(!outside.join("pwned").exists());
+        assert_eq!(fs::read(to.join("home/u/evil/pwned")).unwrap(), b"pwned");
+
+        // Nor through one that was there already.
+        fs::remove_dir_all(&to).unwrap();
+        fs::create_dir_all(to.join("home/u")).unwrap();
+        symlink(&outside, to.join("home/u/evil")).unwrap();
+        let e = from_tar(&tar, home, &options).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(!outside.join("pwned").exists());
+
+        // Nor a symlink through a symlink just restored.
+        let linked = vec![to.join("home/u/evil")];
+        assert!(make_way(&to.join("home/u/evil/link"), None, &linked).is_err());
+        assert!(make_way(&to.join("home/u/evil"), None, &linked).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,686 @@
+// A content-addressed store of chunks, and the snapshots pointing at them.
+//
+// Every file is cut into chunks where its content says so (a rolling hash
+// over the last bytes), not every N bytes: an insertion only changes the
+// chunks around it. A chunk is stored once, named after its HMAC, so a file
+// that didn't change costs nothing but a line in the next snapshot.
+//
+// Nothing is written in the clear: chunks and snapshots are sealed with the
+// key of the store (see encrypter::StoreKey), and only their owner can read
+// them, or enter the directories.
+//
+//     <root>/key                      the salt of the key, and its HMAC
+//     <root>/chunks/ab/cdef...        the sealed bytes of chunk abcdef...
+//     <root>/snapshots/<id>.enc       the files of a run, and their chunks
+//
+// https://en.wikipedia.org/wiki/Content-addressable_storage
+// https://www.usenix.org/conference/atc16/technical-sessions/presentation/xia
+
+extern crate toml;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use encrypter::{self, Params, StoreKey};
+
+const MIN_CHUNK: usize = 2 * 1024;
+const MAX_CHUNK: usize = 64 * 1024;
+// 13 bits: a cut every 8 KiB on average, past the minimum.
+const CUT_MASK: u64 = !0 << (64 - 13);
+
+// 256 random numbers, one per byte value, from SplitMix64.
+const fn gear() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut x: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+static GEAR: [u64; 256] = gear();
+
+// Where the first chunk of data ends: the top bits of the hash depend on the
+// last 64 bytes only, so the same content cuts at the same place wherever it
+// is in the file.
+fn cut(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK {
+        return data.len();
+    }
+    let end = data.len().min(MAX_CHUNK);
+    let mut hash: u64 = 0;
+    for (i, &b) in data[..end].iter().enumerate().skip(MIN_CHUNK) {
+        hash = (hash << 1).wrapping_add(GEAR[b as usize]);
+        if hash & CUT_MASK == 0 {
+            return i + 1;
+        }
+    }
+    end
+}
+
+/// The chunks of data, in order.
+pub fn chunks(mut data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    while !data.is_empty() {
+        let (chunk, rest) = data.split_at(cut(data));
+        chunks.push(chunk);
+        data = rest;
+    }
+    chunks
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub size: u64,
+    pub mode: u32,
+    /// Seconds since the epoch.
+    pub mtime: i64,
+    pub chunks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub id: String,
+    pub files: Vec<Entry>,
+}
+
+impl Snapshot {
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|e| e.size).sum()
+    }
+}
+
+/// What a backup took.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    pub files: usize,
+    pub bytes: u64,
+    /// Files with the size, mode and mtime of the last snapshot: not read.
+    pub unchanged: usize,
+    pub new_chunks: usize,
+    pub new_bytes: u64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} files ({} bytes, {} unchanged), {} new chunks ({} bytes)",
+            self.files, self.bytes, self.unchanged, self.new_chunks, self.new_bytes
+        )
+    }
+}
+
+/// From a snapshot to another.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for path in &self.added {
+            writeln!(f, "+ {}", path)?;
+        }
+        for path in &self.removed {
+            writeln!(f, "- {}", path)?;
+        }
+        for path in &self.modified {
+            writeln!(f, "M {}", path)?;
+        }
+        writeln!(f, "{} unchanged", self.unchanged)
+    }
+}
+
+/// The paths added, removed and modified (in content or permissions), sorted.
+pub fn diff(from: &Snapshot, to: &Snapshot) -> Diff {
+    let before: BTreeMap<&str, &Entry> = from.files.iter().map(|e| (e.path.as_str(), e)).collect();
+    let after: BTreeMap<&str, &Entry> = to.files.iter().map(|e| (e.path.as_str(), e)).collect();
+    let mut diff = Diff::default();
+    for (path, entry) in &after {
+        match before.get(path) {
+            None => diff.added.push(path.to_string()),
+            Some(old) if old.chunks != entry.chunks || old.mode != entry.mode => {
+                diff.modified.push(path.to_string())
+            }
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+    for path in before.keys() {
+        if !after.contains_key(path) {
+            diff.removed.push(path.to_string());
+        }
+    }
+    diff
+}
+
+fn invalid<E: fmt::Display>(what: &Path, e: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", what.display(), e),
+    )
+}
+
+// A file appears whole or not at all: written aside, then renamed. Only its
+// owner can read it.
+fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    // A new one, for the mode to be ours: one left by a crash goes.
+    if let Err(e) = fs::remove_file(&tmp) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+// The order of the snapshots: by tag, compared as a number when it is one
+// (a datetag is), then by the number backup adds to a tag taken already.
+fn snapshot_order(id: &str) -> (Option<u64>, &str, u64) {
+    let (tag, n) = match id.rsplit_once('-') {
+        Some((tag, n)) => match n.parse() {
+            Ok(n) => (tag, n),
+            Err(_) => (id, 1),
+        },
+        None => (id, 1),
+    };
+    (tag.parse().ok(), tag, n)
+}
+
+// A directory only its owner can enter, whoever made it.
+fn private_dir(dir: &Path) -> io::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+// Sealed along with a snapshot: it can't pass for another one.
+fn snapshot_ad(id: &str) -> String {
+    format!("snapshot {}", id)
+}
+
+pub struct Store {
+    root: PathBuf,
+    key: StoreKey,
+}
+
+impl Store {
+    /// Creates the directories and the key if they aren't there; password
+    /// is told whether the store is a new one.
+    pub fn open<P, F>(root: P, params: Params, password: F) -> io::Result<Store>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(bool) -> Vec<u8>,
+    {
+        let root = root.as_ref().to_path_buf();
+        for dir in &[root.clone(), root.join("chunks"), root.join("snapshots")] {
+            private_dir(dir)?;
+        }
+        let path = root.join("key");
+        let key = match File::open(&path) {
+            Ok(mut file) => {
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+                StoreKey::from_bytes(&password(false), &bytes).map_err(|e| invalid(&path, e))?
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                let key = StoreKey::new(&password(true), params)?;
+                write_atomically(&path, &key.to_bytes())?;
+                key
+            }
+            Err(e) => return Err(e),
+        };
+        debug!("chunk store in {:?}", root);
+        Ok(Store { root, key })
+    }
+
+    // Only for what a chunk id can be: 64 hex digits, as StoreKey::name gives.
+    fn chunk_path(&self, id: &str) -> io::Result<PathBuf> {
+        if id.len() != 64 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not a chunk id", id),
+            ));
+        }
+        Ok(self.root.join("chunks").join(&id[..2]).join(&id[2..]))
+    }
+
+    fn snapshot_path(&self, id: &str) -> PathBuf {
+        self.root
+            .join("snapshots")
+            .join(format!("{}.{}", id, encrypter::EXTENSION))
+    }
+
+    pub fn has_chunk(&self, id: &str) -> bool {
+        self.chunk_path(id).is_ok_and(|path| path.is_file())
+    }
+
+    /// Its id, and whether it wasn't in the store already.
+    pub fn put_chunk(&self, data: &[u8]) -> io::Result<(String, bool)> {
+        let id = self.key.name(data);
+        if self.has_chunk(&id) {
+            return Ok((id, false));
+        }
+        let path = self.chunk_path(&id)?;
+        private_dir(path.parent().unwrap())?;
+        write_atomically(&path, &self.key.seal(id.as_bytes(), data)?)?;
+        Ok((id, true))
+    }
+
+    /// Checked against its id.
+    pub fn chunk(&self, id: &str) -> io::Result<Vec<u8>> {
+        let path = self.chunk_path(id)?;
+        let mut sealed = Vec::new();
+        File::open(&path)?.read_to_end(&mut sealed)?;
+        let data = self
+            .key
+            .open(id.as_bytes(), &sealed)
+            .map_err(|e| invalid(&path, e))?;
+        if self.key.name(&data) != id {
+            return Err(invalid(&path, "the content doesn't match the name"));
+        }
+        Ok(data)
+    }
+
+    /// The content of a file of a snapshot.
+    pub fn read(&self, entry: &Entry) -> io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.size as usize);
+        for id in &entry.chunks {
+            data.extend(self.chunk(id)?);
+        }
+        Ok(data)
+    }
+
+    pub fn snapshot(&self, id: &str) -> io::Result<Snapshot> {
+        let path = self.snapshot_path(id);
+        let mut sealed = Vec::new();
+        match File::open(&path) {
+            Ok(mut file) => file.read_to_end(&mut sealed)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no snapshot {:?}", id),
+                ))
+            }
+            Err(e) => return Err(e),
+        };
+        let text = self
+            .key
+            .open(snapshot_ad(id).as_bytes(), &sealed)
+            .map_err(|e| invalid(&path, e))?;
+        let text = String::from_utf8(text).map_err(|e| invalid(&path, e))?;
+        toml::from_str(&text).map_err(|e| invalid(&path, e))
+    }
+
+    /// The oldest first.
+    pub fn snapshots(&self) -> io::Result<Vec<Snapshot>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(self.root.join("snapshots"))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == encrypter::EXTENSION) {
+                if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(id.to_owned());
+                }
+            }
+        }
+        ids.sort_by(|a, b| snapshot_order(a).cmp(&snapshot_order(b)));
+        ids.iter().map(|id| self.snapshot(id)).collect()
+    }
+
+    fn save(&self, snapshot: &Snapshot) -> io::Result<()> {
+        let path = self.snapshot_path(&snapshot.id);
+        let text = toml::to_string(snapshot).map_err(|e| invalid(&path, e))?;
+        let sealed = self
+            .key
+            .seal(snapshot_ad(&snapshot.id).as_bytes(), text.as_bytes())?;
+        write_atomically(&path, &sealed)
+    }
+
+    /// Stores the chunks of the files that changed since the last snapshot,
+    /// and a new snapshot of all of them: its id is tag, unless a snapshot
+    /// has it already.
+    pub fn backup(&self, files: &[PathBuf], tag: &str) -> io::Result<(Snapshot, Stats)> {
+        let last = self.snapshots()?.pop();
+        let previous: BTreeMap<&str, &Entry> = last
+            .iter()
+            .flat_map(|s| s.files.iter())
+            .map(|e| (e.path.as_str(), e))
+            .collect();
+
+        let mut stats = Stats::default();
+        let mut entries = Vec::new();
+        for file in files {
+            let path = match file.to_str() {
+                Some(p) => p.to_owned(),
+                None => {
+                    warn!("skipping {:?}: not UTF-8", file);
+                    continue;
+                }
+            };
+            let meta = match fs::metadata(file) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    error!("cannot read {:?}: {}", file, e);
+                    continue;
+                }
+            };
+            let mtime = meta
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            let mut entry = Entry {
+                path,
+                size: meta.len(),
+                mode: meta.permissions().mode(),
+                mtime,
+                chunks: Vec::new(),
+            };
+
+            if let Some(old) = previous.get(entry.path.as_str()) {
+                if (old.size, old.mode, old.mtime) == (entry.size, entry.mode, entry.mtime)
+                    && old.chunks.iter().all(|id| self.has_chunk(id))
+                {
+                    debug!("{:?} unchanged", file);
+                    entry.chunks = old.chunks.clone();
+                    stats.unchanged += 1;
+                    entries.push(entry);
+                    continue;
+                }
+            }
+
+            debug!("chunking {:?}", file);
+            let mut data = Vec::new();
+            if let Err(e) = File::open(file).and_then(|mut f| f.read_to_end(&mut data)) {
+                error!("cannot read {:?}: {}", file, e);
+                continue;
+            }
+            // What was read, should the file have changed since.
+            entry.size = data.len() as u64;
+            for chunk in chunks(&data) {
+                let (id, new) = self.put_chunk(chunk)?;
+                if new {
+                    stats.new_chunks += 1;
+                    stats.new_bytes += chunk.len() as u64;
+                }
+                entry.chunks.push(id);
+            }
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.dedup_by(|a, b| a.path == b.path);
+        stats.files = entries.len();
+        stats.bytes = entries.iter().map(|e| e.size).sum();
+
+        let mut id = tag.to_owned();
+        let mut n = 1;
+        while self.snapshot_path(&id).exists() {
+            n += 1;
+            id = format!("{}-{}", tag, n);
+        }
+        let snapshot = Snapshot { id, files: entries };
+        self.save(&snapshot)?;
+        info!("snapshot {} taken: {}", snapshot.id, stats);
+        Ok((snapshot, stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // Fast to derive.
+    const PARAMS: Params = Params {
+        iterations: 10,
+        chunk_size: 64,
+    };
+
+    fn open(dir: &Path) -> Store {
+        Store::open(dir, PARAMS, |_| b"secret".to_vec()).unwrap()
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("taker-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Not random, but without any pattern the chunker would see.
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunking() {
+        let data = noise(300_000, 1);
+        let pieces = chunks(&data);
+        assert!(pieces.len() > 10);
+        assert!(pieces.iter().all(|c| c.len() <= MAX_CHUNK));
+        assert!(pieces[..pieces.len() - 1]
+            .iter()
+            .all(|c| c.len() > MIN_CHUNK));
+        assert_eq!(pieces.concat(), data);
+        assert!(chunks(&[]).is_empty());
+
+        // An insertion at the start only changes the first chunks.
+        let mut shifted = b"a few more bytes".to_vec();
+        shifted.extend(&data);
+        let key = StoreKey::new(b"secret", PARAMS).unwrap();
+        let before: Vec<String> = pieces.iter().map(|c| key.name(c)).collect();
+        let after: Vec<String> = chunks(&shifted).iter().map(|c| key.name(c)).collect();
+        let shared = after.iter().filter(|id| before.contains(id)).count();
+        assert!(shared + 2 >= before.len(), "{} of {}", shared, before.len());
+    }
+
+    #[test]
+    fn incremental() {
+        let dir = scratch("incremental");
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::write(&a, noise(100_000, 2)).unwrap();
+        fs::write(&b, b"small").unwrap();
+        let store = open(&dir.join("store"));
+
+        let files = vec![a.clone(), b.clone()];
+        let (first, stats) = store.backup(&files, "1").unwrap();
+        assert_eq!((stats.files, stats.unchanged), (2, 0));
+        assert_eq!(stats.new_bytes, 100_005);
+        assert_eq!(store.read(&first.files[0]).unwrap(), fs::read(&a).unwrap());
+
+        let (second, stats) = store.backup(&files, "1").unwrap();
+        assert_eq!(second.id, "1-2");
+        assert_eq!((stats.unchanged, stats.new_chunks), (2, 0));
+
+        // Appending to a only adds its last chunks.
+        let mut data = fs::read(&a).unwrap();
+        data.extend(noise(1000, 3));
+        fs::write(&a, &data).unwrap();
+        fs::remove_file(&b).unwrap();
+        let files = vec![a.clone(), dir.join("c")];
+        fs::write(&files[1], b"small").unwrap();
+        let (third, stats) = store.backup(&files, "2").unwrap();
+        assert!(stats.new_bytes < 20_000, "{}", stats);
+        assert_eq!(store.read(&third.files[0]).unwrap(), data);
+
+        let ids: Vec<String> = store
+            .snapshots()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec!["1", "1-2", "2"]);
+        let path = |p: &PathBuf| p.to_str().unwrap().to_owned();
+        assert_eq!(
+            diff(&first, &third),
+            Diff {
+                added: vec![path(&files[1])],
+                removed: vec![path(&b)],
+                modified: vec![path(&a)],
+                unchanged: 0,
+            }
+        );
+        assert_eq!(diff(&first, &second).unchanged, 2);
+        assert!(store.snapshot("3").is_err());
+
+        // A file that can't be read is left out, not the whole snapshot.
+        let files = vec![a.clone(), dir.join("missing")];
+        let (fourth, stats) = store.backup(&files, "2").unwrap();
+        assert_eq!((fourth.files.len(), stats.files), (1, 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshots_in_order() {
+        let dir = scratch("order");
+        let store = open(&dir);
+        for id in &[
+            "20240101000000-10",
+            "9",
+            "20240101000000",
+            "20240101000000-2",
+            "10",
+        ] {
+            store
+                .save(&Snapshot {
+                    id: id.to_string(),
+                    files: Vec::new(),
+                })
+                .unwrap();
+        }
+        let ids: Vec<String> = store
+            .snapshots()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "9",
+                "10",
+                "20240101000000",
+                "20240101000000-2",
+                "20240101000000-10"
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn chunk_ids_are_checked() {
+        let dir = scratch("ids");
+        let store = open(&dir);
+        let (id, _) = store.put_chunk(b"data").unwrap();
+        assert_eq!(store.chunk(&id).unwrap(), b"data");
+        for bad in &[
+            "",
+            "a",
+            "../../../../etc/passwd",
+            &id[1..],
+            &id.replace(&id[..1], "g"),
+        ] {
+            assert!(!store.has_chunk(bad));
+            assert_eq!(
+                store.chunk(bad).unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_in_the_clear() {
+        let dir = scratch("sealed");
+        let secret = dir.join("id_rsa");
+        fs::write(&secret, b"BEGIN PRIVATE KEY").unwrap();
+        let root = dir.join("store");
+        let mut asked = Vec::new();
+        let store = Store::open(&root, PARAMS, |new| {
+            asked.push(new);
+            b"secret".to_vec()
+        })
+        .unwrap();
+        let (snapshot, _) = store.backup(&[secret], "1").unwrap();
+
+        let mut paths = vec![root.clone()];
+        let mut i = 0;
+        while i < paths.len() {
+            let path = paths[i].clone();
+            let meta = fs::metadata(&path).unwrap();
+            if meta.is_dir() {
+                assert_eq!(meta.permissions().mode() & 0o777, 0o700, "{:?}", path);
+                for entry in fs::read_dir(&path).unwrap() {
+                    paths.push(entry.unwrap().path());
+                }
+            } else {
+                assert_eq!(meta.permissions().mode() & 0o777, 0o600, "{:?}", path);
+                let data = fs::read(&path).unwrap();
+                for clear in &[&b"PRIVATE"[..], b"id_rsa"] {
+                    assert!(
+                        !data.windows(clear.len()).any(|w| w == *clear),
+                        "{:?}",
+                        path
+                    );
+                }
+            }
+            i += 1;
+        }
+
+        let wrong = Store::open(&root, PARAMS, |new| {
+            asked.push(new);
+            b"guess".to_vec()
+        });
+        assert_eq!(wrong.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        let store = open(&root);
+        assert_eq!(store.snapshot("1").unwrap(), snapshot);
+        assert_eq!(
+            store.read(&snapshot.files[0]).unwrap(),
+            b"BEGIN PRIVATE KEY"
+        );
+        assert_eq!(asked, vec![true, false]);
+
+        // A chunk moved under the name of another one doesn't open.
+        let (a, _) = store.put_chunk(b"a").unwrap();
+        let (b, _) = store.put_chunk(b"b").unwrap();
+        fs::copy(store.chunk_path(&a).unwrap(), store.chunk_path(&b).unwrap()).unwrap();
+        assert_eq!(
+            store.chunk(&b).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,7 +2,7 @@ extern crate chrono;
 
 use timez::chrono::prelude::*;
 
-static FMT_DATETAG: &'static str = "%Y%m%d%H%M%S";
+pub static FMT_DATETAG: &str = "%Y%m%d%H%M%S";
 // static FMT_DAYOFTHEYEAR: &'static str = "%j";
 // static FMT_WEEKOFTHEYEAR: &'static str = "%W";
 
@@ -4,6 +4,7 @@ use std::io;
 
 // https://doc.rust-lang.org/std/fs/fn.read_dir.html
 // one possible implementation of walking a directory only visiting files
+#[allow(dead_code)]
 pub fn visit_dirs<T>(dir: &Path, cb: &T) -> io::Result<()>
 where
     T: Fn(&DirEntry) -> Result<bool, io::Error>,
@@ -17,7 +18,8 @@ where
                 for entry in entries {
                     let entry = entry?;
                     let path = entry.path();
-                    if path.is_dir() {
+                    // A symlink to a directory is visited as the symlink.
+                    if entry.file_type()?.is_dir() {
                         visit_dirs(&path, cb)?;
                     } else {
                         cb(&entry)?;
@@ -31,7 +33,6 @@ where
     Ok(())
 }
 
-#[allow(dead_code)]
 pub fn visit_mut_dirs<T>(dir: &Path, cb: &mut T) -> io::Result<()>
 where
     T: FnMut(&DirEntry) -> Result<bool, io::Error>,
@@ -45,7 +46,8 @@ where
                 for entry in entries {
                     let entry = entry?;
                     let path = entry.path();
-                    if path.is_dir() {
+                    // A symlink to a directory is visited as the symlink.
+                    if entry.file_type()?.is_dir() {
                         visit_mut_dirs(&path, cb)?;
                     } else {
                         cb(&entry)?;
@@ -1,5 +1,16 @@
 // https://doc.rust-lang.org/std/collections/binary_heap/struct.BinaryHeap.html
-
+//
+// BinaryHeap is a max-heap. For the smallest item first, this script used to
+// flip the `Ord` of Thing; now the min-heaps are the PriorityQueue of
+// projects/priority_queue, which takes the order from a comparator.
+
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/priority_queue/src/lib.rs"]
+mod priority_queue;
+
+use priority_queue::PriorityQueue;
 use std::fmt;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
@@ -43,8 +54,9 @@ fn max_heap() {
     assert!(heap.is_empty())
 }
 
-// Thing have a priority, an ordering, where the lower is the greater.
-#[derive(Eq, PartialEq, Debug)]
+// Things are ordered by their content, as derived: the smaller the content,
+// the sooner they come out of a min-heap.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug)]
 struct Thing {
     content: usize,
 }
@@ -55,28 +67,8 @@ impl fmt::Display for Thing {
     }
 }
 
-// Trait std::cmp::Ord
-// https://doc.rust-lang.org/std/cmp/trait.Ord.html
-// fn cmp(&self, other: &Self) -> Ordering
-
-impl Ord for Thing {
-    fn cmp(&self, other: &Thing) -> Ordering {
-        other
-            .content
-            .cmp(&self.content)
-            .then_with(|| Ordering::Equal)
-    }
-}
-
-// `PartialOrd` needs to be implemented as well.
-impl PartialOrd for Thing {
-    fn partial_cmp(&self, other: &Thing) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 fn min_heap() {
-    let mut heap = BinaryHeap::new();
+    let mut heap = PriorityQueue::new();
 
     assert_eq!(heap.peek(), None);
 
@@ -99,6 +91,31 @@ fn min_heap() {
     assert!(heap.is_empty())
 }
 
+// A handle for every item that's pushed, to change its priority later: here a
+// comparator picks the longest task first, and ties go to the first name.
+fn decrease_key() {
+    let by_duration = |a: &(&str, u32), b: &(&str, u32)| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0));
+    let mut tasks = PriorityQueue::with_comparator(by_duration);
+
+    let build = tasks.push(("build", 10));
+    let test = tasks.push(("test", 30));
+    tasks.push(("deploy", 5));
+    assert_eq!(tasks.peek(), Some(&("test", 30)));
+
+    // The build got longer: it moves forward (it's the "decrease" of a
+    // min-heap, where forward is smaller).
+    assert_eq!(tasks.decrease_key(build, ("build", 45)), Ok(("build", 10)));
+    assert_eq!(tasks.peek(), Some(&("build", 45)));
+
+    // No tests after all.
+    assert_eq!(tasks.remove(test), Some(("test", 30)));
+    assert_eq!(tasks.remove(test), None);
+
+    while let Some((name, minutes)) = tasks.pop() {
+        println!("{}: {} minutes", name, minutes);
+    }
+}
+
 // #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,8 +125,8 @@ mod tests {
         let a: Thing = Thing { content: 0 };
         let b: Thing = Thing { content: 1 };
 
-        assert_eq!(&a.cmp(&b), &Ordering::Greater);
-        assert_eq!(&b.cmp(&a), &Ordering::Less);
+        assert_eq!(&a.cmp(&b), &Ordering::Less);
+        assert_eq!(&b.cmp(&a), &Ordering::Greater);
         assert_eq!(&a.cmp(&a), &Ordering::Equal);
     }
 
@@ -119,7 +136,7 @@ mod tests {
         let b: &Thing = &Thing { content: 1 };
         let c: &Thing = &Thing { content: 2 };
 
-        let mut heap = BinaryHeap::new();
+        let mut heap = PriorityQueue::new();
 
         assert_eq!(heap.peek(), None);
 
@@ -158,5 +175,7 @@ fn main() {
 
     max_heap();
     println!("---");
-    min_heap()
+    min_heap();
+    println!("---");
+    decrease_key()
 }
@@ -1,17 +1,30 @@
 // https://doc.rust-lang.org/std/collections/binary_heap/
 // This is a larger example that implements Dijkstra's algorithm to solve the
-// shortest path problem on a directed graph. It shows how to use BinaryHeap
-// with custom types.
+// shortest path problem on a directed graph. It started from the BinaryHeap
+// example of the docs, and now uses the indexed priority queue of
+// projects/priority_queue: when a shorter way to a node is found, the node
+// moves forward in the queue (decrease-key) instead of being pushed again.
+//
+// The `graph` module of fungi has the same algorithm, for any graph that
+// implements the AGraph trait of advanced.rs.
 //
 // - https://en.wikipedia.org/wiki/Dijkstra%27s_algorithm
 // - https://en.wikipedia.org/wiki/Shortest_path_problem
 
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/priority_queue/src/lib.rs"]
+mod priority_queue;
+
+use priority_queue::{Handle, PriorityQueue};
 use std::fmt;
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
 use std::usize;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+// The queue takes the derived order as it is, by cost and then by position,
+// and gives the smallest first: there's no need to flip `Ord` to get a
+// min-heap out of a max-heap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 struct State {
     cost: usize,
     position: usize,
@@ -63,53 +76,6 @@ impl<'a> fmt::Display for StateDisplay<'a> {
     }
 }
 
-// The priority queue depends on `Ord`.
-// Explicitly implement the trait so the queue becomes a min-heap
-// instead of a max-heap.
-//
-// It uses Enum std::cmp::Ordering#then_with
-// https://doc.rust-lang.org/std/cmp/enum.Ordering.html#method.then_with
-//
-// pub fn then_with<F>(self, f: F) -> Ordering
-// where F: FnOnce() -> Ordering,
-//
-//     Chains the ordering with the given function.
-//     Returns self when it's not Equal. Otherwise calls f and returns the result.
-//     Examples
-//
-// use std::cmp::Ordering;
-// let result = Ordering::Equal.then_with(|| Ordering::Less);
-// assert_eq!(result, Ordering::Less);
-// let result = Ordering::Less.then_with(|| Ordering::Equal);
-// assert_eq!(result, Ordering::Less);
-// let result = Ordering::Less.then_with(|| Ordering::Greater);
-// assert_eq!(result, Ordering::Less);
-// let result = Ordering::Equal.then_with(|| Ordering::Equal);
-// assert_eq!(result, Ordering::Equal);
-// let x: (i64, i64, i64) = (1, 2, 7);
-// let y: (i64, i64, i64)  = (1, 5, 3);
-// let result = x.0.cmp(&y.0).then_with(|| x.1.cmp(&y.1)).then_with(|| x.2.cmp(&y.2));
-// assert_eq!(result, Ordering::Less);
-//
-impl Ord for State {
-    fn cmp(&self, other: &State) -> Ordering {
-        // Notice that the we flip the ordering on costs.
-        // In case of a tie we compare positions - this step is necessary
-        // to make implementations of `PartialEq` and `Ord` consistent.
-        other
-            .cost
-            .cmp(&self.cost)
-            .then_with(|| self.position.cmp(&other.position))
-    }
-}
-
-// `PartialOrd` needs to be implemented as well.
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &State) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 // Each node is represented as an `usize`, for a shorter implementation.
 #[derive(Debug)]
 struct Edge {
@@ -132,9 +98,9 @@ fn distance_vector(l: usize) -> Vec<usize> {
 // Dijkstra's shortest path algorithm.
 
 // Start at `start` and use `dist` to track the current shortest distance to
-// each node. This implementation isn't memory-efficient as it may leave
-// duplicate nodes in the queue. It also uses `usize::MAX` as a sentinel value,
-// for a simpler implementation.
+// each node. Every node is in the queue at most once, and `handles` knows
+// where. It also uses `usize::MAX` as a sentinel value, for a simpler
+// implementation.
 fn shortest_path(adj_list: &Vec<Vec<Edge>>, start: usize, goal: usize) -> Option<usize> {
     // Everything is initialised at the MAX possible value (like infinity).
     // dist[node] = current shortest distance from `start` to `node`
@@ -142,22 +108,21 @@ fn shortest_path(adj_list: &Vec<Vec<Edge>>, start: usize, goal: usize) -> Option
     // behaviour of a HashMap.
     let mut dist: Vec<_> = distance_vector(adj_list.len());
 
-    // Module std::collections::binary_heap
-    // https://doc.rust-lang.org/std/collections/binary_heap/index.html
-    //
-    // Struct std::collections::binary_heap::BinaryHeap
-    // A priority queue implemented with a binary heap.
-    // This will be a min-heap.
-    let mut heap = BinaryHeap::new();
+    // projects/priority_queue
+    // A priority queue implemented with a d-ary heap, the smallest first.
+    // `push` returns a handle: handles[node] is where the node is, if it has
+    // been pushed.
+    let mut heap = PriorityQueue::new();
+    let mut handles: Vec<Option<Handle>> = vec![None; adj_list.len()];
 
     // We're at `start`, with a zero cost.
     // We can use dist as an hashmap.
     dist[start] = 0;
 
-    heap.push(State {
+    handles[start] = Some(heap.push(State {
         cost: 0,
         position: start,
-    });
+    }));
 
     println!(
         " - the start node is: {} (top of the heap), the graph has {} nodes",
@@ -206,14 +171,9 @@ fn shortest_path(adj_list: &Vec<Vec<Edge>>, start: usize, goal: usize) -> Option
             return Some(cost);
         }
 
-        // Important as we may have already found a better way.
-        // The cost in the State that we popped from the heap is greater than
-        // the cost in the corresponding vector of distances. This state can be
-        // discarded (continue).
-        if cost > dist[position] {
-            println!(" - [skip] state cost is greater than the one found");
-            continue;
-        }
+        // With BinaryHeap, a node was pushed again for every better way to
+        // it, and the stale states had to be skipped here. Now there's just
+        // one state per node, and it always has the best cost found.
 
         // For each node we can reach, see if we can find a way with
         // a lower cost going through this node
@@ -245,17 +205,28 @@ fn shortest_path(adj_list: &Vec<Vec<Edge>>, start: usize, goal: usize) -> Option
             // This is where the decision is taken, where we decide what can be
             // a possible state, considering the new state that we created from
             // the edge and what the "map of distances" (in our case a vector)
-            // can tell us about this cost. The two possible braches are:
-            // we do pick this new state as a viable option and we put it into
-            // our heap (that will decide the priority for us (min-heap)), or
-            // reaching this position is not the best option at that cost and
-            // the state is discarded;
+            // can tell us about this cost. The three possible braches are:
+            // the node is already in the heap, and we move it forward with
+            // the new cost; it isn't, and we push it (the heap will decide the
+            // priority for us (min-heap)); or reaching this position is not
+            // the best option at that cost and the state is discarded.
             if next.cost < dist[next.position] {
-                println!(
-                    "     better cost found ({}) to go to {}: [pushed to heap]",
-                    next.cost, next.position
-                );
-                heap.push(next);
+                match handles[next.position] {
+                    Some(handle) if heap.contains(handle) => {
+                        println!(
+                            "     better cost found ({}) to go to {}: [decrease key]",
+                            next.cost, next.position
+                        );
+                        heap.decrease_key(handle, next).expect("a node in the heap");
+                    }
+                    _ => {
+                        println!(
+                            "     better cost found ({}) to go to {}: [pushed to heap]",
+                            next.cost, next.position
+                        );
+                        handles[next.position] = Some(heap.push(next));
+                    }
+                }
                 // Relaxation, we have now found a better way
                 dist[next.position] = next.cost;
             } else {
@@ -1,56 +1,79 @@
-extern crate rand;
+// Guess the number, on the engine of projects/guess_game: the rules don't
+// read or print anything, the player on stdin is one strategy among the bots.
+//
+//     guess_game [--range LOW HIGH] [--attempts N]
+//     guess_game tournament [GAMES] [--range LOW HIGH] [--attempts N]
+//
+// The tournament plays the bots against the same secrets and ranks them by
+// the guesses they needed on average.
 
-use std::io;
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/guess_game/src/lib.rs"]
+mod guess_game;
+
+use guess_game::{play, Bisect, Game, Human, RandomBot, Rng, Rules, Strategy, Tournament};
 use std::env;
-use std::cmp::Ordering;
-use self::rand::Rng;
+use std::io;
+use std::process;
 
 fn main() {
-    println!("Guess the number!");
-
-    // a RNG that is local to the current thread of execution and seeded by the
-    // operating system
-    let secret_number = rand::thread_rng().gen_range(1, 101);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("guess_game: {}", e);
+        process::exit(1);
+    }
+}
 
-    // https://doc.rust-lang.org/std/env/fn.var.html
-    let key = "VERBOSE";
-    match env::var(key) {
-        Ok(val) => {
-            println!("{}: {:?}", key, val);
-            println!("The secret number is: {}", secret_number);
+fn run(args: &[String]) -> Result<(), String> {
+    let mut range = (1, 100);
+    let mut attempts = None;
+    let mut tournament = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--range" => range = (number(args.next())?, number(args.next())?),
+            "--attempts" => attempts = Some(number(args.next())?),
+            "tournament" => tournament = Some(1000),
+            _ if tournament.is_some() => tournament = Some(number(Some(arg))?),
+            other => return Err(format!("unexpected argument '{}'", other)),
         }
-        Err(_) => (),
+    }
+    let mut rules = Rules::new(range.0, range.1).map_err(|e| e.to_string())?;
+    if let Some(n) = attempts {
+        rules = rules.with_attempts(n);
     }
 
-    loop {
-        println!("Please input your guess.");
-
-        let mut guess = String::new();
-
-        // returns a Result, the variants are Ok or Err.
-        io::stdin()
-            .read_line(&mut guess)
-            .expect("Failed to read line");
-
-        // https://doc.rust-lang.org/std/primitive.str.html#method.parse
-        let guess: u32 = match guess.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("Please type a number!");
-                continue;
-            }
-        };
-
-        println!("You guessed: {}", guess);
-
-        // variants for Ordering are Less, Greater, and Equal.
-        match guess.cmp(&secret_number) {
-            Ordering::Less => println!("Too small!"),
-            Ordering::Greater => println!("Too big!"),
-            Ordering::Equal => {
-                println!("You win!");
-                break;
-            }
+    if let Some(games) = tournament {
+        let mut bots: Vec<Box<dyn Strategy>> = vec![
+            Box::new(Bisect::new()),
+            Box::new(RandomBot::new(Rng::from_time())),
+        ];
+        let standings = Tournament::new(rules, games)
+            .with_seed(Rng::from_time().next_u64())
+            .run(&mut bots)
+            .map_err(|e| e.to_string())?;
+        for (i, standing) in standings.iter().enumerate() {
+            println!("{}. {}", i + 1, standing);
         }
+        return Ok(());
     }
+
+    let mut game = Game::random(rules, &mut Rng::from_time());
+    // https://doc.rust-lang.org/std/env/fn.var.html
+    if let Ok(val) = env::var("VERBOSE") {
+        println!("VERBOSE: {:?}", val);
+        println!("The secret number is: {}", game.secret());
+    }
+    let stdin = io::stdin();
+    let mut human = Human::new(stdin.lock(), io::stdout());
+    play(&mut game, &mut human).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn number(arg: Option<&String>) -> Result<u32, String> {
+    let arg = arg.ok_or("a number is missing")?;
+    arg.parse()
+        .map_err(|_| format!("'{}' is not a number", arg))
 }
@@ -23,219 +23,73 @@
 //      - 2. Move Plate 2 from Tower 2 to Tower 3
 //      - 3. Move Plate 1 from Tower 1 to Tower 3
 //
-// This approach leads to a natural recursive algorithm.
-
-use std::error::Error;
-use std::fmt;
+// This approach leads to a natural recursive algorithm, the one of
+// `frame_stewart` in projects/hanoi when there are three pegs. With more
+// pegs, more disks can wait aside while the largest ones move, and the same
+// tower takes far fewer moves.
+//
+// The solvers give a list of moves, and `Towers` replays it: a move that puts
+// a disk on a smaller one is an error, not a line to read carefully.
 
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
 #[allow(dead_code)]
-fn move_plates(n: usize, source: &mut Tower, destin: &mut Tower, buffer: &mut Tower) -> () {
-    if n == 1 {
-        // move source to destination directly
-        source.move_top_to(destin);
-    }
-    if n == 2 {
-        // move the 1st and 2nd plate using a buffer
-        source.move_top_to(buffer);
-        source.move_top_to(destin);
-        buffer.move_top_to(destin);
-    }
-    if n == 3 {
-        // move the 1st and 2nd plate using a buffer (from t1 to t2)
-        // move from source to buffer.
-        source.move_top_to(destin);
-        source.move_top_to(buffer);
-        destin.move_top_to(buffer);
-        
-        // move the 3rd plate to destination (from t1 to t3)
-        // move from source to destination.
-        source.move_top_to(destin);
-        
-        // move the 1st and 2nd place using a buffer (from t2 to t3)
-        // move from buffer to destination.
-        buffer.move_top_to(source);
-        buffer.move_top_to(destin);
-        source.move_top_to(destin);
-    }
-    if n == 4 {
-        // move disk 0 from 0 to 1
-        // move disk 1 from 0 to 2
-        // move disk 0 from 1 to 2
-        
-        // move disk 2 from 0 to 1
-        
-        // move disk 0 from 2 to 0
-        // move disk 1 from 2 to 1
-        // move disk 0 from 0 to 1
-        
-        // move disk 3 from 0 to 2
-        
-        // move disk 0 from 1 to 2
-        // move disk 1 from 1 to 0
-        // move disk 0 from 2 to 0
-        
-        // move disk 2 from 1 to 2
-        
-        // move disk 0 from 0 to 1
-        // move disk 1 from 0 to 2
-        // move disk 0 from 1 to 2
-    }
-    if n == 5 {
-         // move disk 0 from 0 to 2 
-         // move disk 1 from 0 to 1 
-         // move disk 0 from 2 to 1 
-        
-         // move disk 2 from 0 to 2 
-        
-         // move disk 0 from 1 to 0 
-         // move disk 1 from 1 to 2 
-         // move disk 0 from 0 to 2 
-        
-         // move disk 3 from 0 to 1 
-        
-         // move disk 0 from 2 to 1 
-         // move disk 1 from 2 to 0 
-         // move disk 0 from 1 to 0 
-        
-         // move disk 2 from 2 to 1 
-        
-         // move disk 0 from 0 to 2 
-         // move disk 1 from 0 to 1 
-         // move disk 0 from 2 to 1 
-        
-         // move disk 4 from 0 to 2 
-        
-         // move disk 0 from 1 to 0 
-         // move disk 1 from 1 to 2 
-         // move disk 0 from 0 to 2 
-        
-         // move disk 2 from 1 to 0 
-        
-         // move disk 0 from 2 to 1 
-         // move disk 1 from 2 to 0 
-         // move disk 0 from 1 to 0 
-        
-         // move disk 3 from 1 to 2 
-        
-         // move disk 0 from 0 to 2 
-         // move disk 1 from 0 to 1 
-         // move disk 0 from 2 to 1 
-        
-         // move disk 2 from 0 to 2 
-        
-         // move disk 0 from 1 to 0 
-         // move disk 1 from 1 to 2 
-         // move disk 0 from 0 to 2 
-    }
-}
+#[path = "../projects/hanoi/src/lib.rs"]
+mod hanoi;
 
-#[allow(dead_code)]
-fn initialise(n: usize) -> Vec<Tower> {
-    let mut towers: Vec<Tower> = Vec::with_capacity(n);
-    for i in 0..3 {
-        let t: Tower = Tower {
-            disks: Vec::<usize>::new(),
-            index: i,
-        };
-        towers.push(t);
-    }
-    for i in (0..n).rev() {
-        towers[0].add(i).unwrap();
-    }
-    return towers;
-}
+use hanoi::{frame_stewart, iterative, moves_needed, solve, Error, Towers};
 
-fn main() -> () {
-    let n: usize = 5;
-    // https://doc.rust-lang.org/std/primitive.slice.html#method.split_at_mut
-    //
-    // &mut towers[0];
-    // towers.get_mut(0).expect("a tower collapsed");
-    let mut t0: Tower = Tower::new(0);
-    let mut t1: Tower = Tower::new(1);
-    let mut t2: Tower = Tower::new(2);
-    // load the first tower
-    for i in (0..n).rev() {
-        t0.add(i).unwrap();
-    }
-    println!("{}", &t0);
-    println!("{}", &t1);
-    println!("{}", &t2);
-    println!("--");
-
-    // move 5 disks (n) from the first tower (t0) to the last tower (t2)
-    // using the middle tower (t1) as buffer.
-    t0.move_disks(n, &mut t2, &mut t1);
-
-    // iterative
-    // move_plates(3, &mut t0, &mut t2, &mut t1);
-    
-    println!("--");
-    println!("{}", &t0);
-    println!("{}", &t1);
-    println!("{}", &t2);
-}
-
-#[derive(Debug)]
-struct Tower {
-    disks: Vec<usize>,
-    index: usize,
-}
-
-impl fmt::Display for Tower {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        return write!(f, "Tower {:02}: stack: {:?}", self.index, self.disks);
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
     }
 }
 
-impl Tower {
-    fn new(index: usize) -> Tower {
-        return Tower {
-            disks: Vec::<usize>::new(),
-            index,
-        };
+fn run() -> Result<(), Error> {
+    // Move 5 disks from the first peg to the last one.
+    let n = 5;
+    let mut towers = Towers::new(3, n)?;
+    print!("{}", towers);
+    println!("--");
+    let moves = frame_stewart(n, 3, 0, 2)?;
+    for m in &moves {
+        println!("{}", m);
     }
+    towers.replay(moves)?;
+    println!("--");
+    print!("{}", towers);
 
-    fn index(&self) -> usize {
-        return self.index;
+    // A fourth peg, and more.
+    for &pegs in &[3, 4, 5, 6] {
+        println!("20 disks, {} pegs: {} moves", pegs, moves_needed(20, pegs));
     }
+    let mut towers = Towers::new(4, 20)?;
+    towers.replay(frame_stewart(20, 4, 0, 3)?)?;
+    println!("solved with 4 pegs: {}", towers.is_solved(3));
 
-    fn add(&mut self, d: usize) -> Result<(), Box<dyn Error>> {
-        if self.disks.first().is_some() && self.disks.first().unwrap() <= &d {
-            return Err(Box::<Error>::from(format!("Error placing disk {}", d)));
-        }
-        self.disks.push(d);
-        return Ok(());
-    }
+    // From a game already started.
+    let towers = Towers::from_pegs(vec![vec![4, 1], vec![3, 2], vec![0]])?;
+    print!("{}", towers);
+    let moves = solve(&towers, 2)?;
+    println!("--");
+    println!("{} moves to peg 2", moves.len());
+    let mut solved = towers.clone();
+    solved.replay(moves)?;
+    print!("{}", solved);
 
-    fn move_top_to(&mut self, t: &mut Tower) {
-        match self.disks.pop() {
-            Some(top) => {
-                match t.add(top) {
-                    Ok(_) => {
-                        println!("move disk {} from {} to {}", top, self.index(), t.index());
-                    }
-                    Err(e) => {
-                        println!("{}", e);
-                    }
-                }
-                return;
-            }
-            None => (),
-        };
+    // Cheating is caught.
+    let mut towers = Towers::new(3, 3)?;
+    let mut moves = frame_stewart(3, 3, 0, 2)?;
+    moves.swap(0, 1);
+    if let Err(e) = towers.replay(moves) {
+        println!("error: {}", e);
     }
 
-    fn move_disks(&mut self, n: usize, destination: &mut Tower, buffer: &mut Tower) {
-        // what we need to recurse here is a case, where for any n > 1
-        // we do 3 steps:
-        //  1. (decrease n) move orig to buff
-        //  2. (direct)     move orig to dest
-        //  3. (decrease n) move buff to dest
-        // alternating these steps we traverse the tree of moves.
-        if n > 0 {
-            self.move_disks(n - 1, buffer, destination);
-            self.move_top_to(destination);
-            buffer.move_disks(n - 1, destination, self);
-        }
+    // The moves of 32 disks, without keeping them: four billion of them.
+    let moves = iterative(32, 0, 2)?;
+    println!("32 disks: {} moves", moves.len());
+    for m in moves.take(4) {
+        println!("{}", m);
     }
+    Ok(())
 }
@@ -1,114 +1,73 @@
-use std::collections::VecDeque;
-use std::env;
-
 // permutations without repetition:
-// where n is the number of objects ((0..10).len())
-// and r is the number of positions (chars.len())
+// where n is the number of objects ((1..6).len())
+// and r is the number of positions
 // the total permutations are P(n,r) = n!/(n-r)!
 //
 // https://en.wikipedia.org/wiki/Permutation
 // https://en.wikipedia.org/wiki/Heap's_algorithm
 // Permutations by interchanges. B.R.Heap, The Computer Journal, 6(3) (1963)
 // http://comjnl.oxfordjournals.org/content/6/3/293.full.pdf
+//
+// The permutations used to come from a recursive function calling a closure
+// for each of them: they couldn't be stopped, resumed or passed around. The
+// iterators of projects/combinatorics can: they lend every permutation from
+// a buffer of theirs, so they don't allocate one Vec per permutation.
 
-fn permute<T, F: FnMut(&[T])>(used: &mut Vec<T>, unused: &mut VecDeque<T>, action: &mut F) {
-    if unused.is_empty() {
-        action(used);
-    } else {
-        for _ in 0..unused.len() {
-            used.push(unused.pop_front().unwrap());
-            permute(used, unused, action);
-            unused.push_back(used.pop().unwrap());
-        }
-    }
-}
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/combinatorics/src/lib.rs"]
+mod combinatorics;
 
-// return permutations of size "size"
-fn permute_with_limit<T, F: FnMut(&[T])>(
-    size: usize,
-    used: &mut Vec<T>,
-    unused: &mut VecDeque<T>,
-    action: &mut F,
-) {
-    if unused.is_empty() || used.len() == size {
-        action(used);
-    } else {
-        for _ in 0..unused.len() {
-            used.push(unused.pop_front().unwrap());
-            permute_with_limit(size, used, unused, action);
-            unused.push_back(used.pop().unwrap());
-        }
-    }
-}
+use combinatorics::{rank, unrank, Combinations, Heap, Lending, Lexicographic};
+use std::env;
 
 fn main() {
-    let mut queue = (1..6).collect::<VecDeque<_>>();
-
-    // a closure with trait Fn.
-    // permute(&mut Vec::new(), &mut queue, &|perm| println!("{:?}", perm));
-    //
-    // http://doc.rust-lang.org/std/ops/trait.FnMut.html
-    // https://stackoverflow.com/a/37949326
-    // https://stackoverflow.com/a/30232500
-    // http://huonw.github.io/blog/2015/05/finding-closure-in-rust/
-    //
-    // There are three traits for closures, all of which provide the
-    // ...(...) call syntax:
-    //
-    // &self is Fn        | fn by_value(_: T) {}
-    // &mut self is FnMut | fn by_mut(_: &mut T) {}
-    // self is FnOnce     | fn by_ref(_: &T) {}
-
-    {
-        let mut mtrix: Vec<Vec<i32>> = Vec::new();
-        permute(&mut Vec::new(), &mut queue, &mut |perm| {
-            mtrix.push(perm.to_vec())
-        });
-
-        // P(n,r) = n!/(n - r)!
-        // 5!/(5 - 5)! = 120
-        println!(
-            "P({},{}) = {}!/({} - {})! = {} permutations of a vector of {} elements",
-            queue.len(),
-            queue.len(),
-            queue.len(),
-            queue.len(),
-            queue.len(),
-            mtrix.len(),
-            queue.len()
-        );
+    let verbose = env::args().nth(1) == Some(String::from("-v"));
+    let items: Vec<i32> = (1..6).collect();
+    let n = items.len();
 
-        if env::args().len() > 1 && env::args().nth(1) == Some(String::from("-v")) {
-            for (i, v) in mtrix.iter().enumerate() {
-                println!("- {:02}: {:?}", i, v);
-            }
+    // P(n,r) = n!/(n - r)!
+    // 5!/(5 - 5)! = 120
+    let mut permutations = Heap::new(items.clone());
+    let mut count = 0;
+    while let Some(p) = permutations.next() {
+        if verbose {
+            println!("- {:03}: {:?}", count, p);
         }
+        count += 1;
     }
+    println!(
+        "P({},{}) = {}!/({} - {})! = {} permutations of a vector of {} elements",
+        n, n, n, n, n, count, n
+    );
 
-    let mut mtrix: Vec<Vec<i32>> = Vec::new();
-    let limit: usize = 3;
-
-    permute_with_limit(limit, &mut Vec::new(), &mut queue, &mut |perm| {
-        mtrix.push(perm.to_vec())
-    });
-
-    // P(n,r) = n!/(n - r)!
+    // The permutations of 3 elements are the permutations of every choice
+    // of 3 elements.
     // 5!/(5 - 3)! = 60
+    let limit: usize = 3;
+    let mut choices = Combinations::new(items.clone(), limit);
+    let mut count = 0;
+    while let Some(choice) = choices.next() {
+        let mut permutations = Heap::new(choice.to_vec());
+        while let Some(p) = permutations.next() {
+            if verbose {
+                println!("- {:02}: {:?}", count, p);
+            }
+            count += 1;
+        }
+    }
     println!(
         "P({},{}) = {}!/({} - {})! = {} permutations of {} elements of a vector of {} elements",
-        queue.len(),
-        limit,
-        queue.len(),
-        queue.len(),
-        limit,
-        mtrix.len(),
-        limit,
-        queue.len()
+        n, limit, n, n, limit, count, limit, n
     );
 
-    if env::args().len() > 1 && env::args().nth(1) == Some(String::from("-v")) {
-        for (i, v) in mtrix.iter().enumerate() {
-            println!("- {:02}: {:?}", i, v);
-        }
+    // In lexicographic order, from the 100th: no need to go through the
+    // first 99.
+    let mut sorted = Lexicographic::from_rank(items.clone(), 99).unwrap();
+    for _ in 0..3 {
+        let p = sorted.next().unwrap();
+        println!("permutation {}: {:?}", rank(p).unwrap(), p);
     }
+    println!("the last one: {:?}", unrank(items, 119).unwrap());
 }
@@ -1,22 +1,52 @@
-//https://doc.rust-lang.org/rand/rand/index.html
+// https://en.wikipedia.org/wiki/Monte_Carlo_method
+//
+// A point at random in the square of side 2 is inside the circle of radius 1
+// with probability pi / 4: four times the share of the points that land in
+// the circle is an estimate of pi.
+//
+// The runner of projects/monte_carlo throws the points on every CPU, and
+// prints how close the estimate gets as the points add up. Run it twice with
+// the same seed for the same digits.
 
-use rand::distributions::{Distribution, Range};
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/monte_carlo/src/lib.rs"]
+mod monte_carlo;
 
-fn main() {
-   let between = Range::new(-1f64, 1.);
-   let mut rng = rand::thread_rng();
+use monte_carlo::{Rng, Runner};
+use std::env;
+use std::f64::consts::PI;
 
-   let total = 1_000_000;
-   let mut in_circle = 0;
+fn main() {
+    let seed = env::args()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2018);
 
-   for _ in 0..total {
-       let a = between.sample(&mut rng);
-       let b = between.sample(&mut rng);
-       if a*a + b*b <= 1. {
-           in_circle += 1;
-       }
-   }
+    let in_circle = |rng: &mut Rng| {
+        let a = rng.uniform(-1., 1.);
+        let b = rng.uniform(-1., 1.);
+        a * a + b * b <= 1.
+    };
+    let report = Runner::new(10_000_000)
+        .with_seed(seed)
+        .run(&|rng: &mut Rng| {
+            if in_circle(rng) {
+                4.
+            } else {
+                0.
+            }
+        });
 
-   // prints something close to 3.14159...
-   println!("{}", 4. * (in_circle as f64) / (total as f64));
+    // Something close to 3.14159...
+    print!("{}", report);
+    println!(
+        "pi is {}in the interval",
+        if report.estimate.contains(PI) {
+            ""
+        } else {
+            "not "
+        }
+    );
 }
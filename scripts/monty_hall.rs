@@ -1,86 +1,40 @@
-// https://doc.rust-lang.org/rand/rand/index.html
-
-use rand::Rng;
-use rand::distributions::{Distribution, Range};
-use rand::distributions::range::RangeInt;
-
-struct SimulationResult {
-    win: bool,
-    switch: bool,
-}
-
-// Run a single simulation of the Monty Hall problem.
-fn simulate<R: Rng>(random_door: &Range<RangeInt<u32>>, rng: &mut R)
-                    -> SimulationResult {
-    let car = random_door.sample(rng);
-
-    // This is our initial choice
-    let mut choice = random_door.sample(rng);
-
-    // The game host opens a door
-    let open = game_host_open(car, choice, rng);
-
-    // Shall we switch?
-    let switch = rng.gen();
-    if switch {
-        choice = switch_door(choice, open);
-    }
-
-    SimulationResult { win: choice == car, switch: switch }
-}
-
-// Returns the door the game host opens given our choice and knowledge of
-// where the car is. The game host will never open the door with the car.
-fn game_host_open<R: Rng>(car: u32, choice: u32, rng: &mut R) -> u32 {
-    let choices = free_doors(&[car, choice]);
-    rand::seq::sample_slice(rng, &choices, 1)[0]
-}
-
-// Returns the door we switch to, given our current choice and
-// the open door. There will only be one valid door.
-fn switch_door(choice: u32, open: u32) -> u32 {
-    free_doors(&[choice, open])[0]
-}
-
-fn free_doors(blocked: &[u32]) -> Vec<u32> {
-    (0..3).filter(|x| !blocked.contains(x)).collect()
-}
+// https://en.wikipedia.org/wiki/Monty_Hall_problem
+//
+// The car is behind one of three doors. We pick one, the host opens another
+// one (never the one with the car), and we can keep our choice or switch to
+// the door still closed. Switching wins twice as often.
+//
+// With N doors, of which the host opens K, keeping wins 1 time out of N, and
+// switching (N - 1)/N times out of the N - K - 1 doors left to switch to: as
+// long as the host opens a door, switching is better.
+
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/monte_carlo/src/lib.rs"]
+mod monte_carlo;
+
+use monte_carlo::{MontyHall, Runner};
 
 fn main() {
-    // The estimation will be more accurate with more simulations
-    let num_simulations = 10000;
-
-    let mut rng = rand::thread_rng();
-    let random_door = Range::new(0u32, 3);
-
-    let (mut switch_wins, mut switch_losses) = (0, 0);
-    let (mut keep_wins, mut keep_losses) = (0, 0);
-
-    println!("Running {} simulations...", num_simulations);
-    for _ in 0..num_simulations {
-        let result = simulate(&random_door, &mut rng);
-
-        match (result.win, result.switch) {
-            (true, true) => switch_wins += 1,
-            (true, false) => keep_wins += 1,
-            (false, true) => switch_losses += 1,
-            (false, false) => keep_losses += 1,
-        }
+    // The estimation will be more accurate with more simulations.
+    let runner = Runner::new(100_000).with_seed(1);
+
+    println!("doors opened    keep                 switch");
+    for &(doors, opened) in &[(3, 1), (4, 1), (4, 2), (10, 1), (10, 8), (100, 98)] {
+        let keep = MontyHall::new(doors, opened, false);
+        let switch = MontyHall::new(doors, opened, true);
+        println!(
+            "{:5} {:6}    {:.3} (exactly {:.3})  {:.3} (exactly {:.3})",
+            doors,
+            opened,
+            runner.run(&keep).estimate,
+            keep.exact(),
+            runner.run(&switch).estimate,
+            switch.exact()
+        );
     }
 
-    let total_switches = switch_wins + switch_losses;
-    let total_keeps = keep_wins + keep_losses;
-
-    println!("Switched door {} times with {} wins and {} losses",
-             total_switches, switch_wins, switch_losses);
-
-    println!("Kept our choice {} times with {} wins and {} losses",
-             total_keeps, keep_wins, keep_losses);
-
-    // With a large number of simulations, the values should converge to
-    // 0.667 and 0.333 respectively.
-    println!("Estimated chance to win if we switch: {}",
-             switch_wins as f32 / total_switches as f32);
-    println!("Estimated chance to win if we don't: {}",
-             keep_wins as f32 / total_keeps as f32);
+    // How the chance to win switching with 3 doors gets to 2/3.
+    print!("{}", runner.run(&MontyHall::new(3, 1, true)));
 }
@@ -1,46 +1,54 @@
-fn main() -> () {
-    let mut m: [[usize; 6]; 6] = [[0; 6]; 6];
-    let mut n: usize = 0;
-    for i in 0..6 {
-        for j in 0..6 {
-            m[i][j] = n;
+// Rotates matrices with the library of projects/matrix, then checks its
+// quarter turn against the frame by frame one of this script, which only
+// moves the elements of a square matrix around without any extra room.
+
+// The library is a single file: include it as a module, whatever of it this
+// script doesn't use.
+#[allow(dead_code)]
+#[path = "../projects/matrix/src/lib.rs"]
+mod matrix;
+
+use matrix::{Matrix, SMatrix};
+
+fn main() {
+    let mut n = 0;
+    let mut m: SMatrix<usize, 6, 6> = SMatrix::new([[0; 6]; 6]);
+    for row in m.data.iter_mut() {
+        for x in row.iter_mut() {
+            *x = n;
             n += 1;
         }
     }
-    printm(&m);
-    println!("---");
-    rotate(&mut m, 6);
-    printm(&m);
-}
+    println!("{}---", m);
 
-fn printm(m: &[[usize; 6]; 6]) -> () {
-    for r in 0..6 {
-        for c in 0..6 {
-            print!("[({:?},{:?}),{:02?}]", r, c, &m[r][c]);
-        }
-        println!("");
+    let mut by_frames = m;
+    rotate(&mut by_frames.data);
+    m.rotate(90).unwrap();
+    println!("{}---", m);
+    assert_eq!(m, by_frames);
+
+    // Not square: 2x3 turns into 3x2.
+    let mut wide: Matrix<i32> = "1 2 3\n4 5 6".parse().unwrap();
+    for degrees in &[90, 180, -90] {
+        wide.rotate(*degrees).unwrap();
+        println!("rotated by {}:\n{}", degrees, wide);
     }
-    println!("---");
 }
 
-fn rotate(matrix: &mut [[usize; 6]; 6], n: usize) {
+fn rotate<T: Copy, const N: usize>(matrix: &mut [[T; N]; N]) {
     // we rotate this matrix by concentric frames
-    for layer in 0..n / 2 {
+    for layer in 0..N / 2 {
         // first is the top edge of the frame
-        let mut first: usize = layer;
+        let first = layer;
         // last is the lower edge of the frame
-        let mut last: usize = n - 1 - layer;
-
-        println!("first {:?}", first);
-        println!("last {:?}", last);
+        let last = N - 1 - layer;
 
         // the distance of the frames is the number of steps
         // to take to do a full rotation
         for i in first..last {
             // i is driving the rotation as reference
-            let mut offset: usize = i - first;
-            println!("offset {:?}", offset);
-            let mut top = matrix[first][i]; // save top
+            let offset = i - first;
+            let top = matrix[first][i]; // save top
 
             // offset 0 is moving the corners
             // [0] -> [1]
@@ -57,39 +65,16 @@ fn rotate(matrix: &mut [[usize; 6]; 6], n: usize) {
             // [ ] <- [2][ ]
 
             // from the left moving to top
-            println!(
-                "swapping ({},{}) with ({},{})",
-                first,
-                i,
-                last - offset,
-                first
-            );
             matrix[first][i] = matrix[last - offset][first];
 
             // bottom -> left
-            println!(
-                "swapping ({},{}) with ({},{})",
-                last - offset,
-                first,
-                last,
-                last - offset
-            );
             matrix[last - offset][first] = matrix[last][last - offset];
 
             // right -> bottom
-            println!(
-                "swapping ({},{}) with ({},{})",
-                last,
-                last - offset,
-                i,
-                last
-            );
             matrix[last][last - offset] = matrix[i][last];
 
             // top -> right
-            println!("swapping ({},{}) with ({},{})", i, last, first, i);
             matrix[i][last] = top; // right <- saved top
-            println!("---");
         }
     }
 }
@@ -1,47 +1,46 @@
-use std::io;
+// The conversions are the ones of the `units` module of the crate:
+//
+//     rustc scripts/temperatures.rs && ./temperatures
 
-// Temp is a generic temperature, fahrenheit or celsius.
-enum Temp {
-    F(f64),
-    C(f64),
-}
+#[path = "../src/units/quantities.rs"]
+mod units;
 
-fn convert_temp(temp: &Temp) -> f64 {
-    match temp {
-        &Temp::F(degrees) => (degrees - 32.0) / 1.8,
-        &Temp::C(degrees) => (degrees * 1.8) + 32.0,
-    }
-}
+use std::io;
+use units::{Measure, ParseQuantityError, Quantity, Temperature, CELSIUS, FAHRENHEIT};
 
-fn print_temp(temp: &Temp) {
-    match temp {
-        &Temp::F(degrees) => println!("{}F = {}C", degrees, convert_temp(temp)),
-        &Temp::C(degrees) => println!("{}C = {}F", degrees, convert_temp(temp)),
-    }
+// From Fahrenheit to Celsius, and from anything else to Fahrenheit.
+fn print_temp(temp: &Measure<Temperature>) {
+    let other = if temp.unit == FAHRENHEIT {
+        CELSIUS
+    } else {
+        FAHRENHEIT
+    };
+    println!("{} = {:.0}", temp, temp.quantity().to(other));
 }
 
 fn sample_temps() {
     println!("Sample conversions:");
 
-    let temps: [Temp; 14] = [
-        Temp::F(-40.0), // -40
-        Temp::F(0.0),   // -18
-        Temp::F(32.0),  // 0
-        Temp::F(60.0),  // 16
-        Temp::F(100.0), // 38
-        Temp::F(150.0), // 66
-        Temp::F(212.0), // 100
-        Temp::C(-40.0), // -40
-        Temp::C(0.0),   // 32
-        Temp::C(15.0),  // 59
-        Temp::C(30.0),  // 86
-        Temp::C(60.0),  // 140
-        Temp::C(100.0), // 212
-        Temp::C(200.0), // 392
+    let temps = [
+        "-40 °F", // -40
+        "0 °F",   // -18
+        "32 °F",  // 0
+        "60 °F",  // 16
+        "100 °F", // 38
+        "150 °F", // 66
+        "212 °F", // 100
+        "-40 °C", // -40
+        "0 °C",   // 32
+        "15 °C",  // 59
+        "30 °C",  // 86
+        "60 °C",  // 140
+        "100 °C", // 212
+        "200 °C", // 392
+        "0 K",    // -460
     ];
 
     for temp in temps.iter() {
-        print_temp(temp);
+        print_temp(&temp.parse().unwrap());
     }
 }
 
@@ -49,10 +48,14 @@ fn get_user_temp() {
     println!("\nType \"quit\" to end the program");
     loop {
         let mut temp_input = String::new();
-        println!("\nPlease input a temperature you want to convert (Format: 100F/f or -40C/c):");
-        io::stdin()
+        println!("\nPlease input a temperature you want to convert (Format: 100F/f, -40C/c or 300K/k):");
+        let read = io::stdin()
             .read_line(&mut temp_input)
             .expect("Failed to read line");
+        // Nothing read: the end of the input, as good as "quit".
+        if read == 0 {
+            break;
+        }
 
         let trimmed = temp_input.trim();
 
@@ -60,20 +63,13 @@ fn get_user_temp() {
             break;
         }
 
-        let (temp, scale) = trimmed.split_at(trimmed.len() - 1);
-
-        let temp: f64 = match temp.parse() {
-            Ok(num) => num,
-            Err(_) => continue,
-        };
-
-        let temp: Temp = match scale.to_uppercase().as_ref() {
-            "C" => Temp::C(temp),
-            "F" => Temp::F(temp),
-            _ => continue,
-        };
-
-        print_temp(&temp);
+        // The units are "F", "C" and "K", in upper case.
+        let temp: Result<Measure<Temperature>, ParseQuantityError> =
+            trimmed.to_uppercase().parse();
+        match temp {
+            Ok(temp) => print_temp(&temp),
+            Err(e) => println!("{}", e),
+        }
     }
 }
 
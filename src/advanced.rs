@@ -345,6 +345,7 @@ fn four() {
     // string slice in the Context have different lifetimes, and we've ensured
     // that the lifetime of the string slice is longer than the reference to the
     // Context.
+    // The `expr` module grows this Context and Parser into a real parser.
 }
 
 #[allow(dead_code)]
@@ -424,6 +425,51 @@ fn six() {
     // references.
 }
 
+// The graph traits of seven(), with their methods: the `graph` module
+// implements AGraph for its backends, and every AGraph is a GGraph too.
+pub trait GGraph<Node, Edge> {
+    /// The edges that leave the node: where they go, and their weight.
+    fn neighbors<'a>(&'a self, node: Node) -> ::graph::Neighbors<'a, Node, Edge>;
+}
+
+/// A graph, with associated types for its nodes and the weights of its edges.
+///
+/// The iterators are boxed, so that the trait can be made into an object.
+pub trait AGraph {
+    type Node: Copy + Eq + ::std::hash::Hash + Ord + ::std::fmt::Debug;
+    type Edge: ::graph::Weight;
+
+    fn directed(&self) -> bool;
+
+    fn node_count(&self) -> usize;
+
+    fn nodes<'a>(&'a self) -> Box<dyn Iterator<Item = Self::Node> + 'a>;
+
+    /// The edges that leave the node. In an undirected graph, all of its
+    /// edges.
+    fn neighbors<'a>(&'a self, node: Self::Node) -> ::graph::Neighbors<'a, Self::Node, Self::Edge>;
+
+    /// Every edge as (from, to, weight). An undirected edge is listed once,
+    /// from the smaller node to the larger one.
+    fn edges(&self) -> Vec<(Self::Node, Self::Node, Self::Edge)> {
+        let mut edges = Vec::new();
+        for from in self.nodes() {
+            for (to, weight) in self.neighbors(from) {
+                if self.directed() || from <= to {
+                    edges.push((from, to, weight));
+                }
+            }
+        }
+        edges
+    }
+}
+
+impl<G: AGraph + ?Sized> GGraph<G::Node, G::Edge> for G {
+    fn neighbors<'a>(&'a self, node: G::Node) -> ::graph::Neighbors<'a, G::Node, G::Edge> {
+        AGraph::neighbors(self, node)
+    }
+}
+
 #[allow(dead_code)]
 #[allow(unused_variables)]
 fn seven() {
@@ -466,24 +512,30 @@ fn seven() {
     // we want an iterator of u32 values everywhere that we call next on
     // Counter.
 
-    trait GGraph<Node, Edge> {
-        // methods would go here
-    }
-
-    trait AGraph {
-        type Node;
-        type Edge;
-
-        // methods would go here
-    }
+    // trait GGraph<Node, Edge> {
+    //     // methods would go here
+    // }
+    //
+    // trait AGraph {
+    //     type Node;
+    //     type Edge;
+    //
+    //     // methods would go here
+    // }
+    //
+    // Both are right above seven(), with their methods.
+    use graph::{bfs, hops, AdjacencyList, Build};
+    use std::hash::Hash;
 
     // Two graph trait definitions, GGraph using generics and AGraph using
     // associated types for Node and Edge.
     // With the GGraph trait defined using generics, our distance function
     // signature would have to look like:
-    fn distance_a<N, E, G: GGraph<N, E>>(graph: &G, start: &N, end: &N) -> u32 {
-        // ...snip...
-        0
+    fn distance_a<N, E, G: GGraph<N, E>>(graph: &G, start: &N, end: &N) -> Option<usize>
+    where
+        N: Copy + Eq + Hash,
+    {
+        hops(graph, *start, *end)
     }
     // The signature of a distance function that uses the trait GGraph and has
     // to specify all the generic parameters.
@@ -496,9 +548,8 @@ fn seven() {
 
     // the definition of distance that uses the AGraph trait with associated
     // types:
-    fn distance_b<G: AGraph>(graph: &G, start: &G::Node, end: &G::Node) -> u32 {
-        // ...snip...
-        0
+    fn distance_b<G: AGraph>(graph: &G, start: &G::Node, end: &G::Node) -> Option<usize> {
+        hops(graph, *start, *end)
     }
     // The signature of a distance function that uses the trait AGraph and the
     // associated type Node This is much cleaner. We only need to have one
@@ -513,9 +564,11 @@ fn seven() {
     // distance functions.
     // The signature for the distance function using the generic GGraph trait
     // does get a bit more concise using a trait object:
-    fn distance_c<N, E>(graph: &GGraph<N, E>, start: &N, end: &N) -> u32 {
-        // ...snip...
-        0
+    fn distance_c<N, E>(graph: &dyn GGraph<N, E>, start: &N, end: &N) -> Option<usize>
+    where
+        N: Copy + Eq + Hash,
+    {
+        hops(graph, *start, *end)
     }
     // Specifying the Edge type is still required, though.
     // It is possible in general to use trait objects of traits that have
@@ -523,11 +576,26 @@ fn seven() {
     // the trait's associated types in other arguments. We do, however, have to
     // specify the concrete types for the associated types in this case. Here,
     // we've chosen to accept types that implement the AGraph trait with the
-    // concrete type of usize as their Node type and a tuple of two usize values
-    // for their Edge type:
-    fn traverse(graph: &AGraph<Node = usize, Edge = (usize, usize)>) {
-        // ...snip...
+    // concrete type of usize as their Node type and a u32 (the weight of the
+    // edge) for their Edge type:
+    fn traverse(graph: &dyn AGraph<Node = usize, Edge = u32>) {
+        if graph.node_count() > 0 {
+            println!("from 0: {:?}", bfs(graph, 0).collect::<Vec<_>>());
+        }
     }
+
+    // A square with a diagonal: 0 - 1 - 2 - 3 - 0, and 0 - 2.
+    let mut square = AdjacencyList::with_nodes(4, false);
+    for &(a, b) in &[(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)] {
+        square.add_edge(a, b, 1u32);
+    }
+    println!(
+        "distance from 1 to 3: {:?}, {:?}, {:?}",
+        distance_a(&square, &1, &3),
+        distance_b(&square, &1, &3),
+        distance_c(&square, &1, &3)
+    );
+    traverse(&square);
 }
 
 fn eight() {
@@ -601,6 +669,8 @@ fn eight() {
     // parameterize the RHS type for Add since the default Self type is what we
     // want. If we want to implement adding Millimeters and Meters, then we need
     // to say impl Add<Meters> to set the value of the RHS type parameter.
+    // (The `units` module goes further: a macro writes these impls for every
+    // pair of compatible quantities.)
     // Default type parameters are used in two main ways:
     // - To extend a type without breaking existing code.
     // - To allow customization in a way most users don't want.
@@ -1138,6 +1208,12 @@ fn thirteen() {
     }
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "advanced",
+    description: "unsafe, advanced lifetimes, traits, types and functions",
+    run: sample,
+};
+
 #[allow(dead_code)]
 #[allow(unused_variables)]
 pub fn sample() {
@@ -8,6 +8,13 @@
 // Use pointers, an indirection like Box, Rc, or &. These are different "pointer
 // types" in Rust.
 
+use std::borrow::Borrow;
+use std::cmp::{self, Ordering};
+use std::collections::Bound;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::RangeBounds;
+
 // & is called a borrow in Rust speech. It's the most common of the three. It's
 // a reference to some place in memory, but it does not own the data it points
 // to. As such, the lifetime of the borrow depends on its owner. Therefore we
@@ -89,10 +96,485 @@ impl TreeBoxOpt {
         self.right = Some(Box::new(leaf));
         self
     }
+
+    // The number of nodes on the longest path from here down to a leaf.
+    fn height(&self) -> usize {
+        let l = self.left.as_ref().map_or(0, |l| l.height());
+        let r = self.right.as_ref().map_or(0, |r| r.height());
+        1 + cmp::max(l, r)
+    }
+
+    // Same idea of scripts/is_balanced.rs, comparing depths, but checked on
+    // every subtree: the heights of the two children of each node differ by
+    // at most one.
+    fn is_balanced(&self) -> bool {
+        let l = self.left.as_ref().map_or(0, |l| l.height());
+        let r = self.right.as_ref().map_or(0, |r| r.height());
+        (cmp::max(l, r) - cmp::min(l, r)) <= 1
+            && self.left.as_ref().is_none_or(|l| l.is_balanced())
+            && self.right.as_ref().is_none_or(|r| r.is_balanced())
+    }
+}
+
+// A TreeBoxOpt has to be assembled by hand, and nothing keeps it ordered or
+// balanced. AvlMap keeps the same shape (a key instead of the root value, and
+// two Option<Box<_>> children) but decides by itself where every node goes:
+// it's a binary search tree, and after every insertion and removal it rotates
+// the nodes that got out of balance, so that its height stays O(log n).
+//
+// - https://en.wikipedia.org/wiki/AVL_tree
+// - https://en.wikipedia.org/wiki/Tree_rotation
+type Link<K, V> = Option<Box<AvlNode<K, V>>>;
+
+#[derive(Debug)]
+struct AvlNode<K, V> {
+    key: K,
+    value: V,
+    // Cached height of the subtree rooted here, a leaf has height 1.
+    height: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+fn height<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |n| n.height)
+}
+
+impl<K, V> AvlNode<K, V> {
+    fn new(key: K, value: V) -> AvlNode<K, V> {
+        AvlNode {
+            key,
+            value,
+            height: 1,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn update_height(&mut self) {
+        self.height = 1 + cmp::max(height(&self.left), height(&self.right));
+    }
+
+    // Positive when the left subtree is the taller one.
+    fn balance_factor(&self) -> isize {
+        height(&self.left) as isize - height(&self.right) as isize
+    }
+
+    //       n             l
+    //      / \           / \
+    //     l   c   =>    a   n
+    //    / \               / \
+    //   a   b             b   c
+    fn rotate_right(mut self: Box<Self>) -> Box<Self> {
+        let mut l = self
+            .left
+            .take()
+            .expect("rotating right without a left child");
+        self.left = l.right.take();
+        self.update_height();
+        l.right = Some(self);
+        l.update_height();
+        l
+    }
+
+    //     n                 r
+    //    / \               / \
+    //   a   r     =>      n   c
+    //      / \           / \
+    //     b   c         a   b
+    fn rotate_left(mut self: Box<Self>) -> Box<Self> {
+        let mut r = self
+            .right
+            .take()
+            .expect("rotating left without a right child");
+        self.right = r.left.take();
+        self.update_height();
+        r.left = Some(self);
+        r.update_height();
+        r
+    }
+
+    // Called on the way back up from an insertion or a removal: the subtrees
+    // are balanced, but their heights may now differ by two.
+    fn rebalance(mut self: Box<Self>) -> Box<Self> {
+        self.update_height();
+        match self.balance_factor() {
+            2 => {
+                // left-right case: make it a left-left one first.
+                if self.left.as_ref().map_or(0, |l| l.balance_factor()) < 0 {
+                    self.left = self.left.take().map(|l| l.rotate_left());
+                }
+                self.rotate_right()
+            }
+            -2 => {
+                if self.right.as_ref().map_or(0, |r| r.balance_factor()) > 0 {
+                    self.right = self.right.take().map(|r| r.rotate_right());
+                }
+                self.rotate_left()
+            }
+            _ => self,
+        }
+    }
+}
+
+impl<K: Ord, V> AvlNode<K, V> {
+    fn insert(link: Link<K, V>, key: K, value: V, old: &mut Option<V>) -> Box<Self> {
+        let mut node = match link {
+            None => return Box::new(AvlNode::new(key, value)),
+            Some(node) => node,
+        };
+        match key.cmp(&node.key) {
+            Ordering::Less => node.left = Some(Self::insert(node.left.take(), key, value, old)),
+            Ordering::Greater => {
+                node.right = Some(Self::insert(node.right.take(), key, value, old))
+            }
+            Ordering::Equal => {
+                *old = Some(mem::replace(&mut node.value, value));
+                return node;
+            }
+        }
+        node.rebalance()
+    }
+
+    // Detach the smallest node of this subtree, returning what's left of the
+    // subtree and the node itself.
+    fn remove_min(mut self: Box<Self>) -> (Link<K, V>, Box<Self>) {
+        match self.left.take() {
+            None => (self.right.take(), self),
+            Some(left) => {
+                let (left, min) = left.remove_min();
+                self.left = left;
+                (Some(self.rebalance()), min)
+            }
+        }
+    }
+
+    fn remove<Q>(link: Link<K, V>, key: &Q, removed: &mut Option<V>) -> Link<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = link?;
+        match key.cmp(node.key.borrow()) {
+            Ordering::Less => node.left = Self::remove(node.left.take(), key, removed),
+            Ordering::Greater => node.right = Self::remove(node.right.take(), key, removed),
+            Ordering::Equal => {
+                let node = *node;
+                *removed = Some(node.value);
+                return match (node.left, node.right) {
+                    (None, child) | (child, None) => child,
+                    // Two children: the successor (the smallest key on the
+                    // right) takes the place of the removed node.
+                    (Some(left), Some(right)) => {
+                        let (right, mut successor) = right.remove_min();
+                        successor.left = Some(left);
+                        successor.right = right;
+                        Some(successor.rebalance())
+                    }
+                };
+            }
+        }
+        Some(node.rebalance())
+    }
+
+    // Every key of the subtree has to be strictly between lo and hi, when
+    // they are there.
+    fn is_balanced(&self, lo: Option<&K>, hi: Option<&K>) -> bool {
+        self.balance_factor().abs() <= 1
+            && self.height == 1 + cmp::max(height(&self.left), height(&self.right))
+            && lo.is_none_or(|lo| *lo < self.key)
+            && hi.is_none_or(|hi| self.key < *hi)
+            && self
+                .left
+                .as_ref()
+                .is_none_or(|l| l.is_balanced(lo, Some(&self.key)))
+            && self
+                .right
+                .as_ref()
+                .is_none_or(|r| r.is_balanced(Some(&self.key), hi))
+    }
+}
+
+/// An ordered map backed by an AVL tree.
+#[derive(Debug)]
+pub struct AvlMap<K, V> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V> AvlMap<K, V> {
+    pub fn new() -> AvlMap<K, V> {
+        AvlMap { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert a key-value pair, returning the previous value of the key if
+    /// it was already in the map.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let mut old = None;
+        self.root = Some(AvlNode::insert(self.root.take(), key, value, &mut old));
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(ref node) = *link {
+            match key.cmp(node.key.borrow()) {
+                Ordering::Less => link = &node.left,
+                Ordering::Greater => link = &node.right,
+                Ordering::Equal => return Some(&node.value),
+            }
+        }
+        None
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &mut self.root;
+        while let Some(ref mut node) = *link {
+            match key.cmp(node.key.borrow()) {
+                Ordering::Less => link = &mut node.left,
+                Ordering::Greater => link = &mut node.right,
+                Ordering::Equal => return Some(&mut node.value),
+            }
+        }
+        None
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Remove a key from the map, returning its value if it was there.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut removed = None;
+        self.root = AvlNode::remove(self.root.take(), key, &mut removed);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// The height of the tree: 0 when empty, 1 for a single node.
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    /// Check the AVL invariant (and the ordering of the keys) on every node.
+    pub fn is_balanced(&self) -> bool {
+        self.root.as_ref().is_none_or(|r| r.is_balanced(None, None))
+    }
+
+    /// Iterate the entries by ascending key (in-order traversal).
+    pub fn iter(&self) -> InOrder<'_, K, V> {
+        let mut it = InOrder { stack: Vec::new() };
+        it.push_left(&self.root);
+        it
+    }
+
+    /// Iterate the entries visiting every node before its children.
+    pub fn pre_order(&self) -> PreOrder<'_, K, V> {
+        PreOrder {
+            stack: self.root.iter().map(|n| &**n).collect(),
+        }
+    }
+
+    /// Iterate the entries visiting every node after its children.
+    pub fn post_order(&self) -> PostOrder<'_, K, V> {
+        PostOrder {
+            stack: self.root.iter().map(|n| (&**n, false)).collect(),
+        }
+    }
+
+    /// Iterate, by ascending key, the entries whose key is in the range.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        // Seed the stack with the path towards the first key in the range:
+        // every node at or after the start bound is pushed before going left.
+        let mut stack = Vec::new();
+        let mut link = &self.root;
+        while let Some(ref node) = *link {
+            let after_start = match range.start_bound() {
+                Bound::Included(start) => node.key >= *start,
+                Bound::Excluded(start) => node.key > *start,
+                Bound::Unbounded => true,
+            };
+            if after_start {
+                stack.push(&**node);
+                link = &node.left;
+            } else {
+                link = &node.right;
+            }
+        }
+        // And look for the last key in the range, where the iteration stops.
+        let mut last = None;
+        let mut link = &self.root;
+        while let Some(ref node) = *link {
+            let before_end = match range.end_bound() {
+                Bound::Included(end) => node.key <= *end,
+                Bound::Excluded(end) => node.key < *end,
+                Bound::Unbounded => true,
+            };
+            if before_end {
+                last = Some(&node.key);
+                link = &node.right;
+            } else {
+                link = &node.left;
+            }
+        }
+        // Nothing in the range: the first key after the start is past the
+        // end already, or there's none.
+        let empty = match (stack.last(), last) {
+            (Some(first), Some(last)) => first.key > *last,
+            _ => true,
+        };
+        if empty {
+            stack.clear();
+        }
+        Range {
+            inner: InOrder { stack },
+            last,
+        }
+    }
+}
+
+impl<K: Ord, V> Default for AvlMap<K, V> {
+    fn default() -> AvlMap<K, V> {
+        AvlMap::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for AvlMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> AvlMap<K, V> {
+        let mut map = AvlMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a AvlMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = InOrder<'a, K, V>;
+
+    fn into_iter(self) -> InOrder<'a, K, V> {
+        self.iter()
+    }
+}
+
+pub struct InOrder<'a, K: 'a, V: 'a> {
+    // The nodes whose left subtree has been (or is being) visited, but that
+    // haven't been yielded yet.
+    stack: Vec<&'a AvlNode<K, V>>,
+}
+
+impl<'a, K, V> InOrder<'a, K, V> {
+    fn push_left(&mut self, mut link: &'a Link<K, V>) {
+        while let Some(ref node) = *link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for InOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        Some((&node.key, &node.value))
+    }
+}
+
+pub struct PreOrder<'a, K: 'a, V: 'a> {
+    stack: Vec<&'a AvlNode<K, V>>,
+}
+
+impl<'a, K, V> Iterator for PreOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = self.stack.pop()?;
+        // Right first, so that the left subtree is popped (visited) first.
+        self.stack.extend(node.right.iter().map(|n| &**n));
+        self.stack.extend(node.left.iter().map(|n| &**n));
+        Some((&node.key, &node.value))
+    }
+}
+
+pub struct PostOrder<'a, K: 'a, V: 'a> {
+    // Every node is pushed twice: the first time it's popped its children get
+    // pushed, the second time it's yielded.
+    stack: Vec<(&'a AvlNode<K, V>, bool)>,
+}
+
+impl<'a, K, V> Iterator for PostOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            let (node, children_done) = self.stack.pop()?;
+            if children_done {
+                return Some((&node.key, &node.value));
+            }
+            self.stack.push((node, true));
+            self.stack.extend(node.right.iter().map(|n| (&**n, false)));
+            self.stack.extend(node.left.iter().map(|n| (&**n, false)));
+        }
+    }
+}
+
+pub struct Range<'a, K: 'a, V: 'a> {
+    inner: InOrder<'a, K, V>,
+    // The last key in the range, none when the range is empty.
+    last: Option<&'a K>,
+}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let (k, v) = self.inner.next()?;
+        match self.last {
+            Some(last) if k < last => {}
+            Some(last) if k == last => self.inner.stack.clear(),
+            _ => {
+                self.inner.stack.clear();
+                return None;
+            }
+        }
+        Some((k, v))
+    }
 }
 
 pub mod samples {
-    use super::TreeBoxOpt;
+    use super::{AvlMap, TreeBoxOpt};
 
     pub fn boxes() {
         let t: TreeBoxOpt = TreeBoxOpt {
@@ -132,4 +614,125 @@ pub mod samples {
                     .right(TreeBoxOpt::new(100)),
             );
     }
+
+    pub fn balanced() {
+        let t = TreeBoxOpt::new(15)
+            .left(TreeBoxOpt::new(12).right(TreeBoxOpt::new(13)))
+            .right(TreeBoxOpt::new(22));
+        println!("height: {}, balanced? {}", t.height(), t.is_balanced());
+        let t = t.right(TreeBoxOpt::new(22).right(TreeBoxOpt::new(100).left(TreeBoxOpt::new(50))));
+        println!("height: {}, balanced? {}", t.height(), t.is_balanced());
+    }
+
+    pub fn avl() {
+        // Sorted input is the worst case for a plain binary search tree: it
+        // degenerates into a list. The AVL tree keeps rotating instead.
+        let mut map: AvlMap<u32, String> = (1..16).map(|k| (k, format!("#{}", k))).collect();
+        println!("{} entries, height {}", map.len(), map.height());
+        map.remove(&8);
+        map.insert(42, String::from("answer"));
+        let keys: Vec<&u32> = map.range(4..13).map(|(k, _)| k).collect();
+        println!("keys in 4..13: {:?}", keys);
+        let keys: Vec<&u32> = map.pre_order().map(|(k, _)| k).collect();
+        println!("pre-order: {:?}", keys);
+        println!("balanced? {}", map.is_balanced());
+    }
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "boxes_and_trees",
+    description: "binary trees with Box, Rc and Option<Box>, and an AVL ordered map",
+    run: sample,
+};
+
+pub fn sample() {
+    samples::boxes();
+    samples::left_right();
+    samples::balanced();
+    samples::avl();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AvlMap;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn traversals() {
+        //       4
+        //     /   \
+        //    2     6
+        //   / \   / \
+        //  1   3 5   7
+        let map: AvlMap<u32, ()> = (1..8).map(|k| (k, ())).collect();
+        let keys =
+            |it: &mut dyn Iterator<Item = (&u32, &())>| it.map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(map.height(), 3);
+        assert_eq!(keys(&mut map.iter()), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(keys(&mut map.pre_order()), vec![4, 2, 1, 3, 6, 5, 7]);
+        assert_eq!(keys(&mut map.post_order()), vec![1, 3, 2, 5, 7, 6, 4]);
+        assert_eq!(keys(&mut map.range(3..6)), vec![3, 4, 5]);
+        assert_eq!(keys(&mut map.range(..=2)), vec![1, 2]);
+        assert_eq!(keys(&mut map.range(6..)), vec![6, 7]);
+        assert_eq!(keys(&mut map.range(8..)), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn empty_ranges() {
+        let keys =
+            |it: &mut dyn Iterator<Item = (&u32, &())>| it.map(|(k, _)| *k).collect::<Vec<_>>();
+        let map: AvlMap<u32, ()> = (1..8).map(|k| (k, ())).collect();
+        assert!(keys(&mut map.range(3..3)).is_empty());
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = keys(&mut map.range(5..3));
+        assert!(inverted.is_empty());
+        assert!(keys(&mut map.range(..1)).is_empty());
+        assert_eq!(keys(&mut map.range(3..=3)), vec![3]);
+
+        // No key between the bounds, but keys on both sides of them.
+        let map: AvlMap<u32, ()> = vec![(1, ()), (10, ())].into_iter().collect();
+        assert!(keys(&mut map.range(5..8)).is_empty());
+        assert!(keys(&mut map.range(2..=9)).is_empty());
+        assert_eq!(keys(&mut map.range(1..=10)), vec![1, 10]);
+
+        let map: AvlMap<u32, ()> = AvlMap::new();
+        assert!(keys(&mut map.range(..)).is_empty());
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = AvlMap::new();
+        assert_eq!(map.insert("b", 2), None);
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("b", 20), Some(2));
+        assert_eq!(map.get("b"), Some(&20));
+        *map.get_mut("a").unwrap() += 10;
+        assert_eq!(map.remove("a"), Some(11));
+        assert_eq!(map.remove("a"), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    // Thousands of random inserts and removals, checking the AVL invariant
+    // after each of them and the content against a BTreeMap.
+    #[test]
+    fn stays_balanced() {
+        let mut rng = XorShiftRng::from_seed([7, 11, 13, 17]);
+        let mut map = AvlMap::new();
+        let mut reference = BTreeMap::new();
+        for i in 0..5000 {
+            let key: u16 = rng.gen_range(0, 1000);
+            if rng.gen_weighted_bool(3) {
+                assert_eq!(map.remove(&key), reference.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i), reference.insert(key, i));
+            }
+            assert!(map.is_balanced());
+            assert_eq!(map.len(), reference.len());
+        }
+        assert!(map.iter().eq(reference.iter()));
+        // An AVL tree is never taller than ~1.44 log2(n + 2).
+        let bound = 1.45 * ((map.len() + 2) as f64).log2();
+        assert!((map.height() as f64) < bound);
+    }
 }
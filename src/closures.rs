@@ -115,6 +115,12 @@ fn generate_workout(intensity: u32, random_number: u32) {
     }
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "closures",
+    description: "closures, capturing the environment and memoization",
+    run: sample,
+};
+
 pub fn sample() {
     let simulated_user_specified_value = 10;
     let simulated_random_number = 7;
@@ -282,6 +282,42 @@ fn eight() {
     // just useful for enforcing concurrency-related invariants.
 }
 
+// A thread pool instead of a thread per job: projects/executor keeps a deque
+// per worker, and idle workers steal from the busy ones.
+fn nine() {
+    use executor::Executor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let pool = Executor::new(4);
+
+    // Every job has a handle to wait for its result, like thread::spawn.
+    // A panic is caught and ends up in the handle, the worker survives it.
+    let squares: Vec<_> = (1..5).map(|i| pool.execute(move || i * i)).collect();
+    let oops = pool.execute(|| -> i32 { panic!("oops") });
+    let squares: Vec<i32> = squares.into_iter().map(|h| h.join().unwrap()).collect();
+    println!("squares = {:?}, then {}", squares, oops.join().unwrap_err());
+
+    // Scoped jobs can borrow from the stack: no Arc is needed for the counter
+    // (and no Mutex either, an atomic is enough for a sum).
+    let numbers: Vec<usize> = (1..=100).collect();
+    let counter = AtomicUsize::new(0);
+    pool.scope(|s| {
+        for chunk in numbers.chunks(10) {
+            let counter = &counter;
+            s.execute(move || counter.fetch_add(chunk.iter().sum(), Ordering::SeqCst));
+        }
+    });
+    println!("Result: {}", counter.into_inner());
+
+    pool.shutdown();
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "concurrency",
+    description: "threads, channels, Mutex and Arc",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -291,4 +327,5 @@ pub fn sample() {
     six();
     seven();
     eight();
+    nine();
 }
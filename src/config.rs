@@ -0,0 +1,95 @@
+// Configuration files, with the `conf` library of projects/conf.
+//
+// The format is the one of `file.conf` (and of the Rosetta Code task), with
+// INI sections: keys are case insensitive, the '=' is optional, a key with no
+// value is a flag, commas separate the values of a list. Values come out
+// typed, or a whole file comes out as a struct with serde; errors say on which
+// line and column the value at fault is.
+//
+// A Config keeps the comments, and the layout of the lines that didn't
+// change: a program can change a setting and write the file back without
+// losing what the user wrote in it.
+
+use conf::Config;
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "config",
+    description: "configuration files: typed values, serde and write-back",
+    run: sample,
+};
+
+const FILE: &str = "\
+# The user's favourite things
+FULLNAME Foo Barber
+FAVOURITEFRUIT = banana   # for now
+NEEDSPEELING
+; SEEDSREMOVED
+OTHERFAMILY Rhu Barber, Harry Barber
+
+[shop]
+opens = 9
+motto = \"fresh, and cheap\"
+";
+
+#[derive(Deserialize)]
+struct Shop {
+    opens: u8,
+    closes: Option<u8>,
+    motto: String,
+}
+
+#[derive(Deserialize)]
+struct Settings {
+    fullname: String,
+    favouritefruit: String,
+    needspeeling: bool,
+    seedsremoved: bool,
+    otherfamily: Vec<String>,
+    shop: Shop,
+}
+
+pub fn sample() {
+    let mut config: Config = FILE.parse().unwrap();
+
+    // One value at a time, of the type asked for.
+    let name: String = config.get("fullname").unwrap();
+    let peeling: bool = config.get("NeedsPeeling").unwrap();
+    let seeds: bool = config.get("seedsremoved").unwrap();
+    let family: Vec<String> = config.get("otherfamily").unwrap();
+    println!("{}: peeling {}, seeds removed {}", name, peeling, seeds);
+    println!("family: {:?}", family);
+    let shop = config.section("SHOP").unwrap();
+    println!(
+        "[{}] opens at {}",
+        shop.name().unwrap(),
+        shop.get::<u8>("opens").unwrap()
+    );
+    match shop.get::<u8>("motto") {
+        Ok(motto) => println!("motto: {}", motto),
+        Err(e) => println!("error: {}", e),
+    }
+
+    // All of them at once.
+    let settings: Settings = config.deserialize().unwrap();
+    println!(
+        "{} likes {}, peeling {}, seeds removed {}, family {:?}",
+        settings.fullname,
+        settings.favouritefruit,
+        settings.needspeeling,
+        settings.seedsremoved,
+        settings.otherfamily
+    );
+    let shop = settings.shop;
+    println!("shop: {} to {:?}, {}", shop.opens, shop.closes, shop.motto);
+
+    // The comments are still there when the file is written back.
+    config.set(None, "favouritefruit", &["kiwi"]);
+    config.set(Some("shop"), "closes", &["19"]);
+    config.remove(None, "needspeeling");
+    print!("{}", config);
+
+    match "[shop\nopens = 9\n".parse::<Config>() {
+        Ok(_) => println!("parsed?"),
+        Err(e) => println!("error: {}", e),
+    }
+}
@@ -97,6 +97,12 @@ fn five() {
     m.call();
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "enums",
+    description: "enums, Option and match",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -0,0 +1,330 @@
+use super::parser::{BinaryOp, ExprKind, UnaryOp};
+use super::{Context, Error, ErrorKind, Expr, Parser, Span};
+use std::collections::HashMap;
+use std::f64::consts;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+}
+
+impl Value {
+    fn type_name(self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Bool(_) => "boolean",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// How many arguments a function takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    fn accepts(self, n: usize) -> bool {
+        match self {
+            Arity::Exactly(m) => n == m,
+            Arity::AtLeast(m) => n >= m,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (prefix, n) = match *self {
+            Arity::Exactly(n) => ("", n),
+            Arity::AtLeast(n) => ("at least ", n),
+        };
+        let s = if n == 1 { "" } else { "s" };
+        write!(f, "{}{} argument{}", prefix, n, s)
+    }
+}
+
+struct Function {
+    name: &'static str,
+    arity: Arity,
+    call: fn(&[f64]) -> f64,
+}
+
+const FUNCTIONS: &[Function] = &[
+    Function {
+        name: "abs",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].abs(),
+    },
+    Function {
+        name: "sqrt",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].sqrt(),
+    },
+    Function {
+        name: "exp",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].exp(),
+    },
+    Function {
+        name: "ln",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].ln(),
+    },
+    Function {
+        name: "sin",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].sin(),
+    },
+    Function {
+        name: "cos",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].cos(),
+    },
+    Function {
+        name: "floor",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].floor(),
+    },
+    Function {
+        name: "ceil",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].ceil(),
+    },
+    Function {
+        name: "round",
+        arity: Arity::Exactly(1),
+        call: |x| x[0].round(),
+    },
+    Function {
+        name: "hypot",
+        arity: Arity::Exactly(2),
+        call: |x| x[0].hypot(x[1]),
+    },
+    Function {
+        name: "min",
+        arity: Arity::AtLeast(1),
+        call: |x| x.iter().cloned().fold(x[0], f64::min),
+    },
+    Function {
+        name: "max",
+        arity: Arity::AtLeast(1),
+        call: |x| x.iter().cloned().fold(x[0], f64::max),
+    },
+];
+
+/// The variables, that outlive the lines that set them: unlike the syntax
+/// tree, the environment owns its names.
+#[derive(Debug, Clone)]
+pub struct Env {
+    variables: HashMap<String, Value>,
+}
+
+impl Env {
+    /// An environment with `pi` and `e`.
+    pub fn new() -> Env {
+        let mut variables = HashMap::new();
+        variables.insert(String::from("pi"), Value::Number(consts::PI));
+        variables.insert(String::from("e"), Value::Number(consts::E));
+        Env { variables }
+    }
+
+    /// The variables, sorted by name.
+    pub fn variables(&self) -> Vec<(&str, Value)> {
+        let mut variables: Vec<_> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+            .collect();
+        variables.sort_by(|a, b| a.0.cmp(b.0));
+        variables
+    }
+
+    pub fn functions() -> Vec<&'static str> {
+        FUNCTIONS.iter().map(|f| f.name).collect()
+    }
+
+    /// Parse and evaluate the input. Assignments are kept for the next
+    /// inputs.
+    pub fn eval<'s>(&mut self, input: &'s str) -> Result<Value, Error<'s>> {
+        let context = Context(input);
+        let expr = Parser::new(&context).parse()?;
+        self.evaluate(&context, &expr)
+    }
+
+    fn evaluate<'s>(&mut self, context: &Context<'s>, expr: &Expr<'s>) -> Result<Value, Error<'s>> {
+        match expr.kind {
+            ExprKind::Number(n) => Ok(Value::Number(n)),
+            ExprKind::Bool(b) => Ok(Value::Bool(b)),
+            ExprKind::Var(name) => match self.variables.get(name) {
+                Some(&value) => Ok(value),
+                None => Err(context.error(ErrorKind::UnknownVariable(name), expr.span)),
+            },
+            ExprKind::Unary(UnaryOp::Neg, ref e) => Ok(Value::Number(-self.number(context, e)?)),
+            ExprKind::Unary(UnaryOp::Not, ref e) => Ok(Value::Bool(!self.boolean(context, e)?)),
+            // The right operand of && and || is only evaluated when needed.
+            ExprKind::Binary(BinaryOp::And, ref l, ref r) => Ok(Value::Bool(
+                self.boolean(context, l)? && self.boolean(context, r)?,
+            )),
+            ExprKind::Binary(BinaryOp::Or, ref l, ref r) => Ok(Value::Bool(
+                self.boolean(context, l)? || self.boolean(context, r)?,
+            )),
+            ExprKind::Binary(op @ BinaryOp::Eq, ref l, ref r)
+            | ExprKind::Binary(op @ BinaryOp::Ne, ref l, ref r) => {
+                let a = self.evaluate(context, l)?;
+                let b = self.evaluate(context, r)?;
+                if a.type_name() != b.type_name() {
+                    let kind = ErrorKind::TypeMismatch {
+                        expected: a.type_name(),
+                        found: b.type_name(),
+                    };
+                    return Err(context.error(kind, r.span));
+                }
+                Ok(Value::Bool((a == b) == (op == BinaryOp::Eq)))
+            }
+            ExprKind::Binary(op, ref l, ref r) => {
+                let a = self.number(context, l)?;
+                let b = self.number(context, r)?;
+                if (op == BinaryOp::Div || op == BinaryOp::Rem) && b == 0.0 {
+                    return Err(context.error(ErrorKind::DivisionByZero, r.span));
+                }
+                Ok(match op {
+                    BinaryOp::Add => Value::Number(a + b),
+                    BinaryOp::Sub => Value::Number(a - b),
+                    BinaryOp::Mul => Value::Number(a * b),
+                    BinaryOp::Div => Value::Number(a / b),
+                    BinaryOp::Rem => Value::Number(a % b),
+                    BinaryOp::Pow => Value::Number(a.powf(b)),
+                    BinaryOp::Lt => Value::Bool(a < b),
+                    BinaryOp::Le => Value::Bool(a <= b),
+                    BinaryOp::Gt => Value::Bool(a > b),
+                    BinaryOp::Ge => Value::Bool(a >= b),
+                    BinaryOp::And | BinaryOp::Or | BinaryOp::Eq | BinaryOp::Ne => unreachable!(),
+                })
+            }
+            ExprKind::Conditional(ref condition, ref then, ref otherwise) => {
+                if self.boolean(context, condition)? {
+                    self.evaluate(context, then)
+                } else {
+                    self.evaluate(context, otherwise)
+                }
+            }
+            ExprKind::Call(name, ref args) => {
+                let span = Span {
+                    start: expr.span.start,
+                    end: expr.span.start + name.len(),
+                };
+                let function = match FUNCTIONS.iter().find(|f| f.name == name) {
+                    Some(function) => function,
+                    None => return Err(context.error(ErrorKind::UnknownFunction(name), span)),
+                };
+                if !function.arity.accepts(args.len()) {
+                    let kind = ErrorKind::WrongArity {
+                        function: name,
+                        expected: function.arity,
+                        found: args.len(),
+                    };
+                    return Err(context.error(kind, expr.span));
+                }
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.number(context, arg)?);
+                }
+                Ok(Value::Number((function.call)(&values)))
+            }
+            ExprKind::Assign(name, ref e) => {
+                let value = self.evaluate(context, e)?;
+                self.variables.insert(name.to_string(), value);
+                Ok(value)
+            }
+        }
+    }
+
+    fn number<'s>(&mut self, context: &Context<'s>, expr: &Expr<'s>) -> Result<f64, Error<'s>> {
+        match self.evaluate(context, expr)? {
+            Value::Number(n) => Ok(n),
+            other => Err(context.error(
+                ErrorKind::TypeMismatch {
+                    expected: "number",
+                    found: other.type_name(),
+                },
+                expr.span,
+            )),
+        }
+    }
+
+    fn boolean<'s>(&mut self, context: &Context<'s>, expr: &Expr<'s>) -> Result<bool, Error<'s>> {
+        match self.evaluate(context, expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(context.error(
+                ErrorKind::TypeMismatch {
+                    expected: "boolean",
+                    found: other.type_name(),
+                },
+                expr.span,
+            )),
+        }
+    }
+}
+
+impl Default for Env {
+    fn default() -> Env {
+        Env::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_and_remembers() {
+        let mut env = Env::new();
+        assert_eq!(env.eval("x = 2 ^ 3 ^ 2 / 8"), Ok(Value::Number(64.0)));
+        assert_eq!(env.eval("-x % 10 + max(1, x, 3)"), Ok(Value::Number(60.0)));
+        assert_eq!(
+            env.eval("x > 10 && !(x == 3) ? hypot(3, 4) : 0"),
+            Ok(Value::Number(5.0))
+        );
+        assert_eq!(env.eval("true != false"), Ok(Value::Bool(true)));
+        // Short circuits: no division by zero.
+        assert_eq!(env.eval("x < 0 && 1 / 0 > 0"), Ok(Value::Bool(false)));
+        assert_eq!(env.eval("round(e * 100) / 100"), Ok(Value::Number(2.72)));
+        let names: Vec<&str> = env.variables().iter().map(|v| v.0).collect();
+        assert_eq!(names, vec!["e", "pi", "x"]);
+    }
+
+    #[test]
+    fn errors_borrow_the_input() {
+        let mut env = Env::new();
+        let cases = vec![
+            ("1 + y", "y"),
+            ("2 * nope(1)", "nope"),
+            ("min()", "min()"),
+            ("1 / (pi - pi)", "(pi - pi)"),
+            ("1 + true", "true"),
+            ("1 == (2 > 1)", "(2 > 1)"),
+            ("!2 || true", "2"),
+        ];
+        for (input, fragment) in cases {
+            let e = env.eval(input).unwrap_err();
+            assert_eq!(e.fragment(), fragment, "{}", input);
+        }
+        let e = env.eval("hypot(1)").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "'hypot' takes 2 arguments, 1 given\n    hypot(1)\n    ^^^^^^^^"
+        );
+    }
+}
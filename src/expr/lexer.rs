@@ -0,0 +1,190 @@
+use super::{Error, ErrorKind, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Number,
+    Ident,
+    True,
+    False,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Bang,
+    AndAnd,
+    OrOr,
+    EqEq,
+    NotEq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Question,
+    Colon,
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// A token and the slice of the input it has been read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'s> {
+    pub kind: TokenKind,
+    pub text: &'s str,
+    pub span: Span,
+}
+
+/// The tokens of the input, in order. After an error it stops.
+pub struct Lexer<'s> {
+    input: &'s str,
+    position: usize,
+}
+
+impl<'s> Lexer<'s> {
+    pub fn new(input: &'s str) -> Lexer<'s> {
+        Lexer { input, position: 0 }
+    }
+
+    fn rest(&self) -> &'s str {
+        &self.input[self.position..]
+    }
+
+    // The length of the longest prefix of the rest whose chars satisfy `f`.
+    fn prefix<F: Fn(char) -> bool>(&self, f: F) -> usize {
+        self.rest()
+            .find(|c| !f(c))
+            .unwrap_or_else(|| self.rest().len())
+    }
+
+    // Digits, then maybe a fraction, then maybe an exponent: "12", "1.5",
+    // "2.", "3e-2". The exponent is only taken if there's a digit in it.
+    fn number(&self) -> usize {
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let rest = self.rest();
+        let mut len = digits(rest);
+        if rest[len..].starts_with('.') {
+            len += 1 + digits(&rest[len + 1..]);
+        }
+        let exponent = &rest[len..];
+        if exponent.starts_with('e') || exponent.starts_with('E') {
+            let sign = if exponent[1..].starts_with('+') || exponent[1..].starts_with('-') {
+                1
+            } else {
+                0
+            };
+            let n = digits(&exponent[1 + sign..]);
+            if n > 0 {
+                len += 1 + sign + n;
+            }
+        }
+        len
+    }
+}
+
+impl<'s> Iterator for Lexer<'s> {
+    type Item = Result<Token<'s>, Error<'s>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.position += self.prefix(char::is_whitespace);
+        let rest = self.rest();
+        let c = rest.chars().next()?;
+        let two = |second, long, short| {
+            if rest[1..].starts_with(second) {
+                (long, 2)
+            } else {
+                (short, 1)
+            }
+        };
+        let (kind, len) = match c {
+            '0'..='9' => (TokenKind::Number, self.number()),
+            c if c.is_alphabetic() || c == '_' => {
+                let len = self.prefix(|c| c.is_alphanumeric() || c == '_');
+                match &rest[..len] {
+                    "true" => (TokenKind::True, len),
+                    "false" => (TokenKind::False, len),
+                    _ => (TokenKind::Ident, len),
+                }
+            }
+            '+' => (TokenKind::Plus, 1),
+            '-' => (TokenKind::Minus, 1),
+            '*' => (TokenKind::Star, 1),
+            '/' => (TokenKind::Slash, 1),
+            '%' => (TokenKind::Percent, 1),
+            '^' => (TokenKind::Caret, 1),
+            '?' => (TokenKind::Question, 1),
+            ':' => (TokenKind::Colon, 1),
+            '(' => (TokenKind::LParen, 1),
+            ')' => (TokenKind::RParen, 1),
+            ',' => (TokenKind::Comma, 1),
+            '!' => two('=', TokenKind::NotEq, TokenKind::Bang),
+            '=' => two('=', TokenKind::EqEq, TokenKind::Eq),
+            '<' => two('=', TokenKind::Le, TokenKind::Lt),
+            '>' => two('=', TokenKind::Ge, TokenKind::Gt),
+            '&' if rest[1..].starts_with('&') => (TokenKind::AndAnd, 2),
+            '|' if rest[1..].starts_with('|') => (TokenKind::OrOr, 2),
+            c => {
+                let span = Span {
+                    start: self.position,
+                    end: self.position + c.len_utf8(),
+                };
+                // Nothing sensible can follow.
+                self.position = self.input.len();
+                return Some(Err(Error {
+                    kind: ErrorKind::UnexpectedChar(c),
+                    span,
+                    input: self.input,
+                }));
+            }
+        };
+        let span = Span {
+            start: self.position,
+            end: self.position + len,
+        };
+        self.position += len;
+        Some(Ok(Token {
+            kind,
+            text: &rest[..len],
+            span,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<(TokenKind, &str)> {
+        Lexer::new(input)
+            .map(|t| t.map(|t| (t.kind, t.text)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn tokens_are_slices_of_the_input() {
+        use self::TokenKind::*;
+        assert_eq!(
+            kinds("x1 >= 2.5e-3&&!true"),
+            vec![
+                (Ident, "x1"),
+                (Ge, ">="),
+                (Number, "2.5e-3"),
+                (AndAnd, "&&"),
+                (Bang, "!"),
+                (True, "true"),
+            ]
+        );
+        assert_eq!(
+            kinds("2e x"),
+            vec![(Number, "2"), (Ident, "e"), (Ident, "x")]
+        );
+        assert_eq!(kinds("   "), vec![]);
+
+        let errors: Vec<_> = Lexer::new("1 & 2").filter_map(|t| t.err()).collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UnexpectedChar('&'));
+        assert_eq!(errors[0].span, Span { start: 2, end: 3 });
+    }
+}
@@ -0,0 +1,273 @@
+// A small language of arithmetic and boolean expressions, grown out of the
+// Context and Parser of `advanced::four`.
+//
+//     r = 2
+//     area = pi * r ^ 2
+//     area > 10 && max(r, 3) == 3 ? area : -1
+//
+// The lexer doesn't copy anything: tokens, names and errors are slices of the
+// input. That is the lesson about lifetime subtyping again: a `Parser<'c, 's>`
+// borrows the `Context` for 'c, but what it returns borrows the input for 's,
+// so it can outlive both the parser and the context (see `parse_context`).
+
+mod eval;
+mod lexer;
+mod parser;
+
+pub use self::eval::{Arity, Env};
+pub use self::parser::{Expr, Parser};
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+
+/// The input to parse.
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'s>(pub &'s str);
+
+impl<'s> Context<'s> {
+    fn error(&self, kind: ErrorKind<'s>, span: Span) -> Error<'s> {
+        Error {
+            kind,
+            span,
+            input: self.0,
+        }
+    }
+}
+
+/// The context is gone by the time this returns, and so is the parser: the
+/// expression (or the error) only borrows the string slice.
+pub fn parse_context(context: Context) -> Result<Expr, Error> {
+    Parser::new(&context).parse()
+}
+
+/// A range of byte offsets into the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// From the start of this span to the end of the other one.
+    fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind<'s> {
+    UnexpectedChar(char),
+    UnexpectedToken {
+        found: &'s str,
+        expected: &'static str,
+    },
+    UnexpectedEnd {
+        expected: &'static str,
+    },
+    InvalidAssignment,
+    UnknownVariable(&'s str),
+    UnknownFunction(&'s str),
+    WrongArity {
+        function: &'s str,
+        expected: Arity,
+        found: usize,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    DivisionByZero,
+}
+
+impl<'s> fmt::Display for ErrorKind<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            ErrorKind::UnexpectedToken { found, expected } => {
+                write!(f, "expected {}, found '{}'", expected, found)
+            }
+            ErrorKind::UnexpectedEnd { expected } => {
+                write!(f, "expected {}, found the end of the input", expected)
+            }
+            ErrorKind::InvalidAssignment => write!(f, "only a variable can be assigned to"),
+            ErrorKind::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            ErrorKind::UnknownFunction(name) => write!(f, "unknown function '{}'", name),
+            ErrorKind::WrongArity {
+                function,
+                expected,
+                found,
+            } => write!(f, "'{}' takes {}, {} given", function, expected, found),
+            ErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "expected a {}, found a {}", expected, found)
+            }
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+/// What went wrong, and where: the span points into the input, that the
+/// error borrows to show it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error<'s> {
+    pub kind: ErrorKind<'s>,
+    pub span: Span,
+    input: &'s str,
+}
+
+impl<'s> Error<'s> {
+    /// The part of the input the error is about (empty at the end of it).
+    pub fn fragment(&self) -> &'s str {
+        &self.input[self.span.start..self.span.end]
+    }
+}
+
+// The line of the input with the error, and carets under the span.
+impl<'s> fmt::Display for Error<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = self.input[..self.span.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let end = self.input[self.span.start..]
+            .find('\n')
+            .map_or(self.input.len(), |i| self.span.start + i);
+        let column = self.input[start..self.span.start].chars().count();
+        let width = self.input[self.span.start..self.span.end.min(end)]
+            .chars()
+            .count()
+            .max(1);
+        write!(
+            f,
+            "{}\n    {}\n    {}{}",
+            self.kind,
+            &self.input[start..end],
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl<'s> error::Error for Error<'s> {}
+
+pub const USAGE: &str = "    fungi repl [<file>]
+                                evaluate expressions one line at a time, from the
+                                file or interactively; 'x = 1' sets a variable";
+
+/// A parsed `fungi repl` command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub file: Option<String>,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    match args {
+        [] => Ok(Invocation { file: None }),
+        [file] => Ok(Invocation {
+            file: Some(file.clone()),
+        }),
+        _ => Err(String::from("repl takes at most one file")),
+    }
+}
+
+const HELP: &str = "numbers, true and false, variables and function calls
+operators: ^  - !  * / %  + -  < <= > >=  == !=  &&  ||  ?:  =
+commands: :vars, :functions, :help, :quit";
+
+/// A file is evaluated up to the first error. Interactively, errors are just
+/// printed.
+pub fn run(invocation: &Invocation) -> Result<(), String> {
+    let mut env = Env::new();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match invocation.file {
+        Some(ref path) => {
+            let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+            for (n, line) in BufReader::new(file).lines().enumerate() {
+                let line = line.map_err(|e| format!("{}: {}", path, e))?;
+                if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                    continue;
+                }
+                let value = env
+                    .eval(&line)
+                    .map_err(|e| format!("{}:{}: {}", path, n + 1, e))?;
+                writeln!(out, "{}", value).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+        None => {
+            let stdin = io::stdin();
+            let mut lines = stdin.lock().lines();
+            loop {
+                write!(out, "> ")
+                    .and_then(|_| out.flush())
+                    .map_err(|e| e.to_string())?;
+                let line = match lines.next() {
+                    Some(line) => line.map_err(|e| e.to_string())?,
+                    None => return Ok(()),
+                };
+                match line.trim() {
+                    l if l.is_empty() || l.starts_with('#') => {}
+                    ":quit" | ":q" => return Ok(()),
+                    ":help" => writeln!(out, "{}", HELP).map_err(|e| e.to_string())?,
+                    ":vars" => {
+                        for (name, value) in env.variables() {
+                            writeln!(out, "{} = {}", name, value).map_err(|e| e.to_string())?;
+                        }
+                    }
+                    ":functions" => writeln!(out, "{}", Env::functions().join(", "))
+                        .map_err(|e| e.to_string())?,
+                    _ => match env.eval(&line) {
+                        Ok(value) => writeln!(out, "{}", value).map_err(|e| e.to_string())?,
+                        Err(e) => writeln!(out, "error: {}", e).map_err(|e| e.to_string())?,
+                    },
+                }
+            }
+        }
+    }
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "expr",
+    description: "a tokenizer, a Pratt parser and an evaluator that borrow their input",
+    run: sample,
+};
+
+pub fn sample() {
+    // The input is dropped last: the error can outlive the context and the
+    // parser that produced it.
+    let input = String::from("2 * (3 + 4");
+    let error = parse_context(Context(&input)).unwrap_err();
+    println!("{}", error);
+
+    // Precedence and associativity, made visible.
+    for input in &[
+        "1 + 2 * 3 ^ 2 ^ 2",
+        "-2 ^ 2",
+        "!a || b && c == d",
+        "f(1, g(x)) ? y : z",
+    ] {
+        let expr = parse_context(Context(input)).unwrap();
+        println!("{:20} => {}", input, expr);
+    }
+
+    let mut env = Env::new();
+    for line in &[
+        "r = 2",
+        "area = pi * r ^ 2",
+        "max(area, 10) / r",
+        "area > 10 && r % 2 == 0 ? area : -1",
+        "r == 2 || 1 / 0 > 1",
+        "1 / (r - 2)",
+        "sqrt(r, 2)",
+        "!r",
+    ] {
+        match env.eval(line) {
+            Ok(value) => println!("{} => {}", line, value),
+            Err(e) => println!("{} => '{}': {}", line, e.fragment(), e),
+        }
+    }
+}
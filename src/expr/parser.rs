@@ -0,0 +1,371 @@
+use super::lexer::{Lexer, Token, TokenKind};
+use super::{Context, Error, ErrorKind, Span};
+use std::fmt;
+use std::iter::Peekable;
+
+/// A node of the syntax tree, and the part of the input it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr<'s> {
+    pub kind: ExprKind<'s>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind<'s> {
+    Number(f64),
+    Bool(bool),
+    Var(&'s str),
+    Unary(UnaryOp, Box<Expr<'s>>),
+    Binary(BinaryOp, Box<Expr<'s>>, Box<Expr<'s>>),
+    /// `condition ? then : otherwise`
+    Conditional(Box<Expr<'s>>, Box<Expr<'s>>, Box<Expr<'s>>),
+    Call(&'s str, Vec<Expr<'s>>),
+    Assign(&'s str, Box<Expr<'s>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    fn from_token(kind: TokenKind) -> Option<BinaryOp> {
+        match kind {
+            TokenKind::Plus => Some(BinaryOp::Add),
+            TokenKind::Minus => Some(BinaryOp::Sub),
+            TokenKind::Star => Some(BinaryOp::Mul),
+            TokenKind::Slash => Some(BinaryOp::Div),
+            TokenKind::Percent => Some(BinaryOp::Rem),
+            TokenKind::Caret => Some(BinaryOp::Pow),
+            TokenKind::EqEq => Some(BinaryOp::Eq),
+            TokenKind::NotEq => Some(BinaryOp::Ne),
+            TokenKind::Lt => Some(BinaryOp::Lt),
+            TokenKind::Le => Some(BinaryOp::Le),
+            TokenKind::Gt => Some(BinaryOp::Gt),
+            TokenKind::Ge => Some(BinaryOp::Ge),
+            TokenKind::AndAnd => Some(BinaryOp::And),
+            TokenKind::OrOr => Some(BinaryOp::Or),
+            _ => None,
+        }
+    }
+
+    // How strongly the operator holds its left and right operands. Left
+    // associative operators hold the right one more strongly, `^` is right
+    // associative.
+    fn binding_power(self) -> (u8, u8) {
+        match self {
+            BinaryOp::Or => (5, 6),
+            BinaryOp::And => (7, 8),
+            BinaryOp::Eq | BinaryOp::Ne => (9, 10),
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => (11, 12),
+            BinaryOp::Add | BinaryOp::Sub => (13, 14),
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => (15, 16),
+            BinaryOp::Pow => (20, 19),
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Pow => "^",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+}
+
+// Between `=` and `?:` (both right associative) and the binary operators.
+const ASSIGN: (u8, u8) = (2, 1);
+const CONDITIONAL: (u8, u8) = (4, 3);
+// `-2 ^ 2` is `-(2 ^ 2)`, but `-2 * 2` is `(-2) * 2`.
+const PREFIX: u8 = 17;
+
+// Fully parenthesized, in prefix notation: `1 + 2 * x` is `(+ 1 (* 2 x))`.
+impl<'s> fmt::Display for Expr<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ExprKind::Number(n) => write!(f, "{}", n),
+            ExprKind::Bool(b) => write!(f, "{}", b),
+            ExprKind::Var(name) => write!(f, "{}", name),
+            ExprKind::Unary(UnaryOp::Neg, ref e) => write!(f, "(- {})", e),
+            ExprKind::Unary(UnaryOp::Not, ref e) => write!(f, "(! {})", e),
+            ExprKind::Binary(op, ref l, ref r) => write!(f, "({} {} {})", op.symbol(), l, r),
+            ExprKind::Conditional(ref c, ref t, ref e) => write!(f, "(? {} {} {})", c, t, e),
+            ExprKind::Call(name, ref args) => {
+                write!(f, "({}", name)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Assign(name, ref e) => write!(f, "(= {} {})", name, e),
+        }
+    }
+}
+
+/// A Pratt parser: every operator has a binding power, and an operand goes
+/// with the operator that holds it more strongly.
+///
+/// As in `advanced::four`, 's outlives 'c: the parser holds the context for
+/// a while, the results hold the input for longer.
+pub struct Parser<'c, 's: 'c> {
+    context: &'c Context<'s>,
+    tokens: Peekable<Lexer<'s>>,
+}
+
+impl<'c, 's> Parser<'c, 's> {
+    pub fn new(context: &'c Context<'s>) -> Parser<'c, 's> {
+        Parser {
+            context,
+            tokens: Lexer::new(context.0).peekable(),
+        }
+    }
+
+    /// Parse the whole input as one expression.
+    pub fn parse(&mut self) -> Result<Expr<'s>, Error<'s>> {
+        let expr = self.expression(0)?;
+        match self.next()? {
+            None => Ok(expr),
+            Some(token) => Err(self.unexpected(token, "an operator")),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<Token<'s>>, Error<'s>> {
+        match self.tokens.peek() {
+            None => Ok(None),
+            Some(Ok(token)) => Ok(Some(*token)),
+            Some(Err(e)) => Err(e.clone()),
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'s>>, Error<'s>> {
+        match self.tokens.next() {
+            None => Ok(None),
+            Some(result) => result.map(Some),
+        }
+    }
+
+    fn unexpected(&self, token: Token<'s>, expected: &'static str) -> Error<'s> {
+        let kind = ErrorKind::UnexpectedToken {
+            found: token.text,
+            expected,
+        };
+        self.context.error(kind, token.span)
+    }
+
+    fn end(&self, expected: &'static str) -> Error<'s> {
+        let end = self.context.0.len();
+        let span = Span { start: end, end };
+        self.context
+            .error(ErrorKind::UnexpectedEnd { expected }, span)
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &'static str) -> Result<Token<'s>, Error<'s>> {
+        match self.next()? {
+            Some(token) if token.kind == kind => Ok(token),
+            Some(token) => Err(self.unexpected(token, expected)),
+            None => Err(self.end(expected)),
+        }
+    }
+
+    // An expression whose operators all hold their left operand at least as
+    // strongly as `min`.
+    fn expression(&mut self, min: u8) -> Result<Expr<'s>, Error<'s>> {
+        let mut lhs = self.operand()?;
+        while let Some(token) = self.peek()? {
+            let start = lhs.span.start;
+            let (kind, end) = match token.kind {
+                TokenKind::Eq if ASSIGN.0 >= min => {
+                    self.next()?;
+                    let value = self.expression(ASSIGN.1)?;
+                    let end = value.span.end;
+                    match lhs.kind {
+                        ExprKind::Var(name) => (ExprKind::Assign(name, Box::new(value)), end),
+                        _ => {
+                            return Err(self.context.error(ErrorKind::InvalidAssignment, lhs.span))
+                        }
+                    }
+                }
+                TokenKind::Question if CONDITIONAL.0 >= min => {
+                    self.next()?;
+                    let then = self.expression(0)?;
+                    self.expect(TokenKind::Colon, "':'")?;
+                    let otherwise = self.expression(CONDITIONAL.1)?;
+                    let end = otherwise.span.end;
+                    let kind =
+                        ExprKind::Conditional(Box::new(lhs), Box::new(then), Box::new(otherwise));
+                    (kind, end)
+                }
+                kind => match BinaryOp::from_token(kind) {
+                    Some(op) if op.binding_power().0 >= min => {
+                        self.next()?;
+                        let rhs = self.expression(op.binding_power().1)?;
+                        let end = rhs.span.end;
+                        (ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), end)
+                    }
+                    _ => break,
+                },
+            };
+            lhs = Expr {
+                kind,
+                span: Span { start, end },
+            };
+        }
+        Ok(lhs)
+    }
+
+    // A number, a variable, a call, a prefix operator and its operand, or an
+    // expression in parentheses.
+    fn operand(&mut self) -> Result<Expr<'s>, Error<'s>> {
+        let token = match self.next()? {
+            Some(token) => token,
+            None => return Err(self.end("an expression")),
+        };
+        let kind = match token.kind {
+            TokenKind::Number => ExprKind::Number(token.text.parse().expect("a valid number")),
+            TokenKind::True => ExprKind::Bool(true),
+            TokenKind::False => ExprKind::Bool(false),
+            TokenKind::Ident => match self.peek()? {
+                Some(Token {
+                    kind: TokenKind::LParen,
+                    ..
+                }) => return self.call(token),
+                _ => ExprKind::Var(token.text),
+            },
+            TokenKind::Minus | TokenKind::Bang => {
+                let op = if token.kind == TokenKind::Minus {
+                    UnaryOp::Neg
+                } else {
+                    UnaryOp::Not
+                };
+                let operand = self.expression(PREFIX)?;
+                return Ok(Expr {
+                    span: token.span.to(operand.span),
+                    kind: ExprKind::Unary(op, Box::new(operand)),
+                });
+            }
+            TokenKind::LParen => {
+                let inner = self.expression(0)?;
+                let close = self.expect(TokenKind::RParen, "')'")?;
+                return Ok(Expr {
+                    span: token.span.to(close.span),
+                    kind: inner.kind,
+                });
+            }
+            _ => return Err(self.unexpected(token, "an expression")),
+        };
+        Ok(Expr {
+            kind,
+            span: token.span,
+        })
+    }
+
+    fn call(&mut self, name: Token<'s>) -> Result<Expr<'s>, Error<'s>> {
+        self.expect(TokenKind::LParen, "'('")?;
+        let mut args = Vec::new();
+        let close = match self.peek()? {
+            Some(token) if token.kind == TokenKind::RParen => {
+                self.next()?;
+                token
+            }
+            _ => loop {
+                args.push(self.expression(0)?);
+                match self.next()? {
+                    Some(token) if token.kind == TokenKind::Comma => {}
+                    Some(token) if token.kind == TokenKind::RParen => break token,
+                    Some(token) => return Err(self.unexpected(token, "',' or ')'")),
+                    None => return Err(self.end("',' or ')'")),
+                }
+            },
+        };
+        Ok(Expr {
+            kind: ExprKind::Call(name.text, args),
+            span: name.span.to(close.span),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_context, Context, ErrorKind, Span};
+
+    fn parse(input: &str) -> String {
+        parse_context(Context(input)).unwrap().to_string()
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(parse("1 + 2 * 3 - 4"), "(- (+ 1 (* 2 3)) 4)");
+        assert_eq!(parse("2 ^ 3 ^ 2"), "(^ 2 (^ 3 2))");
+        assert_eq!(parse("-2 ^ 2 * -x"), "(* (- (^ 2 2)) (- x))");
+        assert_eq!(
+            parse("!a || b && c != d < 1"),
+            "(|| (! a) (&& b (!= c (< d 1))))"
+        );
+        assert_eq!(
+            parse("a = b = c ? 1 : d ? 2 : 3"),
+            "(= a (= b (? c 1 (? d 2 3))))"
+        );
+        assert_eq!(
+            parse("max(1, (2 + 3) * 4, f())"),
+            "(max 1 (* (+ 2 3) 4) (f))"
+        );
+    }
+
+    #[test]
+    fn errors_point_into_the_input() {
+        let cases = vec![
+            ("1 + * 2", "*", 4),
+            ("(1 + 2", "", 6),
+            ("f(1 2)", "2", 4),
+            ("1 + 2 = 3", "1 + 2", 0),
+            ("2 # 3", "#", 2),
+        ];
+        for (input, fragment, start) in cases {
+            let e = parse_context(Context(input)).unwrap_err();
+            assert_eq!((e.fragment(), e.span.start), (fragment, start), "{}", input);
+        }
+
+        let e = parse_context(Context("1 +")).unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::UnexpectedEnd {
+                expected: "an expression"
+            }
+        );
+        assert_eq!(e.span, Span { start: 3, end: 3 });
+        assert_eq!(
+            e.to_string(),
+            "expected an expression, found the end of the input\n    1 +\n       ^"
+        );
+    }
+}
@@ -0,0 +1,589 @@
+// Structured records out of unstructured lines.
+//
+// The patterns live in a JSON configuration file: every pattern has a name, a
+// regular expression with named capture groups, and the type of some of those
+// groups (a group without a type is a string).
+//
+//     {
+//       "patterns": [
+//         {
+//           "name": "login",
+//           "regex": "^(?P<date>\\S+) login (?P<user>\\w+) from (?P<ip>\\S+)$",
+//           "fields": { "date": "date", "ip": "ip" }
+//         }
+//       ]
+//     }
+//
+// As `regexp` explains, compiling a regex is expensive: the configuration is
+// compiled once into a Registry, and every line is then matched against all
+// the patterns at once with a RegexSet. The first pattern (in the order of the
+// configuration) that matches a line wins, and the line becomes a JSON record:
+//
+//     {"fields":{"date":"2018-03-14","ip":"10.0.0.1","user":"bob"},"line":1,"pattern":"login"}
+//
+// Lines that match nothing are flagged, and so are lines whose fields can't be
+// converted to their type.
+
+use regex::{Regex, RegexSet};
+use regex_syntax;
+use serde_json::{self, Map, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::IpAddr;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Int,
+    Float,
+    /// An ISO 8601 date: 2018-03-14.
+    Date,
+    /// An IPv4 or IPv6 address.
+    Ip,
+}
+
+impl FieldType {
+    fn convert(self, text: &str) -> Result<Value, String> {
+        match self {
+            FieldType::String => Ok(Value::from(text)),
+            FieldType::Int => text
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("'{}' is not an int", text)),
+            FieldType::Float => text
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("'{}' is not a float", text)),
+            FieldType::Date => {
+                if is_date(text) {
+                    Ok(Value::from(text))
+                } else {
+                    Err(format!("'{}' is not a date", text))
+                }
+            }
+            FieldType::Ip => text
+                .parse::<IpAddr>()
+                .map(|ip| Value::from(ip.to_string()))
+                .map_err(|_| format!("'{}' is not an IP address", text)),
+        }
+    }
+}
+
+fn is_date(text: &str) -> bool {
+    lazy_static! {
+        static ref DATE: Regex = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap();
+    }
+    let caps = match DATE.captures(text) {
+        Some(caps) => caps,
+        None => return false,
+    };
+    let number = |i: usize| caps[i].parse::<u32>().expect("only digits");
+    let (year, month, day) = (number(1), number(2), number(3));
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    day >= 1 && day <= days
+}
+
+#[derive(Deserialize)]
+struct PatternConfig {
+    name: String,
+    regex: String,
+    #[serde(default)]
+    fields: BTreeMap<String, FieldType>,
+}
+
+#[derive(Deserialize)]
+struct Config {
+    patterns: Vec<PatternConfig>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The regex of a pattern doesn't compile. The position, when known, is a
+    /// char offset in the regex.
+    Regex {
+        pattern: String,
+        regex: String,
+        position: Option<usize>,
+        message: String,
+    },
+    /// A field has a type, but the regex has no group with its name.
+    UnknownField {
+        pattern: String,
+        field: String,
+    },
+    DuplicatePattern(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref e) => write!(f, "{}", e),
+            ConfigError::Json(ref e) => write!(f, "invalid configuration: {}", e),
+            ConfigError::Regex {
+                ref pattern,
+                ref regex,
+                position: Some(position),
+                ref message,
+            } => write!(
+                f,
+                "pattern '{}', at char {}: {}\n    {}\n    {:>width$}",
+                pattern,
+                position,
+                message,
+                regex,
+                "^",
+                width = position + 1
+            ),
+            ConfigError::Regex {
+                ref pattern,
+                ref message,
+                ..
+            } => write!(f, "pattern '{}': {}", pattern, message),
+            ConfigError::UnknownField {
+                ref pattern,
+                ref field,
+            } => write!(
+                f,
+                "pattern '{}': the regex has no group named '{}'",
+                pattern, field
+            ),
+            ConfigError::DuplicatePattern(ref name) => {
+                write!(f, "pattern '{}' is declared more than once", name)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> ConfigError {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(e: serde_json::Error) -> ConfigError {
+        ConfigError::Json(e)
+    }
+}
+
+struct Pattern {
+    name: String,
+    regex: Regex,
+    // The type of every named group.
+    fields: Vec<(String, FieldType)>,
+}
+
+impl Pattern {
+    fn compile(config: PatternConfig) -> Result<Pattern, ConfigError> {
+        // The regex crate reports syntax errors as plain strings: parse the
+        // regex on our own first, to know where the error is.
+        if let Err(e) = regex_syntax::Expr::parse(&config.regex) {
+            return Err(ConfigError::Regex {
+                pattern: config.name,
+                regex: config.regex,
+                position: Some(e.position()),
+                message: e.kind().to_string(),
+            });
+        }
+        let regex = Regex::new(&config.regex).map_err(|e| ConfigError::Regex {
+            pattern: config.name.clone(),
+            regex: config.regex.clone(),
+            position: None,
+            message: e.to_string(),
+        })?;
+
+        let names: Vec<&str> = regex.capture_names().flatten().collect();
+        if let Some(field) = config.fields.keys().find(|f| !names.contains(&f.as_str())) {
+            return Err(ConfigError::UnknownField {
+                pattern: config.name.clone(),
+                field: field.clone(),
+            });
+        }
+        let fields = names
+            .iter()
+            .map(|n| {
+                let t = config.fields.get(*n).cloned().unwrap_or(FieldType::String);
+                (n.to_string(), t)
+            })
+            .collect();
+        Ok(Pattern {
+            name: config.name,
+            regex,
+            fields,
+        })
+    }
+}
+
+/// What a line turned out to be.
+#[derive(Debug, PartialEq)]
+pub enum Outcome<'r> {
+    Record {
+        pattern: &'r str,
+        fields: Map<String, Value>,
+    },
+    /// The pattern matched, but a field has the wrong type.
+    Invalid {
+        pattern: &'r str,
+        field: &'r str,
+        reason: String,
+    },
+    Unmatched,
+}
+
+/// The patterns of a configuration, compiled.
+pub struct Registry {
+    patterns: Vec<Pattern>,
+    set: RegexSet,
+}
+
+impl Registry {
+    pub fn from_json(json: &str) -> Result<Registry, ConfigError> {
+        let config: Config = serde_json::from_str(json)?;
+        let mut patterns: Vec<Pattern> = Vec::new();
+        for p in config.patterns {
+            if patterns.iter().any(|other| other.name == p.name) {
+                return Err(ConfigError::DuplicatePattern(p.name));
+            }
+            patterns.push(Pattern::compile(p)?);
+        }
+        let set = RegexSet::new(patterns.iter().map(|p| p.regex.as_str())).map_err(|e| {
+            ConfigError::Regex {
+                pattern: String::from("(all of them)"),
+                regex: String::new(),
+                position: None,
+                message: e.to_string(),
+            }
+        })?;
+        Ok(Registry { patterns, set })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Registry, ConfigError> {
+        let mut json = String::new();
+        File::open(path)?.read_to_string(&mut json)?;
+        Registry::from_json(&json)
+    }
+
+    /// The names of the patterns, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.patterns.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    pub fn process(&self, line: &str) -> Outcome<'_> {
+        let pattern = match self.set.matches(line).into_iter().next() {
+            Some(i) => &self.patterns[i],
+            None => return Outcome::Unmatched,
+        };
+        let caps = pattern
+            .regex
+            .captures(line)
+            .expect("the set said it matches");
+        let mut fields = Map::new();
+        for &(ref name, t) in &pattern.fields {
+            // Optional groups that took no part in the match are left out.
+            if let Some(m) = caps.name(name) {
+                match t.convert(m.as_str()) {
+                    Ok(value) => {
+                        fields.insert(name.clone(), value);
+                    }
+                    Err(reason) => {
+                        return Outcome::Invalid {
+                            pattern: &pattern.name,
+                            field: name,
+                            reason,
+                        }
+                    }
+                }
+            }
+        }
+        Outcome::Record {
+            pattern: &pattern.name,
+            fields,
+        }
+    }
+
+    /// Process every line of the input, writing one JSON object per line to
+    /// the output: the records, and the lines that have been flagged.
+    pub fn extract<R: BufRead, W: Write>(&self, mut input: R, out: &mut W) -> io::Result<Report> {
+        let mut report = Report {
+            lines: 0,
+            matches: self.patterns.iter().map(|p| (p.name.clone(), 0)).collect(),
+            invalid: 0,
+            unmatched: 0,
+        };
+        let mut line = String::new();
+        while input.read_line(&mut line)? > 0 {
+            report.lines += 1;
+            let text = line.trim_end_matches(['\r', '\n']);
+            let mut json = Map::new();
+            json.insert(String::from("line"), Value::from(report.lines));
+            match self.process(text) {
+                Outcome::Record { pattern, fields } => {
+                    *report.matches.get_mut(pattern).expect("a known pattern") += 1;
+                    json.insert(String::from("pattern"), Value::from(pattern));
+                    json.insert(String::from("fields"), Value::Object(fields));
+                }
+                Outcome::Invalid {
+                    pattern,
+                    field,
+                    reason,
+                } => {
+                    report.invalid += 1;
+                    json.insert(String::from("pattern"), Value::from(pattern));
+                    json.insert(
+                        String::from("error"),
+                        Value::from(format!("field '{}': {}", field, reason)),
+                    );
+                    json.insert(String::from("text"), Value::from(text));
+                }
+                Outcome::Unmatched => {
+                    report.unmatched += 1;
+                    json.insert(String::from("unmatched"), Value::from(text));
+                }
+            }
+            serde_json::to_writer(&mut *out, &json)?;
+            writeln!(out)?;
+            line.clear();
+        }
+        Ok(report)
+    }
+}
+
+/// What happened to the lines of an input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub lines: u64,
+    /// How many records every pattern produced.
+    pub matches: BTreeMap<String, u64>,
+    pub invalid: u64,
+    pub unmatched: u64,
+}
+
+impl Report {
+    pub fn merge(&mut self, other: &Report) {
+        self.lines += other.lines;
+        for (name, count) in &other.matches {
+            *self.matches.entry(name.clone()).or_insert(0) += count;
+        }
+        self.invalid += other.invalid;
+        self.unmatched += other.unmatched;
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "lines: {}", self.lines)?;
+        for (name, count) in &self.matches {
+            writeln!(f, "  {}: {}", name, count)?;
+        }
+        writeln!(f, "invalid: {}", self.invalid)?;
+        write!(f, "unmatched: {}", self.unmatched)
+    }
+}
+
+pub const USAGE: &str = "    fungi extract <config> [<file>...]
+                                turn the lines of the files (or stdin) into JSON
+                                records, with the patterns of the configuration";
+
+/// A parsed `fungi extract` command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub config: String,
+    pub inputs: Vec<String>,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    match args.split_first() {
+        None => Err(String::from("extract needs a configuration file")),
+        Some((config, inputs)) => Ok(Invocation {
+            config: config.clone(),
+            inputs: inputs.to_vec(),
+        }),
+    }
+}
+
+/// Records go to stdout, the report to stderr.
+pub fn run(invocation: &Invocation) -> Result<(), String> {
+    let registry =
+        Registry::load(&invocation.config).map_err(|e| format!("{}: {}", invocation.config, e))?;
+
+    let stdin = [String::from("-")];
+    let inputs = if invocation.inputs.is_empty() {
+        &stdin[..]
+    } else {
+        &invocation.inputs[..]
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut total: Option<Report> = None;
+    for input in inputs {
+        let report = if input == "-" {
+            let stdin = io::stdin();
+            let lock = stdin.lock();
+            registry.extract(lock, &mut out)
+        } else {
+            File::open(input).and_then(|f| registry.extract(BufReader::new(f), &mut out))
+        };
+        let report = report.map_err(|e| format!("{}: {}", input, e))?;
+        match total {
+            Some(ref mut total) => total.merge(&report),
+            None => total = Some(report),
+        }
+    }
+    if let Some(total) = total {
+        eprintln!("{}", total);
+    }
+    Ok(())
+}
+
+static LOG_PATTERNS: &str = r#"{
+  "patterns": [
+    {
+      "name": "request",
+      "regex": "^(?P<ip>[0-9a-f.:]+) (?P<date>\\S+) (?P<method>GET|POST) (?P<path>\\S+) (?P<status>\\d{3}) (?P<ms>[\\d.]+)$",
+      "fields": { "ip": "ip", "date": "date", "status": "int", "ms": "float" }
+    },
+    {
+      "name": "login",
+      "regex": "^(?P<date>\\S+) login (?P<user>\\w+)(?: from (?P<ip>\\S+))?$",
+      "fields": { "date": "date", "ip": "ip" }
+    }
+  ]
+}"#;
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "extract",
+    description: "a registry of named regexes turning lines into JSON records",
+    run: sample,
+};
+
+pub fn sample() {
+    // Compiled the first time it's used, and never again.
+    lazy_static! {
+        static ref LOGS: Registry = Registry::from_json(LOG_PATTERNS).unwrap();
+    }
+
+    let log = "10.0.0.1 2018-03-14 GET /index.html 200 12.5
+2018-03-14 login bob from 10.0.0.7
+2018-03-15 login alice
+::1 2018-03-15 POST /login 500 230
+2018-02-30 login eve
+segmentation fault (core dumped)
+";
+    println!("patterns: {}", LOGS.names().join(", "));
+    let stdout = io::stdout();
+    let report = LOGS
+        .extract(log.as_bytes(), &mut stdout.lock())
+        .expect("could not write to stdout");
+    println!("{}", report);
+
+    let broken = r#"{"patterns": [{"name": "date", "regex": "(?P<y>\\d{4}-(?P<m>\\d{2}"}]}"#;
+    if let Err(e) = Registry::from_json(broken) {
+        println!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Registry {
+        Registry::from_json(LOG_PATTERNS).unwrap()
+    }
+
+    #[test]
+    fn typed_fields() {
+        let r = registry();
+        assert_eq!(r.names(), vec!["request", "login"]);
+        match r.process("::1 2018-03-15 POST /login 500 230") {
+            Outcome::Record { pattern, fields } => {
+                assert_eq!(pattern, "request");
+                assert_eq!(fields["ip"], "::1");
+                assert_eq!(fields["status"], 500);
+                assert_eq!(fields["ms"], 230.0);
+                assert_eq!(fields["path"], "/login");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match r.process("2016-02-29 login alice") {
+            Outcome::Record { fields, .. } => assert!(!fields.contains_key("ip")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            r.process("2018-02-29 login alice"),
+            Outcome::Invalid {
+                pattern: "login",
+                field: "date",
+                reason: String::from("'2018-02-29' is not a date"),
+            }
+        );
+        assert_eq!(r.process("login alice"), Outcome::Unmatched);
+    }
+
+    #[test]
+    fn dates() {
+        assert!(is_date("2000-02-29"));
+        assert!(!is_date("1900-02-29"));
+        assert!(!is_date("2018-13-01"));
+        assert!(!is_date("2018-04-31"));
+        assert!(!is_date("18-04-01"));
+    }
+
+    #[test]
+    fn json_lines_and_report() {
+        let input = "2018-03-14 login bob from 10.0.0.7\nnothing here\n\
+                     2018-03-14 login eve from 300.0.0.1\r\n";
+        let mut out = Vec::new();
+        let report = registry().extract(input.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"{"fields":{"date":"2018-03-14","ip":"10.0.0.7","user":"bob"},"line":1,"pattern":"login"}"#
+        );
+        assert_eq!(lines[1], r#"{"line":2,"unmatched":"nothing here"}"#);
+        assert!(lines[2].contains(r#""error":"field 'ip': '300.0.0.1' is not an IP address""#));
+        assert_eq!(report.lines, 3);
+        assert_eq!(report.matches["login"], 1);
+        assert_eq!(report.matches["request"], 0);
+        assert_eq!((report.invalid, report.unmatched), (1, 1));
+    }
+
+    #[test]
+    fn configuration_errors() {
+        let err = |json: &str| Registry::from_json(json).err().unwrap().to_string();
+        assert_eq!(
+            err(r#"{"patterns": [{"name": "p", "regex": "a(b"}]}"#),
+            "pattern 'p', at char 1: Unclosed parenthesis.\n    a(b\n     ^"
+        );
+        assert_eq!(
+            err(r#"{"patterns": [{"name": "p", "regex": "(?P<a>x)", "fields": {"b": "int"}}]}"#),
+            "pattern 'p': the regex has no group named 'b'"
+        );
+        assert_eq!(
+            err(r#"{"patterns": [{"name": "p", "regex": "x"}, {"name": "p", "regex": "y"}]}"#),
+            "pattern 'p' is declared more than once"
+        );
+        assert!(
+            err(r#"{"patterns": [{"name": "p", "regex": "x", "fields": {"x": "uuid"}}]}"#)
+                .starts_with("invalid configuration: unknown variant `uuid`")
+        );
+    }
+}
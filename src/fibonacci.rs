@@ -5,3 +5,14 @@ pub fn fib(n: u32) -> u32 {
         _ => fib(n - 1) + fib(n - 2),
     }
 }
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "fibonacci",
+    description: "a recursive Fibonacci number",
+    run: sample,
+};
+
+pub fn sample() {
+    let x: u32 = fib(10);
+    println!("fib(10) = {}", x);
+}
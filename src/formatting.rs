@@ -1,6 +1,12 @@
 // https://doc.rust-lang.org/std/fmt/
 use std::fmt;
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "formatting",
+    description: "std::fmt and custom Display implementations",
+    run: sample,
+};
+
 pub fn sample() {
     // In general, the `{}` will be automatically replaced with any
     // arguments. These will be stringified.
@@ -162,6 +162,12 @@ impl<T, U> PointM<T, U> {
     }
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "generics",
+    description: "generic functions, structs and trait bounds",
+    run: sample,
+};
+
 pub fn sample() {
     let number_list = vec![34, 50, 25, 100, 65];
 
@@ -0,0 +1,144 @@
+use super::{AGraph, Build, Neighbors, Weight};
+
+/// For every node, the list of its outgoing edges: good for sparse graphs.
+///
+/// An undirected edge is stored in the lists of both its ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjacencyList<W> {
+    directed: bool,
+    edges: Vec<Vec<(usize, W)>>,
+}
+
+impl<W: Weight> AdjacencyList<W> {
+    pub fn new(directed: bool) -> AdjacencyList<W> {
+        AdjacencyList::with_nodes(0, directed)
+    }
+
+    /// A new node, with no edges.
+    pub fn add_node(&mut self) -> usize {
+        self.edges.push(Vec::new());
+        self.edges.len() - 1
+    }
+
+    pub fn edge_count(&self) -> usize {
+        let ends: usize = self.edges.iter().map(|e| e.len()).sum();
+        if self.directed {
+            ends
+        } else {
+            // Self loops have only one end in the lists.
+            let loops = self
+                .edges
+                .iter()
+                .enumerate()
+                .map(|(i, e)| e.iter().filter(|&&(j, _)| i == j).count())
+                .sum::<usize>();
+            (ends + loops) / 2
+        }
+    }
+}
+
+impl<W: Weight> AGraph for AdjacencyList<W> {
+    type Node = usize;
+    type Edge = W;
+
+    fn directed(&self) -> bool {
+        self.directed
+    }
+
+    fn node_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn nodes<'a>(&'a self) -> Box<dyn Iterator<Item = usize> + 'a> {
+        Box::new(0..self.edges.len())
+    }
+
+    fn neighbors<'a>(&'a self, node: usize) -> Neighbors<'a, usize, W> {
+        Box::new(self.edges[node].iter().cloned())
+    }
+}
+
+impl<W: Weight> Build for AdjacencyList<W> {
+    fn with_nodes(count: usize, directed: bool) -> AdjacencyList<W> {
+        AdjacencyList {
+            directed,
+            edges: vec![Vec::new(); count],
+        }
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, weight: W) {
+        self.edges[from].push((to, weight));
+        if !self.directed && from != to {
+            self.edges[to].push((from, weight));
+        }
+    }
+}
+
+/// A node_count × node_count table with the weight of the edge between any
+/// two nodes, if there is one: good for dense graphs, and to tell quickly
+/// whether two nodes are adjacent.
+///
+/// There's room for one edge from a node to another: the lightest one wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjacencyMatrix<W> {
+    directed: bool,
+    nodes: usize,
+    cells: Vec<Option<W>>,
+}
+
+impl<W: Weight> AdjacencyMatrix<W> {
+    pub fn weight(&self, from: usize, to: usize) -> Option<W> {
+        self.cells[from * self.nodes + to]
+    }
+
+    fn set(&mut self, from: usize, to: usize, weight: W) {
+        let cell = &mut self.cells[from * self.nodes + to];
+        match *cell {
+            Some(w) if w <= weight => {}
+            _ => *cell = Some(weight),
+        }
+    }
+}
+
+impl<W: Weight> AGraph for AdjacencyMatrix<W> {
+    type Node = usize;
+    type Edge = W;
+
+    fn directed(&self) -> bool {
+        self.directed
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes
+    }
+
+    fn nodes<'a>(&'a self) -> Box<dyn Iterator<Item = usize> + 'a> {
+        Box::new(0..self.nodes)
+    }
+
+    fn neighbors<'a>(&'a self, node: usize) -> Neighbors<'a, usize, W> {
+        let row = &self.cells[node * self.nodes..(node + 1) * self.nodes];
+        Box::new(
+            row.iter()
+                .enumerate()
+                .filter_map(|(to, w)| w.map(|w| (to, w))),
+        )
+    }
+}
+
+impl<W: Weight> Build for AdjacencyMatrix<W> {
+    fn with_nodes(count: usize, directed: bool) -> AdjacencyMatrix<W> {
+        AdjacencyMatrix {
+            directed,
+            nodes: count,
+            cells: vec![None; count * count],
+        }
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, weight: W) {
+        self.set(from, to, weight);
+        if !self.directed {
+            self.set(to, from, weight);
+        }
+    }
+}
@@ -0,0 +1,504 @@
+// Graphs from text: edge lists, and the DOT language of Graphviz.
+//
+// Edge lists have one edge per line, "from to [weight]", or a lonely node.
+// Everything after a '#' is a comment:
+//
+//     # roads
+//     rome florence 274
+//     florence bologna 106
+//     elba
+//
+// Of DOT, only what describes the nodes and the edges is read: graph, node
+// and edge defaults are skipped, subgraphs and ports are not supported. The
+// weight of an edge is its "weight" attribute, or its "label" if that's a
+// number:
+//
+//     digraph roads {
+//         rome -> florence -> bologna [weight=106];
+//         "la spezia" -> florence [label="145"]
+//     }
+
+use super::{Build, Weight};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A graph read from a file, and the names of its nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loaded<G> {
+    pub graph: G,
+    pub names: Vec<String>,
+}
+
+impl<G> Loaded<G> {
+    pub fn node(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    pub fn name(&self, node: usize) -> &str {
+        &self.names[node]
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref e) => write!(f, "{}", e),
+            LoadError::Syntax { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+fn syntax<T>(line: usize, message: String) -> Result<T, LoadError> {
+    Err(LoadError::Syntax { line, message })
+}
+
+// The nodes in order of appearance, and the edges between them.
+struct Collected<W> {
+    ids: HashMap<String, usize>,
+    names: Vec<String>,
+    edges: Vec<(usize, usize, W)>,
+}
+
+impl<W> Collected<W> {
+    fn new() -> Collected<W> {
+        Collected {
+            ids: HashMap::new(),
+            names: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    fn node(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    fn build<G: Build<Edge = W>>(self, directed: bool) -> Loaded<G> {
+        let mut graph = G::with_nodes(self.names.len(), directed);
+        for (from, to, weight) in self.edges {
+            graph.add_edge(from, to, weight);
+        }
+        Loaded {
+            graph,
+            names: self.names,
+        }
+    }
+}
+
+fn weight<W: FromStr>(line: usize, text: &str) -> Result<W, LoadError> {
+    text.parse()
+        .or_else(|_| syntax(line, format!("invalid weight '{}'", text)))
+}
+
+/// Read an edge list. Without a weight, an edge weighs one.
+pub fn edge_list<G>(text: &str, directed: bool) -> Result<Loaded<G>, LoadError>
+where
+    G: Build,
+    G::Edge: FromStr,
+{
+    let mut collected = Collected::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        match *line.split_whitespace().collect::<Vec<_>>() {
+            [] => {}
+            [node] => {
+                collected.node(node);
+            }
+            [from, to] => {
+                let edge = (collected.node(from), collected.node(to), G::Edge::one());
+                collected.edges.push(edge);
+            }
+            [from, to, w] => {
+                let edge = (collected.node(from), collected.node(to), weight(i + 1, w)?);
+                collected.edges.push(edge);
+            }
+            _ => return syntax(i + 1, String::from("expected 'from to [weight]'")),
+        }
+    }
+    Ok(collected.build(directed))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Id(String),
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Semicolon,
+    Comma,
+    Equals,
+    // -> or --
+    EdgeOp(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Id(ref id) => write!(f, "'{}'", id),
+            Token::LBrace => write!(f, "'{{'"),
+            Token::RBrace => write!(f, "'}}'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::Semicolon => write!(f, "';'"),
+            Token::Comma => write!(f, "','"),
+            Token::Equals => write!(f, "'='"),
+            Token::EdgeOp(op) => write!(f, "'{}'", op),
+        }
+    }
+}
+
+// The tokens of a DOT file, and their line.
+fn tokens(text: &str) -> Result<Vec<(Token, usize)>, LoadError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ';' => Token::Semicolon,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => return syntax(line, String::from("unterminated comment")),
+                    }
+                }
+                continue;
+            }
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                Token::EdgeOp("->")
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.next();
+                Token::EdgeOp("--")
+            }
+            '"' => {
+                let start = line;
+                let mut id = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'"') => id.push(chars.next().unwrap()),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            id.push(c);
+                        }
+                        None => return syntax(start, String::from("unterminated string")),
+                    }
+                }
+                Token::Id(id)
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut id = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    id.push(c);
+                    chars.next();
+                }
+                Token::Id(id)
+            }
+            c => return syntax(line, format!("unexpected character '{}'", c)),
+        };
+        tokens.push((token, line));
+    }
+    Ok(tokens)
+}
+
+struct DotParser<W> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    collected: Collected<W>,
+}
+
+impl<W: Weight + FromStr> DotParser<W> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|t| &t.0)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |t| t.1)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Token, LoadError> {
+        match self.tokens.get(self.position).cloned() {
+            Some((token, _)) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => syntax(
+                self.line(),
+                format!("expected {}, found the end of the file", expected),
+            ),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), LoadError> {
+        let line = self.line();
+        let found = self.next(&token.to_string())?;
+        if found == token {
+            Ok(())
+        } else {
+            syntax(line, format!("expected {}, found {}", token, found))
+        }
+    }
+
+    fn id(&mut self, expected: &str) -> Result<String, LoadError> {
+        let line = self.line();
+        match self.next(expected)? {
+            Token::Id(id) => Ok(id),
+            other => syntax(line, format!("expected {}, found {}", expected, other)),
+        }
+    }
+
+    // [strict] (graph | digraph) [ID] { statements }, true if directed.
+    fn graph(&mut self) -> Result<bool, LoadError> {
+        let mut keyword = self.id("'graph' or 'digraph'")?;
+        if keyword == "strict" {
+            keyword = self.id("'graph' or 'digraph'")?;
+        }
+        let directed = match keyword.as_str() {
+            "graph" => false,
+            "digraph" => true,
+            other => {
+                return syntax(
+                    1,
+                    format!("expected 'graph' or 'digraph', found '{}'", other),
+                )
+            }
+        };
+        if let Some(&Token::Id(_)) = self.peek() {
+            self.position += 1;
+        }
+        self.expect(Token::LBrace)?;
+        while self.peek() != Some(&Token::RBrace) {
+            self.statement(directed)?;
+        }
+        self.expect(Token::RBrace)?;
+        if self.peek().is_some() {
+            return syntax(self.line(), String::from("expected the end of the file"));
+        }
+        Ok(directed)
+    }
+
+    // [ a = 1, b = 2 ], maybe more than one list, as key and value pairs.
+    fn attributes(&mut self) -> Result<Vec<(String, String)>, LoadError> {
+        let mut attributes = Vec::new();
+        while self.peek() == Some(&Token::LBracket) {
+            self.position += 1;
+            while self.peek() != Some(&Token::RBracket) {
+                let key = self.id("an attribute")?;
+                self.expect(Token::Equals)?;
+                let value = self.id("a value")?;
+                attributes.push((key, value));
+                match self.peek() {
+                    Some(&Token::Comma) | Some(&Token::Semicolon) => self.position += 1,
+                    _ => {}
+                }
+            }
+            self.expect(Token::RBracket)?;
+        }
+        Ok(attributes)
+    }
+
+    fn statement(&mut self, directed: bool) -> Result<(), LoadError> {
+        let line = self.line();
+        let id = self.id("a statement")?;
+        match id.as_str() {
+            "graph" | "node" | "edge" if self.peek() == Some(&Token::LBracket) => {
+                self.attributes()?;
+            }
+            "subgraph" => return syntax(line, String::from("subgraphs are not supported")),
+            _ if self.peek() == Some(&Token::Equals) => {
+                self.position += 1;
+                self.id("a value")?;
+            }
+            _ => {
+                // A chain of edges: a -> b -> c [attributes].
+                let mut chain = vec![self.collected.node(&id)];
+                while let Some(&Token::EdgeOp(op)) = self.peek() {
+                    if (op == "->") != directed {
+                        let kind = if directed {
+                            "a digraph"
+                        } else {
+                            "an undirected graph"
+                        };
+                        return syntax(self.line(), format!("'{}' in {}", op, kind));
+                    }
+                    self.position += 1;
+                    let next = self.id("a node")?;
+                    chain.push(self.collected.node(&next));
+                }
+                let attributes = self.attributes()?;
+                let weight = match attributes.iter().find(|a| a.0 == "weight").or_else(|| {
+                    attributes
+                        .iter()
+                        .find(|a| a.0 == "label" && a.1.parse::<W>().is_ok())
+                }) {
+                    Some((_, w)) => weight(line, w)?,
+                    None => W::one(),
+                };
+                for pair in chain.windows(2) {
+                    self.collected.edges.push((pair[0], pair[1], weight));
+                }
+            }
+        }
+        if self.peek() == Some(&Token::Semicolon) {
+            self.position += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Read a graph in the DOT language, directed if it's a digraph.
+pub fn dot<G>(text: &str) -> Result<Loaded<G>, LoadError>
+where
+    G: Build,
+    G::Edge: FromStr,
+{
+    let mut parser = DotParser {
+        tokens: tokens(text)?,
+        position: 0,
+        collected: Collected::new(),
+    };
+    let directed = parser.graph()?;
+    Ok(parser.collected.build(directed))
+}
+
+/// A .dot (or .gv) file, or an edge list that is directed or not.
+pub fn load<G, P>(path: P, directed: bool) -> Result<Loaded<G>, LoadError>
+where
+    G: Build,
+    G::Edge: FromStr,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("dot") | Some("gv") => dot(&text),
+        _ => edge_list(&text, directed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AGraph, AdjacencyList};
+    use super::*;
+
+    #[test]
+    fn edge_lists() {
+        let text = "# roads\nrome florence 274\nflorence bologna 106 # A1\n\nelba\nrome elba\n";
+        let loaded: Loaded<AdjacencyList<u32>> = edge_list(text, false).unwrap();
+        assert_eq!(loaded.names, vec!["rome", "florence", "bologna", "elba"]);
+        let rome = loaded.node("rome").unwrap();
+        let mut neighbors: Vec<_> = loaded.graph.neighbors(rome).collect();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![(1, 274), (3, 1)]);
+
+        let e = edge_list::<AdjacencyList<u32>>("a b\na b c d\n", true).unwrap_err();
+        assert_eq!(e.to_string(), "line 2: expected 'from to [weight]'");
+        let e = edge_list::<AdjacencyList<u32>>("a b -3\n", true).unwrap_err();
+        assert_eq!(e.to_string(), "line 1: invalid weight '-3'");
+    }
+
+    #[test]
+    fn dot_files() {
+        let text = r#"strict digraph roads {
+            // defaults are skipped
+            node [shape=box]; rankdir = LR
+            rome -> florence -> bologna [weight=106, color="red"];
+            /* a multi-line
+               comment */
+            "la spezia" -> florence [label="145"]
+            elba
+        }"#;
+        let loaded: Loaded<AdjacencyList<f64>> = dot(text).unwrap();
+        assert!(loaded.graph.directed());
+        assert_eq!(
+            loaded.names,
+            vec!["rome", "florence", "bologna", "la spezia", "elba"]
+        );
+        assert_eq!(
+            loaded.graph.edges(),
+            vec![(0, 1, 106.0), (1, 2, 106.0), (3, 1, 145.0)]
+        );
+
+        let errors = vec![
+            ("graph { a -> b }", "line 1: '->' in an undirected graph"),
+            (
+                "digraph {\n subgraph x { a } }",
+                "line 2: subgraphs are not supported",
+            ),
+            (
+                "digraph {\n a -> b [weight=x] }",
+                "line 2: invalid weight 'x'",
+            ),
+            ("digraph { a -> }", "line 1: expected a node, found '}'"),
+            (
+                "digraph {\n a -> b",
+                "line 2: expected a statement, found the end of the file",
+            ),
+        ];
+        for (text, message) in errors {
+            let e = dot::<AdjacencyList<f64>>(text).unwrap_err();
+            assert_eq!(e.to_string(), message, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,433 @@
+// A graph library behind the `GGraph` and `AGraph` traits of advanced.rs,
+// where the `distance_*` functions of `seven` run `hops`: two backends
+// implement AGraph, and the algorithms are written once against it.
+//
+// The algorithms take `&G` with `G: AGraph + ?Sized`, so they work on trait
+// objects too, like `traverse` in advanced.rs:
+//
+//     let graph: &dyn AGraph<Node = usize, Edge = u32> = &list;
+//     let order: Vec<_> = bfs(graph, 0).collect();
+
+mod backends;
+mod load;
+mod paths;
+mod spanning;
+mod traversal;
+
+pub use self::backends::{AdjacencyList, AdjacencyMatrix};
+pub use self::load::{dot, edge_list, load, LoadError, Loaded};
+pub use self::paths::{a_star, bellman_ford, dijkstra};
+pub use self::spanning::minimum_spanning_tree;
+pub use self::traversal::{bfs, dfs, hops, strongly_connected_components, topological_sort};
+pub use advanced::{AGraph, GGraph};
+
+use std::fmt;
+use std::ops::Add;
+
+/// The edges that leave a node: where they go, and their weight.
+pub type Neighbors<'a, N, E> = Box<dyn Iterator<Item = (N, E)> + 'a>;
+
+/// What the weight of an edge can be: integers and floats.
+pub trait Weight: Copy + PartialOrd + Add<Output = Self> + fmt::Debug + fmt::Display {
+    fn zero() -> Self;
+    fn one() -> Self;
+}
+
+impl<T> Weight for T
+where
+    T: Copy + PartialOrd + Add<Output = T> + From<u8> + fmt::Debug + fmt::Display,
+{
+    fn zero() -> T {
+        T::from(0)
+    }
+
+    fn one() -> T {
+        T::from(1)
+    }
+}
+
+/// A graph whose nodes are numbered from 0, built an edge at a time.
+pub trait Build: AGraph<Node = usize> + Sized {
+    fn with_nodes(count: usize, directed: bool) -> Self;
+
+    fn add_edge(&mut self, from: usize, to: usize, weight: Self::Edge);
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "graph",
+    description: "graphs with two backends, traversals, shortest paths and spanning trees",
+    run: sample,
+};
+
+// A maze on a grid: every open cell is a node, and the edges are implicit.
+struct Grid {
+    rows: Vec<Vec<bool>>,
+}
+
+impl Grid {
+    fn parse(text: &str) -> Grid {
+        Grid {
+            rows: text
+                .lines()
+                .map(|l| l.chars().map(|c| c != '#').collect())
+                .collect(),
+        }
+    }
+
+    fn open(&self, (row, column): (usize, usize)) -> bool {
+        self.rows
+            .get(row)
+            .and_then(|r| r.get(column))
+            .cloned()
+            .unwrap_or(false)
+    }
+}
+
+impl AGraph for Grid {
+    type Node = (usize, usize);
+    type Edge = u32;
+
+    fn directed(&self) -> bool {
+        false
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes().count()
+    }
+
+    fn nodes<'a>(&'a self) -> Box<dyn Iterator<Item = (usize, usize)> + 'a> {
+        Box::new(self.rows.iter().enumerate().flat_map(move |(r, row)| {
+            (0..row.len())
+                .map(move |c| (r, c))
+                .filter(move |&cell| self.open(cell))
+        }))
+    }
+
+    fn neighbors<'a>(&'a self, (r, c): (usize, usize)) -> Neighbors<'a, (usize, usize), u32> {
+        let around = vec![
+            (r.wrapping_sub(1), c),
+            (r + 1, c),
+            (r, c.wrapping_sub(1)),
+            (r, c + 1),
+        ];
+        Box::new(
+            around
+                .into_iter()
+                .filter(move |&cell| self.open(cell))
+                .map(|cell| (cell, 1)),
+        )
+    }
+}
+
+pub fn sample() {
+    // The graph of scripts/dijkstra.rs, on both backends.
+    let edges = [
+        (0, 2, 10),
+        (0, 1, 1),
+        (1, 3, 2),
+        (2, 1, 1),
+        (2, 3, 3),
+        (2, 4, 1),
+        (3, 0, 7),
+        (3, 4, 2),
+    ];
+    let mut list = AdjacencyList::new(true);
+    for _ in 0..5 {
+        list.add_node();
+    }
+    let mut matrix = AdjacencyMatrix::with_nodes(5, true);
+    for &(from, to, weight) in &edges {
+        list.add_edge(from, to, weight);
+        matrix.add_edge(from, to, weight);
+    }
+    println!(
+        "{} nodes, {} edges, 2 -> 4 weighs {:?}",
+        list.node_count(),
+        list.edge_count(),
+        matrix.weight(2, 4)
+    );
+    let paths = dijkstra(&matrix, 0);
+    for (node, distance) in paths.distances() {
+        println!(
+            "0 to {}: {} via {:?}",
+            node,
+            distance,
+            paths.path(node).unwrap_or_default()
+        );
+    }
+
+    // Through a trait object, as `traverse` does in advanced.rs.
+    let graph: &dyn AGraph<Node = usize, Edge = u32> = &list;
+    println!("bfs from 2: {:?}", bfs(graph, 2).collect::<Vec<_>>());
+    println!("dfs from 2: {:?}", dfs(graph, 2).collect::<Vec<_>>());
+    println!("components: {:?}", strongly_connected_components(graph));
+    match topological_sort(graph) {
+        Ok(order) => println!("in order: {:?}", order),
+        Err(cycle) => println!("no order, because of a cycle among {:?}", cycle.nodes),
+    }
+
+    // A negative weight is too much for Dijkstra, but not for Bellman-Ford.
+    let mut debts = AdjacencyList::with_nodes(4, true);
+    for &(from, to, weight) in &[(0, 1, 4), (0, 2, 5), (2, 1, -3), (1, 3, 2)] {
+        debts.add_edge(from, to, weight);
+    }
+    match bellman_ford(&debts, 0) {
+        Ok(paths) => println!("with a negative weight: {:?}", paths.distances()),
+        Err(cycle) => println!("negative cycle: {:?}", cycle.nodes),
+    }
+    debts.add_edge(3, 2, -1);
+    match bellman_ford(&debts, 0) {
+        Ok(paths) => println!("with a negative weight: {:?}", paths.distances()),
+        Err(cycle) => println!("negative cycle: {:?}", cycle.nodes),
+    }
+
+    // What to put on before what: the DOT file names the nodes.
+    let clothes: Loaded<AdjacencyList<u32>> = dot("digraph {
+            socks -> shoes; pants -> shoes -> laces
+            pants -> belt; shirt -> belt -> jacket; shirt -> tie -> jacket
+        }")
+    .expect("a valid DOT graph");
+    let order = topological_sort(&clothes.graph).expect("no cycles");
+    let names: Vec<_> = order.into_iter().map(|n| clothes.name(n)).collect();
+    println!("dressing: {}", names.join(", "));
+
+    let cables: Loaded<AdjacencyMatrix<f64>> = edge_list(
+        "# cables between buildings, in km
+        library lab 1.2
+        library hall 0.8
+        lab hall 0.3
+        hall gym 2.5
+        lab gym 2.1",
+        false,
+    )
+    .expect("a valid edge list");
+    let tree = minimum_spanning_tree(&cables.graph);
+    for (a, b, km) in tree.edges {
+        println!("{} - {}: {} km", cables.name(a), cables.name(b), km);
+    }
+    println!("{} km of cable in total", tree.weight);
+
+    // A* through a maze, with the Manhattan distance as the heuristic.
+    let maze = Grid::parse(
+        "\
+.....#....
+.###.#.##.
+...#...#..
+##.#####.#
+.........#",
+    );
+    let (start, goal) = ((0, 0), (0, 9));
+    let manhattan = |(r, c): (usize, usize)| {
+        (r.max(goal.0) - r.min(goal.0) + c.max(goal.1) - c.min(goal.1)) as u32
+    };
+    match a_star(&maze, start, goal, manhattan) {
+        Some((steps, path)) => println!("{} steps through the maze: {:?}", steps, path),
+        None => println!("no way through the maze"),
+    }
+}
+
+pub const USAGE: &str =
+    "    fungi graph [--undirected] [--matrix] <file> <algorithm> [<from> [<to>]]
+                                run bfs, dfs, dijkstra, bellman-ford, topo, scc or mst
+                                on an edge list ('from to [weight]' lines) or a .dot
+                                file; edge lists are directed unless --undirected";
+
+/// A parsed `fungi graph` command line.
+#[derive(Debug, PartialEq)]
+pub struct Invocation {
+    pub file: String,
+    pub algorithm: Algorithm,
+    pub undirected: bool,
+    pub matrix: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Algorithm {
+    Bfs(String),
+    Dfs(String),
+    Dijkstra(String, Option<String>),
+    BellmanFord(String, Option<String>),
+    Topo,
+    Scc,
+    Mst,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let mut undirected = false;
+    let mut matrix = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--undirected" => undirected = true,
+            "--matrix" => matrix = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let (file, algorithm, nodes) = match *positional.as_slice() {
+        [ref file, ref algorithm, ref nodes @ ..] => (file.clone(), algorithm.as_str(), nodes),
+        _ => return Err(String::from("graph needs a file and an algorithm")),
+    };
+    let algorithm = match (algorithm, nodes) {
+        ("bfs", [from]) => Algorithm::Bfs(from.clone()),
+        ("dfs", [from]) => Algorithm::Dfs(from.clone()),
+        ("dijkstra", [from]) => Algorithm::Dijkstra(from.clone(), None),
+        ("dijkstra", [from, to]) => Algorithm::Dijkstra(from.clone(), Some(to.clone())),
+        ("bellman-ford", [from]) => Algorithm::BellmanFord(from.clone(), None),
+        ("bellman-ford", [from, to]) => Algorithm::BellmanFord(from.clone(), Some(to.clone())),
+        ("topo", []) => Algorithm::Topo,
+        ("scc", []) => Algorithm::Scc,
+        ("mst", []) => Algorithm::Mst,
+        ("bfs", _) | ("dfs", _) => return Err(format!("{} needs a start node", algorithm)),
+        ("dijkstra", _) | ("bellman-ford", _) => {
+            return Err(format!(
+                "{} needs a start node, and maybe a goal",
+                algorithm
+            ))
+        }
+        ("topo", _) | ("scc", _) | ("mst", _) => {
+            return Err(format!("{} takes no nodes", algorithm))
+        }
+        (other, _) => return Err(format!("unknown algorithm '{}'", other)),
+    };
+    Ok(Invocation {
+        file,
+        algorithm,
+        undirected,
+        matrix,
+    })
+}
+
+/// Weights are read as f64, the nodes are named as in the file.
+pub fn run(invocation: &Invocation) -> Result<(), String> {
+    let directed = !invocation.undirected;
+    let error = |e: LoadError| format!("{}: {}", invocation.file, e);
+    if invocation.matrix {
+        let loaded: Loaded<AdjacencyMatrix<f64>> =
+            load(&invocation.file, directed).map_err(error)?;
+        execute(&loaded, &invocation.algorithm)
+    } else {
+        let loaded: Loaded<AdjacencyList<f64>> = load(&invocation.file, directed).map_err(error)?;
+        execute(&loaded, &invocation.algorithm)
+    }
+}
+
+fn execute<G: Build<Edge = f64>>(loaded: &Loaded<G>, algorithm: &Algorithm) -> Result<(), String> {
+    let node = |name: &str| {
+        loaded
+            .node(name)
+            .ok_or_else(|| format!("no node named '{}'", name))
+    };
+    let names = |nodes: &[usize]| {
+        nodes
+            .iter()
+            .map(|&n| loaded.name(n))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let graph = &loaded.graph;
+    match *algorithm {
+        Algorithm::Bfs(ref from) => {
+            println!("{}", names(&bfs(graph, node(from)?).collect::<Vec<_>>()))
+        }
+        Algorithm::Dfs(ref from) => {
+            println!("{}", names(&dfs(graph, node(from)?).collect::<Vec<_>>()))
+        }
+        Algorithm::Dijkstra(ref from, ref to) | Algorithm::BellmanFord(ref from, ref to) => {
+            let paths = if let Algorithm::Dijkstra(..) = *algorithm {
+                if let Some((a, b, w)) = graph.edges().into_iter().find(|e| e.2 < 0.0) {
+                    return Err(format!(
+                        "the edge {} -> {} weighs {}: use bellman-ford for negative weights",
+                        loaded.name(a),
+                        loaded.name(b),
+                        w
+                    ));
+                }
+                dijkstra(graph, node(from)?)
+            } else {
+                bellman_ford(graph, node(from)?)
+                    .map_err(|cycle| format!("negative cycle: {}", names(&cycle.nodes)))?
+            };
+            match *to {
+                Some(ref to) => match paths.path(node(to)?) {
+                    Some(path) => println!(
+                        "{}\t{}",
+                        paths.distance(node(to)?).unwrap_or(0.0),
+                        names(&path)
+                    ),
+                    None => return Err(format!("no path from '{}' to '{}'", from, to)),
+                },
+                None => {
+                    for (n, distance) in paths.distances() {
+                        println!("{}\t{}", loaded.name(n), distance);
+                    }
+                }
+            }
+        }
+        Algorithm::Topo => {
+            let order = topological_sort(graph).map_err(|cycle| {
+                format!("not a DAG, on or after a cycle: {}", names(&cycle.nodes))
+            })?;
+            println!("{}", names(&order));
+        }
+        Algorithm::Scc => {
+            for component in strongly_connected_components(graph) {
+                println!("{}", names(&component));
+            }
+        }
+        Algorithm::Mst => {
+            let tree = minimum_spanning_tree(graph);
+            for (a, b, w) in tree.edges {
+                println!("{}\t{}\t{}", loaded.name(a), loaded.name(b), w);
+            }
+            println!("total\t{}", tree.weight);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_the_command_line() {
+        assert_eq!(
+            parse_args(&args(&[
+                "--matrix",
+                "roads.txt",
+                "dijkstra",
+                "rome",
+                "bologna"
+            ])),
+            Ok(Invocation {
+                file: String::from("roads.txt"),
+                algorithm: Algorithm::Dijkstra(String::from("rome"), Some(String::from("bologna"))),
+                undirected: false,
+                matrix: true,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["g.dot", "scc"])).map(|i| i.algorithm),
+            Ok(Algorithm::Scc)
+        );
+        assert!(parse_args(&args(&["g.dot"])).is_err());
+        assert!(parse_args(&args(&["g.dot", "bfs"])).is_err());
+        assert!(parse_args(&args(&["g.dot", "mst", "a"])).is_err());
+        assert!(parse_args(&args(&["g.dot", "floyd"])).is_err());
+        assert!(parse_args(&args(&["--weighted", "g.dot", "scc"])).is_err());
+    }
+
+    #[test]
+    fn edges_of_undirected_graphs_are_listed_once() {
+        let mut g = AdjacencyList::with_nodes(3, false);
+        g.add_edge(1, 0, 2);
+        g.add_edge(2, 2, 1);
+        assert_eq!(g.edges(), vec![(0, 1, 2), (2, 2, 1)]);
+        assert_eq!(g.edge_count(), 2);
+    }
+}
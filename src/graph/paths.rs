@@ -0,0 +1,256 @@
+use super::{AGraph, Weight};
+use priority_queue::PriorityQueue;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The shortest paths from a source to every node it can reach.
+#[derive(Debug, Clone)]
+pub struct ShortestPaths<N, W> {
+    distances: HashMap<N, W>,
+    previous: HashMap<N, N>,
+}
+
+impl<N: Copy + Eq + Hash, W: Weight> ShortestPaths<N, W> {
+    fn new(source: N) -> ShortestPaths<N, W> {
+        let mut distances = HashMap::new();
+        distances.insert(source, W::zero());
+        ShortestPaths {
+            distances,
+            previous: HashMap::new(),
+        }
+    }
+
+    // Go through `from` if it's shorter: true if it is.
+    fn relax(&mut self, from: N, to: N, weight: W) -> bool {
+        let distance = self.distances[&from] + weight;
+        match self.distances.get(&to) {
+            Some(&d) if d <= distance => false,
+            _ => {
+                self.distances.insert(to, distance);
+                self.previous.insert(to, from);
+                true
+            }
+        }
+    }
+
+    /// None if the node can't be reached.
+    pub fn distance(&self, to: N) -> Option<W> {
+        self.distances.get(&to).cloned()
+    }
+
+    /// From the source to the node, both included.
+    pub fn path(&self, to: N) -> Option<Vec<N>> {
+        if !self.distances.contains_key(&to) {
+            return None;
+        }
+        let mut path = vec![to];
+        let mut node = to;
+        while let Some(&previous) = self.previous.get(&node) {
+            path.push(previous);
+            node = previous;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The reachable nodes, nearest first.
+    pub fn distances(&self) -> Vec<(N, W)> {
+        let mut distances: Vec<_> = self.distances.iter().map(|(&n, &w)| (n, w)).collect();
+        distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        distances
+    }
+}
+
+// The queue holds (estimated cost of the paths through the node, node), the
+// cheapest first: the weights are only PartialOrd, so NaNs count as equal.
+fn cheapest_first<N: Ord, W: PartialOrd>(a: &(W, N), b: &(W, N)) -> Ordering {
+    a.0.partial_cmp(&b.0)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a.1.cmp(&b.1))
+}
+
+// Dijkstra is A* with a heuristic that knows nothing: the search stops at
+// the goal, if there is one.
+fn search<G, H>(
+    graph: &G,
+    source: G::Node,
+    goal: Option<G::Node>,
+    heuristic: H,
+) -> ShortestPaths<G::Node, G::Edge>
+where
+    G: AGraph + ?Sized,
+    H: Fn(G::Node) -> G::Edge,
+{
+    let mut paths = ShortestPaths::new(source);
+    let mut queue = PriorityQueue::with_comparator(cheapest_first);
+    let mut handles = HashMap::new();
+    handles.insert(source, queue.push((heuristic(source), source)));
+    while let Some((_, node)) = queue.pop() {
+        if Some(node) == goal {
+            break;
+        }
+        for (next, weight) in graph.neighbors(node) {
+            if !paths.relax(node, next, weight) {
+                continue;
+            }
+            // A shorter way to a node in the queue moves it forward. A node
+            // that already left comes back only if the heuristic
+            // overestimated somewhere.
+            let state = (paths.distances[&next] + heuristic(next), next);
+            let state = match handles.get(&next) {
+                Some(&handle) => match queue.decrease_key(handle, state) {
+                    Ok(_) => continue,
+                    Err(state) => state,
+                },
+                None => state,
+            };
+            handles.insert(next, queue.push(state));
+        }
+    }
+    paths
+}
+
+/// The shortest paths from the source to every node, the weights must not be
+/// negative.
+pub fn dijkstra<G: AGraph + ?Sized>(graph: &G, source: G::Node) -> ShortestPaths<G::Node, G::Edge> {
+    search(graph, source, None, |_| G::Edge::zero())
+}
+
+/// The shortest path from the start to the goal, and its length.
+///
+/// The heuristic estimates the distance from a node to the goal: as long as
+/// it never overestimates, the path is a shortest one, and the better the
+/// estimate the fewer nodes are explored.
+pub fn a_star<G, H>(
+    graph: &G,
+    start: G::Node,
+    goal: G::Node,
+    heuristic: H,
+) -> Option<(G::Edge, Vec<G::Node>)>
+where
+    G: AGraph + ?Sized,
+    H: Fn(G::Node) -> G::Edge,
+{
+    let paths = search(graph, start, Some(goal), heuristic);
+    let distance = paths.distance(goal)?;
+    paths.path(goal).map(|path| (distance, path))
+}
+
+/// Some weights are negative, and a way round these nodes is shorter every
+/// time: there is no shortest path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegativeCycle<N> {
+    pub nodes: Vec<N>,
+}
+
+type PathsOrCycle<N, W> = Result<ShortestPaths<N, W>, NegativeCycle<N>>;
+
+/// The shortest paths from the source to every node, with negative weights
+/// too (Bellman-Ford): slower than Dijkstra, O(nodes × edges).
+pub fn bellman_ford<G: AGraph + ?Sized>(
+    graph: &G,
+    source: G::Node,
+) -> PathsOrCycle<G::Node, G::Edge> {
+    let mut paths = ShortestPaths::new(source);
+    let nodes: Vec<_> = graph.nodes().collect();
+    // After round i, the shortest paths with up to i edges are known.
+    for _ in 1..nodes.len() {
+        let mut changed = false;
+        for &node in &nodes {
+            if !paths.distances.contains_key(&node) {
+                continue;
+            }
+            for (next, weight) in graph.neighbors(node) {
+                changed |= paths.relax(node, next, weight);
+            }
+        }
+        if !changed {
+            return Ok(paths);
+        }
+    }
+    // One more round: a path that still gets shorter goes round a cycle.
+    for &node in &nodes {
+        if !paths.distances.contains_key(&node) {
+            continue;
+        }
+        for (next, weight) in graph.neighbors(node) {
+            if paths.relax(node, next, weight) {
+                // Going back from here as many steps as there are nodes can
+                // only end up on the cycle.
+                let mut on_cycle = next;
+                for _ in 0..nodes.len() {
+                    on_cycle = paths.previous[&on_cycle];
+                }
+                let mut cycle = vec![on_cycle];
+                let mut n = paths.previous[&on_cycle];
+                while n != on_cycle {
+                    cycle.push(n);
+                    n = paths.previous[&n];
+                }
+                cycle.reverse();
+                return Err(NegativeCycle { nodes: cycle });
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AdjacencyList, AdjacencyMatrix, Build};
+    use super::*;
+
+    // The graph of scripts/dijkstra.rs.
+    fn script<G: Build<Edge = i32>>() -> G {
+        let mut g = G::with_nodes(5, true);
+        for &(a, b, w) in &[
+            (0, 2, 10),
+            (0, 1, 1),
+            (1, 3, 2),
+            (2, 1, 1),
+            (2, 3, 3),
+            (2, 4, 1),
+            (3, 0, 7),
+            (3, 4, 2),
+        ] {
+            g.add_edge(a, b, w);
+        }
+        g
+    }
+
+    #[test]
+    fn shortest_paths() {
+        let list: AdjacencyList<i32> = script();
+        let matrix: AdjacencyMatrix<i32> = script();
+        for paths in &[
+            dijkstra(&list, 0),
+            dijkstra(&matrix, 0),
+            bellman_ford(&list, 0).unwrap(),
+        ] {
+            assert_eq!(paths.distance(1), Some(1));
+            assert_eq!(paths.distance(3), Some(3));
+            assert_eq!(paths.distance(4), Some(5));
+            assert_eq!(paths.path(4), Some(vec![0, 1, 3, 4]));
+        }
+        assert_eq!(dijkstra(&list, 4).distance(0), None);
+        assert_eq!(dijkstra(&list, 3).path(2), Some(vec![3, 0, 2]));
+        assert_eq!(a_star(&list, 3, 1, |_| 0), Some((8, vec![3, 0, 1])));
+        assert_eq!(a_star(&list, 4, 0, |_| 0), None);
+    }
+
+    #[test]
+    fn negative_weights() {
+        let mut g: AdjacencyList<i32> = script();
+        g.add_edge(4, 2, -1);
+        let paths = bellman_ford(&g, 0).unwrap();
+        assert_eq!(paths.distance(2), Some(4));
+        assert_eq!(paths.path(2), Some(vec![0, 1, 3, 4, 2]));
+
+        g.add_edge(1, 4, -1);
+        let cycle = bellman_ford(&g, 0).unwrap_err();
+        let mut nodes = cycle.nodes.clone();
+        nodes.sort();
+        assert_eq!(nodes, vec![1, 2, 4]);
+    }
+}
@@ -0,0 +1,114 @@
+use super::{AGraph, Weight};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// The edges that connect all the nodes with the least total weight (a
+/// forest, one tree per connected component, if the graph isn't connected).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanningTree<N, W> {
+    pub edges: Vec<(N, N, W)>,
+    pub weight: W,
+}
+
+// Which set every node belongs to: two nodes are in the same set if they
+// have the same root.
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn root(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            // Path halving: every other node points to its grandparent.
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    // False if they were already in the same set.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.root(a), self.root(b));
+        if a == b {
+            return false;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            Ordering::Less => self.parent[a] = b,
+            Ordering::Greater => self.parent[b] = a,
+            Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+        true
+    }
+}
+
+/// Kruskal's algorithm: the lightest edges first, unless they close a cycle.
+/// The direction of the edges is ignored.
+pub fn minimum_spanning_tree<G: AGraph + ?Sized>(graph: &G) -> SpanningTree<G::Node, G::Edge> {
+    let ids: HashMap<G::Node, usize> = graph.nodes().enumerate().map(|(i, n)| (n, i)).collect();
+    let mut edges = graph.edges();
+    edges.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+
+    let mut sets = UnionFind::new(ids.len());
+    let mut tree = SpanningTree {
+        edges: Vec::new(),
+        weight: G::Edge::zero(),
+    };
+    for (a, b, w) in edges {
+        if sets.union(ids[&a], ids[&b]) {
+            tree.edges.push((a, b, w));
+            tree.weight = tree.weight + w;
+        }
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AdjacencyList, AdjacencyMatrix, Build};
+    use super::*;
+
+    fn graph<G: Build<Edge = f64>>() -> G {
+        // A square with its diagonals, and an island.
+        let mut g = G::with_nodes(6, false);
+        let edges = [
+            (0, 1, 1.0),
+            (1, 2, 2.0),
+            (2, 3, 1.5),
+            (3, 0, 4.0),
+            (0, 2, 2.5),
+            (1, 3, 0.5),
+            (4, 5, 9.0),
+        ];
+        for &(a, b, w) in &edges {
+            g.add_edge(a, b, w);
+        }
+        g
+    }
+
+    #[test]
+    fn kruskal() {
+        let expected = SpanningTree {
+            edges: vec![(1, 3, 0.5), (0, 1, 1.0), (2, 3, 1.5), (4, 5, 9.0)],
+            weight: 12.0,
+        };
+        assert_eq!(
+            minimum_spanning_tree(&graph::<AdjacencyList<f64>>()),
+            expected
+        );
+        assert_eq!(
+            minimum_spanning_tree(&graph::<AdjacencyMatrix<f64>>()),
+            expected
+        );
+    }
+}
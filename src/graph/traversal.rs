@@ -0,0 +1,283 @@
+use super::{AGraph, GGraph};
+use std::cmp::{self, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+/// The nodes reachable from the start, nearest first.
+pub struct Bfs<'g, G: AGraph + ?Sized + 'g> {
+    graph: &'g G,
+    queue: VecDeque<G::Node>,
+    seen: HashSet<G::Node>,
+}
+
+pub fn bfs<'g, G: AGraph + ?Sized>(graph: &'g G, start: G::Node) -> Bfs<'g, G> {
+    let mut seen = HashSet::new();
+    seen.insert(start);
+    Bfs {
+        graph,
+        queue: vec![start].into_iter().collect(),
+        seen,
+    }
+}
+
+impl<'g, G: AGraph + ?Sized> Iterator for Bfs<'g, G> {
+    type Item = G::Node;
+
+    fn next(&mut self) -> Option<G::Node> {
+        let node = self.queue.pop_front()?;
+        for (next, _) in self.graph.neighbors(node) {
+            if self.seen.insert(next) {
+                self.queue.push_back(next);
+            }
+        }
+        Some(node)
+    }
+}
+
+/// How many edges there are on the shortest way from one node to the other,
+/// whatever they weigh; none if there's no way.
+pub fn hops<N, E, G>(graph: &G, from: N, to: N) -> Option<usize>
+where
+    N: Copy + Eq + Hash,
+    G: GGraph<N, E> + ?Sized,
+{
+    let mut seen = HashSet::new();
+    seen.insert(from);
+    let mut queue: VecDeque<(N, usize)> = vec![(from, 0)].into_iter().collect();
+    while let Some((node, hops)) = queue.pop_front() {
+        if node == to {
+            return Some(hops);
+        }
+        for (next, _) in graph.neighbors(node) {
+            if seen.insert(next) {
+                queue.push_back((next, hops + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The nodes reachable from the start, each branch as deep as it goes before
+/// the next one (in preorder).
+pub struct Dfs<'g, G: AGraph + ?Sized + 'g> {
+    graph: &'g G,
+    stack: Vec<G::Node>,
+    seen: HashSet<G::Node>,
+}
+
+pub fn dfs<'g, G: AGraph + ?Sized>(graph: &'g G, start: G::Node) -> Dfs<'g, G> {
+    Dfs {
+        graph,
+        stack: vec![start],
+        seen: HashSet::new(),
+    }
+}
+
+impl<'g, G: AGraph + ?Sized> Iterator for Dfs<'g, G> {
+    type Item = G::Node;
+
+    fn next(&mut self) -> Option<G::Node> {
+        loop {
+            let node = self.stack.pop()?;
+            if !self.seen.insert(node) {
+                continue;
+            }
+            // Reversed, so that the first neighbor is the first one popped.
+            let mut unseen: Vec<_> = self
+                .graph
+                .neighbors(node)
+                .map(|(n, _)| n)
+                .filter(|n| !self.seen.contains(n))
+                .collect();
+            unseen.reverse();
+            self.stack.extend(unseen);
+            return Some(node);
+        }
+    }
+}
+
+/// Found by a topological sort: these nodes are on a cycle, or after one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle<N> {
+    pub nodes: Vec<N>,
+}
+
+/// Every node before the nodes its edges go to (Kahn's algorithm). Among the
+/// nodes that are ready at the same time, the smallest comes first.
+pub fn topological_sort<G: AGraph + ?Sized>(graph: &G) -> Result<Vec<G::Node>, Cycle<G::Node>> {
+    let mut incoming: HashMap<G::Node, usize> = graph.nodes().map(|n| (n, 0)).collect();
+    for node in graph.nodes() {
+        for (next, _) in graph.neighbors(node) {
+            *incoming.entry(next).or_insert(0) += 1;
+        }
+    }
+    let mut ready: BinaryHeap<_> = incoming
+        .iter()
+        .filter(|&(_, &count)| count == 0)
+        .map(|(&n, _)| Reverse(n))
+        .collect();
+    let mut sorted = Vec::with_capacity(incoming.len());
+    while let Some(Reverse(node)) = ready.pop() {
+        sorted.push(node);
+        for (next, _) in graph.neighbors(node) {
+            let count = incoming.get_mut(&next).expect("a node of the graph");
+            *count -= 1;
+            if *count == 0 {
+                ready.push(Reverse(next));
+            }
+        }
+    }
+    if sorted.len() == incoming.len() {
+        Ok(sorted)
+    } else {
+        let mut nodes: Vec<_> = incoming
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(n, _)| n)
+            .collect();
+        nodes.sort();
+        Err(Cycle { nodes })
+    }
+}
+
+// The state of Tarjan's algorithm.
+struct Tarjan<N> {
+    // The order of discovery of every node, and the lowest one reachable from
+    // it through the nodes still on the stack.
+    index: HashMap<N, usize>,
+    low: HashMap<N, usize>,
+    stack: Vec<N>,
+    on_stack: HashSet<N>,
+    components: Vec<Vec<N>>,
+}
+
+impl<N: Copy + Eq + Hash + Ord> Tarjan<N> {
+    fn discover(&mut self, node: N) {
+        let i = self.index.len();
+        self.index.insert(node, i);
+        self.low.insert(node, i);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+    }
+
+    fn lower(&mut self, node: N, to: usize) {
+        let low = self.low.get_mut(&node).expect("a discovered node");
+        *low = cmp::min(*low, to);
+    }
+
+    // Every node of the stack above the root of a component is part of it.
+    fn finish(&mut self, node: N) {
+        if self.low[&node] != self.index[&node] {
+            return;
+        }
+        let mut component = Vec::new();
+        loop {
+            let n = self.stack.pop().expect("the node is on the stack");
+            self.on_stack.remove(&n);
+            component.push(n);
+            if n == node {
+                break;
+            }
+        }
+        component.sort();
+        self.components.push(component);
+    }
+}
+
+/// The groups of nodes that can all reach each other (Tarjan's algorithm,
+/// without recursion). The nodes of a component are sorted, and a component
+/// comes before the components that can reach it.
+pub fn strongly_connected_components<G: AGraph + ?Sized>(graph: &G) -> Vec<Vec<G::Node>> {
+    let mut tarjan = Tarjan {
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    let neighbors = |node| -> Vec<G::Node> { graph.neighbors(node).map(|(n, _)| n).collect() };
+
+    for root in graph.nodes() {
+        if tarjan.index.contains_key(&root) {
+            continue;
+        }
+        // What the recursive version keeps on the call stack: a node, its
+        // neighbors, and how many of them have been visited.
+        tarjan.discover(root);
+        let mut calls = vec![(root, neighbors(root), 0)];
+        while let Some(&mut (node, ref neighbors_of_node, ref mut visited)) = calls.last_mut() {
+            let next = neighbors_of_node.get(*visited).cloned();
+            *visited += 1;
+            match next {
+                Some(next) if !tarjan.index.contains_key(&next) => {
+                    tarjan.discover(next);
+                    calls.push((next, neighbors(next), 0));
+                }
+                Some(next) => {
+                    if tarjan.on_stack.contains(&next) {
+                        let index = tarjan.index[&next];
+                        tarjan.lower(node, index);
+                    }
+                }
+                None => {
+                    calls.pop();
+                    if let Some(&(parent, _, _)) = calls.last() {
+                        let low = tarjan.low[&node];
+                        tarjan.lower(parent, low);
+                    }
+                    tarjan.finish(node);
+                }
+            }
+        }
+    }
+    tarjan.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AdjacencyList, Build};
+    use super::*;
+
+    fn graph(directed: bool, nodes: usize, edges: &[(usize, usize)]) -> AdjacencyList<u32> {
+        let mut g = AdjacencyList::with_nodes(nodes, directed);
+        for &(a, b) in edges {
+            g.add_edge(a, b, 1);
+        }
+        g
+    }
+
+    #[test]
+    fn traversals() {
+        let g = graph(false, 7, &[(0, 1), (0, 2), (1, 3), (1, 4), (2, 5), (6, 6)]);
+        assert_eq!(bfs(&g, 0).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(dfs(&g, 0).collect::<Vec<_>>(), vec![0, 1, 3, 4, 2, 5]);
+        assert_eq!(dfs(&g, 6).collect::<Vec<_>>(), vec![6]);
+        assert_eq!(hops(&g, 3, 5), Some(4));
+        assert_eq!(hops(&g, 6, 6), Some(0));
+        assert_eq!(hops(&g, 0, 6), None);
+        let object: &dyn GGraph<usize, u32> = &g;
+        assert_eq!(hops(object, 5, 4), Some(4));
+    }
+
+    #[test]
+    fn orders_and_components() {
+        let dag = graph(true, 5, &[(3, 1), (1, 0), (4, 0), (2, 4)]);
+        assert_eq!(topological_sort(&dag), Ok(vec![2, 3, 1, 4, 0]));
+
+        let g = graph(
+            true,
+            6,
+            &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3), (5, 4)],
+        );
+        assert_eq!(
+            topological_sort(&g),
+            Err(Cycle {
+                nodes: vec![0, 1, 2, 3, 4]
+            })
+        );
+        assert_eq!(
+            strongly_connected_components(&g),
+            vec![vec![3, 4], vec![0, 1, 2], vec![5]]
+        );
+    }
+}
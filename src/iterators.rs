@@ -35,6 +35,12 @@ fn iterator_demonstration() {
     assert_eq!(v1_iter.next(), None);
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "iterators",
+    description: "the Iterator trait, adaptors and consumers",
+    run: sample,
+};
+
 pub fn sample() {
     let v1 = vec![1, 2, 3];
 
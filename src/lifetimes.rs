@@ -286,6 +286,12 @@ fn four() {
     }
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "lifetimes",
+    description: "lifetime annotations on functions and structs",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -1,111 +1,419 @@
 //! # LinkedLists
 //!
-//! A library with a simple example of linked lists.
+//! A generic singly linked list, with a pointer to its last node so that
+//! appending is O(1).
 //!
-//! Based on [Rust By Examples - testcase linked lists](https://rustbyexample.com/custom_types/enum/testcase_linked_list.html)
+//! Started from [Rust By Examples - testcase linked lists](https://rustbyexample.com/custom_types/enum/testcase_linked_list.html)
+//! and grown following [Learning Rust With Entirely Too Many Linked Lists](http://cglab.ca/~abeinges/blah/too-many-lists/book/).
 
-use self::List::*;
 use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
 
-/// List as a Tuple Struct
+// The first version of this list was a recursive enum:
+//
+// enum List {
+//     Cons(u32, Box<List>),
+//     Nil,
+// }
+//
+// It's the simplest thing that works, but finding the end of the list means
+// walking all of it, so appending is O(n). Keeping a second pointer to the
+// last node fixes that, but a node can't be owned by a Box and, at the same
+// time, be pointed by another (mutable) pointer: the tail pointer would alias
+// the Box. So the nodes are allocated through a Box and then owned through raw
+// (non null) pointers, and giving them back to a Box is the only way to free
+// them.
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    elem: T,
+    next: Link<T>,
+}
+
+/// A singly linked list.
 ///
-/// Cons: Tuple struct that wraps an element and a pointer to the next node
-/// Nil: A node that signifies the end of the linked list
-enum List {
-    Cons(u32, Box<List>),
-    Nil,
+/// Pushing and popping at the front and pushing at the back are O(1),
+/// everything addressed by an index is O(n).
+pub struct List<T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    // The list owns its nodes, even if through raw pointers: tell the drop
+    // checker that dropping a List<T> drops some T.
+    marker: PhantomData<Box<Node<T>>>,
 }
 
-impl fmt::Debug for List {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Cons(x, ref t) => write!(f, "list (Cons) {:?} with tail: {:?}", x, t),
-            Nil => write!(f, "list (empty)"),
-        }
-    }
-}
-
-// Methods can be attached to an enum
-#[allow(dead_code)]
-#[allow(unused_variables)]
-impl List {
-    // Create an empty list
-    fn new() -> List {
-        // `Nil` has type `List`
-        Nil
-    }
-
-    // Consume a list, and return the same list with a new element at its front
-    fn prepend(self, elem: u32) -> List {
-        // `Cons` also has type List
-        Cons(elem, Box::new(self))
-    }
-
-    // returns a mutable reference to the last item (back) which will always be
-    // the Nil variant.
-    fn back(&mut self) -> &mut List {
-        let mut node = self;
-
-        loop {
-            // https://stackoverflow.com/questions/37986640/obtaining-a-mutable-reference-by-iterating-a-recursive-structure
-            // The trick here is that using {anchor} moves the content of anchor
-            // into an unnamed temporary on which the match executes. Therefore,
-            // in the match block we are not borrowing from anchor but from the
-            // temporary, leaving us free to modify anchor.
-            // See the related blog post Stuff the Identity Function Does (in Rust).
-            // https://bluss.github.io/rust/fun/2015/10/11/stuff-the-identity-function-does/
-            //
-            // we need to transfer ownership of the mutable reference when
-            // performing iteration. This is needed to ensure you never have two
-            // mutable references to the same thing.
-            match { node } {
-                &mut Cons(_, ref mut next) => node = next,
-                other => return other,
-            }
+// Raw pointers are neither Send nor Sync, but the list is just a container:
+// it's as thread safe as the elements it holds.
+unsafe impl<T: Send> Send for List<T> {}
+unsafe impl<T: Sync> Sync for List<T> {}
+
+impl<T> List<T> {
+    /// Create an empty list.
+    pub fn new() -> List<T> {
+        List {
+            head: None,
+            tail: None,
+            len: 0,
+            marker: PhantomData,
         }
     }
 
-    /// Takes a mutable reference to self and the element to append as the
-    /// "last" node of the list (actually the last element will always be the
-    /// Nil value of the tuple struct).
-    fn append_ref(&mut self, elem: u32) {
-        *self.back() = Cons(elem, Box::new(Nil));
+    /// Consume a list, and return the same list with a new element at its
+    /// front.
+    pub fn prepend(mut self, elem: T) -> List<T> {
+        self.push_front(elem);
+        self
     }
 
     /// Append the given element to the list.
-    fn append(&mut self, elem: u32) -> &Self {
-        self.append_ref(elem);
+    pub fn append(&mut self, elem: T) -> &mut Self {
+        self.push_back(elem);
         self
     }
 
-    // Return the length of the list
-    fn len(&self) -> u32 {
-        // `self` has to be matched, because the behavior of this method
-        // depends on the variant of `self`
-        // `self` has type `&List`, and `*self` has type `List`, matching on a
-        // concrete type `T` is preferred over a match on a reference `&T`
-        match *self {
-            // Can't take ownership of the tail, because `self` is borrowed;
-            // instead take a reference to the tail
-            Cons(_, ref tail) => 1 + tail.len(),
-            // Base Case: An empty list has zero length
-            Nil => 0,
-        }
-    }
-
-    // Return representation of the list as a (heap allocated) string
-    fn stringify(&self) -> String {
-        match *self {
-            Cons(head, ref tail) => {
-                // `format!` is similar to `print!`, but returns a heap
-                // allocated string instead of printing to the console
-                format!("{}, {}", head, tail.stringify())
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        let node = Box::new(Node {
+            elem,
+            next: self.head,
+        });
+        let node = Some(NonNull::from(Box::leak(node)));
+        if self.tail.is_none() {
+            self.tail = node;
+        }
+        self.head = node;
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        let node = Box::new(Node { elem, next: None });
+        let node = Some(NonNull::from(Box::leak(node)));
+        match self.tail {
+            Some(tail) => unsafe { (*tail.as_ptr()).next = node },
+            None => self.head = node,
+        }
+        self.tail = node;
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.map(|head| {
+            // Back into a Box: the node is freed at the end of this closure.
+            let node = unsafe { Box::from_raw(head.as_ptr()) };
+            self.head = node.next;
+            if self.head.is_none() {
+                self.tail = None;
+            }
+            self.len -= 1;
+            node.elem
+        })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    // The node at the given index; the caller checks that it exists.
+    fn node_at(&self, index: usize) -> NonNull<Node<T>> {
+        debug_assert!(index < self.len);
+        let mut node = self.head.unwrap();
+        for _ in 0..index {
+            node = unsafe { (*node.as_ptr()).next.unwrap() };
+        }
+        node
+    }
+
+    /// Insert an element at the given position, shifting all the elements
+    /// after it towards the back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the list.
+    pub fn insert_at(&mut self, index: usize, elem: T) {
+        assert!(
+            index <= self.len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            self.len
+        );
+        if index == 0 {
+            return self.push_front(elem);
+        }
+        if index == self.len {
+            return self.push_back(elem);
+        }
+        let prev = self.node_at(index - 1);
+        unsafe {
+            let node = Box::new(Node {
+                elem,
+                next: (*prev.as_ptr()).next,
+            });
+            (*prev.as_ptr()).next = Some(NonNull::from(Box::leak(node)));
+        }
+        self.len += 1;
+    }
+
+    /// Remove and return the element at the given position, `None` if there
+    /// isn't one.
+    pub fn remove_at(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        if index == 0 {
+            return self.pop_front();
+        }
+        let prev = self.node_at(index - 1);
+        unsafe {
+            let node = Box::from_raw((*prev.as_ptr()).next.unwrap().as_ptr());
+            (*prev.as_ptr()).next = node.next;
+            if node.next.is_none() {
+                self.tail = Some(prev);
             }
-            Nil => format!("Nil"),
+            self.len -= 1;
+            Some(node.elem)
+        }
+    }
+
+    /// Reverse the list in place, without moving any element.
+    pub fn reverse(&mut self) {
+        let mut prev: Link<T> = None;
+        let mut current = self.head;
+        self.tail = self.head;
+        while let Some(node) = current {
+            unsafe {
+                current = (*node.as_ptr()).next;
+                (*node.as_ptr()).next = prev;
+            }
+            prev = Some(node);
+        }
+        self.head = prev;
+    }
+
+    /// Split the list in two at the given index: `self` keeps the elements
+    /// in `[0, at)` and the returned list gets the ones in `[at, len)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is greater than the length of the list.
+    pub fn split_off(&mut self, at: usize) -> List<T> {
+        assert!(at <= self.len, "`at` out of bounds");
+        if at == 0 {
+            return mem::take(self);
+        }
+        let prev = self.node_at(at - 1);
+        let other = List {
+            head: unsafe { (*prev.as_ptr()).next.take() },
+            tail: if at == self.len { None } else { self.tail },
+            len: self.len - at,
+            marker: PhantomData,
+        };
+        self.tail = Some(prev);
+        self.len = at;
+        other
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            next: self.head,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: fmt::Display> List<T> {
+    /// Return representation of the list as a (heap allocated) string
+    pub fn stringify(&self) -> String {
+        let mut s = String::new();
+        for elem in self {
+            s.push_str(&format!("{}, ", elem));
+        }
+        s.push_str("Nil");
+        s
+    }
+}
+
+// The default, recursive, drop would first drop the head node, which drops
+// its `next`, which drops its own `next`... one stack frame per node. Popping
+// the nodes one at the time keeps the stack flat, whatever the length.
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> List<T> {
+        List::new()
+    }
+}
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> List<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &List<T>) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push_back(elem);
         }
     }
 }
 
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> List<T> {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+pub struct Iter<'a, T: 'a> {
+    next: Link<T>,
+    len: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.next.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            self.next = node.next;
+            self.len -= 1;
+            &node.elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+pub struct IterMut<'a, T: 'a> {
+    next: Link<T>,
+    len: usize,
+    marker: PhantomData<&'a mut Node<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.next.map(|node| unsafe {
+            let node = &mut *node.as_ptr();
+            self.next = node.next;
+            self.len -= 1;
+            &mut node.elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+pub struct IntoIter<T>(List<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "linked_lists",
+    description: "a generic singly linked list with O(1) append",
+    run: sample,
+};
+
 pub fn sample() {
     // Create an empty linked list
     let mut list = List::new();
@@ -118,6 +426,16 @@ pub fn sample() {
     // Show the final state of the list
     println!("linked list has length: {}", list.len());
     println!("{}", list.stringify());
+
+    // Any type will do, and the usual iterator machinery works too.
+    let mut words: List<String> = "she sells sea shells"
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    words.insert_at(2, String::from("salty"));
+    words.reverse();
+    let tail = words.split_off(2);
+    println!("{:?} and {:?}", words, tail);
 }
 
 // Rust test programs hide the stdout of successful tests in order for the test
@@ -139,3 +457,138 @@ fn linked_list_prepend() {
     assert_eq!(ll.len(), 3);
     assert_eq!(ll.stringify(), String::from("1, 0, 2, Nil"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::List;
+    use quickcheck::{Arbitrary, Gen};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn iterators() {
+        let mut list: List<u32> = (1..4).collect();
+        for x in list.iter_mut() {
+            *x *= 10;
+        }
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&10, &20, &30]);
+        list.extend(vec![40, 50]);
+        assert_eq!(
+            list.into_iter().collect::<Vec<_>>(),
+            vec![10, 20, 30, 40, 50]
+        );
+    }
+
+    #[test]
+    fn tail_follows_the_last_node() {
+        let mut list: List<u32> = (0..5).collect();
+        assert_eq!(list.remove_at(4), Some(4));
+        assert_eq!(list.back(), Some(&3));
+        list.reverse();
+        assert_eq!(list.back(), Some(&0));
+        let other = list.split_off(2);
+        assert_eq!(list.back(), Some(&2));
+        assert_eq!(other.back(), Some(&0));
+        list.push_back(7);
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![3, 2, 7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "insertion index (is 2) should be <= len (is 1)")]
+    fn insert_out_of_bounds() {
+        let mut list = List::new();
+        list.push_back(1);
+        list.insert_at(2, 2);
+    }
+
+    #[test]
+    fn drop_a_million_nodes() {
+        let list: List<u32> = (0..1_000_000).collect();
+        assert_eq!(list.len(), 1_000_000);
+        drop(list);
+    }
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        PushFront(i32),
+        PushBack(i32),
+        PopFront,
+        InsertAt(usize, i32),
+        RemoveAt(usize),
+        Reverse,
+        SplitOff(usize),
+        Extend(Vec<i32>),
+    }
+
+    impl Arbitrary for Op {
+        fn arbitrary(g: &mut Gen) -> Op {
+            match u8::arbitrary(g) % 8 {
+                0 => Op::PushFront(i32::arbitrary(g)),
+                1 => Op::PushBack(i32::arbitrary(g)),
+                2 => Op::PopFront,
+                3 => Op::InsertAt(usize::arbitrary(g), i32::arbitrary(g)),
+                4 => Op::RemoveAt(usize::arbitrary(g)),
+                5 => Op::Reverse,
+                6 => Op::SplitOff(usize::arbitrary(g)),
+                _ => Op::Extend(Vec::arbitrary(g)),
+            }
+        }
+    }
+
+    // Run the same operations on a List and on a VecDeque: they must agree on
+    // every result and on the content after every step.
+    quickcheck! {
+        fn behaves_like_a_vec_deque(ops: Vec<Op>) -> bool {
+            let mut list = List::new();
+            let mut deque = VecDeque::new();
+            for op in ops {
+                let agree = match op {
+                    Op::PushFront(x) => {
+                        list.push_front(x);
+                        deque.push_front(x);
+                        true
+                    }
+                    Op::PushBack(x) => {
+                        list.push_back(x);
+                        deque.push_back(x);
+                        true
+                    }
+                    Op::PopFront => list.pop_front() == deque.pop_front(),
+                    Op::InsertAt(i, x) => {
+                        let i = i % (deque.len() + 1);
+                        list.insert_at(i, x);
+                        deque.insert(i, x);
+                        true
+                    }
+                    Op::RemoveAt(i) => {
+                        let i = i % (deque.len() + 1);
+                        list.remove_at(i) == deque.remove(i)
+                    }
+                    Op::Reverse => {
+                        list.reverse();
+                        deque = deque.into_iter().rev().collect();
+                        true
+                    }
+                    Op::SplitOff(i) => {
+                        let i = i % (deque.len() + 1);
+                        let back: Vec<i32> = list.split_off(i).into_iter().collect();
+                        back == deque.split_off(i).into_iter().collect::<Vec<_>>()
+                    }
+                    Op::Extend(v) => {
+                        list.extend(v.clone());
+                        deque.extend(v);
+                        true
+                    }
+                };
+                if !agree
+                    || list.len() != deque.len()
+                    || list.front() != deque.front()
+                    || list.back() != deque.back()
+                    || !list.iter().eq(deque.iter())
+                {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+}
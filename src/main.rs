@@ -1,15 +1,34 @@
 #[macro_use]
 extern crate lazy_static;
+extern crate conf;
+extern crate executor;
+extern crate priority_queue;
 extern crate regex;
+extern crate regex_syntax;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
+#[cfg(test)]
+#[macro_use]
+extern crate quickcheck;
+#[cfg(test)]
+extern crate rand;
 
 mod advanced;
 mod boxes_and_trees;
 mod closures;
 mod concurrency;
+mod config;
 mod enums;
+mod expr;
+mod extract;
 mod fibonacci;
 mod formatting;
 mod generics;
+mod graph;
 mod iterators;
 mod lifetimes;
 mod linked_lists;
@@ -25,53 +44,209 @@ mod ref_and_ampersand;
 mod regexp;
 mod smart_pointers;
 mod state_machine;
+mod stats;
 mod strings;
 mod structs;
 mod testing;
+mod text;
 mod to_string;
+mod topics;
 mod traits;
 mod types;
+mod units;
+mod valid_guess;
 mod vectors;
 
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage:
+    fungi list                  list the available topics
+    fungi run <topic>...        run the samples of the given topics
+    fungi run --all             run the samples of every topic, in order
+    fungi help                  show this message
+";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    List,
+    Run(Vec<String>),
+    RunAll,
+    Text(text::Invocation),
+    Extract(extract::Invocation),
+    Repl(expr::Invocation),
+    Graph(graph::Invocation),
+    Help,
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    match args.split_first() {
+        None => Ok(Command::Help),
+        Some((cmd, rest)) => match cmd.as_str() {
+            "list" if rest.is_empty() => Ok(Command::List),
+            "list" => Err(String::from("list takes no arguments")),
+            "run" if rest.iter().any(|a| a == "--all") => {
+                if rest.len() == 1 {
+                    Ok(Command::RunAll)
+                } else {
+                    Err(String::from("--all cannot be combined with topic names"))
+                }
+            }
+            "run" if rest.is_empty() => Err(String::from("run needs a topic name or --all")),
+            "run" => Ok(Command::Run(rest.to_vec())),
+            "text" => text::parse_args(rest).map(Command::Text),
+            "extract" => extract::parse_args(rest).map(Command::Extract),
+            "repl" => expr::parse_args(rest).map(Command::Repl),
+            "graph" => graph::parse_args(rest).map(Command::Graph),
+            "help" | "-h" | "--help" => Ok(Command::Help),
+            other => Err(format!("unknown command '{}'", other)),
+        },
+    }
+}
+
+// Resolve every requested name before running anything: a typo in the last
+// name should not leave the first topics half-run.
+fn resolve(names: &[String]) -> Result<Vec<&'static topics::Topic>, Vec<String>> {
+    let mut found = Vec::new();
+    let mut errors = Vec::new();
+    for name in names {
+        match topics::find(name) {
+            Some(t) => found.push(t),
+            None => {
+                let suggestions = topics::suggestions(name);
+                if suggestions.is_empty() {
+                    errors.push(format!("unknown topic '{}'", name));
+                } else {
+                    errors.push(format!(
+                        "unknown topic '{}', did you mean: {}?",
+                        name,
+                        suggestions.join(", ")
+                    ));
+                }
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(found)
+    } else {
+        Err(errors)
+    }
+}
+
+fn list() {
+    let width = topics::REGISTRY.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for t in topics::REGISTRY {
+        println!("{:width$}  {}", t.name, t.description, width = width);
+    }
+}
+
 fn main() {
-    types::sample();
-    modules::sample();
-    let x: u32 = fibonacci::fib(10);
-    println!("fib(10) = {}", x);
-    to_string::sample();
-    formatting::sample();
-    ownership::sample();
-    structs::sample();
-    enums::sample();
-    option::sample();
-    vectors::sample();
-    maps::sample();
-    strings::sample();
-    palindrome::sample();
-    boxes_and_trees::samples::boxes();
-    boxes_and_trees::samples::left_right();
-    generics::sample();
-    pointers_and_refs::sample();
-    ref_and_ampersand::sample();
-    traits::sample();
-    lifetimes::sample();
-    testing::sample();
-    closures::sample();
-    iterators::sample();
-    smart_pointers::sample();
-    concurrency::sample();
-    oop::sample();
-    patterns::sample();
-    advanced::sample();
-    regexp::sample();
-    linked_lists::sample();
-    state_machine::sample();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match parse_args(&args) {
+        Ok(Command::List) => list(),
+        Ok(Command::Help) => println!(
+            "{}{}\n{}\n{}\n{}",
+            USAGE,
+            text::USAGE,
+            extract::USAGE,
+            expr::USAGE,
+            graph::USAGE
+        ),
+        Ok(Command::RunAll) => {
+            for t in topics::REGISTRY {
+                (t.run)();
+            }
+        }
+        Ok(Command::Run(names)) => match resolve(&names) {
+            Ok(found) => {
+                for t in found {
+                    (t.run)();
+                }
+            }
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("error: {}", e);
+                }
+                eprintln!("run 'fungi list' to see the available topics");
+                process::exit(1);
+            }
+        },
+        Ok(Command::Text(invocation)) => {
+            if let Err(e) = text::run(&invocation) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Extract(invocation)) => {
+            if let Err(e) = extract::run(&invocation) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Repl(invocation)) => {
+            if let Err(e) = expr::run(&invocation) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Graph(invocation)) => {
+            if let Err(e) = graph::run(&invocation) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "error: {}\n\n{}{}\n{}\n{}\n{}",
+                e,
+                USAGE,
+                text::USAGE,
+                extract::USAGE,
+                expr::USAGE,
+                graph::USAGE
+            );
+            process::exit(2);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{parse_args, resolve, Command};
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
     #[test]
     fn it_works() {
         println!("it works... for now");
     }
+
+    #[test]
+    fn parses_the_command_line() {
+        assert_eq!(parse_args(&args(&[])), Ok(Command::Help));
+        assert_eq!(parse_args(&args(&["list"])), Ok(Command::List));
+        assert_eq!(parse_args(&args(&["run", "--all"])), Ok(Command::RunAll));
+        assert_eq!(
+            parse_args(&args(&["run", "closures", "iterators"])),
+            Ok(Command::Run(args(&["closures", "iterators"])))
+        );
+        assert!(parse_args(&args(&["run"])).is_err());
+        assert!(parse_args(&args(&["run", "--all", "closures"])).is_err());
+        assert!(parse_args(&args(&["walk"])).is_err());
+    }
+
+    #[test]
+    fn resolves_topics_in_the_given_order() {
+        let found = resolve(&args(&["iterators", "closures"])).unwrap();
+        let names: Vec<&str> = found.iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["iterators", "closures"]);
+
+        let errors = resolve(&args(&["closures", "lifetime"])).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![String::from("unknown topic 'lifetime', did you mean: lifetimes?")]
+        );
+    }
 }
@@ -96,6 +96,7 @@ fn two() {
 
 fn three() {
     use std::collections::HashMap;
+    use text::{Analysis, Options};
 
     let mut scores = HashMap::new();
 
@@ -117,18 +118,24 @@ fn three() {
 
     println!("{:?}", scores);
 
+    // Counting words is the classic use of the entry API: `or_insert` returns
+    // a mutable reference to the counter, a new one or the existing one.
+    // `text::Analysis` does it for real, with Unicode words, case folding and
+    // stop words (try `fungi text`).
     let text = "hello world wonderful world";
 
-    let mut map = HashMap::new();
-
-    for word in text.split_whitespace() {
-        let count = map.entry(word).or_insert(0);
-        *count += 1;
-    }
+    let mut analysis = Analysis::new();
+    analysis.feed(text, &Options::new());
 
-    println!("{:?}", map);
+    println!("{:?}", analysis.top_words(3));
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "maps",
+    description: "HashMap and BTreeMap, the entry API and counting words",
+    run: sample,
+};
+
 // By default, HashMap uses a cryptographically secure hashing function that can
 // provide resistance to Denial of Service (DoS) attacks. This is not the
 // fastest hashing algorithm available, but the trade-off for better security
@@ -41,6 +41,12 @@ impl fmt::Debug for TrafficLight {
 
 use self::TrafficLight::{Red, Yellow};
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "modules",
+    description: "nested modules and paths",
+    run: sample,
+};
+
 pub fn sample() {
     a::series::of::nested_modules();
     of::nested_modules();
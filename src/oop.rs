@@ -12,44 +12,59 @@
 // object's definition, thus gaining the parent object's data and behavior
 // without you having to define them again.
 
+use stats::Summary;
+
 #[allow(dead_code)]
 #[allow(unused_variables)]
 pub struct AveragedCollection {
     list: Vec<i32>,
-    average: f64,
+    summary: Summary<i32>,
 }
 
 #[allow(dead_code)]
 #[allow(unused_variables)]
 impl AveragedCollection {
+    pub fn new() -> AveragedCollection {
+        AveragedCollection {
+            list: Vec::new(),
+            summary: Summary::new(),
+        }
+    }
+
     pub fn add(&mut self, value: i32) {
         self.list.push(value);
-        self.update_average();
+        self.summary.add(value);
     }
 
     pub fn remove(&mut self) -> Option<i32> {
         let result = self.list.pop();
         match result {
             Some(value) => {
-                self.update_average();
+                self.update_summary();
                 Some(value)
             }
             None => None,
         }
     }
 
+    // The average of an empty collection is not a number.
     pub fn average(&self) -> f64 {
-        self.average
+        self.summary.mean().unwrap_or(f64::NAN)
     }
 
-    fn update_average(&mut self) {
-        let total: i32 = self.list.iter().sum();
-        self.average = total as f64 / self.list.len() as f64;
+    pub fn summary(&self) -> &Summary<i32> {
+        &self.summary
     }
 
-    // We leave the list and average fields private so that there's no way for
+    // A summary can't forget a value, so removing one means starting again
+    // from what's left.
+    fn update_summary(&mut self) {
+        self.summary = self.list.iter().cloned().collect();
+    }
+
+    // We leave the list and summary fields private so that there's no way for
     // external code to add or remove items to the list field directly,
-    // otherwise the average field might become out of sync when the list
+    // otherwise the summary field might become out of sync when the list
     // changes.
     // If encapsulation is a required aspect for a language to be considered
     // object-oriented, then Rust meets that requirement.
@@ -264,123 +279,370 @@ fn one() {
 // Implementing an Object Oriented Programming Pattern: State.
 // extern crate blog;
 // use blog::Post;
+//
+// The book implements the state pattern with trait objects: every state is a
+// struct implementing a State trait, and every method of the trait consumes
+// the boxed state and returns the next one.
+//
+// pub struct Post {
+//     state: Option<Box<State>>,
+//     content: String,
+// }
+//
+// impl Post {
+//     pub fn new() -> Post {
+//         Post {
+//             state: Some(Box::new(Draft {})),
+//             content: String::new(),
+//         }
+//     }
+//
+//     pub fn add_text(&mut self, text: &str) {
+//         self.content.push_str(text);
+//     }
+//
+//     // Because the goal is to keep all these rules inside the structs that
+//     // implement State, we call a content method on the value in state and pass
+//     // the post instance (that is, self) as an argument. Then we return the
+//     // value that's returned from using the content method on the state value.
+//     //
+//     // We call the as_ref method on the Option because we want a reference to
+//     // the value inside the Option rather than ownership of it. Because state is
+//     // an Option<Box<State>>, calling as_ref returns an Option<&Box<State>>. If
+//     // we didn't call as_ref, we'd get an error because we can't move state out
+//     // of the borrowed &self of the function parameter.
+//     //
+//     // So then we have a &Box<State>, and when we call the content on it, deref
+//     // coercion will take effect on the & and the Box so that the content method
+//     // will ultimately be called on the type that implements the State trait.
+//     //
+//     // That means we need to add content to the State trait definition, and
+//     // that's where We'll put the logic for what content to return depending on
+//     // which state we have.
+//     pub fn content(&self) -> &str {
+//         self.state.as_ref().unwrap().content(&self)
+//     }
+//
+//     pub fn request_review(&mut self) {
+//         if let Some(s) = self.state.take() {
+//             self.state = Some(s.request_review())
+//         }
+//     }
+//
+//     pub fn approve(&mut self) {
+//         if let Some(s) = self.state.take() {
+//             self.state = Some(s.approve())
+//         }
+//     }
+// }
+//
+// // The logic around the rules lives in the state objects rather than scattered
+// // throughout Post.
+// trait State {
+//     // We've added the request_review method to the State trait; all types that
+//     // implement the trait will now need to implement the request_review method.
+//     // Note that rather than having self, &self, or &mut self as the first
+//     // parameter of the method, we have self: Box<Self>. This syntax means the
+//     // method is only valid when called on a Box holding the type. This syntax
+//     // takes ownership of Box<Self>, invalidating the old state so that the
+//     // state value of the Post can transform itself into a new state.
+//     fn request_review(self: Box<Self>) -> Box<State>;
+//     fn approve(self: Box<Self>) -> Box<State>;
+//     // Default implementation for content.
+//     fn content<'a>(&self, _post: &'a Post) -> &'a str {
+//         ""
+//     }
+// }
+//
+// struct Draft {}
+//
+// impl State for Draft {
+//     fn request_review(self: Box<Self>) -> Box<State> {
+//         Box::new(PendingReview {})
+//     }
+//
+//     fn approve(self: Box<Self>) -> Box<State> {
+//         self
+//     }
+// }
+//
+// struct PendingReview {}
+//
+// impl State for PendingReview {
+//     fn request_review(self: Box<Self>) -> Box<State> {
+//         self
+//     }
+//
+//     // Similar to request_review, if we call the approve method on a Draft, it
+//     // will have no effect since it will return self. When we call approve on
+//     // PendingReview, it returns a new, boxed instance of the Published struct.
+//     // The Published struct implements the State trait, and for both the
+//     // request_review method and the approve method, it returns itself, since
+//     // the post should stay in the Published state in those cases.
+//     fn approve(self: Box<Self>) -> Box<State> {
+//         Box::new(Published {})
+//     }
+// }
+//
+// struct Published {}
+//
+// impl State for Published {
+//     fn request_review(self: Box<Self>) -> Box<State> {
+//         self
+//     }
+//
+//     fn approve(self: Box<Self>) -> Box<State> {
+//         self
+//     }
+//
+//     // We're taking a reference to a post as an argument, and returning a
+//     // reference to part of that post, so the lifetime of the returned reference
+//     // is related to the lifetime of the post argument.
+//     fn content<'a>(&self, post: &'a Post) -> &'a str {
+//         &post.content
+//     }
+// }
+//
+// The rules are spread over one impl per state, though. The same rules fit in
+// a transition table for the generic state machine of state_machine.rs: the
+// states become plain values, and the Post just feeds events to the machine.
+
+use serde_json;
+use state_machine::{Machine, Transition, TransitionError};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PostState {
+    Draft,
+    PendingReview,
+    Published,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PostEvent {
+    AddText,
+    RequestReview,
+    Approve,
+    Reject,
+}
+
+// What the guards and the actions of the workflow look at: how many
+// approvals a post needs, and who approved the current review round.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Review {
+    required: usize,
+    approvals: Vec<String>,
+}
+
+fn enough_approvals(review: &Review) -> bool {
+    review.approvals.len() >= review.required
+}
+
+fn new_round(review: &mut Review) {
+    review.approvals.clear();
+}
+
+// The order of the rows matters: an approval publishes the post only when
+// its guard holds, otherwise the next row keeps it waiting for the others.
+static WORKFLOW: &[Transition<PostState, PostEvent, Review>] = &[
+    Transition::new(PostState::Draft, PostEvent::AddText, PostState::Draft),
+    Transition::new(
+        PostState::Draft,
+        PostEvent::RequestReview,
+        PostState::PendingReview,
+    )
+    .action(new_round),
+    Transition::new(
+        PostState::PendingReview,
+        PostEvent::Approve,
+        PostState::Published,
+    )
+    .guard(enough_approvals),
+    Transition::new(
+        PostState::PendingReview,
+        PostEvent::Approve,
+        PostState::PendingReview,
+    ),
+    Transition::new(
+        PostState::PendingReview,
+        PostEvent::Reject,
+        PostState::Draft,
+    )
+    .action(new_round),
+];
+
+/// One line of the audit trail: who did what, and when (seconds since the
+/// Unix epoch).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub who: String,
+    pub event: PostEvent,
+    pub from: PostState,
+    pub to: PostState,
+    pub at: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PostError {
+    /// The operation is not allowed in the current state, like adding text
+    /// to a post that is being reviewed.
+    NotAllowed { state: PostState, event: PostEvent },
+    /// The same reviewer can approve a review round only once.
+    AlreadyApproved(String),
+}
+
+impl fmt::Display for PostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PostError::NotAllowed { state, event } => {
+                write!(f, "cannot {:?} a post in state {:?}", event, state)
+            }
+            PostError::AlreadyApproved(ref who) => write!(f, "{} already approved this post", who),
+        }
+    }
+}
+
+impl Error for PostError {}
+
+impl From<TransitionError<PostState, PostEvent>> for PostError {
+    fn from(e: TransitionError<PostState, PostEvent>) -> PostError {
+        match e {
+            TransitionError::Illegal { state, event }
+            | TransitionError::Rejected { state, event } => PostError::NotAllowed { state, event },
+        }
+    }
+}
 
 pub struct Post {
-    state: Option<Box<State>>,
+    machine: Machine<PostState, PostEvent, Review>,
+    content: String,
+    audit: Vec<AuditEntry>,
+}
+
+// What goes to disk: the machine itself is mostly a static table, its state
+// and its context are enough to rebuild it.
+#[derive(Serialize, Deserialize)]
+struct SavedPost {
+    state: PostState,
     content: String,
+    review: Review,
+    audit: Vec<AuditEntry>,
 }
 
 impl Post {
+    /// A post that needs a single approval to be published.
     pub fn new() -> Post {
+        Post::with_approvals(1)
+    }
+
+    /// A post that needs `required` different reviewers to approve it.
+    pub fn with_approvals(required: usize) -> Post {
+        let review = Review {
+            required: if required == 0 { 1 } else { required },
+            approvals: Vec::new(),
+        };
         Post {
-            state: Some(Box::new(Draft {})),
+            machine: Machine::new(WORKFLOW, PostState::Draft, review),
             content: String::new(),
+            audit: Vec::new(),
         }
     }
 
-    pub fn add_text(&mut self, text: &str) {
-        self.content.push_str(text);
+    pub fn state(&self) -> PostState {
+        self.machine.state()
     }
 
-    // Because the goal is to keep all these rules inside the structs that
-    // implement State, we call a content method on the value in state and pass
-    // the post instance (that is, self) as an argument. Then we return the
-    // value that's returned from using the content method on the state value.
-    //
-    // We call the as_ref method on the Option because we want a reference to
-    // the value inside the Option rather than ownership of it. Because state is
-    // an Option<Box<State>>, calling as_ref returns an Option<&Box<State>>. If
-    // we didn't call as_ref, we'd get an error because we can't move state out
-    // of the borrowed &self of the function parameter.
-    //
-    // So then we have a &Box<State>, and when we call the content on it, deref
-    // coercion will take effect on the & and the Box so that the content method
-    // will ultimately be called on the type that implements the State trait.
-    //
-    // That means we need to add content to the State trait definition, and
-    // that's where We'll put the logic for what content to return depending on
-    // which state we have.
-    pub fn content(&self) -> &str {
-        self.state.as_ref().unwrap().content(&self)
+    pub fn audit(&self) -> &[AuditEntry] {
+        &self.audit
     }
 
-    pub fn request_review(&mut self) {
-        if let Some(s) = self.state.take() {
-            self.state = Some(s.request_review())
-        }
+    pub fn add_text(&mut self, who: &str, text: &str) -> Result<(), PostError> {
+        self.fire(who, PostEvent::AddText)?;
+        self.content.push_str(text);
+        Ok(())
     }
 
-    pub fn approve(&mut self) {
-        if let Some(s) = self.state.take() {
-            self.state = Some(s.approve())
+    // The content is visible only once the post has been published.
+    pub fn content(&self) -> &str {
+        match self.machine.state() {
+            PostState::Published => &self.content,
+            _ => "",
         }
     }
-}
 
-// The logic around the rules lives in the state objects rather than scattered
-// throughout Post.
-trait State {
-    // We've added the request_review method to the State trait; all types that
-    // implement the trait will now need to implement the request_review method.
-    // Note that rather than having self, &self, or &mut self as the first
-    // parameter of the method, we have self: Box<Self>. This syntax means the
-    // method is only valid when called on a Box holding the type. This syntax
-    // takes ownership of Box<Self>, invalidating the old state so that the
-    // state value of the Post can transform itself into a new state.
-    fn request_review(self: Box<Self>) -> Box<State>;
-    fn approve(self: Box<Self>) -> Box<State>;
-    // Default implementation for content.
-    fn content<'a>(&self, _post: &'a Post) -> &'a str {
-        ""
+    pub fn request_review(&mut self, who: &str) -> Result<(), PostError> {
+        self.fire(who, PostEvent::RequestReview)
     }
-}
 
-struct Draft {}
-
-impl State for Draft {
-    fn request_review(self: Box<Self>) -> Box<State> {
-        Box::new(PendingReview {})
+    pub fn approve(&mut self, who: &str) -> Result<(), PostError> {
+        if !self.machine.can(PostEvent::Approve) {
+            return Err(PostError::NotAllowed {
+                state: self.state(),
+                event: PostEvent::Approve,
+            });
+        }
+        if self.machine.context().approvals.iter().any(|a| a == who) {
+            return Err(PostError::AlreadyApproved(who.to_owned()));
+        }
+        self.machine.context_mut().approvals.push(who.to_owned());
+        self.fire(who, PostEvent::Approve)
     }
 
-    fn approve(self: Box<Self>) -> Box<State> {
-        self
+    /// Send the post back to the author: the approvals collected so far are
+    /// dropped, and the next review starts from scratch.
+    pub fn reject(&mut self, who: &str) -> Result<(), PostError> {
+        self.fire(who, PostEvent::Reject)
     }
-}
 
-struct PendingReview {}
-
-impl State for PendingReview {
-    fn request_review(self: Box<Self>) -> Box<State> {
-        self
+    fn fire(&mut self, who: &str, event: PostEvent) -> Result<(), PostError> {
+        let from = self.machine.state();
+        let to = self.machine.fire(event)?;
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.audit.push(AuditEntry {
+            who: who.to_owned(),
+            event,
+            from,
+            to,
+            at,
+        });
+        Ok(())
     }
 
-    // Similar to request_review, if we call the approve method on a Draft, it
-    // will have no effect since it will return self. When we call approve on
-    // PendingReview, it returns a new, boxed instance of the Published struct.
-    // The Published struct implements the State trait, and for both the
-    // request_review method and the approve method, it returns itself, since
-    // the post should stay in the Published state in those cases.
-    fn approve(self: Box<Self>) -> Box<State> {
-        Box::new(Published {})
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&SavedPost {
+            state: self.machine.state(),
+            content: self.content.clone(),
+            review: self.machine.context().clone(),
+            audit: self.audit.clone(),
+        })
     }
-}
 
-struct Published {}
-
-impl State for Published {
-    fn request_review(self: Box<Self>) -> Box<State> {
-        self
+    pub fn from_json(json: &str) -> serde_json::Result<Post> {
+        let saved: SavedPost = serde_json::from_str(json)?;
+        Ok(Post {
+            machine: Machine::new(WORKFLOW, saved.state, saved.review),
+            content: saved.content,
+            audit: saved.audit,
+        })
     }
 
-    fn approve(self: Box<Self>) -> Box<State> {
-        self
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let json = self.to_json()?;
+        File::create(path)?.write_all(json.as_bytes())
     }
 
-    // We're taking a reference to a post as an argument, and returning a
-    // reference to part of that post, so the lifetime of the returned reference
-    // is related to the lifetime of the post argument.
-    fn content<'a>(&self, post: &'a Post) -> &'a str {
-        &post.content
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Post> {
+        let mut json = String::new();
+        File::open(path)?.read_to_string(&mut json)?;
+        Ok(Post::from_json(&json)?)
     }
 }
 
@@ -449,17 +711,146 @@ impl State for Published {
 fn two() {
     let mut post = Post::new();
 
-    post.add_text("I ate a salad for lunch today");
+    post.add_text("alice", "I ate a salad for lunch today")
+        .unwrap();
     assert_eq!("", post.content());
 
-    post.request_review();
+    post.request_review("alice").unwrap();
     assert_eq!("", post.content());
 
-    post.approve();
+    post.approve("bob").unwrap();
     assert_eq!("I ate a salad for lunch today", post.content());
 }
 
+fn three() {
+    let mut post = Post::with_approvals(2);
+    post.add_text("alice", "Salads are overrated.").unwrap();
+    post.request_review("alice").unwrap();
+    if let Err(e) = post.add_text("alice", " Just kidding.") {
+        println!("error: {}", e);
+    }
+    post.reject("bob").unwrap();
+    post.add_text("alice", " Mostly.").unwrap();
+    post.request_review("alice").unwrap();
+    post.approve("bob").unwrap();
+    if let Err(e) = post.approve("bob") {
+        println!("error: {}", e);
+    }
+    post.approve("carol").unwrap();
+
+    for entry in post.audit() {
+        println!(
+            "{}: {} {:?} ({:?} -> {:?})",
+            entry.at, entry.who, entry.event, entry.from, entry.to
+        );
+    }
+    println!("{}", post.content());
+
+    // The whole post, audit trail included, survives a restart.
+    let path = ::std::env::temp_dir().join("fungi-post.json");
+    match post.save(&path).and_then(|_| Post::load(&path)) {
+        Ok(restored) => println!("restored from {:?}: {:?}", path, restored.state()),
+        Err(e) => println!("cannot save the post to {:?}: {}", path, e),
+    }
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "oop",
+    description: "trait objects and the state pattern",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
+    three();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averaged_collection() {
+        let mut c = AveragedCollection::new();
+        assert!(c.average().is_nan());
+        for v in &[3, 5, 10] {
+            c.add(*v);
+        }
+        assert_eq!(c.average(), 6.0);
+        assert_eq!(c.remove(), Some(10));
+        assert_eq!(c.average(), 4.0);
+        assert_eq!(c.summary().max(), Some(5));
+    }
+
+    #[test]
+    fn needs_every_approval() {
+        let mut post = Post::with_approvals(2);
+        post.add_text("alice", "draft").unwrap();
+        post.request_review("alice").unwrap();
+        post.approve("bob").unwrap();
+        assert_eq!(post.state(), PostState::PendingReview);
+        assert_eq!(
+            post.approve("bob"),
+            Err(PostError::AlreadyApproved(String::from("bob")))
+        );
+        post.approve("carol").unwrap();
+        assert_eq!(post.state(), PostState::Published);
+        assert_eq!(post.content(), "draft");
+    }
+
+    #[test]
+    fn rejection_starts_a_new_round() {
+        let mut post = Post::with_approvals(2);
+        post.request_review("alice").unwrap();
+        post.approve("bob").unwrap();
+        post.reject("carol").unwrap();
+        assert_eq!(post.state(), PostState::Draft);
+        post.request_review("alice").unwrap();
+        post.approve("carol").unwrap();
+        assert_eq!(post.state(), PostState::PendingReview);
+
+        let who: Vec<&str> = post.audit().iter().map(|e| e.who.as_str()).collect();
+        assert_eq!(who, vec!["alice", "bob", "carol", "alice", "carol"]);
+    }
+
+    #[test]
+    fn illegal_operations() {
+        let mut post = Post::new();
+        assert_eq!(
+            post.approve("bob"),
+            Err(PostError::NotAllowed {
+                state: PostState::Draft,
+                event: PostEvent::Approve
+            })
+        );
+        post.request_review("alice").unwrap();
+        assert_eq!(
+            post.add_text("alice", "more"),
+            Err(PostError::NotAllowed {
+                state: PostState::PendingReview,
+                event: PostEvent::AddText
+            })
+        );
+        post.approve("bob").unwrap();
+        assert!(post.reject("bob").is_err());
+        assert!(post.request_review("alice").is_err());
+        // Failed operations don't end up in the audit trail.
+        assert_eq!(post.audit().len(), 2);
+    }
+
+    #[test]
+    fn survives_a_round_trip_through_json() {
+        let mut post = Post::with_approvals(2);
+        post.add_text("alice", "persistent").unwrap();
+        post.request_review("alice").unwrap();
+        post.approve("bob").unwrap();
+
+        let mut restored = Post::from_json(&post.to_json().unwrap()).unwrap();
+        assert_eq!(restored.state(), PostState::PendingReview);
+        assert_eq!(restored.audit(), post.audit());
+        assert!(restored.approve("bob").is_err());
+        restored.approve("carol").unwrap();
+        assert_eq!(restored.content(), "persistent");
+    }
 }
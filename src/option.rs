@@ -318,6 +318,12 @@ fn six() {
     }
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "option",
+    description: "the Option type and its combinators",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -1,5 +1,11 @@
 // https://doc.rust-lang.org/book/second-edition/ch04-01-what-is-ownership.html
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "ownership",
+    description: "ownership, moves, borrowing and slices",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
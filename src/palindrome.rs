@@ -1,20 +1,271 @@
-fn is_palindrome(string: &str) -> bool {
-    let half_len = string.len()/2;
-    string.chars().take(half_len).eq(string.chars().rev().take(half_len))
+// A palindrome reads the same backward as forward. Comparing raw chars is not
+// enough for real text:
+//
+// - "A man, a plan, a canal: Panama" is a palindrome only once the case, the
+//   punctuation and the spaces are ignored;
+// - "é" can be one char (U+00E9) or two ('e' followed by the combining accent
+//   U+0301): reversing the chars of the second one moves the accent onto the
+//   previous letter. What a reader sees as a character is a grapheme cluster,
+//   and the two spellings are the same once normalized (NFC).
+//
+// http://unicode.org/reports/tr29/ (grapheme clusters)
+// http://unicode.org/reports/tr15/ (normalization)
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What to ignore, and what to compare, when looking for a palindrome.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    ignore_case: bool,
+    ignore_punctuation: bool,
+    ignore_whitespace: bool,
+    graphemes: bool,
+}
+
+impl Options {
+    /// Ignore case, punctuation and whitespace, compare grapheme clusters.
+    pub fn new() -> Options {
+        Options {
+            ignore_case: true,
+            ignore_punctuation: true,
+            ignore_whitespace: true,
+            graphemes: true,
+        }
+    }
+
+    /// Compare every char as it is.
+    pub fn exact() -> Options {
+        Options {
+            ignore_case: false,
+            ignore_punctuation: false,
+            ignore_whitespace: false,
+            graphemes: false,
+        }
+    }
+
+    pub fn ignore_case(mut self, yes: bool) -> Options {
+        self.ignore_case = yes;
+        self
+    }
+
+    pub fn ignore_punctuation(mut self, yes: bool) -> Options {
+        self.ignore_punctuation = yes;
+        self
+    }
+
+    pub fn ignore_whitespace(mut self, yes: bool) -> Options {
+        self.ignore_whitespace = yes;
+        self
+    }
+
+    pub fn graphemes(mut self, yes: bool) -> Options {
+        self.graphemes = yes;
+        self
+    }
+
+    // The units to compare, once normalized and filtered.
+    fn units(&self, string: &str) -> Vec<String> {
+        let units: Vec<String> = if self.graphemes {
+            let normalized: String = string.nfc().collect();
+            normalized.graphemes(true).map(String::from).collect()
+        } else {
+            string.chars().map(String::from).collect()
+        };
+        units
+            .into_iter()
+            .filter(|u| {
+                let c = u.chars().next().expect("units are never empty");
+                !(self.ignore_whitespace && c.is_whitespace()
+                    || self.ignore_punctuation && !c.is_whitespace() && !c.is_alphanumeric())
+            })
+            .map(|u| {
+                if self.ignore_case {
+                    u.to_lowercase()
+                } else {
+                    u
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options::new()
+    }
+}
+
+pub fn is_palindrome_with(string: &str, options: &Options) -> bool {
+    let units = options.units(string);
+    let half_len = units.len() / 2;
+    units
+        .iter()
+        .take(half_len)
+        .eq(units.iter().rev().take(half_len))
+}
+
+/// Is it a palindrome, ignoring case, punctuation and whitespace?
+pub fn is_palindrome(string: &str) -> bool {
+    is_palindrome_with(string, &Options::new())
+}
+
+/// The longest palindromic substring (the first one, if there are many), in
+/// linear time with Manacher's algorithm. Grapheme clusters are compared
+/// exactly, nothing is ignored.
+///
+/// https://en.wikipedia.org/wiki/Longest_palindromic_substring
+pub fn longest_palindrome(string: &str) -> &str {
+    let units: Vec<(usize, &str)> = string.grapheme_indices(true).collect();
+    let n = units.len();
+
+    // Think of a separator before, between and after the units ("|a|b|a|"):
+    // every palindrome of the new string has a center, even the ones of even
+    // length in the old string. Odd positions are units, even positions are
+    // separators, and `radius[i]` is the length of the longest palindrome
+    // (in units of the old string) centered in `i`.
+    let m = 2 * n + 1;
+    let same = |a: usize, b: usize| a.is_multiple_of(2) || units[a / 2].1 == units[b / 2].1;
+    let mut radius = vec![0; m];
+    // The palindrome reaching the farthest right: its center and its end.
+    let (mut center, mut right) = (0, 0);
+    for i in 0..m {
+        if i < right {
+            // The mirror of `i` around the center already knows a palindrome,
+            // as long as it stays within the one of the center.
+            radius[i] = (right - i).min(radius[2 * center - i]);
+        }
+        while radius[i] < i && i + radius[i] + 1 < m && same(i - radius[i] - 1, i + radius[i] + 1) {
+            radius[i] += 1;
+        }
+        if i + radius[i] > right {
+            center = i;
+            right = i + radius[i];
+        }
+    }
+
+    // The first of the longest ones.
+    let (i, len) = radius.iter().enumerate().fold(
+        (0, 0),
+        |best, (i, &r)| if r > best.1 { (i, r) } else { best },
+    );
+    if len == 0 {
+        return &string[..0];
+    }
+    let first = (i - len) / 2;
+    let start = units[first].0;
+    let end = units[first + len - 1].0 + units[first + len - 1].1.len();
+    &string[start..end]
 }
 
 macro_rules! test {
     ( $( $x:tt ),* ) => { $( println!("'{}': {}", $x, is_palindrome($x)); )* };
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "palindrome",
+    description: "palindromes over grapheme clusters, and the longest one in a string",
+    run: sample,
+};
+
 pub fn sample() {
-    test!("",
-          "a",
-          "ada",
-          "adad",
-          "ingirumimusnocteetconsumimurigni",
-          "人人為我,我為人人",
-          "Я иду с мечем, судия",
-          "아들딸들아",
-          "The quick brown fox");
+    test!(
+        "",
+        "a",
+        "ada",
+        "adad",
+        "ingirumimusnocteetconsumimurigni",
+        "人人為我,我為人人",
+        "Я иду с мечем, судия",
+        "아들딸들아",
+        "A man, a plan, a canal: Panama",
+        "The quick brown fox"
+    );
+
+    // The same sentence, ignoring less and less of it.
+    let panama = "A man, a plan, a canal: Panama";
+    for &(name, options) in &[
+        ("exact", Options::exact()),
+        ("ignoring case", Options::exact().ignore_case(true)),
+        (
+            "and punctuation",
+            Options::exact().ignore_case(true).ignore_punctuation(true),
+        ),
+        (
+            "and whitespace",
+            Options::exact()
+                .ignore_case(true)
+                .ignore_punctuation(true)
+                .ignore_whitespace(true),
+        ),
+        ("by chars", Options::new().graphemes(false)),
+    ] {
+        println!(
+            "'{}' {}: {}",
+            panama,
+            name,
+            is_palindrome_with(panama, &options)
+        );
+    }
+
+    for s in &["babad", "forgeeksskeegfor", "I topi non avevano nipoti"] {
+        println!("longest palindrome in '{}': '{}'", s, longest_palindrome(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences() {
+        assert!(is_palindrome("A man, a plan, a canal: Panama"));
+        assert!(is_palindrome("Я иду с мечем, судия"));
+        assert!(is_palindrome("I topi non avevano nipoti"));
+        assert!(!is_palindrome("The quick brown fox"));
+        assert!(!is_palindrome_with(
+            "A man, a plan, a canal: Panama",
+            &Options::exact()
+        ));
+        assert!(!is_palindrome_with(
+            "Never odd or even",
+            &Options::new().ignore_case(false)
+        ));
+        assert!(is_palindrome_with(
+            "1,2,1",
+            &Options::exact().ignore_punctuation(true)
+        ));
+        assert!(is_palindrome_with(
+            "ab a",
+            &Options::exact().ignore_whitespace(true)
+        ));
+    }
+
+    #[test]
+    fn graphemes() {
+        // Reversed char by char, the accents would come before their letters.
+        let decomposed = "e\u{301}xe\u{301}";
+        assert!(is_palindrome(decomposed));
+        let chars = Options::new().graphemes(false).ignore_punctuation(false);
+        assert!(!is_palindrome_with(decomposed, &chars));
+        // One precomposed and one decomposed "é" are the same grapheme.
+        assert!(is_palindrome("\u{e9}xe\u{301}"));
+        // A flag is made of two regional indicators.
+        assert!(is_palindrome("🇮🇹a🇮🇹"));
+        assert!(!is_palindrome_with("🇮🇹a🇮🇹", &Options::exact()));
+    }
+
+    #[test]
+    fn longest() {
+        assert_eq!(longest_palindrome(""), "");
+        assert_eq!(longest_palindrome("x"), "x");
+        assert_eq!(longest_palindrome("babad"), "bab");
+        assert_eq!(longest_palindrome("cbbd"), "bb");
+        assert_eq!(longest_palindrome("forgeeksskeegfor"), "geeksskeeg");
+        assert_eq!(longest_palindrome("xx人人為我為人人yy"), "人人為我為人人");
+        assert_eq!(
+            longest_palindrome("ae\u{301}be\u{301}c"),
+            "e\u{301}be\u{301}"
+        );
+    }
 }
@@ -3,6 +3,12 @@
 // https://doc.rust-lang.org/stable/book/second-edition/ch18-02-refutability.html
 // https://doc.rust-lang.org/stable/book/second-edition/ch18-03-pattern-syntax.html
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "patterns",
+    description: "patterns, refutability and match syntax",
+    run: sample,
+};
+
 // All the places Patterns may be used
 // - Match Arms
 //   match VALUE {
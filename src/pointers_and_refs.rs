@@ -1,5 +1,11 @@
 // https://rustbyexample.com/flow_control/match/destructuring/destructure_pointers.html
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "pointers_and_refs",
+    description: "destructuring pointers and references",
+    run: sample,
+};
+
 pub fn sample() {
     // Assign a reference of type `i32`. The `&` signifies there
     // is a reference being assigned.
@@ -53,6 +53,12 @@ fn two() {
     // instead match `Name(ref foo)` as `Name(foo)`
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "ref_and_ampersand",
+    description: "ref patterns versus & references",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -40,6 +40,7 @@ fn two() {
 
     // Specifically, in this example, the regex will be compiled when it is used for
     // the first time. On subsequent uses, it will reuse the previous compilation.
+    // The `extract` module does the same with a whole registry of patterns.
 
     let re = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").unwrap();
     let text = "2012-03-14, 2013-01-01 and 2014-07-05";
@@ -78,6 +79,12 @@ fn four() {
     assert_eq!(after, "03/14/2012, 01/01/2013 and 07/05/2014");
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "regexp",
+    description: "regular expressions compiled once with lazy_static",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -809,6 +809,12 @@ fn eleven() {
     );
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "smart_pointers",
+    description: "Box, Deref, Drop, Rc, RefCell and reference cycles",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
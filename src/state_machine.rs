@@ -1,104 +1,432 @@
-#[derive(Debug)]
-struct StateMachine {
-    st: String,
-}
+// A small, reusable, finite state machine.
+//
+// The first version of this module drove a fixed `start -> foo -> end` chain
+// through a tuple struct wrapping a function pointer:
+//
+// type FromStateMachineToStateFn = fn(&mut StateMachine) -> StateFn;
+// struct StateFn(FromStateMachineToStateFn);
+//
+// Every state was a method that did its work and returned the next state.
+// It's compact, but the shape of the machine is hidden inside the bodies of
+// those methods: there's no way to ask which events a state accepts, to refuse
+// an event, or to draw the machine.
+//
+// StateFn implemented Deref, so that `state.deref()(&mut state_machine)`
+// called the function it wrapped:
+//
+// // The Deref trait, provided by the standard library, requires implementing
+// // one method named deref that borrows self and returns a reference to the
+// // inner data.
+// use std::ops::Deref;
+//
+// // Without the Deref trait, the compiler can only dereference & references.
+// // The Deref trait's deref method gives the compiler the ability to take a
+// // value of any type that implements Deref and call the deref method in
+// // order to get a & reference that it knows how to dereference.
+// // https://doc.rust-lang.org/stable/book/second-edition/ch15-02-deref.html
+// //
+// // Using Box<T> Like a Reference
+// // let x = 5;
+// // let y = &x;
+// // let y = Box::new(x);
+// // let y = MyBox::new(x);
+// // assert_eq!(5, x);
+// // assert_eq!(5, *y);
+// //
+// // struct MyBox<T>(T);
+// // impl<T> MyBox<T> {
+// //     fn new(x: T) -> MyBox<T> {
+// //         MyBox(x)
+// //     }
+// // }
+// // use std::ops::Deref;
+// // impl<T> Deref for MyBox<T> {
+// //     type Target = T;
+// //
+// //     fn deref(&self) -> &T {
+// //         &self.0
+// //     }
+// // }
+// impl Deref for StateFn {
+//     // Target is an associated type:
+//     // https://doc.rust-lang.org/stable/book/second-edition/ch19-03-advanced-traits.html#associated-types
+//     // Associated types are a way of associating a type placeholder with a
+//     // trait such that the trait method definitions can use these placeholder
+//     // types in their signatures. The implementor of a trait will specify the
+//     // concrete type to be used in this type's place for the particular
+//     // implementation.
+//     type Target = FromStateMachineToStateFn;
+//
+//     // with &self.0 so that deref returns a reference to the value we want
+//     // to access with the * operator.
+//     fn deref(&self) -> &Self::Target {
+//         &self.0
+//     }
+// }
+//
+// Here the machine is data instead. States and events are plain values (most
+// likely field-less enums), and the machine is a table of transitions: "in
+// this state, on this event, go to that state". A transition can carry a guard
+// (a predicate on a context value, the transition is taken only if it holds)
+// and an action (a function run on the context when the transition is taken).
+// The machine refuses events that have no transition from the current state,
+// and keeps a log of the transitions it took.
 
-type FromStateMachineToStateFn = fn(&mut StateMachine) -> StateFn;
+use std::error::Error;
+use std::fmt;
 
-// a tuple struct with one (implicit) field: a function that takes a mutable
-// reference to a StateMachine and returns another StateFn.
-struct StateFn(FromStateMachineToStateFn);
+pub type Guard<C> = fn(&C) -> bool;
+pub type Action<C> = fn(&mut C);
 
-impl StateMachine {
-    // Returns the next state from the start state: foo
-    fn start(&mut self) -> StateFn {
-        self.st = String::from("start");
-        StateFn(Self::foo)
+/// One row of the transition table.
+pub struct Transition<S, E, C> {
+    pub from: S,
+    pub event: E,
+    pub to: S,
+    pub guard: Option<Guard<C>>,
+    pub action: Option<Action<C>>,
+}
+
+impl<S, E, C> Transition<S, E, C> {
+    // These are `const fn`s so that a table can be a `static`:
+    //
+    // static TABLE: &[Transition<State, Event, Context>] = &[
+    //     Transition::new(State::A, Event::Go, State::B).guard(ready),
+    // ];
+    pub const fn new(from: S, event: E, to: S) -> Transition<S, E, C> {
+        Transition {
+            from,
+            event,
+            to,
+            guard: None,
+            action: None,
+        }
     }
 
-    fn foo(&mut self) -> StateFn {
-        self.st = String::from("foo");
-        StateFn(Self::end)
+    pub const fn guard(mut self, guard: Guard<C>) -> Transition<S, E, C> {
+        self.guard = Some(guard);
+        self
     }
 
-    fn end(&mut self) -> StateFn {
-        self.st = String::from("end");
-        StateFn(Self::end)
+    pub const fn action(mut self, action: Action<C>) -> Transition<S, E, C> {
+        self.action = Some(action);
+        self
     }
 }
 
-#[allow(dead_code)]
-#[allow(unused_variables)]
-impl StateFn {
-    fn step(&self, sm: &mut StateMachine) -> StateFn {
-        self.0(sm)
+/// An entry of the history log: a transition that has been taken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record<S, E> {
+    pub from: S,
+    pub event: E,
+    pub to: S,
+}
+
+/// Why an event has been refused.
+#[derive(Debug, PartialEq)]
+pub enum TransitionError<S, E> {
+    /// There is no transition for the event from the current state.
+    Illegal { state: S, event: E },
+    /// There are transitions for the event, but their guards didn't hold.
+    Rejected { state: S, event: E },
+}
+
+impl<S: fmt::Debug, E: fmt::Debug> fmt::Display for TransitionError<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransitionError::Illegal {
+                ref state,
+                ref event,
+            } => write!(f, "{:?} is not allowed in state {:?}", event, state),
+            TransitionError::Rejected {
+                ref state,
+                ref event,
+            } => write!(f, "{:?} has been rejected in state {:?}", event, state),
+        }
     }
 }
 
-// The Deref trait, provided by the standard library, requires implementing one
-// method named deref that borrows self and returns a reference to the inner
-// data.
-use std::ops::Deref;
+impl<S: fmt::Debug, E: fmt::Debug> Error for TransitionError<S, E> {}
 
-// Without the Deref trait, the compiler can only dereference & references. The
-// Deref trait's deref method gives the compiler the ability to take a value of
-// any type that implements Deref and call the deref method in order to get a &
-// reference that it knows how to dereference.
-// https://doc.rust-lang.org/stable/book/second-edition/ch15-02-deref.html
-//
-// Using Box<T> Like a Reference
-// let x = 5;
-// let y = &x;
-// let y = Box::new(x);
-// let y = MyBox::new(x);
-// assert_eq!(5, x);
-// assert_eq!(5, *y);
-//
-// struct MyBox<T>(T);
-// impl<T> MyBox<T> {
-//     fn new(x: T) -> MyBox<T> {
-//         MyBox(x)
-//     }
-// }
-// use std::ops::Deref;
-// impl<T> Deref for MyBox<T> {
-//     type Target = T;
-//
-//     fn deref(&self) -> &T {
-//         &self.0
-//     }
-// }
-impl Deref for StateFn {
-    // Target is an associated type:
-    // https://doc.rust-lang.org/stable/book/second-edition/ch19-03-advanced-traits.html#associated-types
-    // Associated types are a way of associating a type placeholder with a trait
-    // such that the trait method definitions can use these placeholder types in
-    // their signatures. The implementor of a trait will specify the concrete
-    // type to be used in this type's place for the particular implementation.
-    type Target = FromStateMachineToStateFn;
-
-    // with &self.0 so that deref returns a reference to the value we want to
-    // access with the * operator.
-    fn deref(&self) -> &Self::Target {
-        &self.0
+pub struct Machine<S: 'static, E: 'static, C: 'static> {
+    state: S,
+    context: C,
+    table: &'static [Transition<S, E, C>],
+    history: Vec<Record<S, E>>,
+}
+
+impl<S, E, C> Machine<S, E, C>
+where
+    S: Copy + PartialEq,
+    E: Copy + PartialEq,
+{
+    pub fn new(table: &'static [Transition<S, E, C>], initial: S, context: C) -> Machine<S, E, C> {
+        Machine {
+            state: initial,
+            context,
+            table,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> S {
+        self.state
+    }
+
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut C {
+        &mut self.context
+    }
+
+    pub fn history(&self) -> &[Record<S, E>] {
+        &self.history
+    }
+
+    /// Would the event be accepted right now?
+    pub fn can(&self, event: E) -> bool {
+        self.find(event).is_ok()
+    }
+
+    /// Feed an event to the machine. The first transition of the table that
+    /// matches the current state and the event, and whose guard holds, is
+    /// taken: its action is run and the machine moves to its target state.
+    pub fn fire(&mut self, event: E) -> Result<S, TransitionError<S, E>> {
+        let transition = self.find(event)?;
+        if let Some(action) = transition.action {
+            action(&mut self.context);
+        }
+        self.history.push(Record {
+            from: self.state,
+            event,
+            to: transition.to,
+        });
+        self.state = transition.to;
+        Ok(self.state)
+    }
+
+    fn find(&self, event: E) -> Result<&'static Transition<S, E, C>, TransitionError<S, E>> {
+        let table: &'static [Transition<S, E, C>] = self.table;
+        let mut candidates = table
+            .iter()
+            .filter(|t| t.from == self.state && t.event == event)
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(TransitionError::Illegal {
+                state: self.state,
+                event,
+            });
+        }
+        let context = &self.context;
+        candidates
+            .find(|t| t.guard.is_none_or(|g| g(context)))
+            .ok_or(TransitionError::Rejected {
+                state: self.state,
+                event,
+            })
     }
 }
 
+impl<S, E, C> Machine<S, E, C>
+where
+    S: Copy + PartialEq + fmt::Debug,
+    E: Copy + PartialEq + fmt::Debug,
+{
+    /// The machine as a Graphviz graph: one node per state (the current one
+    /// in bold), one edge per transition, labelled with its event. Guarded
+    /// transitions are dashed.
+    ///
+    /// Render it with `dot -Tpng machine.dot -o machine.png`.
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut states: Vec<S> = Vec::new();
+        for t in self.table {
+            for s in &[t.from, t.to] {
+                if !states.contains(s) {
+                    states.push(*s);
+                }
+            }
+        }
+
+        let mut dot = format!("digraph {} {{\n    rankdir=LR;\n", name);
+        for s in &states {
+            let style = if *s == self.state { " style=bold" } else { "" };
+            dot.push_str(&format!("    \"{:?}\" [shape=box{}];\n", s, style));
+        }
+        for t in self.table {
+            let style = if t.guard.is_some() {
+                " style=dashed"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    \"{:?}\" -> \"{:?}\" [label=\"{:?}\"{}];\n",
+                t.from, t.to, t.event, style
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// The `start -> foo -> end` chain, ported to the table.
+
+#[derive(Debug)]
+struct StateMachine {
+    st: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Idle,
+    Start,
+    Foo,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Next,
+}
+
+static CHAIN: &[Transition<Phase, Step, StateMachine>] = &[
+    Transition::new(Phase::Idle, Step::Next, Phase::Start)
+        .action(|sm| sm.st = String::from("start")),
+    Transition::new(Phase::Start, Step::Next, Phase::Foo).action(|sm| sm.st = String::from("foo")),
+    Transition::new(Phase::Foo, Step::Next, Phase::End).action(|sm| sm.st = String::from("end")),
+    // The end is a sink: stepping again leaves it where it is.
+    Transition::new(Phase::End, Step::Next, Phase::End),
+];
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "state_machine",
+    description: "a table driven state machine with guards, actions and history",
+    run: sample,
+};
+
 pub fn sample() {
-    let mut state_machine = StateMachine {
+    let state_machine = StateMachine {
         st: String::from(""),
     };
+    let mut machine = Machine::new(CHAIN, Phase::Idle, state_machine);
+    println!("{:?}", machine.context());
+
+    for _ in 0..3 {
+        machine
+            .fire(Step::Next)
+            .expect("the chain always has a next step");
+        println!("{:?} {:?}", machine.state(), machine.context());
+    }
+
+    for r in machine.history() {
+        println!("{:?} --{:?}--> {:?}", r.from, r.event, r.to);
+    }
+    print!("{}", machine.to_dot("chain"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Door {
+        Open,
+        Closed,
+        Locked,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Act {
+        Open,
+        Close,
+        Lock,
+        Unlock,
+    }
+
+    struct Keys {
+        have_key: bool,
+        turns: u32,
+    }
+
+    fn have_key(k: &Keys) -> bool {
+        k.have_key
+    }
 
-    let mut state = StateFn(StateMachine::start);
-    println!("{:?}", state_machine);
+    fn turn(k: &mut Keys) {
+        k.turns += 1;
+    }
 
-    state = state(&mut state_machine);
-    println!("{:?}", state_machine);
+    static DOOR: &[Transition<Door, Act, Keys>] = &[
+        Transition::new(Door::Open, Act::Close, Door::Closed),
+        Transition::new(Door::Closed, Act::Open, Door::Open),
+        Transition::new(Door::Closed, Act::Lock, Door::Locked)
+            .guard(have_key)
+            .action(turn),
+        Transition::new(Door::Locked, Act::Unlock, Door::Closed)
+            .guard(have_key)
+            .action(turn),
+    ];
 
-    state = state(&mut state_machine);
-    println!("{:?}", state_machine);
+    #[test]
+    fn guards_actions_and_history() {
+        let mut door = Machine::new(
+            DOOR,
+            Door::Open,
+            Keys {
+                have_key: false,
+                turns: 0,
+            },
+        );
+        assert_eq!(
+            door.fire(Act::Lock),
+            Err(TransitionError::Illegal {
+                state: Door::Open,
+                event: Act::Lock
+            })
+        );
+        assert_eq!(door.fire(Act::Close), Ok(Door::Closed));
+        assert!(!door.can(Act::Lock));
+        assert_eq!(
+            door.fire(Act::Lock),
+            Err(TransitionError::Rejected {
+                state: Door::Closed,
+                event: Act::Lock
+            })
+        );
+        door.context_mut().have_key = true;
+        assert_eq!(door.fire(Act::Lock), Ok(Door::Locked));
+        assert_eq!(door.fire(Act::Unlock), Ok(Door::Closed));
+        assert_eq!(door.context().turns, 2);
+        let events: Vec<Act> = door.history().iter().map(|r| r.event).collect();
+        assert_eq!(events, vec![Act::Close, Act::Lock, Act::Unlock]);
+    }
 
-    // state(&mut state_machine);
-    state.deref()(&mut state_machine);
-    println!("{:?}", state_machine);
+    #[test]
+    fn dot_export() {
+        let door = Machine::new(
+            DOOR,
+            Door::Closed,
+            Keys {
+                have_key: false,
+                turns: 0,
+            },
+        );
+        let dot = door.to_dot("door");
+        assert!(dot.starts_with("digraph door {"));
+        assert!(dot.contains("\"Closed\" [shape=box style=bold];"));
+        assert!(dot.contains("\"Open\" -> \"Closed\" [label=\"Close\"];"));
+        assert!(dot.contains("\"Closed\" -> \"Locked\" [label=\"Lock\" style=dashed];"));
+    }
+
+    #[test]
+    fn the_chain_ends_in_end() {
+        let sm = StateMachine { st: String::new() };
+        let mut machine = Machine::new(CHAIN, Phase::Idle, sm);
+        for _ in 0..5 {
+            machine.fire(Step::Next).unwrap();
+        }
+        assert_eq!(machine.state(), Phase::End);
+        assert_eq!(machine.context().st, "end");
+    }
 }
@@ -0,0 +1,514 @@
+// Streaming statistics.
+//
+// A Summary looks at every value only once, when it's added, and keeps just
+// enough state to answer the usual questions: count, min, max, mean,
+// variance, quantiles, mode and an histogram. Only the mode needs to remember
+// every distinct value it has seen, so its memory grows with them. A NaN
+// isn't a value to summarize: it's only counted, apart from the others. Two
+// summaries of two halves of the same data can be merged into the summary of
+// the whole data, so the work can be split among threads.
+//
+// - https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance
+// - https://www.vldb.org/pvldb/vol12/p2195-masson.pdf (DDSketch)
+
+use std::collections::{BTreeMap, HashMap};
+use std::f64;
+use std::iter::FromIterator;
+
+/// A number that can be summarized.
+pub trait Sample: Copy + PartialOrd {
+    fn to_f64(self) -> f64;
+    // Equal values must have equal keys, this is what the mode counts.
+    fn key(self) -> u64;
+}
+
+macro_rules! int_sample {
+    ($($t:ty),*) => {
+        $(
+            impl Sample for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn key(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! float_sample {
+    ($($t:ty),*) => {
+        $(
+            impl Sample for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn key(self) -> u64 {
+                    // 0.0 and -0.0 are equal, but their bits are not.
+                    if self == 0.0 {
+                        0
+                    } else {
+                        (self as f64).to_bits()
+                    }
+                }
+            }
+        )*
+    };
+}
+
+int_sample!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+float_sample!(f32, f64);
+
+// A quantile sketch with relative accuracy: every value goes into the bucket
+// `i` such that gamma^(i-1) < |value| <= gamma^i, and a quantile is estimated
+// with the middle of the bucket it falls in, which is less than `ACCURACY`
+// (relatively) away from the real value. Buckets are just counters, so two
+// sketches merge by adding them.
+const ACCURACY: f64 = 0.01;
+
+#[derive(Debug, Clone, Default)]
+struct Sketch {
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+    zero: u64,
+}
+
+impl Sketch {
+    fn gamma() -> f64 {
+        (1.0 + ACCURACY) / (1.0 - ACCURACY)
+    }
+
+    fn index(x: f64) -> i32 {
+        (x.ln() / Sketch::gamma().ln()).ceil() as i32
+    }
+
+    fn value(index: i32) -> f64 {
+        let gamma = Sketch::gamma();
+        2.0 * gamma.powi(index) / (gamma + 1.0)
+    }
+
+    fn add(&mut self, x: f64) {
+        if x > f64::MIN_POSITIVE {
+            *self.positive.entry(Sketch::index(x)).or_insert(0) += 1;
+        } else if x < -f64::MIN_POSITIVE {
+            *self.negative.entry(Sketch::index(-x)).or_insert(0) += 1;
+        } else {
+            self.zero += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &Sketch) {
+        for (i, c) in &other.positive {
+            *self.positive.entry(*i).or_insert(0) += c;
+        }
+        for (i, c) in &other.negative {
+            *self.negative.entry(*i).or_insert(0) += c;
+        }
+        self.zero += other.zero;
+    }
+
+    // Every bucket as (estimated value, count), by ascending value.
+    fn buckets(&self) -> Vec<(f64, u64)> {
+        let negative = self
+            .negative
+            .iter()
+            .rev()
+            .map(|(i, c)| (-Sketch::value(*i), *c));
+        let zero = if self.zero > 0 {
+            Some((0.0, self.zero))
+        } else {
+            None
+        };
+        let positive = self.positive.iter().map(|(i, c)| (Sketch::value(*i), *c));
+        negative.chain(zero).chain(positive).collect()
+    }
+
+    // The value with the given rank (0 is the smallest one).
+    fn at_rank(&self, rank: u64) -> f64 {
+        let mut seen = 0;
+        let buckets = self.buckets();
+        for &(value, count) in &buckets {
+            seen += count;
+            if seen > rank {
+                return value;
+            }
+        }
+        buckets.last().map_or(f64::NAN, |b| b.0)
+    }
+}
+
+/// The streaming summary of a sequence of numbers.
+#[derive(Debug, Clone)]
+pub struct Summary<T: Sample> {
+    count: u64,
+    // Left out of everything else: a NaN would stick as the min or the max,
+    // nothing comparing to it.
+    nans: u64,
+    min: Option<T>,
+    max: Option<T>,
+    // Welford's running mean, and the running sum of the squared distances
+    // from the mean.
+    mean: f64,
+    m2: f64,
+    sketch: Sketch,
+    // Distinct values (by key) and how many times they've been seen: it's
+    // never trimmed, one entry for every distinct value added.
+    seen: HashMap<u64, (T, u64)>,
+}
+
+/// One bin of an histogram: the values in `[start, end)` (the last bin
+/// includes its end).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: u64,
+}
+
+impl<T: Sample> Summary<T> {
+    pub fn new() -> Summary<T> {
+        Summary {
+            count: 0,
+            nans: 0,
+            min: None,
+            max: None,
+            mean: 0.0,
+            m2: 0.0,
+            sketch: Sketch::default(),
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, value: T) {
+        let x = value.to_f64();
+        if x.is_nan() {
+            self.nans += 1;
+            return;
+        }
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+
+        if self.min.is_none_or(|m| value < m) {
+            self.min = Some(value);
+        }
+        if self.max.is_none_or(|m| value > m) {
+            self.max = Some(value);
+        }
+        self.sketch.add(x);
+        self.seen.entry(value.key()).or_insert((value, 0)).1 += 1;
+    }
+
+    /// Fold the other summary into this one: the result is the summary of
+    /// all the values added to either of them.
+    pub fn merge(&mut self, other: &Summary<T>) {
+        self.nans += other.nans;
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let (n, m) = (self.count as f64, other.count as f64);
+        self.mean += delta * m / count as f64;
+        self.m2 += other.m2 + delta * delta * n * m / count as f64;
+        self.count = count;
+
+        if let Some(min) = other.min {
+            if self.min.is_none_or(|m| min < m) {
+                self.min = Some(min);
+            }
+        }
+        if let Some(max) = other.max {
+            if self.max.is_none_or(|m| max > m) {
+                self.max = Some(max);
+            }
+        }
+        self.sketch.merge(&other.sketch);
+        for (k, &(v, c)) in &other.seen {
+            self.seen.entry(*k).or_insert((v, 0)).1 += c;
+        }
+    }
+
+    /// Not counting the NaN.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn nans(&self) -> u64 {
+        self.nans
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.mean)
+        }
+    }
+
+    /// The population variance.
+    pub fn variance(&self) -> Option<f64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.m2 / self.count as f64)
+        }
+    }
+
+    /// The sample variance (with Bessel's correction), it needs at least two
+    /// values.
+    pub fn sample_variance(&self) -> Option<f64> {
+        if self.count < 2 {
+            None
+        } else {
+            Some(self.m2 / (self.count - 1) as f64)
+        }
+    }
+
+    /// The population standard deviation.
+    pub fn stddev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// The value below which the fraction `q` (between 0 and 1) of the values
+    /// fall. It's an estimate within 1% of the real value.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        let (min, max) = (self.min?.to_f64(), self.max?.to_f64());
+        let rank = (q * (self.count - 1) as f64).round() as u64;
+        // The extremes are known exactly, and no estimate can be beyond them.
+        let estimate = self.sketch.at_rank(rank).max(min).min(max);
+        Some(match rank {
+            0 => min,
+            r if r == self.count - 1 => max,
+            _ => estimate,
+        })
+    }
+
+    /// The `p`-th percentile, `p` between 0 and 100.
+    pub fn percentile(&self, p: f64) -> Option<f64> {
+        self.quantile(p / 100.0)
+    }
+
+    pub fn median(&self) -> Option<f64> {
+        self.quantile(0.5)
+    }
+
+    /// The most frequent values, in ascending order.
+    pub fn mode(&self) -> Vec<T> {
+        let top = self.seen.values().map(|&(_, c)| c).max().unwrap_or(0);
+        let mut mode: Vec<T> = self
+            .seen
+            .values()
+            .filter(|&&(_, c)| c == top)
+            .map(|&(v, _)| v)
+            .collect();
+        // Only NaN doesn't compare, and it's never seen.
+        mode.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        mode
+    }
+
+    /// Split `[min, max]` into `bins` bins of the same width, and count the
+    /// values falling in each of them. Values are placed according to their
+    /// sketch estimate, so a value very close to an edge may end up in the
+    /// next bin.
+    pub fn histogram(&self, bins: usize) -> Vec<Bin> {
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) if bins > 0 => (min.to_f64(), max.to_f64()),
+            _ => return Vec::new(),
+        };
+        let width = (max - min) / bins as f64;
+        let mut histogram: Vec<Bin> = (0..bins)
+            .map(|i| Bin {
+                start: min + width * i as f64,
+                end: if i + 1 == bins {
+                    max
+                } else {
+                    min + width * (i + 1) as f64
+                },
+                count: 0,
+            })
+            .collect();
+        for (value, count) in self.sketch.buckets() {
+            let i = if width > 0.0 {
+                ((value - min) / width).floor().max(0.0) as usize
+            } else {
+                0
+            };
+            histogram[i.min(bins - 1)].count += count;
+        }
+        histogram
+    }
+}
+
+impl<T: Sample> Default for Summary<T> {
+    fn default() -> Summary<T> {
+        Summary::new()
+    }
+}
+
+impl<T: Sample> Extend<T> for Summary<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add(value);
+        }
+    }
+}
+
+impl<T: Sample> FromIterator<T> for Summary<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Summary<T> {
+        let mut summary = Summary::new();
+        summary.extend(iter);
+        summary
+    }
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "stats",
+    description: "a streaming, mergeable summary: mean, variance, quantiles, mode",
+    run: sample,
+};
+
+pub fn sample() {
+    use std::thread;
+
+    // Every thread summarizes its own slice of the data, the partial
+    // summaries are then merged together.
+    let handles: Vec<thread::JoinHandle<Summary<u32>>> = (0..4)
+        .map(|t| thread::spawn(move || (t * 250..(t + 1) * 250).map(|x| x % 97).collect()))
+        .collect();
+    let mut summary = Summary::new();
+    for h in handles {
+        summary.merge(&h.join().unwrap());
+    }
+
+    println!(
+        "count: {}, min: {:?}, max: {:?}",
+        summary.count(),
+        summary.min(),
+        summary.max()
+    );
+    println!(
+        "mean: {:.3}, stddev: {:.3}, sample variance: {:.3}",
+        summary.mean().unwrap(),
+        summary.stddev().unwrap(),
+        summary.sample_variance().unwrap()
+    );
+    println!(
+        "median: {:.1}, p90: {:.1}, mode: {:?}",
+        summary.median().unwrap(),
+        summary.percentile(90.0).unwrap(),
+        summary.mode()
+    );
+    for bin in summary.histogram(5) {
+        println!("[{:5.1}, {:5.1}) {}", bin.start, bin.end, bin.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn empty() {
+        let s: Summary<f64> = Summary::new();
+        assert_eq!(s.count(), 0);
+        assert_eq!(s.mean(), None);
+        assert_eq!(s.median(), None);
+        assert!(s.mode().is_empty());
+        assert!(s.histogram(3).is_empty());
+    }
+
+    #[test]
+    fn welford() {
+        let s: Summary<i64> = vec![2, 4, 4, 4, 5, 5, 7, 9].into_iter().collect();
+        assert_eq!(s.mean(), Some(5.0));
+        assert_eq!(s.variance(), Some(4.0));
+        assert_eq!(s.stddev(), Some(2.0));
+        assert!(close(s.sample_variance().unwrap(), 32.0 / 7.0, 1e-12));
+        assert_eq!(s.mode(), vec![4]);
+        assert_eq!((s.min(), s.max()), (Some(2), Some(9)));
+    }
+
+    #[test]
+    fn quantiles_are_within_one_percent() {
+        let s: Summary<f64> = (1..10001).map(|x| x as f64).collect();
+        assert_eq!(s.quantile(0.0), Some(1.0));
+        assert_eq!(s.quantile(1.0), Some(10000.0));
+        for &(p, exact) in &[(25.0, 2501.0), (50.0, 5001.0), (99.0, 9900.0)] {
+            assert!(close(s.percentile(p).unwrap(), exact, ACCURACY));
+        }
+
+        let s: Summary<f32> = vec![-3.0, -1.0, 0.0, 0.0, 2.0].into_iter().collect();
+        assert!(close(s.median().unwrap(), 0.0, ACCURACY));
+        assert!(close(s.quantile(0.25).unwrap(), -1.0, ACCURACY));
+        assert_eq!(s.mode(), vec![0.0]);
+    }
+
+    #[test]
+    fn nan_is_counted_apart() {
+        let s: Summary<f64> = vec![f64::NAN, 3.0, f64::NAN, 1.0, 2.0, f64::NAN]
+            .into_iter()
+            .collect();
+        assert_eq!((s.count(), s.nans()), (3, 3));
+        assert_eq!((s.min(), s.max()), (Some(1.0), Some(3.0)));
+        assert_eq!(s.mean(), Some(2.0));
+        assert_eq!(s.mode(), vec![1.0, 2.0, 3.0]);
+        assert!(close(s.median().unwrap(), 2.0, 0.05));
+
+        let mut nans: Summary<f64> = vec![f64::NAN].into_iter().collect();
+        assert_eq!((nans.count(), nans.nans(), nans.min()), (0, 1, None));
+        nans.merge(&s);
+        assert_eq!((nans.count(), nans.nans()), (3, 4));
+        assert_eq!((nans.min(), nans.max()), (Some(1.0), Some(3.0)));
+    }
+
+    #[test]
+    fn merging_is_the_same_as_adding() {
+        let data: Vec<i32> = (0..1000).map(|x| (x * 7919) % 1013 - 500).collect();
+        let whole: Summary<i32> = data.iter().cloned().collect();
+        let mut merged: Summary<i32> = Summary::new();
+        for chunk in data.chunks(300) {
+            merged.merge(&chunk.iter().cloned().collect());
+        }
+        assert_eq!(merged.count(), whole.count());
+        assert_eq!((merged.min(), merged.max()), (whole.min(), whole.max()));
+        assert!(close(merged.mean().unwrap(), whole.mean().unwrap(), 1e-9));
+        assert!(close(
+            merged.variance().unwrap(),
+            whole.variance().unwrap(),
+            1e-9
+        ));
+        assert_eq!(merged.median(), whole.median());
+        assert_eq!(merged.mode(), whole.mode());
+    }
+
+    #[test]
+    fn histogram() {
+        let s: Summary<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 100].into_iter().collect();
+        let h = s.histogram(4);
+        assert_eq!(h.len(), 4);
+        assert_eq!(h[0].start, 0.0);
+        assert_eq!(h[3].end, 100.0);
+        let counts: Vec<u64> = h.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![9, 0, 0, 1]);
+    }
+}
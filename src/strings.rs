@@ -1,3 +1,115 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+// The exercises at the end of the chapter, plus a couple more. They all work on
+// grapheme clusters, what a reader would call a character: "e\u{301}" is one
+// grapheme ('e' and a combining accent) made of two chars.
+// http://unicode.org/reports/tr29/
+
+/// Is `other` the same as `one`, just rotated ("erbottlewat" and
+/// "waterbottle")? It's linear, with Knuth-Morris-Pratt: look for `one` in
+/// `other` followed by itself.
+///
+/// https://en.wikipedia.org/wiki/Knuth%E2%80%93Morris%E2%80%93Pratt_algorithm
+pub fn is_rotation(one: &str, other: &str) -> bool {
+    let pattern: Vec<&str> = one.graphemes(true).collect();
+    let text: Vec<&str> = other.graphemes(true).collect();
+    if pattern.len() != text.len() {
+        return false;
+    }
+    if pattern.is_empty() {
+        return true;
+    }
+
+    let n = text.len();
+    let failure = failure_function(&pattern);
+    // `other` followed by itself, without building it: the last grapheme of
+    // the second copy can't start a match, so it's not needed.
+    let mut matched = 0;
+    for i in 0..2 * n - 1 {
+        let g = text[i % n];
+        while matched > 0 && pattern[matched] != g {
+            matched = failure[matched - 1];
+        }
+        if pattern[matched] == g {
+            matched += 1;
+        }
+        if matched == n {
+            return true;
+        }
+    }
+    false
+}
+
+// For every prefix of the pattern, the length of its longest proper prefix
+// that is also a suffix: where to resume after a mismatch.
+fn failure_function<T: PartialEq>(pattern: &[T]) -> Vec<usize> {
+    let mut failure = vec![0; pattern.len()];
+    let mut k = 0;
+    for i in 1..pattern.len() {
+        while k > 0 && pattern[i] != pattern[k] {
+            k = failure[k - 1];
+        }
+        if pattern[i] == pattern[k] {
+            k += 1;
+        }
+        failure[i] = k;
+    }
+    failure
+}
+
+// Apply `f` to the words of the text, leaving everything else as it is.
+fn map_words<F: Fn(&str) -> String>(text: &str, f: F) -> String {
+    text.split_word_bounds()
+        .map(|w| {
+            if w.chars().next().is_some_and(char::is_alphabetic) {
+                f(w)
+            } else {
+                w.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Pig Latin: the first consonant of each word is moved to the end of the word
+/// and "ay" is added, so "first" becomes "irst-fay". Words that start with a
+/// vowel have "hay" added to the end instead ("apple" becomes "apple-hay").
+/// Accented vowels are vowels ("école" becomes "école-hay").
+pub fn pig_latin(text: &str) -> String {
+    map_words(text, |word| {
+        let first = word.graphemes(true).next().expect("words are never empty");
+        let base = first.nfd().next().expect("graphemes are never empty");
+        if base.to_lowercase().all(|c| "aeiou".contains(c)) {
+            format!("{}-hay", word)
+        } else {
+            format!("{}-{}ay", &word[first.len()..], first)
+        }
+    })
+}
+
+/// Every word with its first letter in uppercase, and the others in lowercase.
+pub fn title_case(text: &str) -> String {
+    map_words(text, |word| {
+        let mut chars = word.chars();
+        let first = chars.next().expect("words are never empty");
+        first
+            .to_uppercase()
+            .chain(chars.as_str().to_lowercase().chars())
+            .collect()
+    })
+}
+
+/// The graphemes in reverse order: accents stay on their letters.
+pub fn reverse(text: &str) -> String {
+    text.graphemes(true).rev().collect()
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "strings",
+    description: "String, str slices, UTF-8, rotations and Pig Latin",
+    run: sample,
+};
+
 pub fn sample() {
     let data = "initial contents";
 
@@ -66,4 +178,55 @@ pub fn sample() {
     for b in "नमस्ते".bytes() {
         println!("{}", b);
     }
+
+    println!(
+        "{}",
+        pig_latin("first apple, école and the quick brown fox")
+    );
+    println!("{}", title_case("война и мир, the lord of the rings"));
+    println!("{}", reverse("Dobrý den, नमस्ते"));
+    println!(
+        "{} {}",
+        is_rotation("waterbottle", "erbottlewat"),
+        is_rotation("apple", "ppale")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotations() {
+        assert!(is_rotation("", ""));
+        assert!(is_rotation("apple", "pleap"));
+        assert!(is_rotation("orange", "georan"));
+        assert!(!is_rotation("foo", "bar"));
+        assert!(!is_rotation("aab", "abb"));
+        assert!(!is_rotation("ab", "abab"));
+        assert!(is_rotation("Здравствуйте", "вуйтеЗдравст"));
+        assert!(is_rotation("ne\u{301}e", "e\u{301}en"));
+        // Rotating chars would split 'e' from its accent.
+        assert!(!is_rotation("ne\u{301}e", "\u{301}een"));
+    }
+
+    #[test]
+    fn failure() {
+        assert_eq!(
+            failure_function(&[1, 2, 1, 2, 3, 1]),
+            vec![0, 0, 1, 2, 0, 1]
+        );
+        assert_eq!(failure_function(b"aaaa"), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn transforms() {
+        assert_eq!(pig_latin("first apple"), "irst-fay apple-hay");
+        assert_eq!(pig_latin("École, ñandú!"), "École-hay, andú-ñay!");
+        assert_eq!(pig_latin("e\u{301}te\u{301}"), "e\u{301}te\u{301}-hay");
+        assert_eq!(title_case("the ÉCOLE of rock"), "The École Of Rock");
+        assert_eq!(title_case("ßig"), "SSig");
+        assert_eq!(reverse("ae\u{301}b"), "be\u{301}a");
+        assert_eq!(reverse("🇮🇹🇫🇷"), "🇫🇷🇮🇹");
+    }
 }
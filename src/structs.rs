@@ -14,6 +14,12 @@ pub struct Rectangle {
     height: u32,
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "structs",
+    description: "defining structs, tuple structs and methods",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
@@ -187,6 +187,18 @@ mod tests {
         Guess::new(200);
     }
 
+    #[test]
+    fn greater_than_100_is_an_error() {
+        use valid_guess;
+
+        let error = valid_guess::Guess::new(200).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Guess value must be between 1 and 100, got 200."
+        );
+        assert_eq!(valid_guess::Guess::new(100).map(|g| g.value()), Ok(100));
+    }
+
     #[test]
     #[ignore]
     fn expensive_test() {
@@ -229,4 +241,20 @@ mod tests {
 
 }
 
-pub fn sample() {}
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "testing",
+    description: "writing and organizing tests",
+    run: sample,
+};
+
+// valid_guess::Guess checks the same range, but leaves it to the caller to
+// decide what to do with a bad value: a test matches the error instead of
+// expecting a panic.
+pub fn sample() {
+    for &value in &[50, 200] {
+        match ::valid_guess::Guess::new(value) {
+            Ok(guess) => println!("a guess of {}", guess.value()),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
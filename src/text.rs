@@ -0,0 +1,678 @@
+// Word, bigram and letter frequencies of a text.
+//
+// `maps` counts the words of a string with the entry API, and
+// scripts/letter_frequency.rs counts the characters of a whole file. This is
+// the same idea grown up: the input is read one line at a time (from files or
+// from stdin), split into Unicode words, case-folded and filtered through a
+// stop-word list. The counters can be saved as JSON and loaded again, so that
+// an analysis can be continued with more text later on.
+//
+//     fungi text --top 5 README.org
+//     cat posts/*.org | fungi text --format csv --save counts.json
+//     fungi text --load counts.json --format json
+
+use serde_json;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+pub static ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "but", "by", "can", "could", "did", "do", "does", "for", "from", "had",
+    "has", "have", "he", "her", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its",
+    "just", "me", "more", "my", "no", "not", "of", "on", "one", "only", "or", "other", "our",
+    "out", "over", "she", "so", "some", "such", "than", "that", "the", "their", "them", "then",
+    "there", "these", "they", "this", "to", "up", "us", "was", "we", "were", "what", "when",
+    "where", "which", "who", "will", "with", "would", "you", "your",
+];
+
+// Letters, digits and the combining accents of decomposed characters
+// ("e\u{301}") are part of a word.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || ('\u{300}'..='\u{36f}').contains(&c)
+}
+
+// Apostrophes and hyphens are part of a word only between two word
+// characters: "don't" and "well-known" are one word, "'quoted'" is not.
+fn is_joiner(c: char) -> bool {
+    c == '\'' || c == '\u{2019}' || c == '-'
+}
+
+/// The words of a text, as slices of it.
+pub struct Tokens<'a> {
+    text: &'a str,
+}
+
+pub fn tokens(text: &str) -> Tokens<'_> {
+    Tokens { text }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let start = match self.text.find(is_word) {
+            Some(start) => start,
+            None => {
+                self.text = "";
+                return None;
+            }
+        };
+        let rest = &self.text[start..];
+        let mut end = 0;
+        let mut chars = rest.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if is_word(c) {
+                end = i + c.len_utf8();
+            } else if !(is_joiner(c) && end == i && chars.peek().is_some_and(|&(_, n)| is_word(n)))
+            {
+                break;
+            }
+        }
+        self.text = &rest[end..];
+        Some(&rest[..end])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Case {
+    /// "The" and "the" are two different words.
+    Keep,
+    /// Every word is lowercased.
+    Fold,
+}
+
+/// How the text is turned into words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    case: Case,
+    // Always lowercase: stop words are matched ignoring the case.
+    stop_words: HashSet<String>,
+}
+
+impl Options {
+    /// Case folding, and the English stop words.
+    pub fn new() -> Options {
+        Options {
+            case: Case::Fold,
+            stop_words: HashSet::new(),
+        }
+        .stop_words(ENGLISH_STOP_WORDS.iter().cloned())
+    }
+
+    pub fn case(mut self, case: Case) -> Options {
+        self.case = case;
+        self
+    }
+
+    /// Replace the stop words.
+    pub fn stop_words<I, S>(mut self, words: I) -> Options
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.stop_words = words
+            .into_iter()
+            .map(|w| w.as_ref().trim().to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect();
+        self
+    }
+
+    /// Replace the stop words with the ones in a file, one per line. Lines
+    /// starting with '#' are comments.
+    pub fn stop_words_from<P: AsRef<Path>>(self, path: P) -> io::Result<Options> {
+        let mut words = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim_start().starts_with('#') {
+                words.push(line);
+            }
+        }
+        Ok(self.stop_words(words))
+    }
+
+    fn is_stop_word(&self, word: &str) -> bool {
+        !self.stop_words.is_empty() && self.stop_words.contains(&word.to_lowercase())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options::new()
+    }
+}
+
+/// The counters of an analysis.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    tokens: u64,
+    words: BTreeMap<String, u64>,
+    // Two words separated by a space: words never contain spaces.
+    bigrams: BTreeMap<String, u64>,
+    letters: BTreeMap<char, u64>,
+    // The last word seen, the first half of the next bigram.
+    #[serde(skip)]
+    previous: Option<String>,
+}
+
+impl Analysis {
+    pub fn new() -> Analysis {
+        Analysis::default()
+    }
+
+    /// Count the words of a piece of text. Consecutive calls are the same
+    /// text: a bigram can start at the end of a line and end at the beginning
+    /// of the next one.
+    ///
+    /// Letters are counted in every word, stop words are not counted as
+    /// words, and a bigram never spans over one of them.
+    pub fn feed(&mut self, text: &str, options: &Options) {
+        for token in tokens(text) {
+            let word = match options.case {
+                Case::Keep => token.to_string(),
+                Case::Fold => token.to_lowercase(),
+            };
+            for c in word.chars().filter(|c| c.is_alphabetic()) {
+                *self.letters.entry(c).or_insert(0) += 1;
+            }
+            if options.is_stop_word(&word) {
+                self.previous = None;
+                continue;
+            }
+            self.tokens += 1;
+            if let Some(previous) = self.previous.take() {
+                *self
+                    .bigrams
+                    .entry(format!("{} {}", previous, word))
+                    .or_insert(0) += 1;
+            }
+            match self.words.get_mut(&word) {
+                Some(count) => *count += 1,
+                None => {
+                    self.words.insert(word.clone(), 1);
+                }
+            }
+            self.previous = Some(word);
+        }
+    }
+
+    /// Count the words of a whole input, one line at a time. Returns the
+    /// number of lines read.
+    pub fn read<R: BufRead>(&mut self, mut input: R, options: &Options) -> io::Result<u64> {
+        let mut line = String::new();
+        let mut lines = 0;
+        while input.read_line(&mut line)? > 0 {
+            self.feed(&line, options);
+            line.clear();
+            lines += 1;
+        }
+        // Two inputs are two different texts.
+        self.previous = None;
+        Ok(lines)
+    }
+
+    /// Add the counters of another analysis to these ones.
+    pub fn merge(&mut self, other: &Analysis) {
+        self.tokens += other.tokens;
+        for (w, c) in &other.words {
+            *self.words.entry(w.clone()).or_insert(0) += c;
+        }
+        for (b, c) in &other.bigrams {
+            *self.bigrams.entry(b.clone()).or_insert(0) += c;
+        }
+        for (l, c) in &other.letters {
+            *self.letters.entry(*l).or_insert(0) += c;
+        }
+        self.previous = None;
+    }
+
+    /// How many words have been counted.
+    pub fn tokens(&self) -> u64 {
+        self.tokens
+    }
+
+    /// How many different words have been counted.
+    pub fn types(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Types over tokens: the closer to 1, the richer the vocabulary.
+    pub fn type_token_ratio(&self) -> Option<f64> {
+        if self.tokens == 0 {
+            None
+        } else {
+            Some(self.types() as f64 / self.tokens as f64)
+        }
+    }
+
+    pub fn count(&self, word: &str) -> u64 {
+        self.words.get(word).cloned().unwrap_or(0)
+    }
+
+    /// The `k` most frequent words, ties in alphabetical order.
+    pub fn top_words(&self, k: usize) -> Vec<(&str, u64)> {
+        top(&self.words, k)
+    }
+
+    /// The `k` most frequent pairs of consecutive words, as "first second".
+    pub fn top_bigrams(&self, k: usize) -> Vec<(&str, u64)> {
+        top(&self.bigrams, k)
+    }
+
+    /// Every letter seen, the most frequent first.
+    pub fn letters(&self) -> Vec<(char, u64)> {
+        let mut letters: Vec<(char, u64)> = self.letters.iter().map(|(l, c)| (*l, *c)).collect();
+        letters.sort_by(|a, b| by_count(a.1, b.1).then(a.0.cmp(&b.0)));
+        letters
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Analysis> {
+        serde_json::from_str(json)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let json = self.to_json()?;
+        File::create(path)?.write_all(json.as_bytes())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Analysis> {
+        let mut json = String::new();
+        File::open(path)?.read_to_string(&mut json)?;
+        Ok(Analysis::from_json(&json)?)
+    }
+
+    /// Write the totals, the top `k` words and bigrams, and the letters.
+    pub fn report<W: Write>(&self, out: &mut W, format: Format, k: usize) -> io::Result<()> {
+        let words = self.top_words(k);
+        let bigrams = self.top_bigrams(k);
+        let letters: Vec<(String, u64)> = self
+            .letters()
+            .into_iter()
+            .map(|(l, c)| (l.to_string(), c))
+            .collect();
+        let letters: Vec<(&str, u64)> = letters.iter().map(|&(ref l, c)| (l.as_str(), c)).collect();
+
+        match format {
+            Format::Table => {
+                writeln!(
+                    out,
+                    "tokens: {}, types: {}, type/token ratio: {}",
+                    self.tokens,
+                    self.types(),
+                    self.type_token_ratio()
+                        .map_or(String::from("-"), |r| format!("{:.3}", r))
+                )?;
+                for &(title, rows) in
+                    &[("word", &words), ("bigram", &bigrams), ("letter", &letters)]
+                {
+                    let width = rows.iter().map(|r| r.0.chars().count()).max().unwrap_or(0);
+                    let width = width.max(title.len());
+                    writeln!(out, "\n{:width$}  count", title, width = width)?;
+                    for &(item, count) in rows.iter() {
+                        writeln!(out, "{:width$}  {:5}", item, count, width = width)?;
+                    }
+                }
+                Ok(())
+            }
+            Format::Csv => {
+                writeln!(out, "kind,item,count")?;
+                writeln!(out, "total,tokens,{}", self.tokens)?;
+                writeln!(out, "total,types,{}", self.types())?;
+                for &(kind, rows) in &[("word", &words), ("bigram", &bigrams), ("letter", &letters)]
+                {
+                    for &(item, count) in rows.iter() {
+                        writeln!(out, "{},{},{}", kind, csv_field(item), count)?;
+                    }
+                }
+                Ok(())
+            }
+            Format::Json => {
+                let report = Report {
+                    tokens: self.tokens,
+                    types: self.types(),
+                    type_token_ratio: self.type_token_ratio(),
+                    words: entries(&words),
+                    bigrams: entries(&bigrams),
+                    letters: entries(&letters),
+                };
+                serde_json::to_writer_pretty(&mut *out, &report)?;
+                writeln!(out)
+            }
+        }
+    }
+}
+
+fn by_count(a: u64, b: u64) -> Ordering {
+    b.cmp(&a)
+}
+
+fn top(counts: &BTreeMap<String, u64>, k: usize) -> Vec<(&str, u64)> {
+    // The map is sorted by key, and the sort is stable: ties stay in
+    // alphabetical order.
+    let mut top: Vec<(&str, u64)> = counts.iter().map(|(w, c)| (w.as_str(), *c)).collect();
+    top.sort_by(|a, b| by_count(a.1, b.1));
+    top.truncate(k);
+    top
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    item: &'a str,
+    count: u64,
+}
+
+fn entries<'a>(rows: &[(&'a str, u64)]) -> Vec<Entry<'a>> {
+    rows.iter()
+        .map(|&(item, count)| Entry { item, count })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    tokens: u64,
+    types: usize,
+    type_token_ratio: Option<f64>,
+    words: Vec<Entry<'a>>,
+    bigrams: Vec<Entry<'a>>,
+    letters: Vec<Entry<'a>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "table" => Ok(Format::Table),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            other => Err(format!(
+                "unknown format '{}', expected table, csv or json",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Format::Table => "table",
+            Format::Csv => "csv",
+            Format::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub const USAGE: &str = "    fungi text [options] [<file>...]
+                                analyze the words of the files (or stdin, or '-')
+        --format <f>            table (the default), csv or json
+        --top <k>               how many words and bigrams to report (10)
+        --keep-case             don't lowercase the words
+        --stop-words <file>     use the stop words in the file, one per line
+        --no-stop-words         count every word
+        --load <file>           start from a saved analysis
+        --save <file>           save the analysis";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopWords {
+    English,
+    Nothing,
+    File(String),
+}
+
+/// A parsed `fungi text` command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub format: Format,
+    pub top: usize,
+    pub case: Case,
+    pub stop_words: StopWords,
+    pub load: Option<String>,
+    pub save: Option<String>,
+    pub inputs: Vec<String>,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let mut invocation = Invocation {
+        format: Format::Table,
+        top: 10,
+        case: Case::Fold,
+        stop_words: StopWords::English,
+        load: None,
+        save: None,
+        inputs: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--format" => invocation.format = value()?.parse()?,
+            "--top" => invocation.top = value()?.parse().map_err(|e| format!("--top: {}", e))?,
+            "--keep-case" => invocation.case = Case::Keep,
+            "--stop-words" => invocation.stop_words = StopWords::File(value()?),
+            "--no-stop-words" => invocation.stop_words = StopWords::Nothing,
+            "--load" => invocation.load = Some(value()?),
+            "--save" => invocation.save = Some(value()?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+            input => invocation.inputs.push(input.to_string()),
+        }
+    }
+    Ok(invocation)
+}
+
+pub fn run(invocation: &Invocation) -> Result<(), String> {
+    let options = Options::new().case(invocation.case);
+    let options = match invocation.stop_words {
+        StopWords::English => options,
+        StopWords::Nothing => options.stop_words(Vec::<String>::new()),
+        StopWords::File(ref path) => options
+            .stop_words_from(path)
+            .map_err(|e| format!("{}: {}", path, e))?,
+    };
+
+    let mut analysis = match invocation.load {
+        Some(ref path) => Analysis::load(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => Analysis::new(),
+    };
+
+    // With nothing to load, no input at all means stdin.
+    let stdin = [String::from("-")];
+    let inputs = if invocation.inputs.is_empty() && invocation.load.is_none() {
+        &stdin[..]
+    } else {
+        &invocation.inputs[..]
+    };
+    for input in inputs {
+        let read = if input == "-" {
+            let stdin = io::stdin();
+            let lock = stdin.lock();
+            analysis.read(lock, &options)
+        } else {
+            File::open(input).and_then(|f| analysis.read(BufReader::new(f), &options))
+        };
+        read.map_err(|e| format!("{}: {}", input, e))?;
+    }
+
+    if let Some(ref path) = invocation.save {
+        analysis
+            .save(path)
+            .map_err(|e| format!("{}: {}", path, e))?;
+    }
+    let stdout = io::stdout();
+    analysis
+        .report(&mut stdout.lock(), invocation.format, invocation.top)
+        .map_err(|e| e.to_string())
+}
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "text",
+    description: "word, bigram and letter frequencies of a text",
+    run: sample,
+};
+
+pub fn sample() {
+    let text = "She sells sea shells by the sea shore.
+The shells she sells are sea shells, I'm sure.
+So if she sells sea shells on the sea shore,
+I'm sure that the shells are sea-shore shells.";
+
+    let mut analysis = Analysis::new();
+    analysis
+        .read(text.as_bytes(), &Options::new())
+        .expect("reading from a slice can't fail");
+
+    // Another text, counted apart and then added to the first one.
+    let mut other = Analysis::new();
+    other.feed("Sea shells? Sea shells!", &Options::new());
+    analysis.merge(&other);
+    println!(
+        "{} words, \"sea\" {} times, the first word {:?}",
+        analysis.tokens(),
+        analysis.count("sea"),
+        tokens(text).next()
+    );
+
+    let stdout = io::stdout();
+    analysis
+        .report(&mut stdout.lock(), Format::Table, 3)
+        .expect("could not write to stdout");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_unicode_words() {
+        let words: Vec<&str> =
+            tokens("Don't stop — the well-known café's naïve 'quote' 42km").collect();
+        assert_eq!(
+            words,
+            vec![
+                "Don't",
+                "stop",
+                "the",
+                "well-known",
+                "café's",
+                "naïve",
+                "quote",
+                "42km"
+            ]
+        );
+        let words: Vec<&str> = tokens("Καλημέρα κόσμε, e\u{301}te -- привет").collect();
+        assert_eq!(words, vec!["Καλημέρα", "κόσμε", "e\u{301}te", "привет"]);
+        assert_eq!(tokens(" ,.; ").count(), 0);
+    }
+
+    #[test]
+    fn counts_words_bigrams_and_letters() {
+        let mut a = Analysis::new();
+        a.read("The cat and the hat\nThe CAT".as_bytes(), &Options::new())
+            .unwrap();
+        assert_eq!(a.tokens(), 3);
+        assert_eq!(a.top_words(1), vec![("cat", 2)]);
+        // "the" is a stop word, "hat" and "cat" are not consecutive.
+        assert!(a.top_bigrams(10).is_empty());
+        assert_eq!(a.letters()[0], ('t', 6));
+
+        let mut a = Analysis::new();
+        let options = Options::new().case(Case::Keep).stop_words(vec!["and"]);
+        a.read("The cat\nthe cat AND the hat".as_bytes(), &options)
+            .unwrap();
+        assert_eq!(a.count("the"), 2);
+        assert_eq!(a.count("The"), 1);
+        assert_eq!(a.top_bigrams(2), vec![("The cat", 1), ("cat the", 1)]);
+        assert_eq!(a.type_token_ratio(), Some(4.0 / 6.0));
+    }
+
+    #[test]
+    fn saves_and_merges() {
+        let options = Options::new();
+        let mut a = Analysis::new();
+        a.feed("sea shells", &options);
+        let mut b = Analysis::from_json(&a.to_json().unwrap()).unwrap();
+        assert_eq!(b.top_words(5), a.top_words(5));
+        b.merge(&a);
+        assert_eq!(b.count("sea"), 2);
+        assert_eq!(b.top_bigrams(1), vec![("sea shells", 2)]);
+        assert_eq!(b.letters()[0], ('s', 6));
+    }
+
+    #[test]
+    fn report_formats() {
+        let mut a = Analysis::new();
+        a.feed("zeta, alpha zeta", &Options::new());
+
+        let mut csv = Vec::new();
+        a.report(&mut csv, Format::Csv, 1).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("kind,item,count\ntotal,tokens,3\ntotal,types,2\nword,zeta,2\n"));
+        assert!(csv.contains("bigram,alpha zeta,1\n"));
+
+        let mut json = Vec::new();
+        a.report(&mut json, Format::Json, 5).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["tokens"], 3);
+        assert_eq!(json["words"][1]["item"], "alpha");
+        assert_eq!(json["letters"][0]["item"], "a");
+
+        let mut table = Vec::new();
+        a.report(&mut table, Format::Table, 5).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert!(table.starts_with("tokens: 3, types: 2, type/token ratio: 0.667\n"));
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn parses_the_command_line() {
+        let args: Vec<String> = [
+            "--top",
+            "3",
+            "--format",
+            "csv",
+            "--no-stop-words",
+            "a.txt",
+            "-",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let i = parse_args(&args).unwrap();
+        assert_eq!(i.top, 3);
+        assert_eq!(i.format, Format::Csv);
+        assert_eq!(i.stop_words, StopWords::Nothing);
+        assert_eq!(i.inputs, vec!["a.txt", "-"]);
+        assert!(parse_args(&[String::from("--top")]).is_err());
+        assert!(parse_args(&[String::from("--format"), String::from("xml")]).is_err());
+        assert!(parse_args(&[String::from("--fast")]).is_err());
+    }
+}
@@ -10,6 +10,12 @@ impl ToString for Circle {
     }
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "to_string",
+    description: "implementing Display to get ToString for free",
+    run: sample,
+};
+
 pub fn sample() {
     let circle = Circle { radius: 6 };
     println!("{}", circle.to_string());
@@ -0,0 +1,129 @@
+// The registry of runnable topics.
+//
+// Every module of this crate exposes its own `TOPIC`, a small descriptor with
+// the name used on the command line, a one-line description and the function
+// that runs its samples. The registry below is just the ordered list of those
+// descriptors: `fungi run --all` walks it from top to bottom.
+
+use std::cmp;
+
+#[derive(Debug)]
+pub struct Topic {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run: fn(),
+}
+
+pub static REGISTRY: &[Topic] = &[
+    ::types::TOPIC,
+    ::modules::TOPIC,
+    ::fibonacci::TOPIC,
+    ::to_string::TOPIC,
+    ::formatting::TOPIC,
+    ::ownership::TOPIC,
+    ::structs::TOPIC,
+    ::enums::TOPIC,
+    ::option::TOPIC,
+    ::vectors::TOPIC,
+    ::maps::TOPIC,
+    ::strings::TOPIC,
+    ::palindrome::TOPIC,
+    ::boxes_and_trees::TOPIC,
+    ::generics::TOPIC,
+    ::pointers_and_refs::TOPIC,
+    ::ref_and_ampersand::TOPIC,
+    ::traits::TOPIC,
+    ::lifetimes::TOPIC,
+    ::testing::TOPIC,
+    ::closures::TOPIC,
+    ::iterators::TOPIC,
+    ::smart_pointers::TOPIC,
+    ::concurrency::TOPIC,
+    ::oop::TOPIC,
+    ::patterns::TOPIC,
+    ::advanced::TOPIC,
+    ::units::TOPIC,
+    ::expr::TOPIC,
+    ::graph::TOPIC,
+    ::regexp::TOPIC,
+    ::extract::TOPIC,
+    ::config::TOPIC,
+    ::linked_lists::TOPIC,
+    ::state_machine::TOPIC,
+    ::stats::TOPIC,
+    ::text::TOPIC,
+];
+
+pub fn find(name: &str) -> Option<&'static Topic> {
+    REGISTRY.iter().find(|t| t.name == name)
+}
+
+// Names of the registered topics that are "close enough" to the given one:
+// the ones starting with it, and the ones within a small edit distance.
+// The closest names come first.
+pub fn suggestions(name: &str) -> Vec<&'static str> {
+    let threshold = cmp::max(2, name.chars().count() / 3);
+    let mut candidates: Vec<(usize, &'static str)> = REGISTRY
+        .iter()
+        .filter_map(|t| {
+            let d = levenshtein(name, t.name);
+            if d <= threshold {
+                Some((d, t.name))
+            } else if !name.is_empty() && t.name.starts_with(name) {
+                Some((threshold + 1, t.name))
+            } else {
+                None
+            }
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().map(|(_, n)| n).collect()
+}
+
+// https://en.wikipedia.org/wiki/Levenshtein_distance
+// The classic dynamic programming version, keeping just one row at a time.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..b.len() + 1).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = cmp::min(substitution, cmp::min(row[j], row[j + 1]) + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_topic_has_a_unique_name() {
+        for (i, t) in REGISTRY.iter().enumerate() {
+            assert!(!t.description.is_empty(), "{} has no description", t.name);
+            assert!(REGISTRY[i + 1..].iter().all(|o| o.name != t.name));
+        }
+    }
+
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("closures", "closures"), 0);
+    }
+
+    #[test]
+    fn did_you_mean() {
+        assert!(find("clousres").is_none());
+        assert_eq!(suggestions("clousres"), vec!["closures"]);
+        assert_eq!(suggestions("iterator"), vec!["iterators"]);
+        assert_eq!(suggestions("smart"), vec!["smart_pointers"]);
+        assert!(suggestions("zzzzzzzzzz").is_empty());
+    }
+}
@@ -211,6 +211,12 @@ fn three() {
     // let s = 3.to_string();
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "traits",
+    description: "defining and implementing traits, default methods",
+    run: sample,
+};
+
 pub fn sample() {
     one();
     two();
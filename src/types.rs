@@ -1,5 +1,11 @@
 const MAX_POINTS: u32 = 100_000;
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "types",
+    description: "variables, constants, shadowing, tuples, arrays and control flow",
+    run: sample,
+};
+
 pub fn sample() {
     println!("The value of MAX_POINTS is: {}", MAX_POINTS);
 
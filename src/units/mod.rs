@@ -0,0 +1,122 @@
+// Units of measure, the way `advanced::eight` adds Millimeters to Meters, but
+// for whole families of units.
+
+mod quantities;
+
+pub use self::quantities::*;
+
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "units",
+    description: "units of measure checked by the compiler, with operator overloading",
+    run: sample,
+};
+
+pub fn sample() {
+    let run = 10.0 * KILOMETER;
+    let time = 52.0 * MINUTE + 30.0 * SECOND;
+    let speed = run / time;
+    println!(
+        "{} in {:.1} is {:.2}, or {:.2}",
+        run.to(KILOMETER),
+        time.to(MINUTE),
+        speed.to(KILOMETER_PER_HOUR),
+        speed.to(MILE_PER_HOUR)
+    );
+    // The dimensions are checked at compile time:
+    // error[E0308]: mismatched types, expected struct `Length`, found struct `Time`
+    // let nonsense = run + time;
+
+    // From a mass and an acceleration to an energy, step by step.
+    let climber = 70.0 * KILOGRAM;
+    let gravity = 9.806_65 * METER_PER_SECOND_SQUARED;
+    let everest = 8848.0 * METER;
+    let work = climber * gravity * everest;
+    println!("{:.0} up {} is {:.0}", climber, everest, work.to(KILOCALORIE));
+
+    let room = (4.0 * METER) * (350.0 * CENTIMETER);
+    let water = room * (2.0 * CENTIMETER);
+    println!("{} under {:.0}", room, water.to(LITER));
+
+    // Two temperatures can't be added, but they can be subtracted.
+    let fever = 102.2 * FAHRENHEIT;
+    let normal = 37.0 * CELSIUS;
+    println!(
+        "{:.1} is {:.1}, {:.1} above normal",
+        fever.to(FAHRENHEIT),
+        fever.to(CELSIUS),
+        (fever - normal).to(CELSIUS_DEGREES)
+    );
+
+    for s in &["12.5 km", "3mi", " 1e3 ft", "12.5 parsecs", "km", "twelve km"] {
+        match s.parse::<Length>() {
+            Ok(length) => println!("{:?} => {:.3}", s, length),
+            Err(e) => println!("{:?} => {}", s, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * a.abs().max(1.0)
+    }
+
+    #[test]
+    fn dimensions_combine() {
+        let side = 3.0 * FOOT;
+        assert!(close((side * side).to(SQUARE_FOOT).value, 9.0));
+        assert!(close((1.0 * MILE).to(KILOMETER).value, 1.609_344));
+
+        let speed = 100.0 * METER / (9.58 * SECOND);
+        assert!(close(speed.to(KILOMETER_PER_HOUR).value, 37.578_288_100_208_77));
+        let distance = speed * (1.0 * MINUTE);
+        assert!(close(distance / (1.0 * METER), 6000.0 / 9.58));
+
+        let energy = (1.0 * KILOWATT) * (30.0 * MINUTE);
+        assert!(close(energy.to(KILOWATT_HOUR).value, 0.5));
+        assert!(close((energy / (1.0 * HOUR)).to(WATT).value, 500.0));
+
+        let total: Mass = vec![1.0 * POUND, 16.0 * OUNCE].into_iter().sum();
+        assert!(close(total.to(POUND).value, 2.0));
+        assert!(1.0 * KILOGRAM > 2.0 * POUND);
+    }
+
+    #[test]
+    fn temperatures_have_an_offset() {
+        assert!(close((-40.0 * FAHRENHEIT).to(CELSIUS).value, -40.0));
+        assert!(close((100.0 * CELSIUS).to(FAHRENHEIT).value, 212.0));
+        assert!(close((0.0 * CELSIUS).to(KELVIN).value, 273.15));
+
+        let delta = (50.0 * FAHRENHEIT) - (10.0 * CELSIUS);
+        assert!(close(delta.to(KELVINS).value, 0.0));
+        let warmer = (20.0 * CELSIUS) + 9.0 * FAHRENHEIT_DEGREES;
+        assert!(close(warmer.to(CELSIUS).value, 25.0));
+        assert_eq!(format!("{:.1}", warmer.to(FAHRENHEIT)), "77.0 °F");
+    }
+
+    #[test]
+    fn parses_and_displays() {
+        assert_eq!("12.5 km".parse::<Length>(), Ok(12.5 * KILOMETER));
+        assert_eq!("-40F".parse::<Temperature>(), Ok(-40.0 * FAHRENHEIT));
+        let m: Measure<Speed> = " 30 mph ".parse().unwrap();
+        assert_eq!(m.unit, MILE_PER_HOUR);
+        assert_eq!(m.to_string(), "30 mph");
+        assert_eq!(format!("{:.2}", 1.5 * HOUR), "5400.00 s");
+
+        assert_eq!(
+            "12".parse::<Mass>(),
+            Err(ParseQuantityError::MissingUnit("mass"))
+        );
+        assert_eq!(
+            "1..2 m".parse::<Length>(),
+            Err(ParseQuantityError::InvalidNumber(String::from("1..2")))
+        );
+        let e = "5 kg".parse::<Time>().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "'kg' is not a unit of time, expected one of: s, ms, min, h, d"
+        );
+    }
+}
@@ -0,0 +1,507 @@
+// Quantities with a dimension, and the units to measure them.
+//
+// Every quantity is a newtype over its value in the SI unit, and the
+// operators are only implemented between compatible dimensions: a length
+// divided by a time is a speed, a length plus a time doesn't compile.
+//
+// This file doesn't depend on the rest of the crate, the scripts include it
+// with `#[path]`.
+
+// A library: not every unit and operator is used by the samples.
+#![allow(dead_code)]
+
+use std::error::Error;
+use std::fmt;
+use std::iter::Sum;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// A kind of quantity, with the units it can be measured in.
+pub trait Quantity: Copy + 'static {
+    /// What it is, for the error messages.
+    const NAME: &'static str;
+    /// The SI unit comes first.
+    const UNITS: &'static [Unit<Self>];
+
+    fn from_si(value: f64) -> Self;
+    fn si(self) -> f64;
+
+    /// The quantity measured in the given unit.
+    fn to(self, unit: Unit<Self>) -> Measure<Self> {
+        Measure {
+            value: unit.value_of(self.si()),
+            unit,
+        }
+    }
+}
+
+/// A unit of the quantity Q: `value * scale + offset` is the value in the SI
+/// unit. Only temperatures have an offset.
+pub struct Unit<Q> {
+    pub symbol: &'static str,
+    aliases: &'static [&'static str],
+    scale: f64,
+    offset: f64,
+    quantity: PhantomData<fn() -> Q>,
+}
+
+impl<Q> Unit<Q> {
+    fn to_si(self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+
+    // The inverse of `to_si`.
+    fn value_of(self, si: f64) -> f64 {
+        (si - self.offset) / self.scale
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        self.symbol == name || self.aliases.contains(&name)
+    }
+}
+
+// Derived, these would need Q: Clone, Q: PartialEq...
+impl<Q> Clone for Unit<Q> {
+    fn clone(&self) -> Unit<Q> {
+        *self
+    }
+}
+
+impl<Q> Copy for Unit<Q> {}
+
+impl<Q> PartialEq for Unit<Q> {
+    fn eq(&self, other: &Unit<Q>) -> bool {
+        self.symbol == other.symbol
+    }
+}
+
+impl<Q> fmt::Debug for Unit<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unit({})", self.symbol)
+    }
+}
+
+/// A value in a given unit, as written: "12.5 km".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measure<Q> {
+    pub value: f64,
+    pub unit: Unit<Q>,
+}
+
+impl<Q: Quantity> Measure<Q> {
+    pub fn quantity(&self) -> Q {
+        Q::from_si(self.unit.to_si(self.value))
+    }
+}
+
+// The precision, if any, is the one of the value: `{:.1}` gives "12.5 km".
+impl<Q> fmt::Display for Measure<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, self.value, self.unit.symbol),
+            None => write!(f, "{} {}", self.value, self.unit.symbol),
+        }
+    }
+}
+
+/// A number, then a unit of the quantity, with or without a space in
+/// between: "12.5 km", "-40°F", "1e3 m".
+impl<Q: Quantity> FromStr for Measure<Q> {
+    type Err = ParseQuantityError;
+
+    fn from_str(s: &str) -> Result<Measure<Q>, ParseQuantityError> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let value = number.parse().map_err(|_| {
+            // "twelve km": the whole word is the number that isn't one.
+            let number = if number.is_empty() {
+                s.split_whitespace().next().unwrap_or("")
+            } else {
+                number
+            };
+            ParseQuantityError::InvalidNumber(number.to_string())
+        })?;
+        let unit = unit.trim();
+        if unit.is_empty() {
+            return Err(ParseQuantityError::MissingUnit(Q::NAME));
+        }
+        match Q::UNITS.iter().find(|u| u.is_named(unit)) {
+            Some(&unit) => Ok(Measure { value, unit }),
+            None => Err(ParseQuantityError::UnknownUnit {
+                unit: unit.to_string(),
+                quantity: Q::NAME,
+                expected: Q::UNITS.iter().map(|u| u.symbol).collect(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseQuantityError {
+    InvalidNumber(String),
+    MissingUnit(&'static str),
+    UnknownUnit {
+        unit: String,
+        quantity: &'static str,
+        expected: Vec<&'static str>,
+    },
+}
+
+impl fmt::Display for ParseQuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseQuantityError::InvalidNumber(ref number) if number.is_empty() => {
+                write!(f, "the number is missing")
+            }
+            ParseQuantityError::InvalidNumber(ref number) => {
+                write!(f, "'{}' is not a number", number)
+            }
+            ParseQuantityError::MissingUnit(quantity) => {
+                write!(f, "the unit of the {} is missing", quantity)
+            }
+            ParseQuantityError::UnknownUnit {
+                ref unit,
+                quantity,
+                ref expected,
+            } => write!(
+                f,
+                "'{}' is not a unit of {}, expected one of: {}",
+                unit,
+                quantity,
+                expected.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for ParseQuantityError {}
+
+// The newtype, its units, and what every quantity has: a display (in the SI
+// unit) and a parser. A unit is `NAME("symbol", scale)`, then maybe an offset
+// and other names it can be parsed from.
+macro_rules! quantity {
+    (
+        $(#[$attr:meta])*
+        $name:ident, $label:expr, [
+            $($unit:ident($symbol:expr, $scale:expr $(, offset $offset:expr)? $(; $($alias:expr),+)?)),+
+            $(,)?
+        ]
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+        pub struct $name(f64);
+
+        $(
+            pub const $unit: Unit<$name> = Unit {
+                symbol: $symbol,
+                aliases: &[$($($alias),+)?],
+                scale: $scale,
+                offset: 0.0 $(+ $offset)?,
+                quantity: PhantomData,
+            };
+        )+
+
+        impl Quantity for $name {
+            const NAME: &'static str = $label;
+            const UNITS: &'static [Unit<$name>] = &[$($unit),+];
+
+            fn from_si(value: f64) -> $name {
+                $name(value)
+            }
+
+            fn si(self) -> f64 {
+                self.0
+            }
+        }
+
+        // `12.5 * KILOMETER` is a Length.
+        impl Mul<Unit<$name>> for f64 {
+            type Output = $name;
+
+            fn mul(self, unit: Unit<$name>) -> $name {
+                $name(unit.to_si(self))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.to($name::UNITS[0]), f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseQuantityError;
+
+            fn from_str(s: &str) -> Result<$name, ParseQuantityError> {
+                s.parse::<Measure<$name>>().map(|m| m.quantity())
+            }
+        }
+    };
+}
+
+// Quantities that can be added to each other and scaled: all of them, but
+// temperatures.
+macro_rules! linear {
+    ($($name:ident),+) => {$(
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                $name(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                $name(self.0 - other.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: $name) {
+                self.0 += other.0;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: $name) {
+                self.0 -= other.0;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $name {
+            type Output = $name;
+
+            fn mul(self, k: f64) -> $name {
+                $name(self.0 * k)
+            }
+        }
+
+        impl Mul<$name> for f64 {
+            type Output = $name;
+
+            fn mul(self, q: $name) -> $name {
+                $name(self * q.0)
+            }
+        }
+
+        impl Div<f64> for $name {
+            type Output = $name;
+
+            fn div(self, k: f64) -> $name {
+                $name(self.0 / k)
+            }
+        }
+
+        // The ratio of two quantities of the same kind is a number.
+        impl Div for $name {
+            type Output = f64;
+
+            fn div(self, other: $name) -> f64 {
+                self.0 / other.0
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = $name>>(iter: I) -> $name {
+                $name(iter.map(|q| q.0).sum())
+            }
+        }
+    )+};
+}
+
+// A * B = C, so B * A = C, C / A = B and C / B = A.
+macro_rules! product {
+    ($a:ident * $b:ident = $c:ident) => {
+        impl Mul<$b> for $a {
+            type Output = $c;
+
+            fn mul(self, other: $b) -> $c {
+                $c(self.0 * other.0)
+            }
+        }
+
+        impl Mul<$a> for $b {
+            type Output = $c;
+
+            fn mul(self, other: $a) -> $c {
+                $c(self.0 * other.0)
+            }
+        }
+
+        impl Div<$a> for $c {
+            type Output = $b;
+
+            fn div(self, other: $a) -> $b {
+                $b(self.0 / other.0)
+            }
+        }
+
+        impl Div<$b> for $c {
+            type Output = $a;
+
+            fn div(self, other: $b) -> $a {
+                $a(self.0 / other.0)
+            }
+        }
+    };
+}
+
+// A * A = C, so C / A = A.
+macro_rules! square {
+    ($a:ident = $c:ident) => {
+        impl Mul for $a {
+            type Output = $c;
+
+            fn mul(self, other: $a) -> $c {
+                $c(self.0 * other.0)
+            }
+        }
+
+        impl Div<$a> for $c {
+            type Output = $a;
+
+            fn div(self, other: $a) -> $a {
+                $a(self.0 / other.0)
+            }
+        }
+    };
+}
+
+quantity!(Length, "length", [
+    METER("m", 1.0; "meter", "meters"),
+    KILOMETER("km", 1e3),
+    CENTIMETER("cm", 1e-2),
+    MILLIMETER("mm", 1e-3),
+    INCH("in", 0.0254),
+    FOOT("ft", 0.3048),
+    YARD("yd", 0.9144),
+    MILE("mi", 1609.344),
+]);
+
+quantity!(Mass, "mass", [
+    KILOGRAM("kg", 1.0),
+    GRAM("g", 1e-3),
+    MILLIGRAM("mg", 1e-6),
+    TONNE("t", 1e3),
+    POUND("lb", 0.453_592_37; "lbs"),
+    OUNCE("oz", 0.028_349_523_125),
+]);
+
+quantity!(Time, "time", [
+    SECOND("s", 1.0; "sec"),
+    MILLISECOND("ms", 1e-3),
+    MINUTE("min", 60.0),
+    HOUR("h", 3600.0; "hr"),
+    DAY("d", 86_400.0),
+]);
+
+quantity!(
+    /// An absolute temperature: temperatures can't be added, but their
+    /// difference is a `TemperatureDelta`.
+    Temperature, "temperature", [
+    KELVIN("K", 1.0),
+    CELSIUS("°C", 1.0, offset 273.15; "C", "℃", "degC"),
+    FAHRENHEIT("°F", 5.0 / 9.0, offset 273.15 - 32.0 * 5.0 / 9.0; "F", "℉", "degF"),
+]);
+
+quantity!(TemperatureDelta, "temperature difference", [
+    KELVINS("K", 1.0),
+    CELSIUS_DEGREES("°C", 1.0; "C"),
+    FAHRENHEIT_DEGREES("°F", 5.0 / 9.0; "F"),
+]);
+
+quantity!(Area, "area", [
+    SQUARE_METER("m²", 1.0; "m2", "m^2"),
+    SQUARE_KILOMETER("km²", 1e6; "km2", "km^2"),
+    HECTARE("ha", 1e4),
+    SQUARE_FOOT("ft²", 0.092_903_04; "ft2", "ft^2"),
+    ACRE("ac", 4_046.856_422_4),
+]);
+
+quantity!(Volume, "volume", [
+    CUBIC_METER("m³", 1.0; "m3", "m^3"),
+    LITER("L", 1e-3; "l"),
+    MILLILITER("mL", 1e-6; "ml"),
+    GALLON("gal", 0.003_785_411_784),
+]);
+
+quantity!(Speed, "speed", [
+    METER_PER_SECOND("m/s", 1.0),
+    KILOMETER_PER_HOUR("km/h", 1.0 / 3.6; "kph", "kmh"),
+    MILE_PER_HOUR("mph", 0.447_04),
+    KNOT("kn", 1852.0 / 3600.0; "kt"),
+]);
+
+quantity!(Acceleration, "acceleration", [
+    METER_PER_SECOND_SQUARED("m/s²", 1.0; "m/s2", "m/s^2"),
+    FOOT_PER_SECOND_SQUARED("ft/s²", 0.3048; "ft/s2", "ft/s^2"),
+]);
+
+quantity!(Force, "force", [
+    NEWTON("N", 1.0),
+    KILONEWTON("kN", 1e3),
+    POUND_FORCE("lbf", 4.448_221_615_260_5),
+]);
+
+quantity!(Energy, "energy", [
+    JOULE("J", 1.0),
+    KILOJOULE("kJ", 1e3),
+    CALORIE("cal", 4.184),
+    KILOCALORIE("kcal", 4184.0),
+    KILOWATT_HOUR("kWh", 3.6e6),
+]);
+
+quantity!(Power, "power", [
+    WATT("W", 1.0),
+    KILOWATT("kW", 1e3),
+    HORSEPOWER("hp", 745.699_871_582_270_2),
+]);
+
+linear!(Length, Mass, Time, TemperatureDelta, Area, Volume, Speed, Acceleration, Force, Energy, Power);
+
+square!(Length = Area);
+product!(Area * Length = Volume);
+product!(Speed * Time = Length);
+product!(Acceleration * Time = Speed);
+product!(Mass * Acceleration = Force);
+product!(Force * Length = Energy);
+product!(Power * Time = Energy);
+
+impl Sub for Temperature {
+    type Output = TemperatureDelta;
+
+    fn sub(self, other: Temperature) -> TemperatureDelta {
+        TemperatureDelta(self.0 - other.0)
+    }
+}
+
+impl Add<TemperatureDelta> for Temperature {
+    type Output = Temperature;
+
+    fn add(self, delta: TemperatureDelta) -> Temperature {
+        Temperature(self.0 + delta.0)
+    }
+}
+
+impl Sub<TemperatureDelta> for Temperature {
+    type Output = Temperature;
+
+    fn sub(self, delta: TemperatureDelta) -> Temperature {
+        Temperature(self.0 - delta.0)
+    }
+}
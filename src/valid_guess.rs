@@ -1,14 +1,33 @@
+use std::fmt;
+
 pub struct Guess {
     value: u32,
 }
 
+// What's wrong with a number that can't be a Guess: the caller decides what
+// to do about it, instead of a panic deciding for everyone.
+#[derive(Debug, PartialEq)]
+pub struct OutOfRange {
+    value: u32,
+}
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Guess value must be between 1 and 100, got {}.",
+            self.value
+        )
+    }
+}
+
 impl Guess {
-    pub fn new(value: u32) -> Guess {
-        if value < 1 || value > 100 {
-            panic!("Guess value must be between 1 and 100, got {}.", value);
+    pub fn new(value: u32) -> Result<Guess, OutOfRange> {
+        if !(1..=100).contains(&value) {
+            return Err(OutOfRange { value });
         }
 
-        Guess { value }
+        Ok(Guess { value })
     }
 
     // a method named value that borrows self, doesn’t have any other
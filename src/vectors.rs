@@ -277,7 +277,7 @@ fn four() {
     let mut vec2 = vec![4, 5, 6];
     vec.append(&mut vec2);
     assert_eq!(vec, [1, 2, 3, 4, 5, 6]);
-    assert_eq!(vec2, []);
+    assert_eq!(vec2, [] as [i32; 0]);
 
     // fn drain<R>(&mut self, range: R) -> Drain<T>
     // where R: RangeArgument<usize>,
@@ -293,7 +293,7 @@ fn four() {
 
     // A full range clears the vector
     v.drain(..);
-    assert_eq!(v, &[]);
+    assert_eq!(v, &[] as &[i32]);
 
     // fn clear(&mut self)
     // Clears the vector, removing all values.
@@ -405,7 +405,15 @@ fn mode(vs: &Vec<i32>) -> Vec<i32> {
     vec_mode
 }
 
+pub const TOPIC: ::topics::Topic = ::topics::Topic {
+    name: "vectors",
+    description: "Vec, iteration and a few statistics over slices",
+    run: sample,
+};
+
 pub fn sample() {
+    use stats::Summary;
+
     one();
     two();
     three();
@@ -415,6 +423,14 @@ pub fn sample() {
     println!("mean of {:?}: {:?}", v, mean(v));
     let v = vec![1, 2, 3, 3, 3, 4, 5, 10, 20, 40];
     println!("mode of {:?}: {:?}", &v, mode(&v));
+    let summary: Summary<i32> = v.iter().cloned().collect();
+    println!(
+        "summary of {:?}: mean {:?}, median {:?}, mode {:?}",
+        &v,
+        summary.mean(),
+        summary.median(),
+        summary.mode()
+    );
 
     let w = &[];
     println!("mean of {:?}: {:?}", w, mean(w));